@@ -54,6 +54,14 @@ func (d *DaemonClient) NotifyOutputsWritten(ctx context.Context, hash string, re
 	return err
 }
 
+// UnwatchGlobs stops the daemon from tracking the globs registered for the given hashes
+func (d *DaemonClient) UnwatchGlobs(ctx context.Context, hashes []string) error {
+	_, err := d.client.UnwatchGlobs(ctx, &turbodprotocol.UnwatchGlobsRequest{
+		Hashes: hashes,
+	})
+	return err
+}
+
 // Status returns the DaemonStatus from the daemon
 func (d *DaemonClient) Status(ctx context.Context) (*Status, error) {
 	resp, err := d.client.Status(ctx, &turbodprotocol.StatusRequest{})
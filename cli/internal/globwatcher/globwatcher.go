@@ -124,6 +124,23 @@ func (g *GlobWatcher) GetChangedGlobs(hash string, candidates []string) ([]strin
 	return diff.UnsafeListOfStrings(), nil
 }
 
+// UnwatchGlobs stops tracking the globs registered for the given hashes. Clients
+// that register globs under hashes that are never reused, like watch mode, use
+// this to avoid leaving them behind in a long-lived daemon.
+func (g *GlobWatcher) UnwatchGlobs(hashes []string) {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	for _, hash := range hashes {
+		delete(g.hashGlobs, hash)
+		for glob, hashStatus := range g.globStatus {
+			hashStatus.Delete(hash)
+			if len(hashStatus) == 0 {
+				delete(g.globStatus, glob)
+			}
+		}
+	}
+}
+
 // OnFileWatchEvent implements FileWatchClient.OnFileWatchEvent
 // On a file change, check if we have a glob that matches this file. Invalidate
 // any matching globs, and remove them from the set of unchanged globs for the corresponding
@@ -230,3 +230,38 @@ func TestWatchSingleFile(t *testing.T) {
 	})
 	assert.Equal(t, 0, len(globWatcher.hashGlobs))
 }
+
+func TestUnwatchGlobs(t *testing.T) {
+	logger := hclog.Default()
+
+	repoRootRaw := t.TempDir()
+	repoRoot := fs.AbsoluteSystemPathFromUpstream(repoRootRaw)
+
+	setup(t, repoRoot)
+
+	globWatcher := New(logger, repoRoot, _noopCookieWaiter)
+
+	globs := fs.TaskOutputs{
+		Inclusions: []string{"my-pkg/dist/**"},
+	}
+	err := globWatcher.WatchGlobs("the-hash", globs)
+	assert.NilError(t, err, "WatchGlobs")
+	err = globWatcher.WatchGlobs("other-hash", globs)
+	assert.NilError(t, err, "WatchGlobs")
+
+	globWatcher.UnwatchGlobs([]string{"the-hash"})
+	assert.Equal(t, 1, len(globWatcher.hashGlobs), "Expected only other-hash to be tracked")
+	assert.Equal(t, 1, len(globWatcher.globStatus["my-pkg/dist/**"]), "Expected only other-hash to track the glob")
+
+	// An unwatched hash is reported as changed, like one that was never registered
+	changed, err := globWatcher.GetChangedGlobs("the-hash", globs.Inclusions)
+	assert.NilError(t, err, "GetChangedGlobs")
+	assert.DeepEqual(t, globs.Inclusions, changed)
+	changed, err = globWatcher.GetChangedGlobs("other-hash", globs.Inclusions)
+	assert.NilError(t, err, "GetChangedGlobs")
+	assert.Equal(t, 0, len(changed), "Expected no changed paths")
+
+	globWatcher.UnwatchGlobs([]string{"other-hash", "unknown-hash"})
+	assert.Equal(t, 0, len(globWatcher.hashGlobs), "Expected no hashes to be tracked")
+	assert.Equal(t, 0, len(globWatcher.globStatus), "Expected no globs to be tracked")
+}
@@ -47,6 +47,15 @@ func ExecuteRun(ctx gocontext.Context, helper *cmdutil.Helper, signalWatcher *si
 
 	opts.runOpts.PassThroughArgs = passThroughArgs
 	run := configureRun(base, opts, signalWatcher)
+	if opts.runOpts.Watch {
+		watchCtx, cancel := gocontext.WithCancel(ctx)
+		signalWatcher.AddOnClose(cancel)
+		if err := run.watch(watchCtx, tasks, executionState); err != nil {
+			base.LogError("watch failed: %v", err)
+			return err
+		}
+		return nil
+	}
 	if err := run.run(ctx, tasks, executionState); err != nil {
 		base.LogError("run failed: %v", err)
 		return err
@@ -99,6 +108,8 @@ func optsFromArgs(args *turbostate.ParsedArgsFromRust) (*Opts, error) {
 	opts.runOpts.Only = runPayload.Only
 	opts.runOpts.NoDaemon = runPayload.NoDaemon
 	opts.runOpts.SinglePackage = args.Command.Run.SinglePackage
+	opts.runOpts.Watch = runPayload.Watch
+	opts.runOpts.WatchDebounce = time.Duration(runPayload.WatchDebounce) * time.Millisecond
 
 	// See comment on Graph in turbostate.go for an explanation on Graph's representation.
 	// If flag is passed...
@@ -144,6 +155,9 @@ type run struct {
 	base      *cmdutil.CmdBase
 	opts      *Opts
 	processes *process.Manager
+	// onWatchedRun is called by runs in watch mode once their tasks are known,
+	// before any of them executes
+	onWatchedRun func(*watchedRun) error
 }
 
 func (r *run) run(ctx gocontext.Context, targets []string, executionState *turbostate.ExecutionState) error {
@@ -326,6 +340,18 @@ func (r *run) run(ctx gocontext.Context, targets []string, executionState *turbo
 		}
 	}
 
+	if rs.Opts.runOpts.Watch {
+		watched, err := newWatchedRun(g, engine, rs.FilteredPkgs)
+		if err != nil {
+			return errors.Wrap(err, "error preparing watch mode")
+		}
+		if r.onWatchedRun != nil {
+			if err := r.onWatchedRun(watched); err != nil {
+				return err
+			}
+		}
+	}
+
 	// Graph Run
 	if rs.Opts.runOpts.GraphFile != "" || rs.Opts.runOpts.GraphDot {
 		return GraphRun(ctx, rs, engine, r.base)
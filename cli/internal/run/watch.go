@@ -0,0 +1,312 @@
+package run
+
+import (
+	gocontext "context"
+	"fmt"
+	"os"
+	"path/filepath"
+	"sort"
+	"strings"
+	"time"
+
+	"github.com/vercel/turbo/cli/internal/core"
+	"github.com/vercel/turbo/cli/internal/daemon"
+	"github.com/vercel/turbo/cli/internal/daemonclient"
+	"github.com/vercel/turbo/cli/internal/fs"
+	"github.com/vercel/turbo/cli/internal/graph"
+	"github.com/vercel/turbo/cli/internal/runcache"
+	"github.com/vercel/turbo/cli/internal/scope"
+	"github.com/vercel/turbo/cli/internal/turbostate"
+	"github.com/vercel/turbo/cli/internal/ui"
+	"github.com/vercel/turbo/cli/internal/util"
+
+	"github.com/pkg/errors"
+	"github.com/pyr-sh/dag"
+)
+
+// _watchIgnoredGlobs are excluded from the inputs of every package, so that
+// writes by the package manager or by turbo itself don't trigger a re-run
+var _watchIgnoredGlobs = []string{"node_modules/**", ".turbo/**"}
+
+// watchedRun holds what watch mode needs to know about a run to decide
+// which packages to run again: the globs of the inputs of the tasks
+// of each package, and the graph to find the dependents of a package in.
+type watchedRun struct {
+	workspaceGraph *dag.AcyclicGraph
+	packages       util.Set
+	inputs         map[string]fs.TaskOutputs
+}
+
+func newWatchedRun(g *graph.CompleteGraph, engine *core.Engine, packages util.Set) (*watchedRun, error) {
+	inputs := make(map[string]fs.TaskOutputs)
+	for _, v := range engine.TaskGraph.Vertices() {
+		taskID, ok := v.(string)
+		if !ok {
+			return nil, fmt.Errorf("unknown task %v", taskID)
+		}
+		if taskID == g.RootNode {
+			continue
+		}
+		packageName, _ := util.GetPackageTaskFromId(taskID)
+		pkg, ok := g.WorkspaceInfos.PackageJSONs[packageName]
+		if !ok {
+			return nil, fmt.Errorf("cannot find package %v for task %v", packageName, taskID)
+		}
+		taskDefinition, ok := g.TaskDefinitions[taskID]
+		if !ok {
+			return nil, fmt.Errorf("missing pipeline entry %v", taskID)
+		}
+		if taskDefinition.Persistent {
+			return nil, fmt.Errorf("%v is a persistent task, which never exits and cannot be re-run in watch mode", taskID)
+		}
+
+		pkgDir := pkg.Dir.ToStringDuringMigration()
+		globs := inputs[packageName]
+		taskInputs := taskDefinition.Inputs
+		if len(taskInputs) == 0 {
+			taskInputs = []string{"**"}
+		}
+		for _, input := range taskInputs {
+			if strings.HasPrefix(input, "!") {
+				globs.Exclusions = append(globs.Exclusions, filepath.Join(pkgDir, input[1:]))
+			} else {
+				globs.Inclusions = append(globs.Inclusions, filepath.Join(pkgDir, input))
+			}
+		}
+		// Outputs are written by the run itself
+		for _, output := range taskDefinition.Outputs.Inclusions {
+			globs.Exclusions = append(globs.Exclusions, filepath.Join(pkgDir, output))
+		}
+		inputs[packageName] = globs
+	}
+
+	for packageName, globs := range inputs {
+		pkgDir := g.WorkspaceInfos.PackageJSONs[packageName].Dir.ToStringDuringMigration()
+		for _, glob := range _watchIgnoredGlobs {
+			globs.Exclusions = append(globs.Exclusions, filepath.Join(pkgDir, glob))
+		}
+		if packageName == util.RootPkgName {
+			// The root package contains all other packages, but only the files outside
+			// of them are inputs of root tasks
+			for name, pkg := range g.WorkspaceInfos.PackageJSONs {
+				if name != util.RootPkgName {
+					globs.Exclusions = append(globs.Exclusions, filepath.Join(pkg.Dir.ToStringDuringMigration(), "**"))
+				}
+			}
+		}
+		inputs[packageName] = fs.TaskOutputs{
+			Inclusions: util.SetFromStrings(globs.Inclusions).UnsafeListOfStrings(),
+			Exclusions: util.SetFromStrings(globs.Exclusions).UnsafeListOfStrings(),
+		}
+	}
+
+	return &watchedRun{
+		workspaceGraph: &g.WorkspaceGraph,
+		packages:       packages.Copy(),
+		inputs:         inputs,
+	}, nil
+}
+
+// scopeOptsFor returns the scope to run the packages with changed inputs in,
+// along with their dependents, as their tasks consume the outputs of the
+// changed packages. The engine orders the tasks by their dependencies.
+// Changes to the root package may affect any package, so they run the original
+// scope again. So do changes that affect no package in scope, as an empty filter
+// would select every package.
+func (w *watchedRun) scopeOptsFor(original scope.Opts, changed util.Set) (scope.Opts, error) {
+	if changed.Includes(util.RootPkgName) {
+		return original, nil
+	}
+	affected := make(util.Set)
+	for _, pkg := range changed.UnsafeListOfStrings() {
+		affected.Add(pkg)
+		dependents, err := w.workspaceGraph.Descendents(pkg)
+		if err != nil {
+			return scope.Opts{}, errors.Wrapf(err, "failed to get dependents of package %v", pkg)
+		}
+		for dependent := range dependents {
+			affected.Add(dependent)
+		}
+	}
+	filterPatterns := affected.Intersection(w.packages).UnsafeListOfStrings()
+	if len(filterPatterns) == 0 {
+		return original, nil
+	}
+	sort.Strings(filterPatterns)
+	return scope.Opts{
+		IgnorePatterns:    original.IgnorePatterns,
+		GlobalDepPatterns: original.GlobalDepPatterns,
+		FilterPatterns:    filterPatterns,
+	}, nil
+}
+
+// _unwatchTimeout bounds deregistering the inputs of a watch session from the
+// daemon. The context of the session is usually cancelled by then.
+var _unwatchTimeout = 5 * time.Second
+
+// inputWatcher is the part of the daemon client that watch mode uses to track
+// the inputs of packages
+type inputWatcher interface {
+	runcache.OutputWatcher
+	UnwatchGlobs(ctx gocontext.Context, hashes []string) error
+}
+
+// watchSession tracks the inputs a watch loop registered with the daemon. The
+// registrations are keyed by this process, so they are removed again when the
+// loop exits rather than left behind in the long-lived daemon.
+type watchSession struct {
+	inputWatcher inputWatcher
+	debounce     time.Duration
+	keyPrefix    string
+	// watched is the first run, which decides which packages are watched
+	watched    *watchedRun
+	registered util.Set
+}
+
+func newWatchSession(inputWatcher inputWatcher, debounce time.Duration) *watchSession {
+	return &watchSession{
+		inputWatcher: inputWatcher,
+		debounce:     debounce,
+		keyPrefix:    fmt.Sprintf("watch-%v", os.Getpid()),
+		registered:   make(util.Set),
+	}
+}
+
+func (s *watchSession) key(packageName string) string {
+	return fmt.Sprintf("%v-%v", s.keyPrefix, packageName)
+}
+
+// prepare registers the inputs of the packages of a run with the daemon. Runs
+// call it before any of their tasks execute, so that files saved while the
+// tasks execute count as changes and trigger another run.
+func (s *watchSession) prepare(ctx gocontext.Context, watched *watchedRun) error {
+	if s.watched == nil {
+		s.watched = watched
+	}
+	for packageName, globs := range watched.inputs {
+		key := s.key(packageName)
+		if err := s.inputWatcher.NotifyOutputsWritten(ctx, key, globs, 0); err != nil {
+			return errors.Wrapf(err, "failed to watch the inputs of %v", packageName)
+		}
+		s.registered.Add(key)
+	}
+	return nil
+}
+
+// waitForChanges waits until the inputs of any watched package changed since
+// they were last registered. Changes keep being collected until none arrived
+// for the debounce duration, so e.g. saving several files at once triggers a
+// single run.
+func (s *watchSession) waitForChanges(ctx gocontext.Context) (util.Set, error) {
+	changed := make(util.Set)
+	for {
+		select {
+		case <-ctx.Done():
+			return nil, ctx.Err()
+		case <-time.After(s.debounce):
+		}
+
+		changedBefore := changed.Len()
+		for packageName, globs := range s.watched.inputs {
+			if changed.Includes(packageName) {
+				continue
+			}
+			changedGlobs, _, err := s.inputWatcher.GetChangedOutputs(ctx, s.key(packageName), globs.Inclusions)
+			if err != nil {
+				return nil, errors.Wrapf(err, "failed to check the inputs of %v for changes", packageName)
+			}
+			if len(changedGlobs) > 0 {
+				changed.Add(packageName)
+			}
+		}
+		if changed.Len() > 0 && changed.Len() == changedBefore {
+			return changed, nil
+		}
+	}
+}
+
+// unwatch removes every registration of this session from the daemon
+func (s *watchSession) unwatch() error {
+	if s.registered.Len() == 0 {
+		return nil
+	}
+	ctx, cancel := gocontext.WithTimeout(gocontext.Background(), _unwatchTimeout)
+	defer cancel()
+	if err := s.inputWatcher.UnwatchGlobs(ctx, s.registered.UnsafeListOfStrings()); err != nil {
+		return errors.Wrap(err, "failed to stop watching inputs")
+	}
+	s.registered = make(util.Set)
+	return nil
+}
+
+// loop calls runOnce, then calls rescope with the packages whose inputs changed
+// and runOnce again, until ctx is cancelled. runOnce must call prepare before
+// any task executes. The registrations of the session are removed when the loop
+// exits.
+func (s *watchSession) loop(ctx gocontext.Context, runOnce func() error, rescope func(changed util.Set) error) (err error) {
+	defer func() {
+		if unwatchErr := s.unwatch(); err == nil {
+			err = unwatchErr
+		}
+	}()
+	for {
+		if err := runOnce(); err != nil {
+			return err
+		}
+		changed, err := s.waitForChanges(ctx)
+		if err != nil {
+			if ctx.Err() != nil {
+				// Interrupted
+				return nil
+			}
+			return err
+		}
+		if err := rescope(changed); err != nil {
+			return err
+		}
+	}
+}
+
+// watch runs the targets, then keeps running them again for the packages whose
+// task inputs changed and their dependents, until it is interrupted. Changes
+// are detected by the file watcher of the daemon.
+func (r *run) watch(ctx gocontext.Context, targets []string, executionState *turbostate.ExecutionState) error {
+	if r.opts.runOpts.NoDaemon {
+		return errors.New("--watch requires the daemon and cannot be used with --no-daemon")
+	}
+	if r.opts.runOpts.DryRun || r.opts.runOpts.GraphDot || r.opts.runOpts.GraphFile != "" {
+		return errors.New("--watch cannot be used with --dry-run or --graph")
+	}
+	turbodClient, err := daemon.GetClient(ctx, r.base.RepoRoot, r.base.Logger, r.base.TurboVersion, daemon.ClientOpts{})
+	if err != nil {
+		return errors.Wrap(err, "failed to contact turbod, which --watch requires")
+	}
+	defer func() { _ = turbodClient.Close() }()
+	session := newWatchSession(daemonclient.New(turbodClient), r.opts.runOpts.WatchDebounce)
+	r.onWatchedRun = func(watched *watchedRun) error {
+		return session.prepare(ctx, watched)
+	}
+
+	originalScopeOpts := r.opts.scopeOpts
+	runOnce := func() error {
+		if err := r.run(ctx, targets, executionState); err != nil {
+			if session.watched == nil {
+				return err
+			}
+			// Keep watching, the next change may fix the failure
+			r.base.LogError("run failed: %v", err)
+		}
+		r.base.UI.Output(ui.Dim("• Watching for changes..."))
+		return nil
+	}
+	// Later runs only cover the packages affected by a change
+	rescope := func(changed util.Set) error {
+		scopeOpts, err := session.watched.scopeOptsFor(originalScopeOpts, changed)
+		if err != nil {
+			return err
+		}
+		r.opts.scopeOpts = scopeOpts
+		return nil
+	}
+	return session.loop(ctx, runOnce, rescope)
+}
@@ -0,0 +1,188 @@
+package run
+
+import (
+	"context"
+	"reflect"
+	"testing"
+	"time"
+
+	"github.com/vercel/turbo/cli/internal/fs"
+	"github.com/vercel/turbo/cli/internal/scope"
+	"github.com/vercel/turbo/cli/internal/util"
+
+	"github.com/pyr-sh/dag"
+)
+
+func TestWatchedRunScopeOptsFor(t *testing.T) {
+	// web depends on ui, which depends on config. docs only depends on config,
+	// and isn't in scope.
+	workspaceGraph := &dag.AcyclicGraph{}
+	for _, pkg := range []string{"web", "ui", "config", "docs", util.RootPkgName} {
+		workspaceGraph.Add(pkg)
+	}
+	workspaceGraph.Connect(dag.BasicEdge("web", "ui"))
+	workspaceGraph.Connect(dag.BasicEdge("ui", "config"))
+	workspaceGraph.Connect(dag.BasicEdge("docs", "config"))
+
+	watched := &watchedRun{
+		workspaceGraph: workspaceGraph,
+		packages:       util.SetFromStrings([]string{"web", "ui", "config", util.RootPkgName}),
+	}
+	original := scope.Opts{
+		FilterPatterns:    []string{"web..."},
+		IgnorePatterns:    []string{"*.md"},
+		GlobalDepPatterns: []string{".env"},
+		Affected:          true,
+	}
+
+	testCases := []struct {
+		name     string
+		changed  []string
+		expected []string
+	}{
+		{
+			name:     "leaf package",
+			changed:  []string{"web"},
+			expected: []string{"web"},
+		},
+		{
+			name:     "dependents in scope",
+			changed:  []string{"config"},
+			expected: []string{"config", "ui", "web"},
+		},
+		{
+			name:     "multiple packages",
+			changed:  []string{"ui", "web"},
+			expected: []string{"ui", "web"},
+		},
+	}
+
+	for _, tc := range testCases {
+		t.Run(tc.name, func(t *testing.T) {
+			opts, err := watched.scopeOptsFor(original, util.SetFromStrings(tc.changed))
+			if err != nil {
+				t.Fatalf("scopeOptsFor: %v", err)
+			}
+			expected := scope.Opts{
+				FilterPatterns:    tc.expected,
+				IgnorePatterns:    original.IgnorePatterns,
+				GlobalDepPatterns: original.GlobalDepPatterns,
+			}
+			if !reflect.DeepEqual(opts, expected) {
+				t.Errorf("got %+v, want %+v", opts, expected)
+			}
+		})
+	}
+
+	t.Run("root package", func(t *testing.T) {
+		opts, err := watched.scopeOptsFor(original, util.SetFromStrings([]string{"ui", util.RootPkgName}))
+		if err != nil {
+			t.Fatalf("scopeOptsFor: %v", err)
+		}
+		if !reflect.DeepEqual(opts, original) {
+			t.Errorf("got %+v, want the original scope %+v", opts, original)
+		}
+	})
+
+	t.Run("package out of scope", func(t *testing.T) {
+		opts, err := watched.scopeOptsFor(original, util.SetFromStrings([]string{"docs"}))
+		if err != nil {
+			t.Fatalf("scopeOptsFor: %v", err)
+		}
+		if !reflect.DeepEqual(opts, original) {
+			t.Errorf("got %+v, want the original scope %+v", opts, original)
+		}
+	})
+}
+
+// fakeInputWatcher stands in for the daemon: it reports the globs of a key as
+// changed once change was called for it after the key was registered
+type fakeInputWatcher struct {
+	registered map[string]fs.TaskOutputs
+	changed    util.Set
+}
+
+var _ inputWatcher = (*fakeInputWatcher)(nil)
+
+func (f *fakeInputWatcher) NotifyOutputsWritten(_ context.Context, hash string, globs fs.TaskOutputs, _ int) error {
+	f.registered[hash] = globs
+	f.changed.Delete(hash)
+	return nil
+}
+
+func (f *fakeInputWatcher) GetChangedOutputs(_ context.Context, hash string, globs []string) ([]string, int, error) {
+	if _, ok := f.registered[hash]; !ok || f.changed.Includes(hash) {
+		return globs, 0, nil
+	}
+	return nil, 0, nil
+}
+
+func (f *fakeInputWatcher) UnwatchGlobs(_ context.Context, hashes []string) error {
+	for _, hash := range hashes {
+		delete(f.registered, hash)
+		f.changed.Delete(hash)
+	}
+	return nil
+}
+
+func (f *fakeInputWatcher) change(hash string) {
+	if _, ok := f.registered[hash]; ok {
+		f.changed.Add(hash)
+	}
+}
+
+func TestWatchSessionChangeDuringRun(t *testing.T) {
+	workspaceGraph := &dag.AcyclicGraph{}
+	workspaceGraph.Add("web")
+	workspaceGraph.Add("ui")
+	workspaceGraph.Connect(dag.BasicEdge("web", "ui"))
+	watched := &watchedRun{
+		workspaceGraph: workspaceGraph,
+		packages:       util.SetFromStrings([]string{"web", "ui"}),
+		inputs: map[string]fs.TaskOutputs{
+			"web": {Inclusions: []string{"apps/web/**"}},
+			"ui":  {Inclusions: []string{"packages/ui/**"}},
+		},
+	}
+	inputs := &fakeInputWatcher{
+		registered: make(map[string]fs.TaskOutputs),
+		changed:    make(util.Set),
+	}
+	session := newWatchSession(inputs, time.Millisecond)
+
+	ctx, cancel := context.WithCancel(context.Background())
+	defer cancel()
+	runs := 0
+	runOnce := func() error {
+		runs++
+		if runs == 1 {
+			if err := session.prepare(ctx, watched); err != nil {
+				return err
+			}
+			// A file is saved while the tasks of the first run execute
+			inputs.change(session.key("ui"))
+		} else {
+			cancel()
+		}
+		return nil
+	}
+	var rescoped []util.Set
+	rescope := func(changed util.Set) error {
+		rescoped = append(rescoped, changed)
+		return nil
+	}
+
+	if err := session.loop(ctx, runOnce, rescope); err != nil {
+		t.Fatalf("loop: %v", err)
+	}
+	if runs != 2 {
+		t.Errorf("got %v runs, want 2", runs)
+	}
+	expected := []util.Set{util.SetFromStrings([]string{"ui"})}
+	if !reflect.DeepEqual(rescoped, expected) {
+		t.Errorf("rescoped with %v, want %v", rescoped, expected)
+	}
+	if len(inputs.registered) != 0 {
+		t.Errorf("inputs still registered after the loop exited: %v", inputs.registered)
+	}
+}
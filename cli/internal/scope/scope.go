@@ -54,6 +54,10 @@ type Opts struct {
 	GlobalDepPatterns []string
 	// Patterns are the filter patterns supplied to --filter on the commandline
 	FilterPatterns []string
+	// Affected is whether to only select the packages changed since the merge base
+	// with the SCM base ref, and their dependents. It narrows the packages selected
+	// by the other filters rather than adding to them.
+	Affected bool
 
 	PackageInferenceRoot turbopath.RelativeSystemPath
 }
@@ -86,6 +90,7 @@ func resolvePackageInferencePath(raw string) (turbopath.RelativeSystemPath, erro
 // OptsFromArgs adds the settings relevant to this package to the given Opts
 func OptsFromArgs(opts *Opts, args *turbostate.ParsedArgsFromRust) error {
 	opts.FilterPatterns = args.Command.Run.Filter
+	opts.Affected = args.Command.Run.Affected
 	opts.IgnorePatterns = args.Command.Run.Ignore
 	opts.GlobalDepPatterns = args.Command.Run.GlobalDeps
 	pkgInferenceRoot, err := resolvePackageInferencePath(args.Command.Run.PkgInferenceRoot)
@@ -133,6 +138,22 @@ func (l *LegacyFilter) AsFilterPatterns() []string {
 	return patterns
 }
 
+// affectedFilterPattern selects the packages changed between the merge base with the
+// SCM base ref and the SCM head ref, including uncommitted changes, and their dependents.
+// The refs default to main and HEAD, and can be set with TURBO_SCM_BASE and TURBO_SCM_HEAD,
+// e.g. to the target branch of a pull request in CI.
+func affectedFilterPattern() string {
+	base := os.Getenv("TURBO_SCM_BASE")
+	if base == "" {
+		base = "main"
+	}
+	head := os.Getenv("TURBO_SCM_HEAD")
+	if head == "" {
+		head = "HEAD"
+	}
+	return fmt.Sprintf("...[%v...%v]", base, head)
+}
+
 // ResolvePackages translates specified flags to a set of entry point packages for
 // the selected tasks. Returns the selected packages and whether or not the selected
 // packages represents a default "all packages".
@@ -151,11 +172,25 @@ func ResolvePackages(opts *Opts, repoRoot turbopath.AbsoluteSystemPath, scm scm.
 	filterPatterns := opts.FilterPatterns
 	legacyFilterPatterns := opts.LegacyFilter.AsFilterPatterns()
 	filterPatterns = append(filterPatterns, legacyFilterPatterns...)
-	isAllPackages := len(filterPatterns) == 0 && opts.PackageInferenceRoot == ""
+	hasFilters := len(filterPatterns) > 0 || opts.PackageInferenceRoot != ""
+	isAllPackages := !hasFilters && !opts.Affected
 	filteredPkgs, err := filterResolver.GetPackagesFromPatterns(filterPatterns)
 	if err != nil {
 		return nil, false, err
 	}
+	if opts.Affected {
+		// Filter patterns are unioned, so --affected can't just be one more pattern:
+		// it narrows whatever the other filters select down to the affected packages.
+		affectedPkgs, err := filterResolver.GetPackagesFromPatterns([]string{affectedFilterPattern()})
+		if err != nil {
+			return nil, false, err
+		}
+		if hasFilters {
+			filteredPkgs = filteredPkgs.Intersection(affectedPkgs)
+		} else {
+			filteredPkgs = affectedPkgs
+		}
+	}
 
 	if isAllPackages {
 		// no filters specified, run every package
@@ -225,6 +225,8 @@ func TestResolvePackages(t *testing.T) {
 		expected            []string
 		expectAllPackages   bool
 		scope               []string
+		filter              []string
+		affected            bool
 		since               string
 		ignore              string
 		globalDeps          []string
@@ -458,6 +460,27 @@ func TestResolvePackages(t *testing.T) {
 			}),
 			since: "dummy",
 		},
+		{
+			name:     "Affected packages and their dependents",
+			changed:  []string{"libs/libA/src/index.ts"},
+			expected: []string{"libA", "app0", "app1"},
+			affected: true,
+		},
+		{
+			// --filter=app1 --affected must not run app1 just because it matches the filter
+			name:     "Filtered package is not affected",
+			changed:  []string{"libs/libC/src/index.ts"},
+			expected: []string{},
+			filter:   []string{"app1"},
+			affected: true,
+		},
+		{
+			name:     "Filtered packages narrowed to the affected ones",
+			changed:  []string{"libs/libA/src/index.ts"},
+			expected: []string{"app1"},
+			filter:   []string{"app1", "app2"},
+			affected: true,
+		},
 		{
 			name:         "Infer app2 from directory",
 			inferPkgPath: "app/app2",
@@ -521,6 +544,8 @@ func TestResolvePackages(t *testing.T) {
 					IncludeDependencies: tc.includeDependencies,
 					SkipDependents:      !tc.includeDependents,
 				},
+				FilterPatterns:       tc.filter,
+				Affected:             tc.affected,
 				IgnorePatterns:       []string{tc.ignore},
 				GlobalDepPatterns:    tc.globalDeps,
 				PackageInferenceRoot: pkgInferenceRoot,
@@ -548,3 +573,17 @@ func TestResolvePackages(t *testing.T) {
 		})
 	}
 }
+
+func TestAffectedFilterPattern(t *testing.T) {
+	t.Setenv("TURBO_SCM_BASE", "")
+	t.Setenv("TURBO_SCM_HEAD", "")
+	if pattern := affectedFilterPattern(); pattern != "...[main...HEAD]" {
+		t.Errorf("affectedFilterPattern got %v, want ...[main...HEAD]", pattern)
+	}
+
+	t.Setenv("TURBO_SCM_BASE", "origin/release")
+	t.Setenv("TURBO_SCM_HEAD", "feature")
+	if pattern := affectedFilterPattern(); pattern != "...[origin/release...feature]" {
+		t.Errorf("affectedFilterPattern got %v, want ...[origin/release...feature]", pattern)
+	}
+}
@@ -171,6 +171,18 @@ func (s *Server) GetChangedOutputs(ctx context.Context, req *turbodprotocol.GetC
 	}, nil
 }
 
+// UnwatchGlobs implements the UnwatchGlobs rpc from turbo.proto
+func (s *Server) UnwatchGlobs(ctx context.Context, req *turbodprotocol.UnwatchGlobsRequest) (*turbodprotocol.UnwatchGlobsResponse, error) {
+	s.timeSavedMu.Lock()
+	for _, hash := range req.Hashes {
+		delete(s.timesSaved, hash)
+	}
+	s.timeSavedMu.Unlock()
+
+	s.globWatcher.UnwatchGlobs(req.Hashes)
+	return &turbodprotocol.UnwatchGlobsResponse{}, nil
+}
+
 // Hello implements the Hello rpc from turbo.proto
 func (s *Server) Hello(ctx context.Context, req *turbodprotocol.HelloRequest) (*turbodprotocol.HelloResponse, error) {
 	clientVersion := req.Version
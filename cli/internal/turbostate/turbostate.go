@@ -23,6 +23,7 @@ type PrunePayload struct {
 
 // RunPayload is the extra flags passed for the `run` subcommand
 type RunPayload struct {
+	Affected           bool         `json:"affected"`
 	CacheDir           string       `json:"cache_dir"`
 	CacheWorkers       int          `json:"cache_workers"`
 	Concurrency        string       `json:"concurrency"`
@@ -58,6 +59,8 @@ type RunPayload struct {
 	Tasks               []string `json:"tasks"`
 	PkgInferenceRoot    string   `json:"pkg_inference_root"`
 	LogPrefix           string   `json:"log_prefix"`
+	Watch               bool     `json:"watch"`
+	WatchDebounce       int      `json:"watch_debounce"`
 	ExperimentalSpaceID string   `json:"experimental_space_id"`
 }
 
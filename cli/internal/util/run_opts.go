@@ -1,6 +1,9 @@
 package util
 
-import "strings"
+import (
+	"strings"
+	"time"
+)
 
 // EnvMode specifies if we will be using strict env vars
 type EnvMode string
@@ -51,5 +54,10 @@ type RunOpts struct {
 	// Whether turbo should create a run summary
 	Summarize bool
 
+	// Whether turbo should keep running and re-run tasks whose inputs changed
+	Watch bool
+	// How long to wait for further changes before re-running tasks in watch mode
+	WatchDebounce time.Duration
+
 	ExperimentalSpaceID string
 }
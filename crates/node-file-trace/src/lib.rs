@@ -13,7 +13,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 #[cfg(feature = "cli")]
 use clap::Parser;
 #[cfg(feature = "node-api")]
@@ -45,7 +45,7 @@ use turbopack_core::{
     compile_time_info::CompileTimeInfo,
     context::{AssetContext, AssetContextVc},
     environment::{EnvironmentIntention, EnvironmentVc, ExecutionEnvironment, NodeJsEnvironment},
-    issue::{IssueContextExt, IssueReporter, IssueSeverity, IssueVc},
+    issue::{export::IssuesExport, IssueContextExt, IssueReporter, IssueSeverity, IssueVc},
     reference::all_assets,
     resolve::options::{ImportMapping, ResolvedMap},
     source_asset::SourceAssetVc,
@@ -60,12 +60,16 @@ use crate::nft_json::NftJsonAssetVc;
     derive(Serialize, Deserialize),
     serde(rename_all = "camelCase")
 )]
-#[derive(Debug, Clone)]
-struct CacheArgs {
-    #[clap(long)]
+#[derive(Debug, Clone, Default)]
+pub struct CacheArgs {
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "node-api", serde(default))]
+    /// Persist results to this file and reuse them in the next run.
     cache: Option<String>,
 
-    #[clap(long)]
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "node-api", serde(default))]
+    /// Wait until all results are persisted before exiting.
     cache_fully: bool,
 }
 
@@ -124,6 +128,17 @@ pub struct CommonArgs {
     /// Expand the log details.
     log_detail: bool,
 
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "node-api", serde(default))]
+    /// Fail when there are issues at least as severe as this, e.g. `warning`.
+    /// Defaults to `fatal`.
+    fail_on: Option<IssueSeverityCliOption>,
+
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "node-api", serde(default))]
+    /// Write all issues as JSON to this file.
+    issues_json: Option<PathBuf>,
+
     /// Whether to skip the glob logic
     /// assume the provided input is not glob even if it contains `*` and `[]`
     #[cfg_attr(feature = "cli", clap(short, long))]
@@ -327,15 +342,16 @@ pub async fn start(
     #[cfg(feature = "persistent_cache")]
     if let Some(cache) = cache {
         use tokio::time::timeout;
+        use turbo_tasks_file_store::FileStorePersistedGraph;
         use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
-        use turbo_tasks_rocksdb::RocksDbPersistedGraph;
 
-        run(
-            &args,
+        let cache_fully = *cache_fully;
+        return run(
+            args.clone(),
             || {
                 let start = Instant::now();
                 let backend = MemoryBackendWithPersistedGraph::new(
-                    RocksDbPersistedGraph::new(cache).unwrap(),
+                    FileStorePersistedGraph::new(cache).unwrap(),
                 );
                 let tt = TurboTasks::new(backend);
                 let elapsed = start.elapsed();
@@ -343,8 +359,9 @@ pub async fn start(
                 tt
             },
             |tt, _, duration| async move {
-                let mut start = Instant::now();
-                if *cache_fully {
+                // Stopping writes the cache
+                let start = Instant::now();
+                if cache_fully {
                     tt.wait_background_done().await;
                     tt.stop_and_wait().await;
                     let elapsed = start.elapsed();
@@ -363,14 +380,11 @@ pub async fn start(
                         println!("flushed cache completely {}", FormatDuration(elapsed));
                     }
                 }
-                start = Instant::now();
-                drop(tt);
-                let elapsed = start.elapsed();
-                println!("writing cache {}", FormatDuration(elapsed));
             },
+            module_options,
+            resolve_options,
         )
         .await;
-        return;
     }
 
     run(
@@ -414,8 +428,12 @@ async fn run<B: Backend + 'static, F: Future<Output = ()>>(
         show_all,
         log_detail,
         log_level,
+        fail_on,
+        ref issues_json,
         ..
     } = args.common();
+    let fail_on = fail_on.map_or_else(|| IssueSeverity::Fatal, |l| l.0);
+    let issues_json = issues_json.clone();
 
     let start = Instant::now();
     let finish = |tt: Arc<TurboTasks<B>>, root_task: TaskId| async move {
@@ -468,6 +486,12 @@ async fn run<B: Backend + 'static, F: Future<Output = ()>>(
     let (sender, mut receiver) = channel(1);
     let dir = current_dir().unwrap();
     let tt = create_tt();
+    // A restored persistent cache executes session dependent tasks, e.g. file
+    // reads, again on startup. Wait for them, so changes since the last run
+    // invalidate the restored results before they are used.
+    if tt.get_in_progress_count() != 0 {
+        tt.get_or_wait_aggregated_update_info(Duration::ZERO).await;
+    }
     let module_options = TransientInstance::new(module_options.unwrap_or_default());
     let resolve_options = TransientInstance::new(resolve_options.unwrap_or_default());
     let log_options = TransientInstance::new(LogOptions {
@@ -476,6 +500,7 @@ async fn run<B: Backend + 'static, F: Future<Output = ()>>(
         show_all,
         log_detail,
         log_level: log_level.map_or_else(|| IssueSeverity::Error, |l| l.0),
+        fail_on,
     });
     let task = tt.spawn_root_task(move || {
         let dir = dir.clone();
@@ -484,6 +509,7 @@ async fn run<B: Backend + 'static, F: Future<Output = ()>>(
         let module_options = module_options.clone();
         let resolve_options = resolve_options.clone();
         let log_options = log_options.clone();
+        let issues_json = issues_json.clone();
         Box::pin(async move {
             let output = main_operation(
                 TransientValue::new(dir.clone()),
@@ -499,9 +525,20 @@ async fn run<B: Backend + 'static, F: Future<Output = ()>>(
                 .await?;
 
             let console_ui = ConsoleUiVc::new(log_options);
-            console_ui
+            let has_failing = console_ui
                 .as_issue_reporter()
-                .report_issues(TransientInstance::new(issues), source);
+                .report_issues(TransientInstance::new(issues.clone()), source);
+
+            if let Some(issues_json) = &issues_json {
+                let plain_issues = issues.get_plain_issues().await?;
+                let export = IssuesExport::new(&plain_issues, fail_on);
+                fs::write(issues_json, export.to_json()?)
+                    .with_context(|| format!("writing {}", issues_json.display()))?;
+            }
+
+            if *has_failing.await? {
+                bail!("Issues with severity {} or higher occurred", fail_on);
+            }
 
             if has_return_value {
                 let output_read_ref = output.await?;
@@ -1,5 +1,7 @@
 #![feature(min_specialization)]
 
+pub mod remote_fs;
+
 use anyhow::Result;
 use turbo_tasks::primitives::{OptionStringVc, StringVc};
 use turbo_tasks_fs::FileSystemPathVc;
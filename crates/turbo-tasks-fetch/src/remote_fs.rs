@@ -0,0 +1,289 @@
+use std::{collections::HashSet, io::ErrorKind, path::PathBuf, sync::Mutex};
+
+use anyhow::{bail, Context, Result};
+use mime::Mime;
+use reqwest::{header, StatusCode};
+use turbo_tasks::{
+    get_invalidator, mark_stateful, primitives::StringVc, CompletionVc, Invalidator, ValueToString,
+    ValueToStringVc,
+};
+use turbo_tasks_fs::{
+    DirectoryContentVc, File, FileContent, FileContentVc, FileMetaVc, FileSystem, FileSystemPathVc,
+    FileSystemVc, LinkContent, LinkContentVc,
+};
+use turbo_tasks_hash::hash_xxh3_hash64;
+use turbopack_core::{issue::IssueSeverity, read_only_fs::ReadOnlyWriteIssueVc};
+
+use crate::FetchError;
+
+/// A read-only [FileSystem] that fetches files over HTTP(S), for URL imports
+/// and resolving sources from a remote server.
+///
+/// Paths are joined to the base URL. Responses are cached on disk along with
+/// their `ETag`, which is sent as `If-None-Match` when the file is fetched
+/// again. When the server can't be reached, the cached response is served
+/// and a warning is emitted.
+///
+/// Reads are only repeated after [RemoteFileSystemVc::revalidate] was called.
+/// HTTP has no directory listings, so directories are always empty.
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+pub struct RemoteFileSystem {
+    /// The URL that paths are relative to, e.g. `https://example.com/src`
+    base_url: String,
+    /// The directory on disk that responses are cached in
+    cache_dir: String,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    invalidators: Mutex<HashSet<Invalidator>>,
+}
+
+/// A response stored in the cache directory
+struct CachedResponse {
+    body: Vec<u8>,
+    etag: Option<String>,
+    content_type: Option<String>,
+}
+
+#[turbo_tasks::value_impl]
+impl RemoteFileSystemVc {
+    /// Creates a file system for the files below `base_url`, caching
+    /// responses in `cache_dir`. File systems with the same arguments are the
+    /// same instance.
+    #[turbo_tasks::function]
+    pub fn new(base_url: String, cache_dir: String) -> Self {
+        mark_stateful();
+        Self::cell(RemoteFileSystem {
+            base_url,
+            cache_dir,
+            invalidators: Mutex::new(HashSet::new()),
+        })
+    }
+}
+
+impl RemoteFileSystemVc {
+    /// Fetches all files again on their next read. Files with an unchanged
+    /// `ETag` keep their content, so only tasks that depend on files that
+    /// changed are recomputed.
+    pub async fn revalidate(self) -> Result<()> {
+        let this = self.await?;
+        let invalidators = std::mem::take(&mut *this.invalidators.lock().unwrap());
+        for invalidator in invalidators {
+            invalidator.invalidate();
+        }
+        Ok(())
+    }
+}
+
+impl RemoteFileSystem {
+    /// registers the current task to be invalidated on `revalidate`, has to be
+    /// called within a turbo-tasks function
+    fn register_invalidator(&self) {
+        self.invalidators.lock().unwrap().insert(get_invalidator());
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// The path of the cached body for `url`. The headers are stored next to
+    /// it with a `.headers` extension.
+    fn cache_path(&self, url: &str) -> PathBuf {
+        PathBuf::from(&self.cache_dir).join(format!("{:016x}", hash_xxh3_hash64(url)))
+    }
+
+    async fn read_cache(&self, url: &str) -> Result<Option<CachedResponse>> {
+        let path = self.cache_path(url);
+        let body = match tokio::fs::read(&path).await {
+            Ok(body) => body,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("reading cached {}", path.display())),
+        };
+        let headers_path = path.with_extension("headers");
+        let headers = match tokio::fs::read_to_string(&headers_path).await {
+            Ok(headers) => headers,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("reading cached {}", headers_path.display()))
+            }
+        };
+        let mut cached = CachedResponse {
+            body,
+            etag: None,
+            content_type: None,
+        };
+        for line in headers.lines() {
+            match line.split_once(": ") {
+                Some(("etag", etag)) => cached.etag = Some(etag.to_string()),
+                Some(("content-type", content_type)) => {
+                    cached.content_type = Some(content_type.to_string())
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(cached))
+    }
+
+    async fn write_cache(&self, url: &str, response: &CachedResponse) -> Result<()> {
+        let path = self.cache_path(url);
+        tokio::fs::create_dir_all(&self.cache_dir)
+            .await
+            .with_context(|| format!("creating cache directory {}", self.cache_dir))?;
+        let mut headers = String::new();
+        if let Some(etag) = &response.etag {
+            headers.push_str(&format!("etag: {etag}\n"));
+        }
+        if let Some(content_type) = &response.content_type {
+            headers.push_str(&format!("content-type: {content_type}\n"));
+        }
+        // The body is written first. If writing the headers fails, the body
+        // is paired with an older `ETag`, which only causes a full refetch.
+        tokio::fs::write(&path, &response.body)
+            .await
+            .with_context(|| format!("writing cached {}", path.display()))?;
+        let headers_path = path.with_extension("headers");
+        tokio::fs::write(&headers_path, headers)
+            .await
+            .with_context(|| format!("writing cached {}", headers_path.display()))?;
+        Ok(())
+    }
+
+    /// Fetches `path`, revalidating a cached response if there is one.
+    /// Returns `None` when the file doesn't exist.
+    async fn fetch(&self, fs_path: FileSystemPathVc) -> Result<Option<CachedResponse>> {
+        let url = self.url(&fs_path.await?.path);
+        let cached = self.read_cache(&url).await?;
+
+        let client = reqwest::Client::new();
+        let mut request = client.get(&url);
+        if let Some(etag) = cached.as_ref().and_then(|cached| cached.etag.as_ref()) {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await.and_then(|response| {
+            if response.status() == StatusCode::NOT_FOUND {
+                Ok(response)
+            } else {
+                response.error_for_status()
+            }
+        });
+        let error = match response {
+            Ok(response) if response.status() == StatusCode::NOT_FOUND => return Ok(None),
+            Ok(response) if response.status() == StatusCode::NOT_MODIFIED => {
+                if cached.is_some() {
+                    return Ok(cached);
+                }
+                bail!("received 304 Not Modified for {url}, which isn't cached");
+            }
+            Ok(response) => {
+                let header_value = |name: header::HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(|value| value.to_string())
+                };
+                let etag = header_value(header::ETAG);
+                let content_type = header_value(header::CONTENT_TYPE);
+                match response.bytes().await {
+                    Ok(body) => {
+                        let fetched = CachedResponse {
+                            body: body.to_vec(),
+                            etag,
+                            content_type,
+                        };
+                        self.write_cache(&url, &fetched).await?;
+                        return Ok(Some(fetched));
+                    }
+                    Err(err) => err,
+                }
+            }
+            Err(err) => err,
+        };
+
+        // Serve the cached response while offline, but make sure it's noticed
+        let severity = if cached.is_some() {
+            IssueSeverity::Warning
+        } else {
+            IssueSeverity::Error
+        };
+        FetchError::from_reqwest_error(&error, &url)
+            .cell()
+            .to_issue(severity.cell(), fs_path)
+            .as_issue()
+            .emit();
+        Ok(cached)
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for RemoteFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        self.register_invalidator();
+        let Some(response) = self.fetch(fs_path).await? else {
+            return Ok(FileContent::NotFound.cell());
+        };
+        let mut file = File::from(response.body);
+        if let Some(content_type) = response
+            .content_type
+            .and_then(|content_type| content_type.parse::<Mime>().ok())
+        {
+            file = file.with_content_type(content_type);
+        }
+        Ok(FileContent::Content(file).cell())
+    }
+
+    #[turbo_tasks::function]
+    fn read_link(&self, _fs_path: FileSystemPathVc) -> LinkContentVc {
+        LinkContent::NotFound.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn read_dir(&self, _fs_path: FileSystemPathVc) -> DirectoryContentVc {
+        DirectoryContentVc::not_found()
+    }
+
+    #[turbo_tasks::function]
+    fn track(&self, _fs_path: FileSystemPathVc) -> CompletionVc {
+        self.register_invalidator();
+        CompletionVc::new()
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        ReadOnlyWriteIssueVc::emit_for_write(fs_path, content).await?;
+        Ok(CompletionVc::immutable())
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        ReadOnlyWriteIssueVc::emit_for_write_link(fs_path, target).await?;
+        Ok(CompletionVc::immutable())
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let content = fs_path.read().await?;
+        let FileContent::Content(file) = &*content else {
+            bail!(
+                "reading metadata for {}: not found",
+                self.url(&fs_path.await?.path)
+            );
+        };
+        Ok(file.meta().clone().cell())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for RemoteFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> StringVc {
+        StringVc::cell(self.base_url.clone())
+    }
+}
@@ -0,0 +1,107 @@
+#![cfg(test)]
+
+use turbo_tasks_fetch::{register, remote_fs::RemoteFileSystemVc};
+use turbo_tasks_fs::{FileContent, FileSystem, FileSystemVc};
+use turbo_tasks_testing::{register, run};
+
+register!();
+
+#[tokio::test]
+async fn reads_files() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let resource_mock = server.mock(|when, then| {
+            when.path("/src/index.js");
+            then.status(200)
+                .header("Content-Type", "text/javascript")
+                .body("export default 1");
+        });
+        let cache_dir = tempfile::tempdir()?;
+
+        let fs = RemoteFileSystemVc::new(
+            server.url("/src"),
+            cache_dir.path().to_string_lossy().to_string(),
+        );
+        let root = FileSystemVc::from(fs).root();
+        let FileContent::Content(file) = &*root.join("index.js").read().await? else {
+            panic!()
+        };
+        resource_mock.assert();
+        assert_eq!(file.content().to_str()?, "export default 1");
+        assert_eq!(file.content_type().map(|mime| mime.essence_str()), Some("text/javascript"));
+
+        assert!(matches!(&*root.join("missing.js").read().await?, FileContent::NotFound));
+    }
+}
+
+#[tokio::test]
+async fn revalidates_with_etag() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let mut resource_mock = server.mock(|when, then| {
+            when.path("/index.js");
+            then.status(200)
+                .header("ETag", "\"v1\"")
+                .body("export default 1");
+        });
+        let cache_dir = tempfile::tempdir()?;
+
+        let fs = RemoteFileSystemVc::new(
+            server.url(""),
+            cache_dir.path().to_string_lossy().to_string(),
+        );
+        let path = FileSystemVc::from(fs).root().join("index.js");
+        assert!(matches!(&*path.read().await?, FileContent::Content(_)));
+        resource_mock.assert();
+
+        resource_mock.delete();
+        let not_modified_mock = server.mock(|when, then| {
+            when.path("/index.js").header("If-None-Match", "\"v1\"");
+            then.status(304);
+        });
+        fs.revalidate().await?;
+        let FileContent::Content(file) = &*path.read().await? else {
+            panic!()
+        };
+        not_modified_mock.assert();
+        assert_eq!(file.content().to_str()?, "export default 1");
+    }
+}
+
+#[tokio::test]
+async fn serves_cached_files_when_offline() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let mut resource_mock = server.mock(|when, then| {
+            when.path("/index.js");
+            then.status(200)
+                .body("export default 1");
+        });
+        let cache_dir = tempfile::tempdir()?;
+
+        let fs = RemoteFileSystemVc::new(
+            server.url(""),
+            cache_dir.path().to_string_lossy().to_string(),
+        );
+        let path = FileSystemVc::from(fs).root().join("index.js");
+        assert!(matches!(&*path.read().await?, FileContent::Content(_)));
+
+        resource_mock.delete();
+        let error_mock = server.mock(|when, then| {
+            when.path("/index.js");
+            then.status(503);
+        });
+        fs.revalidate().await?;
+        let FileContent::Content(file) = &*path.read().await? else {
+            panic!()
+        };
+        error_mock.assert();
+        assert_eq!(file.content().to_str()?, "export default 1");
+    }
+}
@@ -0,0 +1,911 @@
+//! A [PersistedGraph] that is stored in a single file, so a new process can
+//! reuse the task results of a previous one instead of starting cold.
+//!
+//! The graph is kept in memory while the process runs and written to the file
+//! when turbo-tasks is stopped. [TaskId]s are only valid in the process that
+//! created them, so tasks are identified by their [PersistentTaskType] in the
+//! file, which contains the function and its inputs.
+//!
+//! Results of tasks that depend on the outside world, e.g. file reads (see
+//! [turbo_tasks::mark_session_dependent]), are not trusted in a new session.
+//! These tasks are restored as dirty and scheduled on startup. Cells are only
+//! updated when their content changes, so dependent tasks are only
+//! invalidated when e.g. the content of a file has changed.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fs, io,
+    mem::take,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{
+    backend::PersistentTaskType,
+    persisted_graph::{
+        ActivateResult, DeactivateResult, PersistResult, PersistTaskState, PersistedGraph,
+        PersistedGraphApi, ReadTaskState, TaskData,
+    },
+    with_task_id_mapping, CellId, IdMapping, RawVc, TaskId,
+};
+
+/// Changes whenever the layout of [Snapshot] changes
+const FORMAT_VERSION: u32 = 1;
+
+/// The id of a task in the persisted graph. Unlike [TaskId]s, these are stable
+/// between sessions.
+type PersistedId = usize;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+enum PersistedVc {
+    TaskOutput(PersistedId),
+    TaskCell(PersistedId, CellId),
+}
+
+impl PersistedVc {
+    fn task(&self) -> PersistedId {
+        match *self {
+            PersistedVc::TaskOutput(id) | PersistedVc::TaskCell(id, _) => id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedTask {
+    /// The serialized [TaskData], with task ids mapped to persisted ids
+    data: Vec<u8>,
+    children: Vec<PersistedId>,
+    dependencies: Vec<PersistedVc>,
+    dirty: bool,
+    session_dependent: bool,
+}
+
+/// The contents of the file
+#[derive(Deserialize)]
+struct Snapshot {
+    version: u32,
+    executable: String,
+    /// Serialized [PersistentTaskType]s, indexed by persisted id
+    task_types: Vec<Vec<u8>>,
+    tasks: Vec<(PersistedId, PersistedTask)>,
+}
+
+/// Borrowed version of [Snapshot] for writing, with the same layout
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    executable: &'a str,
+    task_types: &'a [Vec<u8>],
+    tasks: Vec<(PersistedId, &'a PersistedTask)>,
+}
+
+#[derive(Default)]
+struct TaskState {
+    /// `None` when the task has an id, e.g. because it's a child of a
+    /// persisted task, but wasn't persisted itself
+    data: Option<PersistedTask>,
+    /// Kept active by the memory graph. This isn't restored from the file, the
+    /// memory graph activates the tasks it needs in a new session.
+    externally_active: bool,
+    /// The number of active persisted tasks that have this task as child
+    active_parents: usize,
+    /// The task has been activated and keeps its children active
+    active: bool,
+}
+
+impl TaskState {
+    fn needs_activation(&self) -> bool {
+        self.externally_active || self.active_parents > 0
+    }
+
+    fn is_dirty(&self) -> bool {
+        matches!(&self.data, Some(data) if data.dirty)
+    }
+
+    fn children(&self) -> &[PersistedId] {
+        self.data
+            .as_ref()
+            .map(|data| data.children.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct Graph {
+    task_types: Vec<Vec<u8>>,
+    ids_by_task_type: HashMap<Vec<u8>, PersistedId>,
+    /// Indexed by persisted id
+    tasks: Vec<TaskState>,
+    dependents: HashMap<PersistedVc, HashSet<PersistedId>>,
+    /// Restored session dependent tasks that need to be executed again
+    session_dependent: Vec<PersistedId>,
+
+    /// The mapping between task ids of this session and persisted ids
+    ids_by_task: HashMap<TaskId, PersistedId>,
+    tasks_by_id: HashMap<PersistedId, TaskId>,
+}
+
+impl Graph {
+    /// Restores the graph from the contents of the file. Starts with an empty
+    /// graph when the file was written by a different build or is inconsistent.
+    fn read(content: &[u8], executable: Option<&str>) -> Self {
+        match postcard::from_bytes::<Snapshot>(content) {
+            Ok(snapshot)
+                if snapshot.version == FORMAT_VERSION
+                    && Some(snapshot.executable.as_str()) == executable =>
+            {
+                Graph::restore(snapshot).unwrap_or_default()
+            }
+            _ => Graph::default(),
+        }
+    }
+
+    /// Returns `None` when the snapshot references a task that doesn't exist or
+    /// contains a task twice
+    fn restore(snapshot: Snapshot) -> Option<Self> {
+        let task_count = snapshot.task_types.len();
+        let mut graph = Graph {
+            ids_by_task_type: snapshot
+                .task_types
+                .iter()
+                .enumerate()
+                .map(|(id, task_type)| (task_type.clone(), id))
+                .collect(),
+            tasks: snapshot
+                .task_types
+                .iter()
+                .map(|_| TaskState::default())
+                .collect(),
+            task_types: snapshot.task_types,
+            ..Default::default()
+        };
+        for (id, mut task) in snapshot.tasks {
+            let is_consistent = id < task_count
+                && task.children.iter().all(|&child| child < task_count)
+                && task
+                    .dependencies
+                    .iter()
+                    .all(|dependency| dependency.task() < task_count);
+            if !is_consistent || graph.tasks[id].data.is_some() {
+                return None;
+            }
+            for &dependency in task.dependencies.iter() {
+                graph.dependents.entry(dependency).or_default().insert(id);
+            }
+            if task.session_dependent {
+                task.dirty = true;
+                graph.session_dependent.push(id);
+            }
+            graph.tasks[id].data = Some(task);
+        }
+        Some(graph)
+    }
+
+    fn snapshot<'a>(&'a self, executable: &'a str) -> SnapshotRef<'a> {
+        SnapshotRef {
+            version: FORMAT_VERSION,
+            executable,
+            task_types: &self.task_types,
+            tasks: self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter_map(|(id, task)| Some((id, task.data.as_ref()?)))
+                .collect(),
+        }
+    }
+
+    fn create_id(&mut self, task_type: Vec<u8>) -> PersistedId {
+        let id = self.task_types.len();
+        self.task_types.push(task_type.clone());
+        self.ids_by_task_type.insert(task_type, id);
+        self.tasks.push(TaskState::default());
+        id
+    }
+
+    /// Marks the tasks that depend on `vc` as dirty. Returns the active ones,
+    /// which need to be executed again.
+    fn make_dependents_dirty(&mut self, vc: PersistedVc) -> Vec<PersistedId> {
+        let mut active_dependents = Vec::new();
+        let Some(dependents) = self.dependents.get(&vc) else {
+            return active_dependents;
+        };
+        for &dependent in dependents.iter() {
+            let task = &mut self.tasks[dependent];
+            if let Some(data) = &mut task.data {
+                if !data.dirty {
+                    data.dirty = true;
+                    if task.active {
+                        active_dependents.push(dependent);
+                    }
+                }
+            }
+        }
+        active_dependents
+    }
+
+    /// Returns true when the task needs to be activated
+    fn increment_active_parents(&mut self, id: PersistedId) -> bool {
+        let task = &mut self.tasks[id];
+        task.active_parents += 1;
+        task.active_parents == 1 && !task.active
+    }
+
+    /// Returns true when the task needs to be deactivated
+    fn decrement_active_parents(&mut self, id: PersistedId) -> bool {
+        let task = &mut self.tasks[id];
+        task.active_parents = task.active_parents.saturating_sub(1);
+        task.active_parents == 0 && task.active
+    }
+}
+
+/// Maps the [TaskId]s of this session to [PersistedId]s while serializing and
+/// back while deserializing
+struct Mapping<'a> {
+    graph: RefCell<&'a mut Graph>,
+    api: &'a dyn PersistedGraphApi,
+    /// A task id couldn't be mapped, e.g. because it's a transient task
+    failed: Cell<bool>,
+}
+
+impl<'a> Mapping<'a> {
+    fn new(graph: &'a mut Graph, api: &'a dyn PersistedGraphApi) -> Self {
+        Self {
+            graph: RefCell::new(graph),
+            api,
+            failed: Cell::new(false),
+        }
+    }
+
+    /// Runs `func` with the mapping applied to all (de)serialized task ids.
+    /// Returns `None` when a task id couldn't be mapped.
+    fn with<T>(&self, func: impl FnOnce() -> Option<T>) -> Option<T> {
+        self.failed.set(false);
+        let result = with_task_id_mapping(self, func);
+        if self.failed.get() {
+            return None;
+        }
+        result
+    }
+
+    /// The persisted id of the `task`. A new id is assigned to unknown tasks
+    /// when `create` is true. Must be called in [Mapping::with].
+    fn id(&self, task: TaskId, create: bool) -> Option<PersistedId> {
+        if let Some(&id) = self.graph.borrow().ids_by_task.get(&task) {
+            return Some(id);
+        }
+        let task_type = self.api.lookup_task_type(task)?;
+        // Task ids in the inputs are mapped as well
+        let task_type = postcard::to_allocvec(task_type).ok()?;
+        if self.failed.get() {
+            return None;
+        }
+        let mut graph = self.graph.borrow_mut();
+        let id = match graph.ids_by_task_type.get(&task_type) {
+            Some(&id) => id,
+            None if create => graph.create_id(task_type),
+            None => return None,
+        };
+        graph.ids_by_task.insert(task, id);
+        graph.tasks_by_id.insert(id, task);
+        Some(id)
+    }
+
+    /// The task of this session with the persisted `id`, which is created if
+    /// needed. Must be called in [Mapping::with].
+    fn task(&self, id: PersistedId) -> Option<TaskId> {
+        if let Some(&task) = self.graph.borrow().tasks_by_id.get(&id) {
+            return Some(task);
+        }
+        let task_type = self.graph.borrow().task_types.get(id)?.clone();
+        let task_type: PersistentTaskType = postcard::from_bytes(&task_type).ok()?;
+        if self.failed.get() {
+            return None;
+        }
+        let task = self.api.get_or_create_task_type(task_type);
+        let mut graph = self.graph.borrow_mut();
+        graph.ids_by_task.insert(task, id);
+        graph.tasks_by_id.insert(id, task);
+        Some(task)
+    }
+
+    fn vc(&self, vc: RawVc, create: bool) -> Option<PersistedVc> {
+        Some(match vc {
+            RawVc::TaskOutput(task) => PersistedVc::TaskOutput(self.id(task, create)?),
+            RawVc::TaskCell(task, cell) => PersistedVc::TaskCell(self.id(task, create)?, cell),
+        })
+    }
+
+    /// The persisted id of the `task`, if it's known to the persisted graph
+    fn lookup(&self, task: TaskId) -> Option<PersistedId> {
+        self.with(|| self.id(task, false))
+    }
+
+    /// The tasks of this session for the persisted `ids`. Tasks that can't be
+    /// restored are skipped.
+    fn tasks(&self, ids: impl IntoIterator<Item = PersistedId>) -> Vec<TaskId> {
+        ids.into_iter()
+            .filter_map(|id| self.with(|| self.task(id)))
+            .collect()
+    }
+}
+
+impl IdMapping<TaskId> for Mapping<'_> {
+    fn forward(&self, task: TaskId) -> usize {
+        self.id(task, true).unwrap_or_else(|| {
+            self.failed.set(true);
+            usize::MAX
+        })
+    }
+
+    fn backward(&self, id: usize) -> TaskId {
+        self.task(id).unwrap_or_else(|| {
+            self.failed.set(true);
+            TaskId::from(usize::MAX)
+        })
+    }
+}
+
+/// Identifies the executable. Persisted values can only be read by the build
+/// that wrote them, as their layout can change between builds.
+fn executable_fingerprint() -> Option<String> {
+    let executable = std::env::current_exe().ok()?;
+    let metadata = fs::metadata(&executable).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!(
+        "{}:{}:{}",
+        executable.display(),
+        metadata.len(),
+        modified.as_nanos()
+    ))
+}
+
+pub struct FileStorePersistedGraph {
+    path: PathBuf,
+    executable: Option<String>,
+    graph: Mutex<Graph>,
+}
+
+impl FileStorePersistedGraph {
+    /// Restores the graph stored at `path`. Starts with an empty graph when the
+    /// file doesn't exist, was written by a different build or is inconsistent.
+    /// All value types need to be registered before.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let executable = executable_fingerprint();
+        let graph = match fs::read(&path) {
+            Ok(content) => Graph::read(&content, executable.as_deref()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Graph::default(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("reading {}", path.display()));
+            }
+        };
+        Ok(Self {
+            path,
+            executable,
+            graph: Mutex::new(graph),
+        })
+    }
+
+    fn write(&self) -> Result<()> {
+        let Some(executable) = &self.executable else {
+            // It couldn't be restored anyway
+            return Ok(());
+        };
+        let content = {
+            let graph = self.graph.lock().unwrap();
+            postcard::to_allocvec(&graph.snapshot(executable))?
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        // Write to a temporary file first, so an interrupted write doesn't leave a
+        // broken file behind
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, content)
+            .with_context(|| format!("writing {}", temp_path.display()))?;
+        fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("writing {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+impl PersistedGraph for FileStorePersistedGraph {
+    fn read(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<(TaskData, ReadTaskState)>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.lookup(task) else {
+            return Ok(None);
+        };
+        let (data, state) = {
+            let graph = mapping.graph.borrow();
+            let task = &graph.tasks[id];
+            let Some(data) = &task.data else {
+                return Ok(None);
+            };
+            let state = ReadTaskState {
+                clean: !data.dirty,
+                keeps_external_active: task.active_parents > 0,
+            };
+            (data.data.clone(), state)
+        };
+        // Data that can't be restored, e.g. because a value doesn't deserialize, is
+        // treated like it wasn't persisted, so the task is executed again
+        Ok(mapping
+            .with(|| postcard::from_bytes::<TaskData>(&data).ok())
+            .map(|data| (data, state)))
+    }
+
+    fn lookup(
+        &self,
+        _partial_task_type: &PersistentTaskType,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<bool> {
+        // Tasks are looked up one by one
+        Ok(false)
+    }
+
+    fn lookup_one(
+        &self,
+        task_type: &PersistentTaskType,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<TaskId>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(task_type) = mapping.with(|| postcard::to_allocvec(task_type).ok()) else {
+            return Ok(None);
+        };
+        let id = {
+            let graph = mapping.graph.borrow();
+            match graph.ids_by_task_type.get(&task_type) {
+                Some(&id) if graph.tasks[id].data.is_some() => id,
+                _ => return Ok(None),
+            }
+        };
+        Ok(mapping.with(|| mapping.task(id)))
+    }
+
+    fn is_persisted(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.lookup(task) else {
+            return Ok(false);
+        };
+        let graph = mapping.graph.borrow();
+        Ok(graph.tasks[id].data.is_some())
+    }
+
+    fn persist(
+        &self,
+        task: TaskId,
+        data: TaskData,
+        state: PersistTaskState,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<PersistResult>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        // Tasks that reference transient tasks or contain values that aren't
+        // serializable can't be persisted
+        let Some((id, serialized, children, dependencies)) = mapping.with(|| {
+            let id = mapping.id(task, true)?;
+            let serialized = postcard::to_allocvec(&data).ok()?;
+            let children = data
+                .children
+                .iter()
+                .map(|&child| mapping.id(child, true))
+                .collect::<Option<Vec<_>>>()?;
+            let dependencies = data
+                .dependencies
+                .iter()
+                .map(|&dependency| mapping.vc(dependency, true))
+                .collect::<Option<Vec<_>>>()?;
+            Some((id, serialized, children, dependencies))
+        }) else {
+            return Ok(None);
+        };
+
+        let mut tasks_to_activate = Vec::new();
+        let mut tasks_to_deactivate = Vec::new();
+        {
+            let mut graph = mapping.graph.borrow_mut();
+            let graph = &mut **graph;
+            let old_data = graph.tasks[id].data.take();
+            let (old_children, old_dependencies) = old_data
+                .map(|data| (data.children, data.dependencies))
+                .unwrap_or_default();
+            for dependency in old_dependencies {
+                if let Some(dependents) = graph.dependents.get_mut(&dependency) {
+                    dependents.remove(&id);
+                }
+            }
+            for &dependency in dependencies.iter() {
+                graph.dependents.entry(dependency).or_default().insert(id);
+            }
+
+            let task = &mut graph.tasks[id];
+            task.externally_active = state.externally_active;
+            task.data = Some(PersistedTask {
+                data: serialized,
+                children: children.clone(),
+                dependencies,
+                dirty: false,
+                session_dependent: state.session_dependent,
+            });
+            if task.active {
+                // Update the children that are kept active by this task
+                let old_children: HashSet<_> = old_children.into_iter().collect();
+                let new_children: HashSet<_> = children.into_iter().collect();
+                for &child in new_children.difference(&old_children) {
+                    if graph.increment_active_parents(child) {
+                        tasks_to_activate.push(child);
+                    }
+                }
+                for &child in old_children.difference(&new_children) {
+                    if graph.decrement_active_parents(child) {
+                        tasks_to_deactivate.push(child);
+                    }
+                }
+                if !graph.tasks[id].needs_activation() {
+                    tasks_to_deactivate.push(id);
+                }
+            } else if task.needs_activation() {
+                tasks_to_activate.push(id);
+            }
+        }
+
+        Ok(Some(PersistResult {
+            tasks_to_activate: mapping.tasks(tasks_to_activate),
+            tasks_to_deactivate: mapping.tasks(tasks_to_deactivate),
+        }))
+    }
+
+    fn activate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<ActivateResult>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.lookup(task) else {
+            return Ok(None);
+        };
+        let mut more_tasks_to_activate = Vec::new();
+        let result = {
+            let mut graph = mapping.graph.borrow_mut();
+            let graph = &mut **graph;
+            if !graph.tasks[id].active && graph.tasks[id].needs_activation() {
+                graph.tasks[id].active = true;
+                for child in graph.tasks[id].children().to_vec() {
+                    if graph.increment_active_parents(child) {
+                        more_tasks_to_activate.push(child);
+                    }
+                }
+            }
+            let task = &graph.tasks[id];
+            ActivateResult {
+                keeps_external_active: task.active_parents > 0,
+                external: task.data.is_none(),
+                dirty: task.is_dirty(),
+                more_tasks_to_activate: Vec::new(),
+            }
+        };
+        Ok(Some(ActivateResult {
+            more_tasks_to_activate: mapping.tasks(more_tasks_to_activate),
+            ..result
+        }))
+    }
+
+    fn deactivate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<DeactivateResult>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.lookup(task) else {
+            return Ok(None);
+        };
+        let mut more_tasks_to_deactivate = Vec::new();
+        {
+            let mut graph = mapping.graph.borrow_mut();
+            let graph = &mut **graph;
+            if graph.tasks[id].active_parents > 0 {
+                // Still kept active by persisted parents
+                return Ok(None);
+            }
+            if graph.tasks[id].active && !graph.tasks[id].externally_active {
+                graph.tasks[id].active = false;
+                for child in graph.tasks[id].children().to_vec() {
+                    if graph.decrement_active_parents(child) {
+                        more_tasks_to_deactivate.push(child);
+                    }
+                }
+            }
+        }
+        Ok(Some(DeactivateResult {
+            more_tasks_to_deactivate: mapping.tasks(more_tasks_to_deactivate),
+        }))
+    }
+
+    fn set_externally_active(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.with(|| mapping.id(task, true)) else {
+            return Ok(false);
+        };
+        let mut graph = mapping.graph.borrow_mut();
+        let task = &mut graph.tasks[id];
+        task.externally_active = true;
+        Ok(!task.active)
+    }
+
+    fn unset_externally_active(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.lookup(task) else {
+            return Ok(false);
+        };
+        let mut graph = mapping.graph.borrow_mut();
+        let task = &mut graph.tasks[id];
+        task.externally_active = false;
+        Ok(task.active && task.active_parents == 0)
+    }
+
+    fn remove_outdated_externally_active(
+        &self,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<Vec<TaskId>> {
+        // External keep alives are not restored from the file, so none of them can
+        // be outdated
+        Ok(Vec::new())
+    }
+
+    fn make_dirty(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(id) = mapping.lookup(task) else {
+            return Ok(false);
+        };
+        let mut graph = mapping.graph.borrow_mut();
+        let task = &mut graph.tasks[id];
+        let Some(data) = &mut task.data else {
+            return Ok(false);
+        };
+        data.dirty = true;
+        Ok(task.active)
+    }
+
+    fn make_clean(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<()> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        if let Some(id) = mapping.lookup(task) {
+            if let Some(data) = &mut mapping.graph.borrow_mut().tasks[id].data {
+                data.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn make_dependent_dirty(&self, vc: RawVc, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let Some(vc) = mapping.with(|| mapping.vc(vc, false)) else {
+            return Ok(Vec::new());
+        };
+        let active_dependents = mapping.graph.borrow_mut().make_dependents_dirty(vc);
+        Ok(mapping.tasks(active_dependents))
+    }
+
+    fn get_active_external_tasks(&self, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let ids = mapping
+            .graph
+            .borrow()
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.data.is_none() && task.active_parents > 0)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        Ok(mapping.tasks(ids))
+    }
+
+    fn get_dirty_active_tasks(&self, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        let mut graph = self.graph.lock().unwrap();
+        let mapping = Mapping::new(&mut graph, api);
+        let ids = {
+            let mut graph = mapping.graph.borrow_mut();
+            // Restored session dependent tasks are executed again on startup, so
+            // changes since the last session invalidate their dependent tasks
+            let mut ids = take(&mut graph.session_dependent);
+            ids.extend(
+                graph
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, task)| task.active && task.is_dirty())
+                    .map(|(id, _)| id),
+            );
+            ids
+        };
+        Ok(mapping.tasks(ids))
+    }
+
+    fn get_pending_active_update(
+        &self,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<(Vec<TaskId>, Vec<TaskId>)> {
+        // Activation is not restored from the file
+        Ok((Vec::new(), Vec::new()))
+    }
+
+    fn stop(&self, _api: &dyn PersistedGraphApi) -> Result<()> {
+        self.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXECUTABLE: &str = "turbo:1:2";
+
+    fn task(children: Vec<PersistedId>, dependencies: Vec<PersistedId>) -> PersistedTask {
+        PersistedTask {
+            data: Vec::new(),
+            children,
+            dependencies: dependencies
+                .into_iter()
+                .map(PersistedVc::TaskOutput)
+                .collect(),
+            dirty: false,
+            session_dependent: false,
+        }
+    }
+
+    fn serialize(
+        version: u32,
+        executable: &str,
+        task_types: usize,
+        tasks: &[(PersistedId, PersistedTask)],
+    ) -> Vec<u8> {
+        let task_types = (0..task_types).map(|id| vec![id as u8]).collect::<Vec<_>>();
+        postcard::to_allocvec(&SnapshotRef {
+            version,
+            executable,
+            task_types: &task_types,
+            tasks: tasks.iter().map(|(id, task)| (*id, task)).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut session_dependent = task(vec![], vec![]);
+        session_dependent.session_dependent = true;
+        let content = serialize(
+            FORMAT_VERSION,
+            EXECUTABLE,
+            3,
+            &[(0, task(vec![1], vec![1])), (1, session_dependent)],
+        );
+        let graph = Graph::read(&content, Some(EXECUTABLE));
+
+        assert_eq!(graph.task_types.len(), 3);
+        assert_eq!(graph.ids_by_task_type[&vec![2]], 2);
+        assert_eq!(graph.tasks[0].children(), &[1]);
+        assert!(!graph.tasks[0].is_dirty());
+        assert!(graph.tasks[2].data.is_none());
+        assert_eq!(
+            graph.dependents[&PersistedVc::TaskOutput(1)],
+            HashSet::from([0])
+        );
+        // Session dependent tasks are not trusted in a new session
+        assert!(graph.tasks[1].is_dirty());
+        assert_eq!(graph.session_dependent, vec![1]);
+
+        // Restoring doesn't change what is written back
+        let written = postcard::to_allocvec(&graph.snapshot(EXECUTABLE)).unwrap();
+        let mut session_dependent = task(vec![], vec![]);
+        session_dependent.session_dependent = true;
+        session_dependent.dirty = true;
+        let expected = serialize(
+            FORMAT_VERSION,
+            EXECUTABLE,
+            3,
+            &[(0, task(vec![1], vec![1])), (1, session_dependent)],
+        );
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let content = serialize(
+            FORMAT_VERSION + 1,
+            EXECUTABLE,
+            1,
+            &[(0, task(vec![], vec![]))],
+        );
+        let graph = Graph::read(&content, Some(EXECUTABLE));
+        assert!(graph.task_types.is_empty());
+        assert!(graph.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_executable_mismatch() {
+        let content = serialize(FORMAT_VERSION, EXECUTABLE, 1, &[(0, task(vec![], vec![]))]);
+        assert!(Graph::read(&content, Some("turbo:1:3")).tasks.is_empty());
+        // Without a fingerprint nothing can be restored
+        assert!(Graph::read(&content, None).tasks.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_content() {
+        let content = serialize(FORMAT_VERSION, EXECUTABLE, 1, &[(0, task(vec![], vec![]))]);
+        let graph = Graph::read(&content[..content.len() - 1], Some(EXECUTABLE));
+        assert!(graph.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_inconsistent_snapshot() {
+        for tasks in [
+            // Unknown task
+            vec![(2, task(vec![], vec![]))],
+            // Unknown child
+            vec![(0, task(vec![2], vec![]))],
+            // Unknown dependency
+            vec![(0, task(vec![], vec![2]))],
+            // Duplicate task
+            vec![(0, task(vec![], vec![])), (0, task(vec![1], vec![]))],
+        ] {
+            let content = serialize(FORMAT_VERSION, EXECUTABLE, 2, &tasks);
+            let graph = Graph::read(&content, Some(EXECUTABLE));
+            assert!(graph.tasks.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_make_dependents_dirty() {
+        let content = serialize(
+            FORMAT_VERSION,
+            EXECUTABLE,
+            4,
+            &[
+                (0, task(vec![], vec![])),
+                (1, task(vec![], vec![0])),
+                (2, task(vec![], vec![0])),
+                (3, task(vec![], vec![1])),
+            ],
+        );
+        let mut graph = Graph::read(&content, Some(EXECUTABLE));
+        graph.tasks[1].active = true;
+
+        // All dependents are dirty, but only active ones need to be executed again
+        assert_eq!(
+            graph.make_dependents_dirty(PersistedVc::TaskOutput(0)),
+            vec![1]
+        );
+        assert!(graph.tasks[1].is_dirty());
+        assert!(graph.tasks[2].is_dirty());
+        // Tasks that depend on the dirty tasks are invalidated once they changed
+        assert!(!graph.tasks[0].is_dirty());
+        assert!(!graph.tasks[3].is_dirty());
+        // Dirty tasks are not returned again
+        assert!(graph
+            .make_dependents_dirty(PersistedVc::TaskOutput(0))
+            .is_empty());
+        assert!(graph
+            .make_dependents_dirty(PersistedVc::TaskOutput(3))
+            .is_empty());
+    }
+}
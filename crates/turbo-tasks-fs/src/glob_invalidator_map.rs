@@ -0,0 +1,170 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::Hash,
+    mem::take,
+    path::{Path, PathBuf},
+};
+
+use turbo_tasks::Invalidator;
+
+use crate::{
+    glob::{Glob, GlobVc},
+    util::sys_to_unix,
+};
+
+/// The `read_glob` tasks that need to be invalidated when an entry that could
+/// match their glob is created, removed or renamed below their directory.
+///
+/// Keyed by the directory and the glob, so that re-executing a `read_glob`
+/// replaces its invalidator instead of adding another one, and a change only
+/// has to look at the globs read from the directories around it.
+pub struct GlobInvalidatorMap<I = Invalidator> {
+    dirs: BTreeMap<PathBuf, HashMap<GlobVc, GlobInvalidators<I>>>,
+}
+
+struct GlobInvalidators<I> {
+    glob: Glob,
+    invalidators: HashSet<I>,
+}
+
+impl<I> Default for GlobInvalidatorMap<I> {
+    fn default() -> Self {
+        Self {
+            dirs: BTreeMap::new(),
+        }
+    }
+}
+
+impl<I: Hash + Eq> GlobInvalidatorMap<I> {
+    pub fn insert(&mut self, dir: PathBuf, glob_vc: GlobVc, glob: Glob, invalidator: I) {
+        self.dirs
+            .entry(dir)
+            .or_default()
+            .entry(glob_vc)
+            .or_insert_with(|| GlobInvalidators {
+                glob,
+                invalidators: HashSet::new(),
+            })
+            .invalidators
+            .insert(invalidator);
+    }
+
+    /// Removes all invalidators
+    pub fn take_all(&mut self) -> impl Iterator<Item = I> {
+        take(&mut self.dirs)
+            .into_values()
+            .flat_map(|globs| globs.into_values())
+            .flat_map(|globs| globs.invalidators)
+    }
+
+    /// Removes the invalidators of the globs whose results can be changed by
+    /// creating, removing or renaming `path`
+    pub fn take_affected(&mut self, path: &Path) -> Vec<I> {
+        let mut affected = Vec::new();
+
+        // The directory itself, or one of its parents, changed. Paths order
+        // by component, so the directories below `path` directly follow it.
+        let below: Vec<_> = self
+            .dirs
+            .range(path.to_path_buf()..)
+            .map(|(dir, _)| dir)
+            .take_while(|dir| dir.starts_with(path))
+            .cloned()
+            .collect();
+        for dir in below {
+            if let Some(globs) = self.dirs.remove(&dir) {
+                affected.extend(globs.into_values().flat_map(|globs| globs.invalidators));
+            }
+        }
+
+        // A directory that a glob read from above `path` can match in was
+        // created or removed
+        for dir in path.ancestors().skip(1) {
+            let Some(globs) = self.dirs.get_mut(dir) else {
+                continue;
+            };
+            let Some(relative_path) = path.strip_prefix(dir).ok().and_then(|p| p.to_str()) else {
+                continue;
+            };
+            let relative_path = sys_to_unix(relative_path);
+            globs.retain(|_, glob_invalidators| {
+                let glob = &glob_invalidators.glob;
+                let is_affected =
+                    glob.execute(&relative_path) || glob.execute(&format!("{relative_path}/"));
+                if is_affected {
+                    affected.extend(take(&mut glob_invalidators.invalidators));
+                }
+                !is_affected
+            });
+            if globs.is_empty() {
+                self.dirs.remove(dir);
+            }
+        }
+
+        affected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use turbo_tasks::{RawVc, TaskId};
+
+    use super::GlobInvalidatorMap;
+    use crate::glob::{Glob, GlobVc};
+
+    fn insert(
+        map: &mut GlobInvalidatorMap<&'static str>,
+        dir: &str,
+        glob: &'static str,
+        id: usize,
+    ) {
+        let glob_vc = GlobVc::from(RawVc::TaskOutput(TaskId::from(id)));
+        map.insert(
+            PathBuf::from(dir),
+            glob_vc,
+            Glob::parse(glob).unwrap(),
+            glob,
+        );
+    }
+
+    fn take_affected(map: &mut GlobInvalidatorMap<&'static str>, path: &str) -> Vec<&'static str> {
+        let mut affected = map.take_affected(Path::new(path));
+        affected.sort();
+        affected
+    }
+
+    #[test]
+    fn test_take_affected() {
+        let mut map = GlobInvalidatorMap::default();
+        insert(&mut map, "/root", "src/*.js", 1);
+        insert(&mut map, "/root", "*.json", 2);
+        insert(&mut map, "/root/src", "*.css", 3);
+        insert(&mut map, "/root/other", "**", 4);
+
+        // Entries that none of the globs can match don't affect them
+        assert!(take_affected(&mut map, "/root/README.md").is_empty());
+        assert!(take_affected(&mut map, "/root/lib/a.js").is_empty());
+        assert!(take_affected(&mut map, "/elsewhere/a.js").is_empty());
+
+        // A matching entry affects the globs read from its parents
+        assert_eq!(take_affected(&mut map, "/root/src/a.css"), vec!["*.css"]);
+        assert_eq!(take_affected(&mut map, "/root/src/a.js"), vec!["src/*.js"]);
+        // The invalidators were taken, so the same change doesn't affect them
+        // again until the globs are read again
+        assert!(take_affected(&mut map, "/root/src/a.js").is_empty());
+
+        // A directory affects the globs read from below it, and the globs
+        // that can match in it
+        insert(&mut map, "/root", "src/*.js", 1);
+        insert(&mut map, "/root/src", "*.css", 3);
+        assert_eq!(
+            take_affected(&mut map, "/root/src"),
+            vec!["*.css", "src/*.js"]
+        );
+
+        assert_eq!(take_affected(&mut map, "/root"), vec!["**", "*.json"]);
+        assert_eq!(map.take_all().count(), 0);
+    }
+}
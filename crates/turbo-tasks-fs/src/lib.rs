@@ -8,15 +8,18 @@
 pub mod attach;
 pub mod embed;
 pub mod glob;
+mod glob_invalidator_map;
 mod invalidation;
 mod invalidator_map;
 pub mod json;
 mod mutex_map;
 mod read_glob;
+pub mod rebased;
 mod retry;
 pub mod rope;
 pub mod source_context;
 pub mod util;
+mod watch_options;
 
 use std::{
     borrow::Cow,
@@ -28,17 +31,18 @@ use std::{
     mem::take,
     path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::{
-        mpsc::{channel, RecvError, TryRecvError},
+        mpsc::{channel, RecvError, RecvTimeoutError, TryRecvError},
         Arc, Mutex,
     },
-    time::Duration,
+    time::Instant,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use auto_hash_map::AutoMap;
 use bitflags::bitflags;
 use dunce::simplified;
-use glob::GlobVc;
+use glob::{Glob, GlobVc};
+use glob_invalidator_map::GlobInvalidatorMap;
 use invalidator_map::InvalidatorMap;
 use jsonc_parser::{parse_to_serde_value, ParseOptions};
 use mime::Mime;
@@ -53,7 +57,7 @@ use tokio::{
 };
 use tracing::{instrument, Level};
 use turbo_tasks::{
-    mark_stateful,
+    mark_session_dependent, mark_stateful,
     primitives::{BoolVc, StringReadRef, StringVc},
     spawn_thread,
     trace::TraceRawVcs,
@@ -61,6 +65,7 @@ use turbo_tasks::{
 };
 use turbo_tasks_hash::hash_xxh3_hash64;
 use util::{extract_disk_access, join_path, normalize_path, sys_to_unix, unix_to_sys};
+pub use watch_options::{WatchOptions, WatchOptionsVc};
 
 use self::{invalidation::WatchStart, json::UnparseableJson, mutex_map::MutexMap};
 use crate::{
@@ -83,6 +88,24 @@ pub trait FileSystem: ValueToString {
     fn write(&self, fs_path: FileSystemPathVc, content: FileContentVc) -> CompletionVc;
     fn write_link(&self, fs_path: FileSystemPathVc, target: LinkContentVc) -> CompletionVc;
     fn metadata(&self, fs_path: FileSystemPathVc) -> FileMetaVc;
+    /// Reads the entries below `fs_path` that match `glob`.
+    ///
+    /// The default implementation reads every directory the glob can match
+    /// in, so it's invalidated by any change to those directories. File
+    /// systems that can tell which changes affect the glob should override it
+    /// to only be invalidated by those.
+    fn read_glob(
+        self_vc: FileSystemVc,
+        fs_path: FileSystemPathVc,
+        glob: GlobVc,
+        include_dot_files: bool,
+    ) -> ReadGlobResultVc {
+        // `fs_path` already points into this file system. The receiver has to
+        // be named `self_vc` for the override to take `&self`, so it can't be
+        // prefixed with an underscore.
+        let _ = self_vc;
+        read_glob(fs_path, glob, include_dot_files)
+    }
 }
 
 #[derive(Default)]
@@ -156,6 +179,9 @@ pub struct DiskFileSystem {
     dir_invalidator_map: Arc<InvalidatorMap>,
     #[turbo_tasks(debug_ignore, trace_ignore)]
     #[serde(skip)]
+    glob_invalidators: Arc<Mutex<GlobInvalidatorMap>>,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    #[serde(skip)]
     watcher: Arc<DiskWatcher>,
 }
 
@@ -166,8 +192,10 @@ impl DiskFileSystem {
     }
 
     /// registers the path as an invalidator for the current task,
-    /// has to be called within a turbo-tasks function
+    /// has to be called within a turbo-tasks function. The file can change
+    /// between sessions, so the task is marked as session dependent.
     fn register_invalidator(&self, path: &Path) -> Result<()> {
+        mark_session_dependent();
         let invalidator = turbo_tasks::get_invalidator();
         self.invalidator_map.insert(path_to_key(path), invalidator);
         #[cfg(not(any(target_os = "macos", target_os = "windows")))]
@@ -177,9 +205,98 @@ impl DiskFileSystem {
         Ok(())
     }
 
+    /// registers the glob in the directory as an invalidator for the current
+    /// task, has to be called within a turbo-tasks function. The directories
+    /// the glob is read from need to be watched separately.
+    fn register_glob_invalidator(&self, path: &Path, glob_vc: GlobVc, glob: Glob) {
+        mark_session_dependent();
+        let invalidator = turbo_tasks::get_invalidator();
+        self.glob_invalidators.lock().unwrap().insert(
+            path.to_path_buf(),
+            glob_vc,
+            glob,
+            invalidator,
+        );
+    }
+
+    /// Reads the entries of `dir` that match `glob`, recursing into the
+    /// directories the glob can match in. `prefix` is the path of `dir`
+    /// relative to the directory the glob is read from, including a trailing
+    /// `/`.
+    fn read_glob_dir(
+        &self,
+        fs: FileSystemVc,
+        dir: &Path,
+        prefix: &str,
+        glob: &Glob,
+    ) -> Result<ReadGlobResult> {
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        self.watcher.ensure_watching(dir, self.root_path())?;
+
+        let mut result = ReadGlobResult::default();
+        let read_dir = match std::fs::read_dir(dir) {
+            Ok(dir) => dir,
+            Err(e)
+                if e.kind() == ErrorKind::NotFound
+                    || e.kind() == ErrorKind::NotADirectory
+                    || e.kind() == ErrorKind::InvalidFilename =>
+            {
+                return Ok(result)
+            }
+            Err(e) => bail!(anyhow!(e).context(format!("reading dir {}", dir.display()))),
+        };
+        for entry in read_dir {
+            let entry =
+                entry.with_context(|| format!("reading directory item in {}", dir.display()))?;
+            let path = entry.path();
+
+            // we filter out any non unicode names and paths without the same root here
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(path_to_root) = path.strip_prefix(&self.root).ok().and_then(|p| p.to_str())
+            else {
+                continue;
+            };
+            let fs_path =
+                FileSystemPathVc::new_normalized(fs, sys_to_unix(path_to_root).to_string());
+
+            let full_path = format!("{prefix}{file_name}");
+            let file_type = entry
+                .file_type()
+                .with_context(|| format!("reading file type of {}", path.display()))?;
+            if file_type.is_dir() {
+                let full_path_prefix = format!("{full_path}/");
+                if glob.execute(&full_path) {
+                    result
+                        .results
+                        .insert(full_path.clone(), DirectoryEntry::Directory(fs_path));
+                }
+                if glob.execute(&full_path_prefix) {
+                    let inner = self.read_glob_dir(fs, &path, &full_path_prefix, glob)?;
+                    result
+                        .inner
+                        .insert(full_path, ReadGlobResultVc::cell(inner));
+                }
+            } else if glob.execute(&full_path) {
+                let entry = if file_type.is_file() {
+                    DirectoryEntry::File(fs_path)
+                } else if file_type.is_symlink() {
+                    DirectoryEntry::Symlink(fs_path)
+                } else {
+                    DirectoryEntry::Other(fs_path)
+                };
+                result.results.insert(full_path, entry);
+            }
+        }
+        Ok(result)
+    }
+
     /// registers the path as an invalidator for the current task,
-    /// has to be called within a turbo-tasks function
+    /// has to be called within a turbo-tasks function. The directory can
+    /// change between sessions, so the task is marked as session dependent.
     fn register_dir_invalidator(&self, path: &Path) -> Result<()> {
+        mark_session_dependent();
         let invalidator = turbo_tasks::get_invalidator();
         self.dir_invalidator_map
             .insert(path_to_key(path), invalidator);
@@ -195,6 +312,9 @@ impl DiskFileSystem {
         for (_, invalidators) in take(&mut *self.dir_invalidator_map.lock().unwrap()).into_iter() {
             invalidators.into_iter().for_each(|i| i.invalidate());
         }
+        for invalidator in self.glob_invalidators.lock().unwrap().take_all() {
+            invalidator.invalidate();
+        }
     }
 
     pub fn invalidate_with_reason<T: InvalidationReason + Clone>(&self, reason: T) {
@@ -208,34 +328,42 @@ impl DiskFileSystem {
                 .into_iter()
                 .for_each(|i| i.invalidate_with_reason(reason.clone()));
         }
+        for invalidator in self.glob_invalidators.lock().unwrap().take_all() {
+            invalidator.invalidate_with_reason(reason.clone());
+        }
     }
 
     pub fn start_watching(&self) -> Result<()> {
-        self.start_watching_internal(false)
+        self.start_watching_with_options(WatchOptions::default())
     }
 
     pub fn start_watching_with_invalidation_reason(&self) -> Result<()> {
-        self.start_watching_internal(true)
+        self.start_watching_with_options(WatchOptions {
+            report_invalidation_reason: true,
+            ..Default::default()
+        })
     }
 
-    fn start_watching_internal(&self, report_invalidation_reason: bool) -> Result<()> {
+    pub fn start_watching_with_options(&self, options: WatchOptions) -> Result<()> {
         let mut watcher_guard = self.watcher.watcher.lock().unwrap();
         if watcher_guard.is_some() {
             return Ok(());
         }
         let invalidator_map = self.invalidator_map.clone();
         let dir_invalidator_map = self.dir_invalidator_map.clone();
+        let glob_invalidators = self.glob_invalidators.clone();
         let root = self.root.clone();
         let root_path = self.root_path().to_path_buf();
 
-        let report_invalidation_reason =
-            report_invalidation_reason.then(|| (self.name.clone(), root_path.clone()));
+        let report_invalidation_reason = options
+            .report_invalidation_reason
+            .then(|| (self.name.clone(), root_path.clone()));
 
         // Create a channel to receive the events.
         let (tx, rx) = channel();
         // Create a watcher object, delivering debounced events.
         // The notification back-end is selected based on the platform.
-        let mut watcher = watcher(tx, Duration::from_millis(1))?;
+        let mut watcher = watcher(tx, options.debounce)?;
         // Add a path to be watched. All files and directories at that path and
         // below will be monitored for changes.
         #[cfg(any(target_os = "macos", target_os = "windows"))]
@@ -269,6 +397,15 @@ impl DiskFileSystem {
                 }
             });
         }
+        for invalidator in glob_invalidators.lock().unwrap().take_all() {
+            if report_invalidation_reason.is_some() {
+                invalidator.invalidate_with_reason(WatchStart {
+                    name: self.name.clone(),
+                })
+            } else {
+                invalidator.invalidate();
+            }
+        }
 
         watcher_guard.replace(watcher);
         drop(watcher_guard);
@@ -288,6 +425,7 @@ impl DiskFileSystem {
                 let mut event = rx.recv().map_err(|e| match e {
                     RecvError => TryRecvError::Disconnected,
                 });
+                let batch_start = Instant::now();
                 loop {
                     match event {
                         Ok(DebouncedEvent::Write(path)) => {
@@ -355,7 +493,14 @@ impl DiskFileSystem {
                             break;
                         }
                     }
-                    event = rx.try_recv();
+                    event = match options.settle_timeout(batch_start) {
+                        // Wait for the file system to settle before applying the batch
+                        Some(timeout) => rx.recv_timeout(timeout).map_err(|e| match e {
+                            RecvTimeoutError::Timeout => TryRecvError::Empty,
+                            RecvTimeoutError::Disconnected => TryRecvError::Disconnected,
+                        }),
+                        None => rx.try_recv(),
+                    };
                 }
                 #[instrument(parent = None, level = Level::INFO, name = "DiskFileSystem file change", skip_all, fields(name = display(path.display())))]
                 fn invalidate(
@@ -401,6 +546,17 @@ impl DiskFileSystem {
                         }
                     }
                 }
+                fn invalidate_globs<'a>(
+                    report_invalidation_reason: &Option<(String, PathBuf)>,
+                    glob_invalidators: &mut GlobInvalidatorMap,
+                    paths: impl Iterator<Item = &'a PathBuf>,
+                ) {
+                    for path in paths {
+                        for invalidator in glob_invalidators.take_affected(path) {
+                            invalidate(report_invalidation_reason, path, invalidator);
+                        }
+                    }
+                }
                 // We need to start watching first before invalidating the changed paths
                 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
                 {
@@ -408,6 +564,12 @@ impl DiskFileSystem {
                         let _ = disk_watcher.restore_if_watching(&path, &root_path);
                     }
                 }
+                // Only creating, removing and renaming entries changes the results of globs
+                invalidate_globs(
+                    &report_invalidation_reason,
+                    &mut glob_invalidators.lock().unwrap(),
+                    batched_invalidate_path_and_children.iter(),
+                );
                 {
                     let mut invalidator_map = invalidator_map.lock().unwrap();
                     invalidate_path(
@@ -491,6 +653,7 @@ impl DiskFileSystemVc {
             mutex_map: Default::default(),
             invalidator_map: Arc::new(InvalidatorMap::new()),
             dir_invalidator_map: Arc::new(InvalidatorMap::new()),
+            glob_invalidators: Default::default(),
             watcher: Default::default(),
         };
 
@@ -825,6 +988,27 @@ impl FileSystem for DiskFileSystem {
 
         Ok(FileMetaVc::cell(meta.into()))
     }
+
+    /// Reads the whole glob in one task, which is only invalidated when an
+    /// entry the glob could match is created, removed or renamed. Changes to
+    /// other entries in the same directories don't invalidate it.
+    #[turbo_tasks::function]
+    async fn read_glob(
+        self_vc: DiskFileSystemVc,
+        fs_path: FileSystemPathVc,
+        glob: GlobVc,
+        _include_dot_files: bool,
+    ) -> Result<ReadGlobResultVc> {
+        let this = self_vc.await?;
+        let full_path = this.to_sys_path(fs_path).await?;
+        let glob_vc = glob;
+        let glob = glob.await?;
+        this.register_glob_invalidator(&full_path, glob_vc, (*glob).clone());
+
+        let fs = fs_path.await?.fs;
+        let result = this.read_glob_dir(fs, &full_path, "", &glob)?;
+        Ok(ReadGlobResultVc::cell(result))
+    }
 }
 
 #[turbo_tasks::value_impl]
@@ -1061,7 +1245,7 @@ impl FileSystemPathVc {
 
     #[turbo_tasks::function]
     pub async fn read_glob(self, glob: GlobVc, include_dot_files: bool) -> ReadGlobResultVc {
-        read_glob(self, glob, include_dot_files)
+        self.fs().read_glob(self, glob, include_dot_files)
     }
 
     #[turbo_tasks::function]
@@ -1834,6 +2018,20 @@ pub enum DirectoryEntry {
     Error,
 }
 
+impl DirectoryEntry {
+    /// Returns an entry of the same type that points at `path` instead, e.g.
+    /// for file systems that wrap another one.
+    pub fn with_path(self, path: FileSystemPathVc) -> Self {
+        match self {
+            DirectoryEntry::File(_) => DirectoryEntry::File(path),
+            DirectoryEntry::Directory(_) => DirectoryEntry::Directory(path),
+            DirectoryEntry::Symlink(_) => DirectoryEntry::Symlink(path),
+            DirectoryEntry::Other(_) => DirectoryEntry::Other(path),
+            DirectoryEntry::Error => DirectoryEntry::Error,
+        }
+    }
+}
+
 #[turbo_tasks::value]
 #[derive(Hash, Clone, Copy, Debug)]
 pub enum FileSystemEntryType {
@@ -1881,6 +2079,23 @@ pub enum DirectoryContent {
     NotFound,
 }
 
+impl DirectoryContent {
+    /// Returns the same listing with the entries pointing into the directory
+    /// `to` instead, e.g. for file systems that wrap another one and list the
+    /// inner file system's directory as their own.
+    pub fn rebase(&self, to: FileSystemPathVc) -> DirectoryContentVc {
+        match self {
+            DirectoryContent::Entries(entries) => DirectoryContentVc::new(
+                entries
+                    .iter()
+                    .map(|(name, entry)| (name.clone(), entry.with_path(to.join(name))))
+                    .collect(),
+            ),
+            DirectoryContent::NotFound => DirectoryContentVc::not_found(),
+        }
+    }
+}
+
 impl DirectoryContentVc {
     pub fn new(entries: AutoMap<String, DirectoryEntry>) -> Self {
         Self::cell(DirectoryContent::Entries(entries))
@@ -4,7 +4,7 @@ use anyhow::Result;
 
 use crate::{glob::GlobVc, DirectoryContent, DirectoryEntry, FileSystemPathVc};
 
-#[turbo_tasks::value]
+#[turbo_tasks::value(shared)]
 #[derive(Default, Debug)]
 pub struct ReadGlobResult {
     pub results: HashMap<String, DirectoryEntry>,
@@ -0,0 +1,253 @@
+use anyhow::{bail, Result};
+use auto_hash_map::AutoMap;
+use turbo_tasks::{primitives::StringVc, CompletionVc, ValueToString, ValueToStringVc};
+
+use crate::{
+    util::{join_path, normalize_path},
+    DirectoryContentVc, DirectoryEntry, FileContent, FileContentVc, FileMeta, FileMetaVc,
+    FileSystem, FileSystemPathOptionVc, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc,
+    LinkType,
+};
+
+/// A [FileSystem] which shows a directory of another [FileSystem] at a
+/// different path, e.g. to mount a package's source at a stable path no
+/// matter where it's located on disk.
+///
+/// The ancestors of the mount path are directories that only contain the next
+/// directory towards it. Everything else outside of the mount path doesn't
+/// exist. Reads are forwarded to the inner [FileSystem], so they are
+/// invalidated by its changes as usual.
+///
+/// Caveat: Absolute symlinks to targets outside of the mounted directory are
+/// reported as invalid, since their targets aren't visible.
+#[turbo_tasks::value]
+pub struct RebasedFileSystem {
+    /// The directory of the inner [FileSystem] that is mounted
+    inner_root: FileSystemPathVc,
+    /// The normalized path the directory is mounted at, empty for the root
+    mount_path: String,
+}
+
+/// Where a path on a [RebasedFileSystem] is relative to its mount path
+enum Location<'a> {
+    /// At or below the mount path, with the path relative to it
+    Mounted(&'a str),
+    /// An ancestor of the mount path, with the name of the next directory
+    /// towards it
+    Ancestor(&'a str),
+    Outside,
+}
+
+impl RebasedFileSystem {
+    fn locate<'a>(&'a self, path: &'a str) -> Location<'a> {
+        if let Some(rest) = strip_dir_prefix(path, &self.mount_path) {
+            return Location::Mounted(rest);
+        }
+        match strip_dir_prefix(&self.mount_path, path) {
+            Some(rest) => Location::Ancestor(rest.split('/').next().unwrap_or(rest)),
+            None => Location::Outside,
+        }
+    }
+}
+
+/// Returns the rest of `path` when it's equal to or inside of `dir`
+fn strip_dir_prefix<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    if dir.is_empty() {
+        return Some(path);
+    }
+    let rest = path.strip_prefix(dir)?;
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix('/')
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl RebasedFileSystemVc {
+    /// Creates a [RebasedFileSystem] which shows `inner_root` at the
+    /// /-separated `mount_path`, or at its root for an empty `mount_path`
+    #[turbo_tasks::function]
+    pub fn new(inner_root: FileSystemPathVc, mount_path: String) -> Result<Self> {
+        let Some(mount_path) = normalize_path(&mount_path) else {
+            bail!("mount path {} leaves the root", mount_path);
+        };
+        Ok(RebasedFileSystem {
+            inner_root,
+            mount_path,
+        }
+        .cell())
+    }
+
+    /// Converts a path in the mounted directory of the inner [FileSystem] to
+    /// the path it's shown at on this [FileSystem]
+    #[turbo_tasks::function]
+    pub async fn convert_path(self, inner_path: FileSystemPathVc) -> Result<FileSystemPathVc> {
+        let this = self.await?;
+        let inner_root = this.inner_root.await?;
+        let inner_path_value = inner_path.await?;
+        let Some(rest) = inner_root.get_path_to(&inner_path_value) else {
+            bail!(
+                "path {} is not inside of the mounted directory {}",
+                inner_path.to_string().await?,
+                this.inner_root.to_string().await?
+            );
+        };
+        Ok(self
+            .root()
+            .resolve()
+            .await?
+            .join(&this.mount_path)
+            .join(rest))
+    }
+
+    /// Resolves the path on the inner [FileSystem] from a path on the
+    /// [RebasedFileSystem], or `None` when it's outside of the mount path
+    #[turbo_tasks::function]
+    pub async fn get_inner_fs_path(self, path: FileSystemPathVc) -> Result<FileSystemPathOptionVc> {
+        let this = self.await?;
+        let path = path.await?;
+        let self_fs: FileSystemVc = self.into();
+
+        if path.fs != self_fs {
+            bail!(
+                "path fs does not match (expected {}, got {})",
+                self_fs.to_string().await?,
+                path.fs.to_string().await?
+            )
+        }
+
+        Ok(FileSystemPathOptionVc::cell(
+            match this.locate(&path.path) {
+                Location::Mounted(rest) => Some(this.inner_root.join(rest)),
+                Location::Ancestor(_) | Location::Outside => None,
+            },
+        ))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for RebasedFileSystem {
+    #[turbo_tasks::function]
+    async fn read(self_vc: RebasedFileSystemVc, path: FileSystemPathVc) -> Result<FileContentVc> {
+        Ok(match *self_vc.get_inner_fs_path(path).await? {
+            Some(inner_path) => inner_path.read(),
+            None => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(
+        self_vc: RebasedFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<LinkContentVc> {
+        let Some(inner_path) = *self_vc.get_inner_fs_path(path).await? else {
+            return Ok(LinkContent::NotFound.cell());
+        };
+        let link_content = inner_path.read_link();
+        let LinkContent::Link { target, link_type } = &*link_content.await? else {
+            return Ok(link_content);
+        };
+        if !link_type.contains(LinkType::ABSOLUTE) {
+            return Ok(link_content);
+        }
+        // Absolute targets are relative to the root of the file system, so they
+        // have to be moved to the mount path
+        let this = self_vc.await?;
+        let inner_root = this.inner_root.await?;
+        let target = strip_dir_prefix(target, &inner_root.path)
+            .and_then(|rest| join_path(&this.mount_path, rest));
+        Ok(match target {
+            Some(target) => LinkContent::Link {
+                target,
+                link_type: *link_type,
+            }
+            .cell(),
+            None => LinkContent::Invalid.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(
+        self_vc: RebasedFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<DirectoryContentVc> {
+        let this = self_vc.await?;
+        let path_value = path.await?;
+        let dir_content = match this.locate(&path_value.path) {
+            Location::Mounted(rest) => this.inner_root.join(rest).read_dir().await?,
+            Location::Ancestor(name) => {
+                let mut entries = AutoMap::new();
+                entries.insert(name.to_string(), DirectoryEntry::Directory(path.join(name)));
+                return Ok(DirectoryContentVc::new(entries));
+            }
+            Location::Outside => return Ok(DirectoryContentVc::not_found()),
+        };
+        Ok(dir_content.rebase(path))
+    }
+
+    #[turbo_tasks::function]
+    async fn track(self_vc: RebasedFileSystemVc, path: FileSystemPathVc) -> Result<CompletionVc> {
+        Ok(match *self_vc.get_inner_fs_path(path).await? {
+            Some(inner_path) => inner_path.track(),
+            // Nothing changes outside of the mount path
+            None => CompletionVc::immutable(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        self_vc: RebasedFileSystemVc,
+        path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let Some(inner_path) = *self_vc.get_inner_fs_path(path).await? else {
+            bail!(
+                "can't write {}, it's outside of the mounted directory",
+                path.to_string().await?
+            );
+        };
+        Ok(inner_path.write(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        self_vc: RebasedFileSystemVc,
+        path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        let Some(inner_path) = *self_vc.get_inner_fs_path(path).await? else {
+            bail!(
+                "can't write {}, it's outside of the mounted directory",
+                path.to_string().await?
+            );
+        };
+        Ok(inner_path.write_link(target))
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(self_vc: RebasedFileSystemVc, path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let this = self_vc.await?;
+        let path_value = path.await?;
+        Ok(match this.locate(&path_value.path) {
+            Location::Mounted(rest) => this.inner_root.join(rest).metadata(),
+            Location::Ancestor(_) => FileMeta::default().cell(),
+            Location::Outside => bail!(
+                "reading metadata for {}: not found",
+                path.to_string().await?
+            ),
+        })
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for RebasedFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "{}-at-{}",
+            self.inner_root.to_string().await?,
+            self.mount_path
+        )))
+    }
+}
@@ -4,7 +4,12 @@ use std::{
     path::Path,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+
+use crate::LinkType;
+
+/// The number of symlinks that are followed before giving up, like `ELOOP`
+pub const MAX_SYMLINK_DEPTH: usize = 40;
 
 /// Joins two /-separated paths into a normalized path.
 /// Paths are concatenated with /.
@@ -58,6 +63,47 @@ pub fn unix_to_sys(path: &str) -> Cow<'_, str> {
     }
 }
 
+/// Splits a normalized path into its parent directory and file name, or
+/// returns `None` for the root
+pub fn split_parent(path: &str) -> Option<(&str, &str)> {
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.rsplit_once('/').unwrap_or(("", path)))
+    }
+}
+
+/// Looks up the entry at the normalized `path` with `get`, following it while
+/// `as_link` returns a symlink target for it. This is for file systems that
+/// keep their entries by path, so only the last component of `path` can be a
+/// symlink. Absolute links are resolved relative to the root, and links that
+/// leave the root don't lead anywhere.
+pub fn follow_links<T>(
+    path: &str,
+    mut get: impl FnMut(&str) -> Option<T>,
+    as_link: impl Fn(&T) -> Option<(&str, LinkType)>,
+) -> Result<Option<T>> {
+    let mut path = path.to_string();
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        let Some(entry) = get(&path) else {
+            return Ok(None);
+        };
+        let Some((target, link_type)) = as_link(&entry) else {
+            return Ok(Some(entry));
+        };
+        let base = if link_type.contains(LinkType::ABSOLUTE) {
+            ""
+        } else {
+            split_parent(&path).map_or("", |(parent, _)| parent)
+        };
+        let Some(target) = join_path(base, target) else {
+            return Ok(None);
+        };
+        path = target;
+    }
+    bail!("too many levels of symbolic links at {}", path)
+}
+
 /// Normalizes a /-separated path into a form that contains no leading /, no
 /// double /, no "." seqment, no ".." seqment.
 ///
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+/// Controls how file system events are batched before the reads they affect
+/// are invalidated.
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct WatchOptions {
+    /// Report the changed paths as invalidation reasons. See
+    /// [turbo_tasks::InvalidationReason].
+    pub report_invalidation_reason: bool,
+    /// Events for the same path within this window are merged by the watcher.
+    pub debounce: Duration,
+    /// A batch of events is only applied once no further event arrived for
+    /// this long, so a storm of events (e. g. a `git checkout` or a package
+    /// install) invalidates once instead of many times. Zero applies every
+    /// batch as soon as the watcher delivered it.
+    pub settle: Duration,
+    /// Applies a batch after this long even when events keep arriving, so a
+    /// constantly changing file can't hold back all other changes.
+    pub max_settle: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            report_invalidation_reason: false,
+            debounce: Duration::from_millis(1),
+            settle: Duration::ZERO,
+            max_settle: Duration::from_secs(1),
+        }
+    }
+}
+
+impl WatchOptions {
+    /// How long to wait for further events of a batch that started at
+    /// `batch_start`. `None` when the batch should be applied now.
+    pub(crate) fn settle_timeout(&self, batch_start: Instant) -> Option<Duration> {
+        if self.settle.is_zero() {
+            return None;
+        }
+        let remaining = self.max_settle.saturating_sub(batch_start.elapsed());
+        (!remaining.is_zero()).then(|| self.settle.min(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::WatchOptions;
+
+    #[test]
+    fn settle_timeout() {
+        let options = WatchOptions {
+            settle: Duration::from_millis(50),
+            max_settle: Duration::from_secs(60),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.settle_timeout(Instant::now()),
+            Some(Duration::from_millis(50))
+        );
+
+        let started_long_ago = Instant::now() - Duration::from_secs(61);
+        assert_eq!(options.settle_timeout(started_long_ago), None);
+
+        assert_eq!(WatchOptions::default().settle_timeout(Instant::now()), None);
+    }
+}
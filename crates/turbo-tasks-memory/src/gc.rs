@@ -1,5 +1,5 @@
 use std::{
-    cmp::Reverse,
+    cmp::{min, Reverse},
     collections::HashMap,
     time::{Duration, Instant},
 };
@@ -84,6 +84,12 @@ pub struct GcQueue {
     queue: ConcurrentPriorityQueue<TaskId, Reverse<GcPriority>>,
 }
 
+impl Default for GcQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GcQueue {
     pub fn new() -> Self {
         Self {
@@ -217,6 +223,27 @@ impl GcQueue {
     }
 }
 
+/// Computes how much work garbage collection should do for the current memory
+/// usage, as the `factor` passed to [GcQueue::run_gc]. Returns `None` when
+/// the usage is below the target for the `memory_limit`, in which case
+/// nothing should be collected.
+pub fn collect_factor(usage: usize, memory_limit: usize, idle: bool) -> Option<u8> {
+    const MAX_COLLECT_FACTOR: u8 = u8::MAX / 8;
+
+    let target = if idle {
+        memory_limit * 3 / 4
+    } else {
+        memory_limit * 7 / 8
+    };
+    if usage < target {
+        return None;
+    }
+    Some(min(
+        MAX_COLLECT_FACTOR as usize,
+        (usage - target) * u8::MAX as usize / (memory_limit - target),
+    ) as u8)
+}
+
 /// Converts a value to an logarithmic scale.
 pub fn to_exp_u8(value: u64) -> u8 {
     value
@@ -9,7 +9,7 @@
 mod cell;
 mod concurrent_priority_queue;
 mod count_hash_set;
-mod gc;
+pub mod gc;
 mod map_guard;
 mod memory_backend;
 mod memory_backend_with_pg;
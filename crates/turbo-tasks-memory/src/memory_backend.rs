@@ -1,7 +1,6 @@
 use std::{
     borrow::{Borrow, Cow},
     cell::RefCell,
-    cmp::min,
     collections::VecDeque,
     future::Future,
     hash::{BuildHasher, BuildHasherDefault, Hash},
@@ -33,7 +32,7 @@ use turbo_tasks::{
 
 use crate::{
     cell::RecomputingCell,
-    gc::GcQueue,
+    gc::{collect_factor, GcQueue},
     output::Output,
     priority_pair::PriorityPair,
     scope::{TaskScope, TaskScopeId},
@@ -116,6 +115,7 @@ impl MemoryBackend {
         func: F,
     ) -> Result<Result<T, EventListener>> {
         self.with_task(id, |task| {
+            self.on_task_used(task, turbo_tasks);
             task.get_or_wait_output(strongly_consistent, func, note, self, turbo_tasks)
         })
     }
@@ -226,6 +226,14 @@ impl MemoryBackend {
         }
     }
 
+    /// Tracks when a task was read last, so garbage collection can evict the
+    /// least recently used tasks first. Only tracked with a memory limit.
+    fn on_task_used(&self, task: &Task, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
+        if self.gc_queue.is_some() {
+            task.gc_mark_used(turbo_tasks.program_duration_until(Instant::now()));
+        }
+    }
+
     pub fn on_task_might_become_inactive(&self, task: TaskId) {
         if let Some(gc_queue) = &self.gc_queue {
             gc_queue.task_might_become_inactive(task);
@@ -240,28 +248,17 @@ impl MemoryBackend {
 
     pub fn run_gc(&self, idle: bool, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
         if let Some(gc_queue) = &self.gc_queue {
-            const MAX_COLLECT_FACTOR: u8 = u8::MAX / 8;
-
-            let mem_limit = self.memory_limit;
-
             let usage = turbo_tasks_malloc::TurboMalloc::memory_usage();
-            let target = if idle {
-                mem_limit * 3 / 4
-            } else {
-                mem_limit * 7 / 8
-            };
-            if usage < target {
-                if idle {
-                    // Always run propagation when idle
-                    gc_queue.run_gc(0, self, turbo_tasks);
+            let collect_factor = match collect_factor(usage, self.memory_limit, idle) {
+                Some(factor) => factor,
+                None => {
+                    if idle {
+                        // Always run propagation when idle
+                        gc_queue.run_gc(0, self, turbo_tasks);
+                    }
+                    return;
                 }
-                return;
-            }
-
-            let collect_factor = min(
-                MAX_COLLECT_FACTOR as usize,
-                (usage - target) * u8::MAX as usize / (mem_limit - target),
-            ) as u8;
+            };
 
             let collected = gc_queue.run_gc(collect_factor, self, turbo_tasks);
 
@@ -524,6 +521,7 @@ impl Backend for MemoryBackend {
         } else {
             Task::add_dependency_to_current(TaskDependency::TaskCell(task_id, index));
             self.with_task(task_id, |task| {
+                self.on_task_used(task, turbo_tasks);
                 match task.with_cell_mut(index, |cell| {
                     cell.read_content(
                         reader,
@@ -561,6 +559,7 @@ impl Backend for MemoryBackend {
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Result<Result<CellContent, EventListener>> {
         self.with_task(task_id, |task| {
+            self.on_task_used(task, turbo_tasks);
             match task.with_cell_mut(index, |cell| {
                 cell.read_content_untracked(
                     move || format!("{task_id}"),
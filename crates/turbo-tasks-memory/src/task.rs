@@ -11,7 +11,10 @@ use std::{
     hash::Hash,
     mem::{replace, take},
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{self, AtomicU32},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
@@ -185,6 +188,11 @@ pub struct Task {
     /// The mutable state of the task
     /// Unset state is equal to a Dirty task that has not been executed yet
     state: RwLock<TaskMetaState>,
+    /// The last time the task was executed or read, in milliseconds since the
+    /// start of the program. Garbage collection evicts least recently used
+    /// tasks first. It's kept outside of the state, so that reads can record
+    /// it without taking the state lock.
+    last_used: AtomicU32,
 }
 
 impl Debug for Task {
@@ -532,6 +540,7 @@ impl Task {
                 description,
                 stats_type,
             )))),
+            last_used: AtomicU32::new(0),
         }
     }
 
@@ -549,6 +558,7 @@ impl Task {
             state: RwLock::new(TaskMetaState::Full(Box::new(
                 TaskState::new_scheduled_in_scope(description, scope, stats_type),
             ))),
+            last_used: AtomicU32::new(0),
         }
     }
 
@@ -566,6 +576,7 @@ impl Task {
             state: RwLock::new(TaskMetaState::Full(Box::new(
                 TaskState::new_scheduled_in_scope(description, scope, stats_type),
             ))),
+            last_used: AtomicU32::new(0),
         }
     }
 
@@ -587,6 +598,7 @@ impl Task {
                 description,
                 stats_type,
             )))),
+            last_used: AtomicU32::new(0),
         }
     }
 
@@ -610,6 +622,7 @@ impl Task {
                 scope,
                 stats_type,
             )))),
+            last_used: AtomicU32::new(0),
         }
     }
 
@@ -994,9 +1007,8 @@ impl Task {
         {
             let mut state = self.full_state_mut();
 
-            state
-                .stats
-                .register_execution(duration, turbo_tasks.program_duration_until(instant));
+            state.stats.register_execution(duration);
+            self.gc_mark_used(turbo_tasks.program_duration_until(instant));
             match state.state_type {
                 InProgress {
                     ref mut event,
@@ -1191,6 +1203,8 @@ impl Task {
                     }
                     state.state_type = InProgressDirty { event };
                     drop(state);
+                    // The result of the running execution will be discarded
+                    turbo_tasks.cancel_execution(self.id);
                 }
             }
 
@@ -2444,9 +2458,7 @@ impl Task {
                 let compute_duration = last_duration.into();
 
                 let age = to_exp_u8(
-                    (now_relative_to_start
-                        .saturating_sub(state.stats.last_execution_relative_to_start()))
-                    .as_secs(),
+                    (now_relative_to_start.saturating_sub(self.gc_last_used())).as_secs(),
                 );
 
                 let min_prio_that_needs_total_duration = if active {
@@ -2654,6 +2666,18 @@ impl Task {
         }
     }
 
+    /// Records that the task was executed or read, so it's collected later
+    /// than tasks that haven't been used for a longer time. Doesn't lock the
+    /// task state.
+    pub(crate) fn gc_mark_used(&self, now_relative_to_start: Duration) {
+        let millis = u32::try_from(now_relative_to_start.as_millis()).unwrap_or(u32::MAX);
+        self.last_used.fetch_max(millis, atomic::Ordering::Relaxed);
+    }
+
+    fn gc_last_used(&self) -> Duration {
+        Duration::from_millis(self.last_used.load(atomic::Ordering::Relaxed) as u64)
+    }
+
     pub(crate) fn gc_compute_duration(&self) -> Duration {
         if let TaskMetaStateReadGuard::Full(state) = self.state() {
             state.stats.last_duration()
@@ -36,7 +36,7 @@ impl TaskStats {
     }
 
     /// Registers a task duration.
-    pub fn register_execution(&mut self, duration: Duration, duration_since_start: Duration) {
+    pub fn register_execution(&mut self, duration: Duration) {
         match self {
             Self::Full(stats) => {
                 stats.total_duration += duration;
@@ -44,7 +44,6 @@ impl TaskStats {
             }
             Self::Essential(stats) => {
                 stats.last_duration = duration.into();
-                stats.last_execution_relative_to_start = duration_since_start.into();
             }
         }
     }
@@ -59,7 +58,6 @@ impl TaskStats {
             }
             Self::Essential(stats) => {
                 stats.last_duration = SmallDuration::MIN;
-                stats.last_execution_relative_to_start = SmallDuration::MIN;
             }
         }
     }
@@ -71,24 +69,12 @@ impl TaskStats {
             Self::Essential(stats) => stats.last_duration(),
         }
     }
-
-    /// Returns the last execution of the task relative to the start of the
-    /// program.
-    pub fn last_execution_relative_to_start(&self) -> Duration {
-        match self {
-            Self::Full(stats) => stats.last_execution_relative_to_start(),
-            Self::Essential(stats) => stats.last_execution_relative_to_start(),
-        }
-    }
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct TaskStatsEssential {
     /// The last duration of the task, with a precision of 10 microseconds.
     last_duration: SmallDuration<10_000>,
-    /// The last execution of the task relative to the start of the program,
-    /// with a precision of 1 millisecond.
-    last_execution_relative_to_start: SmallDuration<1_000_000>,
 }
 
 impl TaskStatsEssential {
@@ -96,13 +82,6 @@ impl TaskStatsEssential {
     pub fn last_duration(&self) -> Duration {
         self.last_duration.into()
     }
-
-    /// Returns the last execution of the task relative to the start of the
-    /// program.
-    #[allow(dead_code)] // NOTE(alexkirsz) This will be useful for GC.
-    pub fn last_execution_relative_to_start(&self) -> Duration {
-        self.last_execution_relative_to_start.into()
-    }
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
@@ -113,9 +92,6 @@ pub struct TaskStatsFull {
     last_duration: Duration,
     /// The total duration of the task.
     total_duration: Duration,
-    /// The last execution of the task relative to the start of the program,
-    /// with a precision of 1 millisecond.
-    last_execution_relative_to_start: SmallDuration<1_000_000>,
 }
 
 impl TaskStatsFull {
@@ -133,11 +109,4 @@ impl TaskStatsFull {
     pub fn total_duration(&self) -> Duration {
         self.total_duration
     }
-
-    /// Returns the last execution of the task relative to the start of the
-    /// program.
-    #[allow(dead_code)] // NOTE(alexkirsz) This will be useful for GC.
-    pub fn last_execution_relative_to_start(&self) -> Duration {
-        self.last_execution_relative_to_start.into()
-    }
 }
@@ -0,0 +1,105 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use turbo_tasks::{get_invalidator, is_cancelled, run_once, Invalidator, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{register, run};
+
+register!();
+
+#[tokio::test]
+async fn invalidated_execution_is_cancelled() {
+    run! {
+        let changing = ChangingVc::cell(Changing { value: Mutex::new((0, None)) });
+        let result = wait_until_cancelled(changing);
+
+        // Give the task time to start waiting
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        changing.await?.set(1);
+
+        assert_eq!(*result.strongly_consistent().await?, 1);
+    }
+}
+
+#[tokio::test]
+async fn orphaned_once_task_is_dropped() {
+    *REGISTER;
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let dropped = Arc::new(AtomicBool::new(false));
+    let guard = SetOnDrop(dropped.clone());
+    let read = run_once(tt.clone(), async move {
+        let _guard = guard;
+        std::future::pending::<()>().await;
+        Ok(())
+    });
+    assert!(tokio::time::timeout(Duration::from_millis(50), read)
+        .await
+        .is_err());
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !dropped.load(Ordering::Acquire) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+struct Step(u32);
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Changing {
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    value: Mutex<(u32, Option<Invalidator>)>,
+}
+
+impl Changing {
+    fn set(&self, step: u32) {
+        let mut lock = self.value.lock().unwrap();
+        lock.0 = step;
+        if let Some(invalidator) = lock.1.take() {
+            invalidator.invalidate();
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ChangingVc {
+    #[turbo_tasks::function]
+    async fn get(self) -> Result<StepVc> {
+        let this = self.await?;
+        let mut lock = this.value.lock().unwrap();
+        lock.1 = Some(get_invalidator());
+        Ok(StepVc::cell(lock.0))
+    }
+}
+
+#[turbo_tasks::function]
+async fn wait_until_cancelled(changing: ChangingVc) -> Result<StepVc> {
+    let step = *changing.get().await?;
+    if step == 0 {
+        // Never finishes unless the execution is cancelled
+        while !is_cancelled() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        bail!("cancelled");
+    }
+    Ok(StepVc::cell(step))
+}
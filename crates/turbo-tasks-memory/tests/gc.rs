@@ -0,0 +1,77 @@
+use std::{cmp::Reverse, collections::HashSet, time::Duration};
+
+use turbo_tasks::TaskId;
+use turbo_tasks_memory::gc::{collect_factor, to_exp_u8, GcPriority, GcQueue};
+
+/// Seconds since each task was last used.
+const LAST_USED: [(usize, u64); 8] = [
+    (1, 1),
+    (2, 3),
+    (3, 10),
+    (4, 30),
+    (5, 60),
+    (6, 600),
+    (7, 3600),
+    (8, 86400),
+];
+
+fn queue_with_ages() -> GcQueue {
+    let queue = GcQueue::new();
+    for (task, _) in LAST_USED {
+        queue.task_executed(TaskId::from(task), Duration::from_millis(10));
+    }
+    // The first pass only computes the age of each task, like `Task::run_gc`
+    // does, and requeues it with that priority.
+    let (_, count) = queue
+        .select_tasks(u8::MAX, |task, _, _| {
+            let (_, secs) = LAST_USED.iter().find(|(id, _)| *id == *task).unwrap();
+            Some(GcPriority::InactiveUnload {
+                age: Reverse(to_exp_u8(*secs)),
+                total_compute_duration: 0,
+            })
+        })
+        .unwrap();
+    assert_eq!(count, LAST_USED.len());
+    queue
+}
+
+#[test]
+fn evicts_least_recently_used_first() {
+    let queue = queue_with_ages();
+
+    let mut evicted = HashSet::new();
+    queue.select_tasks(u8::MAX / 2 + 1, |task, _, _| {
+        evicted.insert(*task);
+        None
+    });
+    assert_eq!(evicted, HashSet::from([5, 6, 7, 8]));
+
+    let mut evicted = Vec::new();
+    queue.select_tasks(u8::MAX, |task, _, _| {
+        evicted.push(*task);
+        None
+    });
+    evicted.sort();
+    assert_eq!(evicted, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn respects_the_memory_limit() {
+    const LIMIT: usize = 1024 * 1024;
+
+    // Nothing is collected below the target.
+    assert_eq!(collect_factor(0, LIMIT, false), None);
+    assert_eq!(collect_factor(LIMIT * 7 / 8 - 1, LIMIT, false), None);
+    // The idle target is lower, so idle collection starts earlier.
+    assert_eq!(collect_factor(LIMIT * 3 / 4 - 1, LIMIT, true), None);
+    assert!(collect_factor(LIMIT * 7 / 8 - 1, LIMIT, true).is_some());
+
+    // The amount of work grows with the usage, but is capped so a single
+    // collection never evicts everything.
+    let slightly_over = collect_factor(LIMIT * 7 / 8 + LIMIT / 1024, LIMIT, false).unwrap();
+    let at_limit = collect_factor(LIMIT, LIMIT, false).unwrap();
+    let far_over = collect_factor(LIMIT * 4, LIMIT, false).unwrap();
+    assert!(slightly_over < at_limit);
+    assert_eq!(at_limit, u8::MAX / 8);
+    assert_eq!(far_over, u8::MAX / 8);
+}
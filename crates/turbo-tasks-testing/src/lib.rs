@@ -222,6 +222,10 @@ impl TurboTasksApi for VcStorage {
         // no-op
     }
 
+    fn mark_own_task_as_session_dependent(&self, _task: TaskId) {
+        // no-op
+    }
+
     fn detached(
         &self,
         _f: std::pin::Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
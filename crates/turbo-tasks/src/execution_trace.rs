@@ -0,0 +1,399 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use indexmap::{IndexMap, IndexSet};
+use serde_json::json;
+
+use crate::TaskId;
+
+/// Why a task was (re)computed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecomputationCause {
+    /// The task wasn't invalidated since the trace has been started, usually
+    /// because it's executed for the first time
+    Initial,
+    /// A cell or the output the task depends on has been changed by the
+    /// execution of this task
+    Task(TaskId),
+    /// The task has been invalidated from outside of the task graph, e.g. by a
+    /// file watcher, with the user-facing reason if there is one
+    External(Option<String>),
+}
+
+/// A single execution of a task in an [ExecutionTrace]. Times are relative to
+/// the start of the trace.
+#[derive(Clone, Debug)]
+pub struct TaskExecution {
+    pub task: TaskId,
+    pub start: Duration,
+    pub end: Duration,
+    /// The time the task was actually running, excluding the time it was
+    /// waiting for other tasks
+    pub busy: Duration,
+    pub cause: RecomputationCause,
+}
+
+/// A recording of task executions, the dependencies between tasks and the
+/// causes of recomputations. See
+/// [TurboTasks::start_execution_trace](crate::TurboTasks::start_execution_trace).
+///
+/// It can be exported into the Chrome trace format, which can be viewed with
+/// https://ui.perfetto.dev/, or into folded stacks for flamegraph tools, to see
+/// which invalidations cascade into long rebuilds.
+pub struct ExecutionTrace {
+    start: Instant,
+    executions: Vec<TaskExecution>,
+    descriptions: HashMap<TaskId, String>,
+    /// Pairs of reader and the task that has been read, in the order of the
+    /// first read
+    dependencies: IndexSet<(TaskId, TaskId)>,
+    /// Causes of invalidations that haven't lead to an execution yet
+    pending_causes: HashMap<TaskId, RecomputationCause>,
+    in_progress: HashMap<TaskId, (Duration, RecomputationCause)>,
+}
+
+impl ExecutionTrace {
+    pub(crate) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            executions: Vec::new(),
+            descriptions: HashMap::new(),
+            dependencies: IndexSet::new(),
+            pending_causes: HashMap::new(),
+            in_progress: HashMap::new(),
+        }
+    }
+
+    /// Starts a new trace that takes over the in progress executions and the
+    /// pending invalidations, so nothing is lost when traces are taken
+    /// continuously.
+    pub(crate) fn take(&mut self) -> Self {
+        let mut next = Self::new();
+        next.pending_causes = self.pending_causes.clone();
+        next.in_progress = self
+            .in_progress
+            .iter()
+            .map(|(&task, (_, cause))| (task, (Duration::ZERO, cause.clone())))
+            .collect();
+        std::mem::replace(self, next)
+    }
+
+    fn since_start(&self, instant: Instant) -> Duration {
+        instant.saturating_duration_since(self.start)
+    }
+
+    pub(crate) fn invalidated(&mut self, task: TaskId, cause: RecomputationCause) {
+        // The first invalidation is the one that causes the recomputation
+        self.pending_causes.entry(task).or_insert(cause);
+    }
+
+    pub(crate) fn dependency(&mut self, reader: TaskId, task: TaskId) {
+        self.dependencies.insert((reader, task));
+    }
+
+    pub(crate) fn execution_started(&mut self, task: TaskId, start: Instant) {
+        let cause = self
+            .pending_causes
+            .remove(&task)
+            .unwrap_or(RecomputationCause::Initial);
+        let start = self.since_start(start);
+        self.in_progress.insert(task, (start, cause));
+    }
+
+    pub(crate) fn execution_finished(
+        &mut self,
+        task: TaskId,
+        end: Instant,
+        busy: Duration,
+        description: String,
+    ) {
+        let Some((start, cause)) = self.in_progress.remove(&task) else {
+            return;
+        };
+        self.descriptions.insert(task, description);
+        self.executions.push(TaskExecution {
+            task,
+            start,
+            end: self.since_start(end),
+            busy,
+            cause,
+        });
+    }
+
+    /// All executions that finished while tracing, in order of their start
+    pub fn executions(&self) -> Vec<&TaskExecution> {
+        let mut executions: Vec<_> = self.executions.iter().collect();
+        executions.sort_by_key(|execution| (execution.start, execution.end));
+        executions
+    }
+
+    /// All pairs of reader and the task that has been read while tracing
+    pub fn dependencies(&self) -> impl Iterator<Item = (TaskId, TaskId)> + '_ {
+        self.dependencies.iter().copied()
+    }
+
+    pub fn description(&self, task: TaskId) -> String {
+        self.descriptions
+            .get(&task)
+            .cloned()
+            .unwrap_or_else(|| format!("[{}] unknown", *task))
+    }
+
+    /// The task that lead to the creation of a task, which is the first task
+    /// that read it
+    fn first_reader(&self) -> HashMap<TaskId, TaskId> {
+        let mut first_reader = HashMap::new();
+        for &(reader, task) in self.dependencies.iter() {
+            first_reader.entry(task).or_insert(reader);
+        }
+        first_reader
+    }
+
+    /// Writes the trace in the Chrome trace format. Executions are complete
+    /// events distributed on virtual threads, recomputations caused by other
+    /// tasks are flow events from the cause to the recomputation.
+    pub fn write_chrome_trace(&self, mut writer: impl Write) -> io::Result<()> {
+        fn micros(duration: Duration) -> f64 {
+            duration.as_nanos() as f64 / 1000.0
+        }
+
+        let executions = self.executions();
+        let lanes = assign_lanes(executions.iter().map(|e| (e.start, e.end)));
+        let mut dependencies: HashMap<TaskId, Vec<String>> = HashMap::new();
+        for &(reader, task) in self.dependencies.iter() {
+            dependencies
+                .entry(reader)
+                .or_default()
+                .push(self.description(task));
+        }
+
+        let mut events = Vec::new();
+        // The most recent execution of each task, to connect flows to it
+        let mut latest: HashMap<TaskId, (Duration, usize)> = HashMap::new();
+        for (i, (execution, &lane)) in executions.iter().zip(lanes.iter()).enumerate() {
+            let cause = match &execution.cause {
+                RecomputationCause::Initial => "initial".to_string(),
+                RecomputationCause::Task(task) => {
+                    format!("invalidated by {}", self.description(*task))
+                }
+                RecomputationCause::External(Some(reason)) => reason.clone(),
+                RecomputationCause::External(None) => "external invalidation".to_string(),
+            };
+            events.push(json!({
+                "name": self.description(execution.task),
+                "cat": "task",
+                "ph": "X",
+                "ts": micros(execution.start),
+                "dur": micros(execution.end - execution.start),
+                "pid": 1,
+                "tid": lane,
+                "args": {
+                    "cause": cause,
+                    "busy": micros(execution.busy),
+                    "dependencies": dependencies.get(&execution.task),
+                },
+            }));
+            if let RecomputationCause::Task(cause) = execution.cause {
+                if let Some(&(cause_end, cause_lane)) = latest.get(&cause) {
+                    events.push(json!({
+                        "name": "invalidation",
+                        "cat": "invalidation",
+                        "ph": "s",
+                        "id": i,
+                        "ts": micros(cause_end),
+                        "pid": 1,
+                        "tid": cause_lane,
+                    }));
+                    events.push(json!({
+                        "name": "invalidation",
+                        "cat": "invalidation",
+                        "ph": "f",
+                        "bp": "e",
+                        "id": i,
+                        "ts": micros(execution.start),
+                        "pid": 1,
+                        "tid": lane,
+                    }));
+                }
+            }
+            latest.insert(execution.task, (execution.end, lane));
+        }
+        serde_json::to_writer(&mut writer, &events)?;
+        writer.flush()
+    }
+
+    /// Writes the trace as folded stacks, with the busy time in microseconds as
+    /// value. The stack of an execution is the chain of recomputation causes
+    /// leading to it, so flamegraphs show how invalidations cascade. Tasks
+    /// executed for the first time are attributed to the task that read them
+    /// first.
+    pub fn write_folded_stacks(&self, mut writer: impl Write) -> io::Result<()> {
+        let first_reader = self.first_reader();
+        let names: HashMap<TaskId, String> = self
+            .descriptions
+            .iter()
+            .map(|(&task, description)| (task, folded_frame(description)))
+            .collect();
+        let name =
+            |task: TaskId| -> &str { names.get(&task).map_or("unknown", |name| name.as_str()) };
+
+        // The stack of the most recent execution of each task
+        let mut stacks: HashMap<TaskId, Vec<&str>> = HashMap::new();
+        let mut folded: IndexMap<String, u128> = IndexMap::new();
+        for execution in self.executions() {
+            let parent = match &execution.cause {
+                RecomputationCause::Initial => first_reader.get(&execution.task).copied(),
+                RecomputationCause::Task(task) => Some(*task),
+                RecomputationCause::External(_) => None,
+            };
+            let mut stack = match (parent, &execution.cause) {
+                (Some(parent), _) => stacks
+                    .get(&parent)
+                    .cloned()
+                    .unwrap_or_else(|| vec![name(parent)]),
+                (None, RecomputationCause::External(Some(reason))) => vec![reason.as_str()],
+                (None, RecomputationCause::External(None)) => vec!["external invalidation"],
+                (None, _) => Vec::new(),
+            };
+            stack.push(name(execution.task));
+            let busy = execution.busy.as_micros();
+            if busy > 0 {
+                let frames: Vec<_> = stack.iter().map(|frame| frame.replace(';', ",")).collect();
+                *folded.entry(frames.join(";")).or_default() += busy;
+            }
+            stacks.insert(execution.task, stack);
+        }
+        for (stack, busy) in folded {
+            writeln!(writer, "{stack} {busy}")?;
+        }
+        writer.flush()
+    }
+}
+
+/// Strips the task id from a task description, so executions of the same
+/// function are merged in flamegraphs
+fn folded_frame(description: &str) -> String {
+    description
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .filter(|(id, _)| id.chars().all(|c| c.is_ascii_digit()))
+        .map_or(description, |(_, name)| name)
+        .to_string()
+}
+
+/// Distributes the time ranges on as few lanes as possible, so that ranges on
+/// the same lane don't overlap. Expects the ranges to be sorted by start.
+fn assign_lanes(ranges: impl Iterator<Item = (Duration, Duration)>) -> Vec<usize> {
+    let mut busy = BinaryHeap::new();
+    let mut free = BinaryHeap::new();
+    let mut lane_count = 0;
+    ranges
+        .map(|(start, end)| {
+            while let Some(&Reverse((busy_until, lane))) = busy.peek() {
+                if busy_until > start {
+                    break;
+                }
+                busy.pop();
+                free.push(Reverse(lane));
+            }
+            let lane = free.pop().map_or_else(
+                || {
+                    lane_count += 1;
+                    lane_count - 1
+                },
+                |Reverse(lane)| lane,
+            );
+            busy.push(Reverse((end, lane)));
+            lane
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn execute(trace: &mut ExecutionTrace, task: usize, name: &str, start: u64, end: u64) {
+        let task = TaskId::from(task);
+        trace.execution_started(task, trace.start + ms(start));
+        trace.execution_finished(
+            task,
+            trace.start + ms(end),
+            ms(end - start),
+            format!("[{}] {}", *task, name),
+        );
+    }
+
+    fn folded_stacks(trace: &ExecutionTrace) -> String {
+        let mut out = Vec::new();
+        trace.write_folded_stacks(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_assign_lanes() {
+        let ranges = [(0, 10), (2, 5), (5, 8), (6, 12), (10, 11), (13, 14)];
+        let lanes = assign_lanes(ranges.iter().map(|&(start, end)| (ms(start), ms(end))));
+        assert_eq!(lanes, vec![0, 1, 1, 2, 0, 0]);
+    }
+
+    #[test]
+    fn test_folded_frame() {
+        assert_eq!(folded_frame("[12] parse"), "parse");
+        assert_eq!(folded_frame("[3] [resolve] parse"), "[resolve] parse");
+        assert_eq!(folded_frame("[resolve] parse"), "[resolve] parse");
+    }
+
+    #[test]
+    fn test_folded_stacks_follow_causes() {
+        let mut trace = ExecutionTrace::new();
+        let (root, read, parse) = (TaskId::from(1), TaskId::from(2), TaskId::from(3));
+        trace.dependency(root, parse);
+        trace.dependency(parse, read);
+        execute(&mut trace, 1, "root", 0, 10);
+        execute(&mut trace, 3, "parse", 1, 9);
+        execute(&mut trace, 2, "read", 2, 3);
+
+        trace.invalidated(
+            read,
+            RecomputationCause::External(Some("a.js changed".into())),
+        );
+        execute(&mut trace, 2, "read", 20, 22);
+        trace.invalidated(parse, RecomputationCause::Task(read));
+        // Only the first invalidation is the cause
+        trace.invalidated(parse, RecomputationCause::External(None));
+        execute(&mut trace, 3, "parse", 22, 30);
+
+        assert_eq!(
+            folded_stacks(&trace),
+            "root 10000\nroot;parse 8000\nroot;parse;read 1000\na.js changed;read 2000\na.js \
+             changed;read;parse 8000\n"
+        );
+    }
+
+    #[test]
+    fn test_take_keeps_pending_state() {
+        let mut trace = ExecutionTrace::new();
+        let (a, b) = (TaskId::from(1), TaskId::from(2));
+        trace.invalidated(a, RecomputationCause::External(None));
+        trace.execution_started(b, Instant::now());
+
+        let taken = trace.take();
+        assert!(taken.executions().is_empty());
+        trace.execution_started(a, Instant::now());
+        trace.execution_finished(a, Instant::now(), ms(1), "[1] a".into());
+        trace.execution_finished(b, Instant::now(), ms(1), "[2] b".into());
+        let causes: Vec<_> = trace.executions().iter().map(|e| e.cause.clone()).collect();
+        assert_eq!(causes.len(), 2);
+        assert!(causes.contains(&RecomputationCause::External(None)));
+        assert!(causes.contains(&RecomputationCause::Initial));
+    }
+}
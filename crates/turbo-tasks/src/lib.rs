@@ -40,6 +40,7 @@ pub mod debug;
 mod display;
 pub mod duration_span;
 pub mod event;
+pub mod execution_trace;
 pub mod graph;
 mod id;
 mod id_factory;
@@ -81,10 +82,10 @@ pub use invalidation::{
 };
 pub use join_iter_ext::{JoinIterExt, TryJoinIterExt};
 pub use manager::{
-    dynamic_call, emit, get_invalidator, mark_finished, mark_stateful, run_once,
-    run_once_with_reason, spawn_blocking, spawn_thread, trait_call, turbo_tasks, Invalidator,
-    StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi, TurboTasksCallApi,
-    Unused, UpdateInfo,
+    dynamic_call, emit, get_invalidator, is_cancelled, mark_finished, mark_session_dependent,
+    mark_stateful, run_once, run_once_with_reason, spawn_blocking, spawn_thread, trait_call,
+    turbo_tasks, Invalidator, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi,
+    TurboTasksBackendApi, TurboTasksCallApi, Unused, UpdateInfo,
 };
 pub use native_function::{NativeFunction, NativeFunctionVc};
 pub use nothing::{Nothing, NothingVc};
@@ -15,8 +15,9 @@ use std::{
     time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use auto_hash_map::AutoSet;
+use dashmap::DashMap;
 use futures::FutureExt;
 use nohash_hasher::BuildNoHashHasher;
 use serde::{de::Visitor, Deserialize, Serialize};
@@ -26,6 +27,7 @@ use tracing::{instrument, trace_span, Instrument, Level};
 use crate::{
     backend::{Backend, CellContent, PersistentTaskType, TransientTaskType},
     event::{Event, EventListener},
+    execution_trace::{ExecutionTrace, RecomputationCause},
     id::{BackendJobId, FunctionId, TraitTypeId},
     id_factory::IdFactory,
     invalidation::InvalidationReasonSet,
@@ -117,6 +119,7 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     fn read_own_task_cell(&self, task: TaskId, index: CellId) -> Result<CellContent>;
     fn update_own_task_cell(&self, task: TaskId, index: CellId, content: CellContent);
     fn mark_own_task_as_finished(&self, task: TaskId);
+    fn mark_own_task_as_session_dependent(&self, task: TaskId);
 
     fn connect_task(&self, task: TaskId);
 
@@ -207,6 +210,10 @@ pub trait TurboTasksBackendApi<B: Backend + 'static>:
     /// eventually call `invalidate_tasks()` on all tasks.
     fn schedule_notify_tasks_set(&self, tasks: &AutoSet<TaskId, BuildNoHashHasher<TaskId>>);
 
+    /// Signals the running execution of the task that its result is no longer
+    /// needed, because the task will be executed again. See [is_cancelled].
+    fn cancel_execution(&self, task: TaskId);
+
     /// Returns the stats reporting type.
     fn stats_type(&self) -> StatsType;
     /// Sets the stats reporting type.
@@ -276,6 +283,11 @@ pub struct TurboTasks<B: Backend + 'static> {
     // locking overhead.
     enable_full_stats: AtomicBool,
     program_start: Instant,
+    // Checked before locking `execution_trace` to avoid the locking overhead when not tracing.
+    enable_execution_trace: AtomicBool,
+    execution_trace: Mutex<Option<ExecutionTrace>>,
+    /// The cancellation flags of the running task executions
+    running_executions: DashMap<TaskId, Arc<AtomicBool>, BuildNoHashHasher<TaskId>>,
 }
 
 #[derive(Default)]
@@ -287,6 +299,9 @@ struct CurrentTaskState {
 
     // true, if the current task has state in cells
     stateful: bool,
+
+    /// Set when the result of the execution is no longer needed
+    cancelled: Arc<AtomicBool>,
 }
 
 // TODO implement our own thread pool and make these thread locals instead
@@ -327,6 +342,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
             event_background: Event::new(|| "TurboTasks::event_background".to_string()),
             enable_full_stats: AtomicBool::new(false),
             program_start: Instant::now(),
+            enable_execution_trace: AtomicBool::new(false),
+            execution_trace: Mutex::new(None),
+            running_executions: DashMap::default(),
         });
         this.backend.startup(&*this);
         this
@@ -372,9 +390,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
     ) -> Result<T> {
         let (tx, rx) = tokio::sync::oneshot::channel();
         let task_id = self.spawn_once_task(async move {
-            let result = future.await?;
-            tx.send(result)
-                .map_err(|_| anyhow!("unable to send result"))?;
+            run_and_send(future, tx).await?;
             Ok(CompletionVc::new().into())
         });
         // INVALIDATION: A Once task will never invalidate, therefore we don't need to
@@ -458,10 +474,17 @@ impl<B: Backend + 'static> TurboTasks<B> {
                     // Setup thread locals
                     let execution_future = CELL_COUNTERS.scope(Default::default(), async {
                         let execution = this.backend.try_start_task_execution(task_id, &*this)?;
-                        Some(
+                        this.with_execution_trace(|trace| {
+                            trace.execution_started(task_id, Instant::now())
+                        });
+                        let cancelled =
+                            CURRENT_TASK_STATE.with(|cell| cell.borrow().cancelled.clone());
+                        this.running_executions.insert(task_id, cancelled);
+                        let result =
                             TimedFuture::new(AssertUnwindSafe(execution.future).catch_unwind())
-                                .await,
-                        )
+                                .await;
+                        this.running_executions.remove(&task_id);
+                        Some(result)
                     });
                     if let Some((result, duration, instant)) = execution_future.await {
                         if cfg!(feature = "log_function_stats") && duration.as_millis() > 1000 {
@@ -483,6 +506,14 @@ impl<B: Backend + 'static> TurboTasks<B> {
                         let reexecute = this
                             .backend
                             .task_execution_completed(task_id, duration, instant, stateful, &*this);
+                        if this.enable_execution_trace.load(Ordering::Acquire) {
+                            // The description is computed before locking the trace, as the
+                            // backend might record to the trace while holding task locks
+                            let description = this.backend.get_task_description(task_id);
+                            this.with_execution_trace(|trace| {
+                                trace.execution_finished(task_id, instant, duration, description)
+                            });
+                        }
                         if !reexecute {
                             return false;
                         }
@@ -797,6 +828,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
             let CurrentTaskState {
                 tasks_to_notify,
                 stateful,
+                ..
             } = &mut *cell.borrow_mut();
             let tasks = take(tasks_to_notify);
             if !tasks.is_empty() {
@@ -810,6 +842,53 @@ impl<B: Backend + 'static> TurboTasks<B> {
     pub fn backend(&self) -> &B {
         &self.backend
     }
+
+    /// Starts recording task executions, dependencies between tasks and the
+    /// causes of recomputations into an [ExecutionTrace]. This slows down
+    /// execution, so it should only be enabled when needed.
+    pub fn start_execution_trace(&self) {
+        let mut execution_trace = self.execution_trace.lock().unwrap();
+        execution_trace.get_or_insert_with(ExecutionTrace::new);
+        self.enable_execution_trace.store(true, Ordering::Release);
+    }
+
+    /// Returns everything recorded since the trace has been started or taken
+    /// the last time, and continues recording into a new trace. Returns `None`
+    /// when not tracing.
+    pub fn take_execution_trace(&self) -> Option<ExecutionTrace> {
+        let mut execution_trace = self.execution_trace.lock().unwrap();
+        execution_trace.as_mut().map(ExecutionTrace::take)
+    }
+
+    /// Stops recording and returns the trace. Returns `None` when not tracing.
+    pub fn stop_execution_trace(&self) -> Option<ExecutionTrace> {
+        self.enable_execution_trace.store(false, Ordering::Release);
+        self.execution_trace.lock().unwrap().take()
+    }
+
+    fn with_execution_trace(&self, func: impl FnOnce(&mut ExecutionTrace)) {
+        if !self.enable_execution_trace.load(Ordering::Acquire) {
+            return;
+        }
+        if let Some(trace) = &mut *self.execution_trace.lock().unwrap() {
+            func(trace);
+        }
+    }
+
+    fn trace_invalidation(&self, tasks: impl IntoIterator<Item = TaskId>) {
+        self.with_execution_trace(|trace| {
+            let cause = CURRENT_TASK_ID
+                .try_with(|&task| RecomputationCause::Task(task))
+                .unwrap_or(RecomputationCause::External(None));
+            for task in tasks {
+                trace.invalidated(task, cause.clone());
+            }
+        });
+    }
+
+    fn trace_dependency(&self, reader: TaskId, task: TaskId) {
+        self.with_execution_trace(|trace| trace.dependency(reader, task));
+    }
 }
 
 impl<B: Backend + 'static> TurboTasksCallApi for TurboTasks<B> {
@@ -873,11 +952,17 @@ impl<B: Backend + 'static> TurboTasksCallApi for TurboTasks<B> {
 impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     #[instrument(level = Level::INFO, skip_all, name = "invalidate")]
     fn invalidate(&self, task: TaskId) {
+        self.with_execution_trace(|trace| {
+            trace.invalidated(task, RecomputationCause::External(None))
+        });
         self.backend.invalidate_task(task, self);
     }
 
     #[instrument(level = Level::INFO, skip_all, name = "invalidate", fields(name = display(&reason)))]
     fn invalidate_with_reason(&self, task: TaskId, reason: StaticOrArc<dyn InvalidationReason>) {
+        self.with_execution_trace(|trace| {
+            trace.invalidated(task, RecomputationCause::External(Some(reason.to_string())))
+        });
         {
             let (_, reason_set) = &mut *self.aggregated_update.lock().unwrap();
             reason_set.insert(reason);
@@ -903,12 +988,10 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         strongly_consistent: bool,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend.try_read_task_output(
-            task,
-            current_task("reading Vcs"),
-            strongly_consistent,
-            self,
-        )
+        let reader = current_task("reading Vcs");
+        self.trace_dependency(reader, task);
+        self.backend
+            .try_read_task_output(task, reader, strongly_consistent, self)
     }
 
     fn try_read_task_output_untracked(
@@ -925,8 +1008,9 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
-        self.backend
-            .try_read_task_cell(task, index, current_task("reading Vcs"), self)
+        let reader = current_task("reading Vcs");
+        self.trace_dependency(reader, task);
+        self.backend.try_read_task_cell(task, index, reader, self)
     }
 
     fn try_read_task_cell_untracked(
@@ -947,12 +1031,10 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     }
 
     fn read_task_collectibles(&self, task: TaskId, trait_id: TraitTypeId) -> RawVcSetVc {
-        self.backend.read_task_collectibles(
-            task,
-            trait_id,
-            current_task("reading collectibles"),
-            self,
-        )
+        let reader = current_task("reading collectibles");
+        self.trace_dependency(reader, task);
+        self.backend
+            .read_task_collectibles(task, trait_id, reader, self)
     }
 
     fn emit_collectible(&self, trait_type: TraitTypeId, collectible: RawVc) {
@@ -1002,6 +1084,10 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         self.backend.mark_own_task_as_finished(task, self);
     }
 
+    fn mark_own_task_as_session_dependent(&self, task: TaskId) {
+        self.backend.mark_own_task_as_session_dependent(task, self);
+    }
+
     fn detached(
         &self,
         f: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
@@ -1085,6 +1171,7 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
             } = &mut *cell.borrow_mut();
             tasks_to_notify.extend(tasks.iter());
         });
+        self.trace_invalidation(tasks.iter().copied());
         if result.is_err() {
             let _guard = trace_span!("schedule_notify_tasks", count = tasks.len()).entered();
             self.backend.invalidate_tasks(tasks.to_vec(), self);
@@ -1100,6 +1187,7 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
             } = &mut *cell.borrow_mut();
             tasks_to_notify.extend(tasks.iter());
         });
+        self.trace_invalidation(tasks.iter().copied());
         if result.is_err() {
             let _guard = trace_span!("schedule_notify_tasks_set", count = tasks.len()).entered();
             self.backend
@@ -1107,6 +1195,12 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
         };
     }
 
+    fn cancel_execution(&self, task: TaskId) {
+        if let Some(cancelled) = self.running_executions.get(&task) {
+            cancelled.store(true, Ordering::Release);
+        }
+    }
+
     #[track_caller]
     fn schedule(&self, task: TaskId) {
         self.schedule(task)
@@ -1264,12 +1358,7 @@ pub async fn run_once<T: Send + 'static>(
 ) -> Result<T> {
     let (tx, rx) = tokio::sync::oneshot::channel();
 
-    let task_id = tt.run_once(Box::pin(async move {
-        let result = future.await?;
-        tx.send(result)
-            .map_err(|_| anyhow!("unable to send result"))?;
-        Ok(())
-    }));
+    let task_id = tt.run_once(Box::pin(run_and_send(future, tx)));
 
     // INVALIDATION: A Once task will never invalidate, therefore we don't need to
     // track a dependency
@@ -1288,12 +1377,7 @@ pub async fn run_once_with_reason<T: Send + 'static>(
 
     let task_id = tt.run_once_with_reason(
         (Arc::new(reason) as Arc<dyn InvalidationReason>).into(),
-        Box::pin(async move {
-            let result = future.await?;
-            tx.send(result)
-                .map_err(|_| anyhow!("unable to send result"))?;
-            Ok(())
-        }),
+        Box::pin(run_and_send(future, tx)),
     );
 
     // INVALIDATION: A Once task will never invalidate, therefore we don't need to
@@ -1304,6 +1388,22 @@ pub async fn run_once_with_reason<T: Send + 'static>(
     Ok(rx.await?)
 }
 
+/// Runs the `future` of a once task and sends its result. The `future` is
+/// dropped when the receiver is dropped, e.g. when a client disconnected while
+/// waiting for a strongly consistent read, so orphaned reads stop running.
+async fn run_and_send<T>(
+    future: impl Future<Output = Result<T>>,
+    mut tx: tokio::sync::oneshot::Sender<T>,
+) -> Result<()> {
+    let result = select! {
+        result = future => result?,
+        () = tx.closed() => bail!("the result of the once task is no longer needed"),
+    };
+    tx.send(result)
+        .map_err(|_| anyhow!("unable to send result"))?;
+    Ok(())
+}
+
 /// see [TurboTasks] `dynamic_call`
 pub fn dynamic_call(func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
     with_turbo_tasks(|tt| tt.dynamic_call(func, inputs))
@@ -1372,6 +1472,28 @@ pub fn mark_finished() {
     });
 }
 
+/// Marks the current task as session dependent. Its result depends on the
+/// state of the outside world, e.g. the file system, which can change between
+/// sessions. A persisted graph doesn't reuse its result in a new session
+/// without executing it again.
+pub fn mark_session_dependent() {
+    with_turbo_tasks(|tt| {
+        tt.mark_own_task_as_session_dependent(current_task("turbo_tasks::mark_session_dependent()"))
+    });
+}
+
+/// Returns true when the result of the current task execution is no longer
+/// needed, because the task has been invalidated while executing and will be
+/// executed again. Long running tasks can check this to stop early, as their
+/// result is discarded anyway.
+///
+/// Always returns false outside of a task execution, e.g. in [spawn_blocking].
+pub fn is_cancelled() -> bool {
+    CURRENT_TASK_STATE
+        .try_with(|cell| cell.borrow().cancelled.load(Ordering::Acquire))
+        .unwrap_or(false)
+}
+
 /// Marks the current task as stateful. This prevents the tasks from being
 /// dropped without persisting the state.
 pub fn mark_stateful() {
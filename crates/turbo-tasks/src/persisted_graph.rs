@@ -32,6 +32,10 @@ pub struct ReadTaskState {
 
 pub struct PersistTaskState {
     pub externally_active: bool,
+    /// The task depends on the outside world (see
+    /// [crate::mark_session_dependent]), so it needs to be executed again in a
+    /// new session
+    pub session_dependent: bool,
 }
 
 /*
@@ -199,7 +203,8 @@ pub trait PersistedGraph: Sync + Send {
 pub trait PersistedGraphApi {
     fn get_or_create_task_type(&self, ty: PersistentTaskType) -> TaskId;
 
-    fn lookup_task_type(&self, id: TaskId) -> &PersistentTaskType;
+    /// Returns `None` for transient tasks, which can't be persisted.
+    fn lookup_task_type(&self, id: TaskId) -> Option<&PersistentTaskType>;
 }
 
 /*
@@ -14,6 +14,7 @@ use turbopack_core::{
     },
     environment::EnvironmentVc,
     ident::AssetIdentVc,
+    source_map::{SourceMapsType, SourceMapsTypeVc},
 };
 use turbopack_css::chunk::CssChunkVc;
 use turbopack_ecmascript::{
@@ -37,6 +38,11 @@ impl BuildChunkingContextBuilder {
         self
     }
 
+    pub fn source_maps_type(mut self, source_maps_type: SourceMapsType) -> Self {
+        self.context.source_maps_type = source_maps_type;
+        self
+    }
+
     /// Builds the chunking context.
     pub fn build(self) -> BuildChunkingContextVc {
         BuildChunkingContextVc::new(Value::new(self.context))
@@ -62,6 +68,8 @@ pub struct BuildChunkingContext {
     environment: EnvironmentVc,
     /// The kind of runtime to include in the output.
     runtime_type: RuntimeType,
+    /// Whether source maps are separate files or inlined into the chunks
+    source_maps_type: SourceMapsType,
 }
 
 impl BuildChunkingContextVc {
@@ -82,6 +90,7 @@ impl BuildChunkingContextVc {
                 layer: None,
                 environment,
                 runtime_type: Default::default(),
+                source_maps_type: SourceMapsType::External,
             },
         }
     }
@@ -132,6 +141,7 @@ impl BuildChunkingContextVc {
         module: EcmascriptModuleAssetVc,
         evaluatable_assets: EvaluatableAssetsVc,
     ) -> Result<AssetVc> {
+        let evaluatable_assets = evaluatable_assets.for_chunking_context(self_vc.into());
         let entry_chunk = module.as_root_chunk(self_vc.into());
 
         let assets = self_vc
@@ -228,7 +238,12 @@ impl ChunkingContext for BuildChunkingContext {
 
     #[turbo_tasks::function]
     fn reference_chunk_source_maps(&self, _chunk: AssetVc) -> BoolVc {
-        BoolVc::cell(true)
+        BoolVc::cell(self.source_maps_type == SourceMapsType::External)
+    }
+
+    #[turbo_tasks::function]
+    fn source_maps_type(&self) -> SourceMapsTypeVc {
+        self.source_maps_type.cell()
     }
 
     #[turbo_tasks::function]
@@ -303,6 +318,7 @@ impl ChunkingContext for BuildChunkingContext {
         entry_chunk: ChunkVc,
         evaluatable_assets: EvaluatableAssetsVc,
     ) -> Result<AssetsVc> {
+        let evaluatable_assets = evaluatable_assets.for_chunking_context(self_vc.into());
         let mut assets = self_vc
             .get_evaluate_chunk_assets(entry_chunk, evaluatable_assets)
             .await?;
@@ -3,9 +3,10 @@ use std::io::Write;
 use anyhow::Result;
 use indoc::writedoc;
 use turbo_tasks::{TryJoinIterExt, Value};
-use turbo_tasks_fs::File;
+use turbo_tasks_fs::{rope::RopeBuilder, File};
 use turbopack_core::{
     asset::{Asset, AssetContentVc},
+    chunk::ChunkingContext,
     code_builder::{CodeBuilder, CodeVc},
     source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc},
 };
@@ -43,7 +44,6 @@ impl EcmascriptBuildNodeChunkContentVc {
     #[turbo_tasks::function]
     async fn code(self) -> Result<CodeVc> {
         let this = self.await?;
-        let chunk_path = this.chunk.ident().path().await?;
 
         let mut code = CodeBuilder::default();
 
@@ -76,19 +76,30 @@ impl EcmascriptBuildNodeChunkContentVc {
 
         write!(code, "\n}};")?;
 
-        if code.has_source_map() {
-            let filename = chunk_path.file_name();
-            write!(code, "\n\n//# sourceMappingURL={}.map", filename)?;
-        }
-
         let code = code.build();
         Ok(code.cell())
     }
 
     #[turbo_tasks::function]
     pub async fn content(self_vc: EcmascriptBuildNodeChunkContentVc) -> Result<AssetContentVc> {
-        let code = self_vc.code().await?;
-        Ok(File::from(code.source_code().clone()).into())
+        let this = self_vc.await?;
+        let code_vc = self_vc.code();
+        let code = code_vc.await?;
+        let mut source_code = RopeBuilder::default();
+        source_code += code.source_code();
+
+        if code.has_source_map() {
+            let chunk_path = this.chunk.ident().path().await?;
+            let url = code_vc
+                .source_mapping_url(
+                    chunk_path.file_name(),
+                    this.chunking_context.source_maps_type(),
+                )
+                .await?;
+            write!(source_code, "\n\n//# sourceMappingURL={}", url)?;
+        }
+
+        Ok(File::from(source_code.build()).into())
     }
 }
 
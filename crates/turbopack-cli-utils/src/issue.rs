@@ -223,6 +223,9 @@ pub struct LogOptions {
     pub show_all: bool,
     pub log_detail: bool,
     pub log_level: IssueSeverity,
+    /// Reporting issues at least as severe as this fails, see
+    /// [IssueReporter::report_issues]
+    pub fail_on: IssueSeverity,
 }
 
 /// Tracks the state of currently seen issues.
@@ -361,6 +364,7 @@ impl IssueReporter for ConsoleUi {
             show_all,
             log_detail,
             log_level,
+            fail_on,
             ..
         } = self.options;
         let mut grouped_issues: GroupedIssues = HashMap::new();
@@ -382,15 +386,15 @@ impl IssueReporter for ConsoleUi {
             .unwrap()
             .new_ids(source.into_value(), issue_ids);
 
-        let mut has_fatal = false;
+        let mut has_failing = false;
         for (plain_issue, id) in issues {
             if !new_ids.remove(&id) {
                 continue;
             }
 
             let severity = plain_issue.severity;
-            if severity == IssueSeverity::Fatal {
-                has_fatal = true;
+            if severity.is_at_least(fail_on) {
+                has_failing = true;
             }
 
             let context_path = make_relative_to_cwd(&plain_issue.context, project_dir, current_dir);
@@ -529,7 +533,7 @@ impl IssueReporter for ConsoleUi {
             }
         }
 
-        Ok(BoolVc::cell(has_fatal))
+        Ok(BoolVc::cell(has_failing))
     }
 }
 
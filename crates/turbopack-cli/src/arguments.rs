@@ -1,9 +1,11 @@
 use std::{
     net::IpAddr,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use clap::{Args, Parser};
+use turbo_tasks_fs::WatchOptions;
 use turbopack_cli_utils::issue::IssueSeverityCliOption;
 
 #[derive(Debug, Parser)]
@@ -50,6 +52,12 @@ pub struct CommonArguments {
     #[clap(long)]
     pub full_stats: bool,
 
+    /// Record which tasks are executed and why they are recomputed, and write
+    /// a Chrome trace and folded stacks for flamegraphs into this directory
+    /// for every compilation.
+    #[clap(long, value_parser)]
+    pub trace_tasks: Option<PathBuf>,
+
     /// Enable experimental garbage collection with the provided memory limit in
     /// MB.
     #[clap(long)]
@@ -82,6 +90,22 @@ pub struct DevArguments {
     #[clap(long)]
     pub no_open: bool,
 
+    /// Merge file system events for the same path that arrive within this
+    /// many milliseconds.
+    #[clap(long, value_parser, default_value_t = 1)]
+    pub watch_debounce: u64,
+
+    /// Wait until no file system event arrived for this many milliseconds
+    /// before recompiling, so e. g. a `git checkout` or a package install
+    /// triggers a single recompilation. Changes are applied after at most
+    /// `--watch-max-settle` milliseconds while events keep arriving.
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub watch_settle: u64,
+
+    /// See `--watch-settle`.
+    #[clap(long, value_parser, default_value_t = 1000)]
+    pub watch_max_settle: u64,
+
     // ==
     // = Inherited options from next-dev, need revisit later.
     // ==
@@ -90,3 +114,14 @@ pub struct DevArguments {
     #[clap(long)]
     pub allow_retry: bool,
 }
+
+impl DevArguments {
+    pub fn watch_options(&self) -> WatchOptions {
+        WatchOptions {
+            debounce: Duration::from_millis(self.watch_debounce),
+            settle: Duration::from_millis(self.watch_settle),
+            max_settle: Duration::from_millis(self.watch_max_settle),
+            ..Default::default()
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use std::{
     collections::HashSet,
     env::current_dir,
+    fs::{create_dir_all, File},
     future::{join, Future},
-    io::{stdout, Write},
+    io::{stdout, BufWriter, Write},
     net::{IpAddr, SocketAddr},
-    path::{PathBuf, MAIN_SEPARATOR},
+    path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -13,10 +14,11 @@ use anyhow::{Context, Result};
 use dunce::canonicalize;
 use owo_colors::OwoColorize;
 use turbo_tasks::{
+    execution_trace::ExecutionTrace,
     util::{FormatBytes, FormatDuration},
     StatsType, TransientInstance, TurboTasks, TurboTasksBackendApi, UpdateInfo, Value,
 };
-use turbo_tasks_fs::{DiskFileSystemVc, FileSystem, FileSystemVc};
+use turbo_tasks_fs::{DiskFileSystemVc, FileSystem, FileSystemVc, WatchOptions};
 use turbo_tasks_malloc::TurboMalloc;
 use turbo_tasks_memory::MemoryBackend;
 use turbopack::evaluate_context::node_build_environment;
@@ -66,6 +68,7 @@ pub struct TurbopackDevServerBuilder {
     show_all: bool,
     log_detail: bool,
     allow_retry: bool,
+    watch_options: WatchOptions,
 }
 
 impl TurbopackDevServerBuilder {
@@ -90,6 +93,7 @@ impl TurbopackDevServerBuilder {
             show_all: false,
             log_detail: false,
             allow_retry: false,
+            watch_options: WatchOptions::default(),
         }
     }
 
@@ -138,6 +142,11 @@ impl TurbopackDevServerBuilder {
         self
     }
 
+    pub fn watch_options(mut self, watch_options: WatchOptions) -> TurbopackDevServerBuilder {
+        self.watch_options = watch_options;
+        self
+    }
+
     pub fn issue_reporter(
         mut self,
         issue_reporter: Box<dyn IssueReporterProvider>,
@@ -199,12 +208,14 @@ impl TurbopackDevServerBuilder {
         let show_all = self.show_all;
         let log_detail = self.log_detail;
         let browserslist_query = self.browserslist_query;
+        let watch_options = self.watch_options;
         let log_args = Arc::new(LogOptions {
             current_dir: current_dir().unwrap(),
             project_dir: PathBuf::from(project_dir.clone()),
             show_all,
             log_detail,
             log_level: self.log_level,
+            fail_on: IssueSeverity::Fatal,
         });
         let entry_requests = Arc::new(self.entry_requests);
         let tasks = turbo_tasks.clone();
@@ -221,6 +232,7 @@ impl TurbopackDevServerBuilder {
                 eager_compile,
                 turbo_tasks.clone().into(),
                 browserslist_query.clone(),
+                Value::new(watch_options),
             )
         };
 
@@ -230,16 +242,20 @@ impl TurbopackDevServerBuilder {
 }
 
 #[turbo_tasks::function]
-async fn project_fs(project_dir: &str) -> Result<FileSystemVc> {
+async fn project_fs(project_dir: &str, watch_options: Value<WatchOptions>) -> Result<FileSystemVc> {
     let disk_fs = DiskFileSystemVc::new("project".to_string(), project_dir.to_string());
-    disk_fs.await?.start_watching()?;
+    disk_fs
+        .await?
+        .start_watching_with_options(watch_options.into_value())?;
     Ok(disk_fs.into())
 }
 
 #[turbo_tasks::function]
-async fn output_fs(project_dir: &str) -> Result<FileSystemVc> {
+async fn output_fs(project_dir: &str, watch_options: Value<WatchOptions>) -> Result<FileSystemVc> {
     let disk_fs = DiskFileSystemVc::new("output".to_string(), project_dir.to_string());
-    disk_fs.await?.start_watching()?;
+    disk_fs
+        .await?
+        .start_watching_with_options(watch_options.into_value())?;
     Ok(disk_fs.into())
 }
 
@@ -252,9 +268,10 @@ async fn source(
     eager_compile: bool,
     turbo_tasks: TransientInstance<TurboTasks<MemoryBackend>>,
     browserslist_query: String,
+    watch_options: Value<WatchOptions>,
 ) -> Result<ContentSourceVc> {
-    let output_fs = output_fs(&project_dir);
-    let fs = project_fs(&root_dir);
+    let output_fs = output_fs(&project_dir, watch_options);
+    let fs = project_fs(&root_dir, watch_options);
     let project_relative = project_dir.strip_prefix(&root_dir).unwrap();
     let project_relative = project_relative
         .strip_prefix(MAIN_SEPARATOR)
@@ -372,6 +389,10 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
     };
     tt.set_stats_type(stats_type);
 
+    if args.common.trace_tasks.is_some() {
+        tt.start_execution_trace();
+    }
+
     let tt_clone = tt.clone();
 
     #[allow(unused_mut)]
@@ -382,6 +403,7 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
         .port(args.port)
         .log_detail(args.common.log_detail)
         .show_all(args.common.show_all)
+        .watch_options(args.watch_options())
         .log_level(
             args.common
                 .log_level
@@ -419,6 +441,7 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
         }
 
         let mut progress_counter = 0;
+        let mut update_counter = 0;
         loop {
             let update_future = profile_timeout(
                 tt_clone.as_ref(),
@@ -433,6 +456,17 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
             }) = update_future.await
             {
                 progress_counter = 0;
+                if let Some(dir) = &args.common.trace_tasks {
+                    if let Some(trace) = tt_clone.take_execution_trace() {
+                        if let Err(err) = write_execution_trace(dir, update_counter, &trace) {
+                            println!(
+                                "{event_type} - failed to write task trace: {err:?}",
+                                event_type = "error".red(),
+                            );
+                        }
+                    }
+                }
+                update_counter += 1;
                 match (args.common.log_detail, !reasons.is_empty()) {
                     (true, true) => {
                         println!(
@@ -494,6 +528,21 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
     Ok(())
 }
 
+/// Writes the trace of a compilation as `update-<n>.trace.json` in the Chrome
+/// trace format and as `update-<n>.folded` folded stacks into `dir`.
+fn write_execution_trace(dir: &Path, update: usize, trace: &ExecutionTrace) -> Result<()> {
+    create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let path = dir.join(format!("update-{update}.trace.json"));
+    File::create(&path)
+        .and_then(|file| trace.write_chrome_trace(BufWriter::new(file)))
+        .with_context(|| format!("writing {}", path.display()))?;
+    let path = dir.join(format!("update-{update}.folded"));
+    File::create(&path)
+        .and_then(|file| trace.write_folded_stacks(BufWriter::new(file)))
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
 #[cfg(feature = "profile")]
 // When profiling, exits the process when no new updates have been received for
 // a given timeout and there are no more tasks in progress.
@@ -0,0 +1,471 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+};
+
+use anyhow::{bail, Context, Result};
+use auto_hash_map::AutoMap;
+use tar::EntryType;
+use turbo_tasks::{primitives::StringVc, CompletionVc, ValueToString, ValueToStringVc};
+use turbo_tasks_fs::{
+    util::{follow_links, normalize_path, split_parent},
+    DirectoryContentVc, DirectoryEntry, File, FileContent, FileContentVc, FileMeta, FileMetaVc,
+    FileSystem, FileSystemEntryType, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc,
+    LinkType,
+};
+
+use crate::read_only_fs::ReadOnlyWriteIssueVc;
+
+/// The magic number at the start of every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// A read-only [FileSystem] over the contents of a .tar or .tar.zst archive,
+/// e.g. a restored cache artifact or a packed dependency, so that modules can
+/// be resolved out of it without extracting it to disk.
+///
+/// The archive is read and indexed on the first access, and again whenever
+/// the archive file changes. Writes are rejected with a
+/// [ReadOnlyWriteIssue](crate::read_only_fs::ReadOnlyWriteIssue).
+#[turbo_tasks::value]
+pub struct ArchiveFileSystem {
+    /// The .tar or .tar.zst file. Whether it's compressed is detected from
+    /// its content.
+    archive: FileSystemPathVc,
+}
+
+#[turbo_tasks::value_impl]
+impl ArchiveFileSystemVc {
+    #[turbo_tasks::function]
+    pub fn new(archive: FileSystemPathVc) -> Self {
+        ArchiveFileSystem { archive }.cell()
+    }
+
+    #[turbo_tasks::function]
+    async fn index(self) -> Result<ArchiveIndexVc> {
+        let archive = self.await?.archive;
+        let content = archive.read().await?;
+        let FileContent::Content(file) = &*content else {
+            bail!("archive {} not found", archive.to_string().await?);
+        };
+        let index = ArchiveIndex::from_bytes(&file.content().to_bytes()?);
+        let archive_name = archive.to_string().await?;
+        Ok(index
+            .with_context(|| format!("reading archive {}", archive_name))?
+            .cell())
+    }
+}
+
+/// The entries of an archive by their normalized path. The root directory is
+/// the empty path.
+#[turbo_tasks::value]
+struct ArchiveIndex {
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+#[turbo_tasks::value(shared)]
+#[derive(Clone)]
+enum ArchiveEntry {
+    File(File),
+    Symlink {
+        target: String,
+        link_type: LinkType,
+    },
+    /// The names of the entries in the directory and their types
+    Directory(BTreeMap<String, FileSystemEntryType>),
+}
+
+impl ArchiveEntry {
+    fn entry_type(&self) -> FileSystemEntryType {
+        match self {
+            ArchiveEntry::File(_) => FileSystemEntryType::File,
+            ArchiveEntry::Symlink { .. } => FileSystemEntryType::Symlink,
+            ArchiveEntry::Directory(_) => FileSystemEntryType::Directory,
+        }
+    }
+
+    fn as_link(&self) -> Option<(&str, LinkType)> {
+        match self {
+            ArchiveEntry::Symlink { target, link_type } => Some((target, *link_type)),
+            _ => None,
+        }
+    }
+}
+
+impl ArchiveIndex {
+    /// Indexes a .tar or .tar.zst archive, detecting the compression from its
+    /// content
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            Self::parse(zstd::Decoder::new(bytes)?)
+        } else {
+            Self::parse(bytes)
+        }
+    }
+
+    /// Indexes the uncompressed tar archive read from `reader`. Entries with
+    /// paths outside of the archive root and special files like devices are
+    /// skipped.
+    fn parse(reader: impl Read) -> Result<Self> {
+        let mut entries = HashMap::new();
+        entries.insert(String::new(), ArchiveEntry::Directory(BTreeMap::new()));
+
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let Some(path) = entry.path()?.to_str().and_then(normalize_path) else {
+                continue;
+            };
+            if path.is_empty() {
+                continue;
+            }
+            let entry_type = entry.header().entry_type();
+            let archive_entry = match entry_type {
+                EntryType::Regular | EntryType::Continuous => {
+                    let mut content = Vec::with_capacity(entry.size() as usize);
+                    entry
+                        .read_to_end(&mut content)
+                        .with_context(|| format!("reading {path}"))?;
+                    ArchiveEntry::File(File::from(content))
+                }
+                EntryType::Directory => ArchiveEntry::Directory(BTreeMap::new()),
+                EntryType::Symlink => {
+                    let Some(target) = entry.link_name()? else {
+                        continue;
+                    };
+                    let Some(target) = target.to_str() else {
+                        continue;
+                    };
+                    let link_type = if target.starts_with('/') {
+                        LinkType::ABSOLUTE
+                    } else {
+                        LinkType::empty()
+                    };
+                    ArchiveEntry::Symlink {
+                        target: target.to_string(),
+                        link_type,
+                    }
+                }
+                // Hard links share the content of an earlier entry
+                EntryType::Link => {
+                    let Some(target) = entry.link_name()? else {
+                        continue;
+                    };
+                    let target = target.to_str().and_then(normalize_path);
+                    match target.and_then(|target| entries.get(&target)) {
+                        Some(file @ ArchiveEntry::File(_)) => file.clone(),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            entries.insert(path, archive_entry);
+        }
+
+        let mut index = ArchiveIndex { entries };
+        index.link_parents();
+        index.mark_directory_links();
+        Ok(index)
+    }
+
+    /// Adds every entry to its parent directory, creating directories that
+    /// aren't in the archive themselves
+    fn link_parents(&mut self) {
+        let mut paths: Vec<_> = self.entries.keys().cloned().collect();
+        // Parents come before their children
+        paths.sort();
+        for path in paths {
+            let mut entry_type = self.entries[&path].entry_type();
+            let mut child = path.as_str();
+            while let Some((parent, name)) = split_parent(child) {
+                let parent_entry = self
+                    .entries
+                    .entry(parent.to_string())
+                    .or_insert_with(|| ArchiveEntry::Directory(BTreeMap::new()));
+                let created = match parent_entry {
+                    ArchiveEntry::Directory(children) => {
+                        children.insert(name.to_string(), entry_type).is_none()
+                    }
+                    // A file or link was used as a directory, its entries
+                    // can't be reached
+                    _ => break,
+                };
+                if !created {
+                    break;
+                }
+                entry_type = FileSystemEntryType::Directory;
+                child = parent;
+            }
+        }
+    }
+
+    /// Sets [LinkType::DIRECTORY] on symlinks to directories, like
+    /// [DiskFileSystem](turbo_tasks_fs::DiskFileSystem) does
+    fn mark_directory_links(&mut self) {
+        let directory_links: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(path, entry)| {
+                matches!(entry, ArchiveEntry::Symlink { .. })
+                    && matches!(
+                        self.get_following_links(path),
+                        Ok(Some(ArchiveEntry::Directory(_)))
+                    )
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in directory_links {
+            if let Some(ArchiveEntry::Symlink { link_type, .. }) = self.entries.get_mut(&path) {
+                *link_type |= LinkType::DIRECTORY;
+            }
+        }
+    }
+
+    /// Returns the entry at `path`, following the symlink at `path`, if any.
+    /// Absolute links are resolved relative to the archive root.
+    fn get_following_links(&self, path: &str) -> Result<Option<&ArchiveEntry>> {
+        follow_links(path, |path| self.entries.get(path), |entry| entry.as_link())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for ArchiveFileSystem {
+    #[turbo_tasks::function]
+    async fn read(
+        self_vc: ArchiveFileSystemVc,
+        fs_path: FileSystemPathVc,
+    ) -> Result<FileContentVc> {
+        let index = self_vc.index().await?;
+        let path = fs_path.await?.path.clone();
+        Ok(match index.get_following_links(&path)? {
+            Some(ArchiveEntry::File(file)) => FileContent::Content(file.clone()).cell(),
+            _ => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(
+        self_vc: ArchiveFileSystemVc,
+        fs_path: FileSystemPathVc,
+    ) -> Result<LinkContentVc> {
+        let index = self_vc.index().await?;
+        let path = fs_path.await?.path.clone();
+        Ok(match index.entries.get(&path) {
+            Some(ArchiveEntry::Symlink { target, link_type }) => LinkContent::Link {
+                target: target.clone(),
+                link_type: *link_type,
+            }
+            .cell(),
+            _ => LinkContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(
+        self_vc: ArchiveFileSystemVc,
+        fs_path: FileSystemPathVc,
+    ) -> Result<DirectoryContentVc> {
+        let index = self_vc.index().await?;
+        let path = fs_path.await?.path.clone();
+        let Some(ArchiveEntry::Directory(children)) = index.get_following_links(&path)? else {
+            return Ok(DirectoryContentVc::not_found());
+        };
+        let entries: AutoMap<_, _> = children
+            .iter()
+            .map(|(name, entry_type)| {
+                let child = fs_path.join(name);
+                let entry = match entry_type {
+                    FileSystemEntryType::File => DirectoryEntry::File(child),
+                    FileSystemEntryType::Directory => DirectoryEntry::Directory(child),
+                    FileSystemEntryType::Symlink => DirectoryEntry::Symlink(child),
+                    _ => DirectoryEntry::Other(child),
+                };
+                (name.clone(), entry)
+            })
+            .collect();
+        Ok(DirectoryContentVc::new(entries))
+    }
+
+    #[turbo_tasks::function]
+    async fn track(
+        self_vc: ArchiveFileSystemVc,
+        _fs_path: FileSystemPathVc,
+    ) -> Result<CompletionVc> {
+        // Entries only change when the whole archive changes
+        self_vc.index().await?;
+        Ok(CompletionVc::new())
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        ReadOnlyWriteIssueVc::emit_for_write(fs_path, content).await?;
+        Ok(CompletionVc::immutable())
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        ReadOnlyWriteIssueVc::emit_for_write_link(fs_path, target).await?;
+        Ok(CompletionVc::immutable())
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(
+        self_vc: ArchiveFileSystemVc,
+        fs_path: FileSystemPathVc,
+    ) -> Result<FileMetaVc> {
+        let index = self_vc.index().await?;
+        let path = fs_path.await?.path.clone();
+        if index.get_following_links(&path)?.is_none() {
+            bail!(
+                "reading metadata for {}: not found",
+                fs_path.to_string().await?
+            );
+        }
+        Ok(FileMeta::default().cell())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for ArchiveFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "archive {}",
+            self.archive.to_string().await?
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn append(builder: &mut tar::Builder<Vec<u8>>, path: &str, entry_type: EntryType, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        // `set_path` rejects `..`, so the name is written directly
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+
+    fn append_link(
+        builder: &mut tar::Builder<Vec<u8>>,
+        path: &str,
+        entry_type: EntryType,
+        target: &str,
+    ) {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(0);
+        header.set_link_name(target).unwrap();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_cksum();
+        builder.append(&header, &[][..]).unwrap();
+    }
+
+    fn archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        append(
+            &mut builder,
+            "package/lib/index.js",
+            EntryType::Regular,
+            b"index",
+        );
+        append_link(
+            &mut builder,
+            "package/main.js",
+            EntryType::Link,
+            "package/lib/index.js",
+        );
+        append_link(&mut builder, "package/lib-link", EntryType::Symlink, "lib");
+        append(
+            &mut builder,
+            "../outside.js",
+            EntryType::Regular,
+            b"outside",
+        );
+        append(
+            &mut builder,
+            "package/../../outside.js",
+            EntryType::Regular,
+            b"outside",
+        );
+        builder.into_inner().unwrap()
+    }
+
+    fn content(index: &ArchiveIndex, path: &str) -> Option<String> {
+        match index.entries.get(path) {
+            Some(ArchiveEntry::File(file)) => Some(file.content().to_str().unwrap().to_string()),
+            _ => None,
+        }
+    }
+
+    fn children(index: &ArchiveIndex, path: &str) -> Vec<(String, FileSystemEntryType)> {
+        match index.entries.get(path) {
+            Some(ArchiveEntry::Directory(children)) => children
+                .iter()
+                .map(|(name, entry_type)| (name.clone(), *entry_type))
+                .collect(),
+            _ => panic!("{path} is not a directory"),
+        }
+    }
+
+    #[test]
+    fn test_hard_links_share_content() {
+        let index = ArchiveIndex::from_bytes(&archive()).unwrap();
+        assert_eq!(content(&index, "package/main.js").as_deref(), Some("index"));
+    }
+
+    #[test]
+    fn test_implicit_parent_directories() {
+        let index = ArchiveIndex::from_bytes(&archive()).unwrap();
+        assert_eq!(
+            children(&index, ""),
+            [("package".to_string(), FileSystemEntryType::Directory)]
+        );
+        assert_eq!(
+            children(&index, "package"),
+            [
+                ("lib".to_string(), FileSystemEntryType::Directory),
+                ("lib-link".to_string(), FileSystemEntryType::Symlink),
+                ("main.js".to_string(), FileSystemEntryType::File),
+            ]
+        );
+        assert!(matches!(
+            index.entries.get("package/lib-link"),
+            Some(ArchiveEntry::Symlink { link_type, .. }) if link_type.contains(LinkType::DIRECTORY)
+        ));
+        assert!(matches!(
+            index.get_following_links("package/lib-link").unwrap(),
+            Some(ArchiveEntry::Directory(_))
+        ));
+    }
+
+    #[test]
+    fn test_entries_outside_the_root_are_skipped() {
+        let index = ArchiveIndex::from_bytes(&archive()).unwrap();
+        assert!(!index.entries.contains_key("outside.js"));
+        assert_eq!(index.entries.len(), 6);
+    }
+
+    #[test]
+    fn test_zstd_is_detected() {
+        let compressed = zstd::encode_all(&*archive(), 0).unwrap();
+        assert!(compressed.starts_with(&ZSTD_MAGIC));
+        let index = ArchiveIndex::from_bytes(&compressed).unwrap();
+        assert_eq!(
+            content(&index, "package/lib/index.js").as_deref(),
+            Some("index")
+        );
+    }
+}
@@ -1,14 +1,18 @@
 use anyhow::Result;
 use indexmap::IndexSet;
-use turbo_tasks::CompletionVc;
+use turbo_tasks::{
+    primitives::{StringVc, U64Vc},
+    CompletionVc, TryJoinIterExt, ValueToString,
+};
 use turbo_tasks_fs::{
     File, FileContent, FileContentVc, FileJsonContent, FileJsonContentVc, FileLinesContent,
     FileLinesContentVc, FileSystemPathVc, LinkContent, LinkType,
 };
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64, Xxh3Hash64Hasher};
 
 use crate::{
     ident::AssetIdentVc,
-    reference::AssetReferencesVc,
+    reference::{all_assets, AssetReferencesVc},
     version::{VersionedAssetContentVc, VersionedContentVc},
 };
 
@@ -49,6 +53,31 @@ pub trait Asset {
     async fn versioned_content(&self) -> Result<VersionedContentVc> {
         Ok(VersionedAssetContentVc::new(self.content()).into())
     }
+
+    /// A fingerprint of the content of the [Asset] and of all [Asset]s it
+    /// transitively references. It's stable across runs and only changes when
+    /// one of these contents changes, so it can be used to skip work for
+    /// unchanged assets, e.g. re-emitting unchanged chunks.
+    async fn fingerprint(self_vc: AssetVc) -> Result<StringVc> {
+        let mut contents = all_assets(self_vc)
+            .await?
+            .iter()
+            .map(|asset| async move {
+                Ok((
+                    asset.ident().to_string().await?.clone_value(),
+                    *asset.content().hash().await?,
+                ))
+            })
+            .try_join()
+            .await?;
+        contents.sort();
+        let mut hasher = Xxh3Hash64Hasher::new();
+        for (ident, hash) in contents.iter() {
+            hasher.write_ref(ident);
+            hasher.write_ref(hash);
+        }
+        Ok(StringVc::cell(encode_hex(hasher.finish())))
+    }
 }
 
 /// An optional [Asset]
@@ -125,6 +154,19 @@ impl AssetContentVc {
         }
     }
 
+    /// A hash of the content, see [hash_xxh3_hash64].
+    #[turbo_tasks::function]
+    pub async fn hash(self) -> Result<U64Vc> {
+        let this = self.await?;
+        Ok(U64Vc::cell(match &*this {
+            AssetContent::File(content) => match &*content.await? {
+                FileContent::Content(file) => hash_xxh3_hash64(file.content()),
+                FileContent::NotFound => 0,
+            },
+            AssetContent::Redirect { target, .. } => hash_xxh3_hash64(target),
+        }))
+    }
+
     #[turbo_tasks::function]
     pub async fn write(self, path: FileSystemPathVc) -> Result<CompletionVc> {
         let this = self.await?;
@@ -0,0 +1,247 @@
+use std::collections::VecDeque;
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{debug::ValueDebugFormat, trace::TraceRawVcs, ValueToString};
+
+use crate::{
+    asset::{Asset, AssetVc, AssetsVc},
+    ident::AssetIdentVc,
+    issue::IssueContextExt,
+    reference::all_referenced_assets,
+};
+
+/// An asset in the [AssetGraph], with the indices of the assets it references
+/// and is referenced by
+#[derive(TraceRawVcs, PartialEq, Eq, Clone, Debug, Serialize, Deserialize, ValueDebugFormat)]
+struct AssetGraphNode {
+    asset: AssetVc,
+    is_entry: bool,
+    dependencies: Vec<usize>,
+    importers: Vec<usize>,
+}
+
+/// The graph of all assets reachable from a set of entries, which can be
+/// queried for the importers and dependencies of an asset and the import
+/// paths that lead to it, e.g. to answer why an asset ends up in the output.
+///
+/// Assets are identified by their idents. Both primary and secondary
+/// references are followed, like in [all_referenced_assets].
+#[turbo_tasks::value(shared)]
+pub struct AssetGraph {
+    nodes: IndexMap<AssetIdentVc, AssetGraphNode>,
+}
+
+/// Import paths in an [AssetGraph], each from an entry to an asset
+#[turbo_tasks::value(transparent)]
+pub struct AssetPaths(Vec<Vec<AssetVc>>);
+
+#[turbo_tasks::value_impl]
+impl AssetGraphVc {
+    /// Computes the graph of all assets reachable from `entries`
+    #[turbo_tasks::function]
+    pub async fn new(entries: AssetsVc) -> Result<Self> {
+        let mut nodes: IndexMap<AssetIdentVc, AssetGraphNode> = IndexMap::new();
+        for &entry in entries.await?.iter() {
+            let ident = entry.ident().resolve().await?;
+            nodes
+                .entry(ident)
+                .or_insert_with(|| AssetGraphNode {
+                    asset: entry,
+                    is_entry: false,
+                    dependencies: Vec::new(),
+                    importers: Vec::new(),
+                })
+                .is_entry = true;
+        }
+
+        // New assets are appended to `nodes` while visiting, so this visits every
+        // reachable asset once
+        let mut index = 0;
+        while let Some((_, node)) = nodes.get_index(index) {
+            let asset = node.asset;
+            let references = all_referenced_assets(asset)
+                .issue_context(asset.ident().path(), "expanding references of asset")
+                .await?;
+            for &referenced in references.await?.iter() {
+                let ident = referenced.ident().resolve().await?;
+                let entry = nodes.entry(ident);
+                let dependency = entry.index();
+                let importers = &mut entry
+                    .or_insert_with(|| AssetGraphNode {
+                        asset: referenced,
+                        is_entry: false,
+                        dependencies: Vec::new(),
+                        importers: Vec::new(),
+                    })
+                    .importers;
+                if !importers.contains(&index) {
+                    importers.push(index);
+                    nodes[index].dependencies.push(dependency);
+                }
+            }
+            index += 1;
+        }
+
+        Ok(AssetGraph { nodes }.cell())
+    }
+
+    /// The assets directly referenced by the asset with the `ident`
+    #[turbo_tasks::function]
+    pub async fn dependencies(self, ident: AssetIdentVc) -> Result<AssetsVc> {
+        let this = self.await?;
+        let node = this.node(ident).await?;
+        Ok(this.assets(&node.dependencies))
+    }
+
+    /// The assets directly referencing the asset with the `ident`
+    #[turbo_tasks::function]
+    pub async fn importers(self, ident: AssetIdentVc) -> Result<AssetsVc> {
+        let this = self.await?;
+        let node = this.node(ident).await?;
+        Ok(this.assets(&node.importers))
+    }
+
+    /// The import paths from any entry to the asset with the `ident`, shortest
+    /// first. Paths don't visit an asset twice. The number of paths can grow
+    /// exponentially with the size of the graph, so at most `max_paths` are
+    /// returned.
+    #[turbo_tasks::function]
+    pub async fn paths_from_entries(
+        self,
+        ident: AssetIdentVc,
+        max_paths: usize,
+    ) -> Result<AssetPathsVc> {
+        let this = self.await?;
+        let target = this.index(ident).await?;
+        let paths = find_paths_from_entries(
+            target,
+            |index| this.nodes[index].importers.as_slice(),
+            |index| this.nodes[index].is_entry,
+            max_paths,
+        );
+        Ok(AssetPathsVc::cell(
+            paths
+                .into_iter()
+                .map(|path| {
+                    path.into_iter()
+                        .map(|index| this.nodes[index].asset)
+                        .collect()
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl AssetGraph {
+    async fn index(&self, ident: AssetIdentVc) -> Result<usize> {
+        let Some(index) = self.nodes.get_index_of(&ident.resolve().await?) else {
+            bail!(
+                "{} is not part of the asset graph",
+                ident.to_string().await?
+            );
+        };
+        Ok(index)
+    }
+
+    async fn node(&self, ident: AssetIdentVc) -> Result<&AssetGraphNode> {
+        Ok(&self.nodes[self.index(ident).await?])
+    }
+
+    fn assets(&self, indices: &[usize]) -> AssetsVc {
+        AssetsVc::cell(
+            indices
+                .iter()
+                .map(|&index| self.nodes[index].asset)
+                .collect(),
+        )
+    }
+}
+
+/// Finds up to `max_paths` paths from entries to `target` by walking the
+/// importers backwards, in order of their length. A path doesn't contain a
+/// node twice.
+fn find_paths_from_entries<'a>(
+    target: usize,
+    importers: impl Fn(usize) -> &'a [usize],
+    is_entry: impl Fn(usize) -> bool,
+    max_paths: usize,
+) -> Vec<Vec<usize>> {
+    let mut paths = Vec::new();
+    // Partial paths in reverse, from the target towards the entries
+    let mut queue = VecDeque::from([vec![target]]);
+    while let Some(path) = queue.pop_front() {
+        if paths.len() >= max_paths {
+            break;
+        }
+        let first = *path.last().unwrap();
+        if is_entry(first) {
+            paths.push(path.iter().rev().copied().collect());
+        }
+        for &importer in importers(first) {
+            if !path.contains(&importer) {
+                let mut path = path.clone();
+                path.push(importer);
+                queue.push_back(path);
+            }
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_paths(importers: &[Vec<usize>], entries: &[usize], target: usize) -> Vec<Vec<usize>> {
+        find_paths_from_entries(
+            target,
+            |index| importers[index].as_slice(),
+            |index| entries.contains(&index),
+            usize::MAX,
+        )
+    }
+
+    #[test]
+    fn test_paths_shortest_first() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 1, 0 -> 3
+        let importers = vec![vec![], vec![0, 2], vec![0], vec![1, 0]];
+        assert_eq!(
+            find_paths(&importers, &[0], 3),
+            vec![vec![0, 3], vec![0, 1, 3], vec![0, 2, 1, 3]]
+        );
+    }
+
+    #[test]
+    fn test_paths_through_entries_and_cycles() {
+        // 0 -> 1 -> 2 -> 1, 1 is an entry as well
+        let importers = vec![vec![], vec![0, 2], vec![1]];
+        assert_eq!(
+            find_paths(&importers, &[0, 1], 2),
+            vec![vec![1, 2], vec![0, 1, 2]]
+        );
+        assert_eq!(
+            find_paths(&importers, &[0, 1], 1),
+            vec![vec![1], vec![0, 1]]
+        );
+    }
+
+    #[test]
+    fn test_paths_limit() {
+        let importers = vec![vec![], vec![0], vec![0], vec![1, 2]];
+        let paths = find_paths_from_entries(
+            3,
+            |index| importers[index].as_slice(),
+            |index| index == 0,
+            1,
+        );
+        assert_eq!(paths, vec![vec![0, 1, 3]]);
+    }
+
+    #[test]
+    fn test_paths_unreachable() {
+        let importers = vec![vec![], vec![]];
+        assert!(find_paths(&importers, &[0], 1).is_empty());
+    }
+}
@@ -9,8 +9,25 @@ use crate::{
     asset::{AssetVc, AssetsVc},
     environment::EnvironmentVc,
     ident::AssetIdentVc,
+    source_map::{SourceMapsType, SourceMapsTypeVc},
 };
 
+/// An entry chunk and the assets to evaluate for it, see
+/// [ChunkingContext::evaluated_chunk_groups]
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Copy, Debug)]
+pub struct EvaluatedEntry {
+    pub entry_chunk: ChunkVc,
+    pub evaluatable_assets: EvaluatableAssetsVc,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct EvaluatedEntries(Vec<EvaluatedEntry>);
+
+/// The output assets of multiple chunk groups, in the order of their entries
+#[turbo_tasks::value(transparent)]
+pub struct ChunkGroups(Vec<AssetsVc>);
+
 /// A context for the chunking that influences the way chunks are created
 #[turbo_tasks::value_trait]
 pub trait ChunkingContext {
@@ -31,6 +48,12 @@ pub trait ChunkingContext {
     /// Reference Source Map Assets for chunks
     fn reference_chunk_source_maps(&self, chunk: AssetVc) -> BoolVc;
 
+    /// How the source maps of chunks are emitted. Chunks with inlined source
+    /// maps don't reference separate Source Map Assets.
+    fn source_maps_type(&self) -> SourceMapsTypeVc {
+        SourceMapsType::External.cell()
+    }
+
     fn can_be_in_same_chunk(&self, asset_a: AssetVc, asset_b: AssetVc) -> BoolVc;
 
     fn asset_path(
@@ -56,4 +79,25 @@ pub trait ChunkingContext {
         entry: ChunkVc,
         evaluatable_assets: EvaluatableAssetsVc,
     ) -> AssetsVc;
+
+    /// Computes the evaluated chunk groups of multiple entries at once, e.g.
+    /// the pages of an app. Chunking contexts may move what's used by at least
+    /// `min_shared_entries` of the entries into shared chunks, which are part
+    /// of all of their chunk groups, to avoid loading the same code in
+    /// different chunks. By default, every entry gets its own chunk group.
+    async fn evaluated_chunk_groups(
+        self_vc: ChunkingContextVc,
+        entries: EvaluatedEntriesVc,
+        _min_shared_entries: usize,
+    ) -> Result<ChunkGroupsVc> {
+        Ok(ChunkGroupsVc::cell(
+            entries
+                .await?
+                .iter()
+                .map(|entry| {
+                    self_vc.evaluated_chunk_group(entry.entry_chunk, entry.evaluatable_assets)
+                })
+                .collect(),
+        ))
+    }
 }
@@ -1,10 +1,18 @@
 use anyhow::{bail, Result};
-use turbo_tasks::{Value, ValueToString};
+use indexmap::IndexSet;
+use turbo_tasks::{primitives::StringVc, Value, ValueToString};
+use turbo_tasks_fs::FileSystemPathVc;
 
-use super::{ChunkableAsset, ChunkableAssetVc};
+use super::{
+    availability_info::AvailabilityInfo, ChunkVc, ChunkableAsset, ChunkableAssetVc,
+    ChunkingContext, ChunkingContextVc,
+};
 use crate::{
-    asset::{Asset, AssetVc},
+    asset::{Asset, AssetContentVc, AssetVc},
     context::{AssetContext, AssetContextVc},
+    ident::AssetIdentVc,
+    issue::{Issue, IssueVc},
+    reference::AssetReferencesVc,
     reference_type::{EntryReferenceSubType, ReferenceType},
 };
 
@@ -19,17 +27,154 @@ pub trait EvaluatableAsset: Asset + ChunkableAsset {}
 impl EvaluatableAssetVc {
     #[turbo_tasks::function]
     pub async fn from_asset(asset: AssetVc, context: AssetContextVc) -> Result<EvaluatableAssetVc> {
-        let asset = context.process(
-            asset,
-            Value::new(ReferenceType::Entry(EntryReferenceSubType::Runtime)),
-        );
+        let reference_type = ReferenceType::Entry(EntryReferenceSubType::Runtime);
+        let asset = context.process(asset, Value::new(reference_type.clone()));
         let Some(entry) = EvaluatableAssetVc::resolve_from(asset).await? else {
+            NotEvaluatableIssue {
+                ident: asset.ident(),
+                reference_type,
+            }
+            .cell()
+            .as_issue()
+            .emit();
             bail!("{} is not a valid evaluated entry", asset.ident().to_string().await?)
         };
         Ok(entry)
     }
 }
 
+/// The condition under which a [ConditionalEvaluatableAsset] is evaluated
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum EvaluationCondition {
+    /// Only in chunking contexts with hot module replacement enabled, i.e. in
+    /// development. Useful for the HMR client or devtools.
+    HotModuleReplacementEnabled,
+    /// Only in chunking contexts without hot module replacement, i.e. in
+    /// production builds
+    HotModuleReplacementDisabled,
+}
+
+impl EvaluationCondition {
+    async fn holds_for(&self, context: ChunkingContextVc) -> Result<bool> {
+        let hmr_enabled = *context.is_hot_module_replacement_enabled().await?;
+        Ok(match self {
+            EvaluationCondition::HotModuleReplacementEnabled => hmr_enabled,
+            EvaluationCondition::HotModuleReplacementDisabled => !hmr_enabled,
+        })
+    }
+}
+
+fn conditional_modifier() -> StringVc {
+    StringVc::cell("conditional".to_string())
+}
+
+/// An [EvaluatableAsset] that is only evaluated when its
+/// [EvaluationCondition] holds for the chunking context. Chunking contexts
+/// resolve it with [EvaluatableAssetsVc::for_chunking_context], so the wrapped
+/// asset isn't part of the chunk graph at all when the condition doesn't
+/// hold.
+#[turbo_tasks::value]
+pub struct ConditionalEvaluatableAsset {
+    asset: EvaluatableAssetVc,
+    condition: EvaluationCondition,
+}
+
+#[turbo_tasks::value_impl]
+impl ConditionalEvaluatableAssetVc {
+    #[turbo_tasks::function]
+    pub fn new(asset: EvaluatableAssetVc, condition: Value<EvaluationCondition>) -> Self {
+        ConditionalEvaluatableAsset {
+            asset,
+            condition: condition.into_value(),
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for ConditionalEvaluatableAsset {
+    #[turbo_tasks::function]
+    fn ident(&self) -> AssetIdentVc {
+        self.asset
+            .as_asset()
+            .ident()
+            .with_modifier(conditional_modifier())
+    }
+
+    #[turbo_tasks::function]
+    fn content(&self) -> AssetContentVc {
+        self.asset.as_asset().content()
+    }
+
+    #[turbo_tasks::function]
+    fn references(&self) -> AssetReferencesVc {
+        self.asset.as_asset().references()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ChunkableAsset for ConditionalEvaluatableAsset {
+    #[turbo_tasks::function]
+    fn as_chunk(
+        &self,
+        context: ChunkingContextVc,
+        availability_info: Value<AvailabilityInfo>,
+    ) -> ChunkVc {
+        self.asset
+            .as_chunkable_asset()
+            .as_chunk(context, availability_info)
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl EvaluatableAsset for ConditionalEvaluatableAsset {}
+
+/// An asset that was processed as an evaluated entry, but didn't turn into an
+/// [EvaluatableAsset]
+#[turbo_tasks::value(shared)]
+pub struct NotEvaluatableIssue {
+    /// The ident of the processed asset
+    pub ident: AssetIdentVc,
+    /// The reference type the asset was processed with
+    pub reference_type: ReferenceType,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for NotEvaluatableIssue {
+    #[turbo_tasks::function]
+    fn category(&self) -> StringVc {
+        StringVc::cell("chunking".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn code(&self) -> StringVc {
+        StringVc::cell("entry-not-evaluatable".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> StringVc {
+        StringVc::cell("Entry can't be evaluated".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn context(&self) -> FileSystemPathVc {
+        self.ident.path()
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "Processing {} as {} reference didn't produce an evaluatable asset.\n\nOnly \
+             EcmaScript modules can be evaluated. Make sure the asset context has a module rule \
+             that transforms this kind of file into an EcmaScript module, or pass an EcmaScript \
+             module as entry instead.",
+            self.ident.to_string().await?,
+            self.reference_type
+        )))
+    }
+}
+
 #[turbo_tasks::value(transparent)]
 pub struct EvaluatableAssets(Vec<EvaluatableAssetVc>);
 
@@ -45,10 +190,75 @@ impl EvaluatableAssetsVc {
         EvaluatableAssets(vec![entry]).cell()
     }
 
+    /// Appends `entry`, unless it's already included
     #[turbo_tasks::function]
     pub async fn with_entry(self, entry: EvaluatableAssetVc) -> Result<EvaluatableAssetsVc> {
-        let mut entries = self.await?.clone_value();
-        entries.push(entry);
-        Ok(EvaluatableAssets(entries).cell())
+        let entries = self.await?;
+        EvaluatableAssets::deduplicated(entries.iter().copied().chain([entry]).collect()).await
+    }
+
+    /// Appends the `entries` that aren't already included, in order
+    #[turbo_tasks::function]
+    pub async fn with_entries(
+        self,
+        entries: Vec<EvaluatableAssetVc>,
+    ) -> Result<EvaluatableAssetsVc> {
+        let current = self.await?;
+        EvaluatableAssets::deduplicated(current.iter().copied().chain(entries).collect()).await
+    }
+
+    /// Appends the entries of `other` that aren't already included, in order
+    #[turbo_tasks::function]
+    pub async fn concat(self, other: EvaluatableAssetsVc) -> Result<EvaluatableAssetsVc> {
+        let current = self.await?;
+        let other = other.await?;
+        EvaluatableAssets::deduplicated(current.iter().chain(other.iter()).copied().collect()).await
+    }
+
+    /// Resolves the [ConditionalEvaluatableAsset]s for `context`: entries
+    /// whose condition holds are replaced with the assets they wrap, the others
+    /// are dropped. Chunking contexts call this before building an evaluated
+    /// chunk group.
+    #[turbo_tasks::function]
+    pub async fn for_chunking_context(
+        self,
+        context: ChunkingContextVc,
+    ) -> Result<EvaluatableAssetsVc> {
+        let mut entries = Vec::new();
+        for &entry in self.await?.iter() {
+            if let Some(entry) = unwrap_conditional(entry, context).await? {
+                entries.push(entry);
+            }
+        }
+        EvaluatableAssets::deduplicated(entries).await
+    }
+}
+
+/// Returns the asset wrapped by `entry` when all of its conditions hold for
+/// `context`, or `entry` itself when it isn't conditional
+async fn unwrap_conditional(
+    mut entry: EvaluatableAssetVc,
+    context: ChunkingContextVc,
+) -> Result<Option<EvaluatableAssetVc>> {
+    while let Some(conditional) = ConditionalEvaluatableAssetVc::resolve_from(entry).await? {
+        let conditional = conditional.await?;
+        if !conditional.condition.holds_for(context).await? {
+            return Ok(None);
+        }
+        entry = conditional.asset;
+    }
+    Ok(Some(entry))
+}
+
+impl EvaluatableAssets {
+    /// Collects `entries`, keeping only the first occurrence of every asset.
+    /// Entries are compared by identity, so the same runtime module coming
+    /// from several sources is only evaluated once.
+    async fn deduplicated(entries: Vec<EvaluatableAssetVc>) -> Result<EvaluatableAssetsVc> {
+        let mut unique = IndexSet::new();
+        for entry in entries {
+            unique.insert(entry.resolve().await?);
+        }
+        Ok(EvaluatableAssets(unique.into_iter().collect()).cell())
     }
 }
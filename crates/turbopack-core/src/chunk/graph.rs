@@ -0,0 +1,187 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use turbo_tasks::{primitives::StringVc, ValueToString};
+
+use super::{Chunk, ChunkGroupReferenceVc, ChunkVc, ChunksVc};
+use crate::{asset::Asset, ident::AssetIdentVc};
+
+/// A chunk item with the size of the code it contributes to its chunk
+#[turbo_tasks::value(shared)]
+#[derive(Clone)]
+pub struct ChunkItemSize {
+    pub ident: AssetIdentVc,
+    /// The size of the code in bytes
+    pub size: u64,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct ChunkItemSizes(Vec<ChunkItemSize>);
+
+#[turbo_tasks::value_impl]
+impl ChunkItemSizesVc {
+    #[turbo_tasks::function]
+    pub fn empty() -> Self {
+        Self::cell(Vec::new())
+    }
+}
+
+/// How a chunk in the [ChunkGraph] is loaded from another chunk
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ChunkGraphEdgeType {
+    /// Loaded in parallel to the referencing chunk
+    Parallel,
+    /// Loaded on demand as part of an async chunk group
+    Async,
+}
+
+/// A chunk item in a [ChunkGraphNode]
+#[turbo_tasks::value(shared)]
+#[derive(Clone)]
+pub struct ChunkGraphItem {
+    pub ident: String,
+    pub size: u64,
+}
+
+/// A chunk in the [ChunkGraph]
+#[turbo_tasks::value(shared)]
+#[derive(Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkGraphNode {
+    pub path: String,
+    /// The total size of the chunk items, in bytes
+    pub size: u64,
+    pub items: Vec<ChunkGraphItem>,
+    /// Whether the chunk is one of the entries the graph was computed from
+    pub is_entry: bool,
+}
+
+/// A reference between two chunks in the [ChunkGraph], by their indices in
+/// [ChunkGraph::chunks]
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Copy)]
+pub struct ChunkGraphEdge {
+    pub from: usize,
+    pub to: usize,
+    #[serde(rename = "type")]
+    pub ty: ChunkGraphEdgeType,
+}
+
+/// The chunks reachable from a set of entry chunks, with their chunk items,
+/// sizes and the references between them. Exported as JSON or as a Graphviz
+/// DOT graph, it shows why a module landed in a chunk and how the bundle is
+/// composed.
+///
+/// The sizes are the sizes of the chunk items' code before the chunking
+/// context wraps them into output chunks. Chunk types which don't report
+/// their chunk items (see [Chunk::chunk_item_sizes]) show up without items.
+#[turbo_tasks::value(shared)]
+pub struct ChunkGraph {
+    pub chunks: Vec<ChunkGraphNode>,
+    pub edges: Vec<ChunkGraphEdge>,
+}
+
+#[turbo_tasks::value_impl]
+impl ChunkGraphVc {
+    /// Computes the graph of all chunks reachable from `entries`, following
+    /// parallel chunks and async chunk groups.
+    #[turbo_tasks::function]
+    pub async fn new(entries: ChunksVc) -> Result<Self> {
+        let mut indices: IndexMap<ChunkVc, usize> = IndexMap::new();
+        for &entry in entries.await?.iter() {
+            let entry = entry.resolve().await?;
+            let next_index = indices.len();
+            indices.entry(entry).or_insert(next_index);
+        }
+        let entry_count = indices.len();
+
+        let mut chunks = Vec::new();
+        let mut edges = Vec::new();
+        // New chunks are appended to `indices` while visiting, so this visits
+        // every reachable chunk once
+        let mut index = 0;
+        while let Some((&chunk, _)) = indices.get_index(index) {
+            let mut targets = Vec::new();
+            for &parallel_chunk in chunk.parallel_chunks().await?.iter() {
+                targets.push((parallel_chunk, ChunkGraphEdgeType::Parallel));
+            }
+            for &reference in chunk.references().await?.iter() {
+                if let Some(group) = ChunkGroupReferenceVc::resolve_from(reference).await? {
+                    targets.push((group.await?.entry, ChunkGraphEdgeType::Async));
+                }
+            }
+            for (target, ty) in targets {
+                let target = target.resolve().await?;
+                let next_index = indices.len();
+                let to = *indices.entry(target).or_insert(next_index);
+                edges.push(ChunkGraphEdge {
+                    from: index,
+                    to,
+                    ty,
+                });
+            }
+
+            let mut items = Vec::new();
+            for item in chunk.chunk_item_sizes().await?.iter() {
+                items.push(ChunkGraphItem {
+                    ident: item.ident.to_string().await?.clone_value(),
+                    size: item.size,
+                });
+            }
+            chunks.push(ChunkGraphNode {
+                path: chunk.path().await?.path.clone(),
+                size: items.iter().map(|item| item.size).sum(),
+                items,
+                is_entry: index < entry_count,
+            });
+            index += 1;
+        }
+
+        Ok(ChunkGraph { chunks, edges }.cell())
+    }
+
+    /// The graph as JSON
+    #[turbo_tasks::function]
+    pub async fn to_json(self) -> Result<StringVc> {
+        Ok(StringVc::cell(serde_json::to_string_pretty(&*self.await?)?))
+    }
+
+    /// The graph in the Graphviz DOT language. Parallel chunks are connected
+    /// with solid edges, async chunk groups with dashed edges.
+    #[turbo_tasks::function]
+    pub async fn to_dot(self) -> Result<StringVc> {
+        let this = self.await?;
+        let mut dot = String::from("digraph chunks {\n");
+        for (index, chunk) in this.chunks.iter().enumerate() {
+            writeln!(
+                dot,
+                "  {index} [label=\"{}\\n{} bytes, {} items\"{}];",
+                escape_dot(&chunk.path),
+                chunk.size,
+                chunk.items.len(),
+                if chunk.is_entry { ", shape=box" } else { "" }
+            )?;
+        }
+        for edge in &this.edges {
+            writeln!(
+                dot,
+                "  {} -> {}{};",
+                edge.from,
+                edge.to,
+                match edge.ty {
+                    ChunkGraphEdgeType::Parallel => "",
+                    ChunkGraphEdgeType::Async => " [style=dashed]",
+                }
+            )?;
+        }
+        dot.push_str("}\n");
+        Ok(StringVc::cell(dot))
+    }
+}
+
+fn escape_dot(str: &str) -> String {
+    str.replace('\\', "\\\\").replace('"', "\\\"")
+}
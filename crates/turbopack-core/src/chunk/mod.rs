@@ -4,6 +4,8 @@ pub(crate) mod chunking_context;
 pub(crate) mod containment_tree;
 pub(crate) mod data;
 pub(crate) mod evaluate;
+pub mod graph;
+pub mod naming;
 pub mod optimize;
 
 use std::{
@@ -27,11 +29,17 @@ use turbo_tasks::{
 use turbo_tasks_fs::FileSystemPathVc;
 use turbo_tasks_hash::DeterministicHash;
 
-use self::availability_info::AvailabilityInfo;
+use self::{availability_info::AvailabilityInfo, graph::ChunkItemSizesVc};
 pub use self::{
-    chunking_context::{ChunkingContext, ChunkingContextVc},
+    chunking_context::{
+        ChunkGroups, ChunkGroupsVc, ChunkingContext, ChunkingContextVc, EvaluatedEntries,
+        EvaluatedEntriesVc, EvaluatedEntry, EvaluatedEntryVc,
+    },
     data::{ChunkData, ChunkDataOption, ChunkDataOptionVc, ChunkDataVc, ChunksData, ChunksDataVc},
-    evaluate::{EvaluatableAsset, EvaluatableAssetVc, EvaluatableAssets, EvaluatableAssetsVc},
+    evaluate::{
+        ConditionalEvaluatableAsset, ConditionalEvaluatableAssetVc, EvaluatableAsset,
+        EvaluatableAssetVc, EvaluatableAssets, EvaluatableAssetsVc, EvaluationCondition,
+    },
 };
 use crate::{
     asset::{Asset, AssetVc, AssetsVc},
@@ -127,6 +135,11 @@ pub trait Chunk: Asset {
     fn parallel_chunks(&self) -> ChunksVc {
         ChunksVc::empty()
     }
+    /// Returns the chunk items placed in this chunk with the sizes of their
+    /// code. Used for reports like the [graph::ChunkGraph].
+    fn chunk_item_sizes(&self) -> ChunkItemSizesVc {
+        ChunkItemSizesVc::empty()
+    }
 }
 
 /// Aggregated information about a chunk content that can be used by the runtime
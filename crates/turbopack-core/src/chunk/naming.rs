@@ -0,0 +1,142 @@
+//! Content hash based naming of finished output assets.
+//!
+//! Chunking contexts derive chunk paths from idents, which keeps them stable
+//! during development, and this module doesn't change that. For long-term
+//! browser caching, a [ContentHashNaming] can move output assets that are only
+//! referenced from the outside, e.g. static files listed in a manifest, to
+//! paths derived from their content, so the name changes exactly when the
+//! content does.
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use turbo_tasks::{Value, ValueToString};
+use turbo_tasks_fs::{FileContent, FileSystemPathVc};
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64};
+
+use crate::{
+    asset::{Asset, AssetContent, AssetVc, AssetsVc},
+    proxied_asset::ProxiedAssetVc,
+};
+
+/// The maximum length of a content hash, the number of hex digits of the 64
+/// bit hash
+const MAX_HASH_LENGTH: usize = 16;
+
+/// Where a [ContentHashNaming] puts the content hash
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum ContentHashPlacement {
+    /// Between the name and the extension, e.g. `index.1a2b3c4d.js`
+    BeforeExtension,
+    /// Instead of the name, e.g. `1a2b3c4d.js`
+    ReplaceName,
+    /// In a directory named after the hash, e.g. `1a2b3c4d/index.js`
+    Directory,
+}
+
+/// A naming strategy that places output assets at paths derived from a hash
+/// of their content.
+///
+/// Assets are only moved, their content stays the same. Assets that refer to
+/// other assets by path, e.g. chunks loading other chunks, have to be built
+/// with the final paths, so this is meant for assets that are referenced from
+/// the outside, e.g. through a manifest (see [ContentHashNamingVc::manifest]).
+#[turbo_tasks::value(shared)]
+pub struct ContentHashNaming {
+    /// The number of hex digits of the hash used in names
+    length: usize,
+    placement: ContentHashPlacement,
+}
+
+/// The final paths of assets named by a [ContentHashNaming], by the paths of
+/// their idents. Paths are relative to the root of their file system.
+#[turbo_tasks::value(transparent)]
+pub struct ContentHashManifest(IndexMap<String, String>);
+
+#[turbo_tasks::value_impl]
+impl ContentHashNamingVc {
+    /// Creates a [ContentHashNaming] that uses the first `length` hex digits
+    /// of the content hash, at most 16.
+    #[turbo_tasks::function]
+    pub fn new(length: usize, placement: Value<ContentHashPlacement>) -> Result<Self> {
+        if length == 0 || length > MAX_HASH_LENGTH {
+            bail!(
+                "content hash length must be between 1 and {}, got {}",
+                MAX_HASH_LENGTH,
+                length
+            );
+        }
+        Ok(ContentHashNaming {
+            length,
+            placement: placement.into_value(),
+        }
+        .cell())
+    }
+
+    /// The path of `asset` under this naming strategy, derived from the path
+    /// of its ident and its content
+    #[turbo_tasks::function]
+    pub async fn hashed_path(self, asset: AssetVc) -> Result<FileSystemPathVc> {
+        let this = self.await?;
+        let ident_path = asset.ident().path();
+        let AssetContent::File(file) = &*asset.content().await? else {
+            bail!(
+                "{} can't be named by content hash, it's not a file",
+                ident_path.to_string().await?
+            );
+        };
+        let FileContent::Content(file) = &*file.await? else {
+            bail!(
+                "{} can't be named by content hash, it doesn't exist",
+                ident_path.to_string().await?
+            );
+        };
+        let hash = encode_hex(hash_xxh3_hash64(file.content()));
+        let hash = &hash[..this.length];
+
+        let path = ident_path.await?;
+        let file_name = path.file_name();
+        let name = match (this.placement, path.extension()) {
+            (ContentHashPlacement::BeforeExtension, Some(extension)) => format!(
+                "{}.{hash}.{extension}",
+                &file_name[..file_name.len() - extension.len() - 1]
+            ),
+            (ContentHashPlacement::BeforeExtension, None) => format!("{file_name}.{hash}"),
+            (ContentHashPlacement::ReplaceName, Some(extension)) => format!("{hash}.{extension}"),
+            (ContentHashPlacement::ReplaceName, None) => hash.to_string(),
+            (ContentHashPlacement::Directory, _) => format!("{hash}/{file_name}"),
+        };
+        Ok(ident_path.parent().join(&name))
+    }
+
+    /// Exposes `asset` at its content hashed path
+    #[turbo_tasks::function]
+    pub fn apply(self, asset: AssetVc) -> AssetVc {
+        ProxiedAssetVc::new(asset, self.hashed_path(asset)).into()
+    }
+
+    /// Exposes all `assets` at their content hashed paths
+    #[turbo_tasks::function]
+    pub async fn apply_all(self, assets: AssetsVc) -> Result<AssetsVc> {
+        Ok(AssetsVc::cell(
+            assets
+                .await?
+                .iter()
+                .map(|&asset| self.apply(asset))
+                .collect(),
+        ))
+    }
+
+    /// Maps the paths of the `assets`' idents to their content hashed paths,
+    /// e.g. to write a manifest for the server or other tools
+    #[turbo_tasks::function]
+    pub async fn manifest(self, assets: AssetsVc) -> Result<ContentHashManifestVc> {
+        let mut manifest = IndexMap::new();
+        for &asset in assets.await?.iter() {
+            let ident_path = asset.ident().path().await?;
+            let hashed_path = self.hashed_path(asset).await?;
+            manifest.insert(ident_path.path.clone(), hashed_path.path.clone());
+        }
+        Ok(ContentHashManifestVc::cell(manifest))
+    }
+}
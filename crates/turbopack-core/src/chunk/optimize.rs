@@ -3,11 +3,133 @@
 //! Usually chunks are optimized by limiting their total count, restricting
 //! their size and eliminating duplicates between them.
 
+use std::fmt::Write;
+
 use anyhow::Result;
-use turbo_tasks::TryJoinIterExt;
+use turbo_tasks::{primitives::StringVc, TryJoinIterExt};
 use turbo_tasks_fs::{FileSystemPathOptionVc, FileSystemPathVc};
 
-use crate::chunk::containment_tree::{ContainmentTree, ContainmentTreeKey};
+use crate::{
+    chunk::containment_tree::{ContainmentTree, ContainmentTreeKey},
+    issue::{Issue, IssueSeverity, IssueSeverityVc, IssueVc},
+};
+
+/// Size budgets for the chunks of a chunk group, in bytes of chunk item code.
+/// Chunks below `min_size` are merged and chunks above `max_size` are split.
+/// Limits that are `None` aren't enforced.
+#[turbo_tasks::value(shared)]
+#[derive(Default, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct ChunkSizeConstraints {
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+/// A change to the chunks of a chunk group made to satisfy the
+/// [ChunkSizeConstraints]
+#[turbo_tasks::value(shared)]
+#[derive(Clone)]
+pub enum ChunkSizeDecision {
+    /// Chunks below the minimum size were merged into one chunk
+    Merged { chunks: Vec<String>, size: u64 },
+    /// A chunk above the maximum size was split into parts of the given sizes.
+    /// `duplicated` is the size of the chunk items that are shared between
+    /// entries and now included in more than one part.
+    Split {
+        chunk: String,
+        parts: Vec<u64>,
+        duplicated: u64,
+    },
+    /// A chunk is above the maximum size, but can't be split since it only has
+    /// a single entry
+    Oversized { chunk: String, size: u64 },
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct ChunkSizeDecisions(Vec<ChunkSizeDecision>);
+
+/// Reports the [ChunkSizeDecision]s made for a chunk group
+#[turbo_tasks::value(shared)]
+pub struct ChunkSizeDecisionsIssue {
+    pub context: FileSystemPathVc,
+    pub decisions: ChunkSizeDecisionsVc,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ChunkSizeDecisionsIssue {
+    /// A warning when a chunk couldn't be brought within the limits, a note
+    /// otherwise
+    #[turbo_tasks::function]
+    async fn severity(&self) -> Result<IssueSeverityVc> {
+        let oversized = self
+            .decisions
+            .await?
+            .iter()
+            .any(|decision| matches!(decision, ChunkSizeDecision::Oversized { .. }));
+        Ok(if oversized {
+            IssueSeverity::Warning.into()
+        } else {
+            IssueSeverity::Note.into()
+        })
+    }
+
+    #[turbo_tasks::function]
+    fn category(&self) -> StringVc {
+        StringVc::cell("chunking".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn code(&self) -> StringVc {
+        StringVc::cell("chunk-size-constraints".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> StringVc {
+        StringVc::cell("Chunk size constraints applied".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn context(&self) -> FileSystemPathVc {
+        self.context
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<StringVc> {
+        let mut description = String::new();
+        for decision in self.decisions.await?.iter() {
+            match decision {
+                ChunkSizeDecision::Merged { chunks, size } => writeln!(
+                    description,
+                    "- merged {} into one chunk of {} bytes",
+                    chunks.join(", "),
+                    size
+                )?,
+                ChunkSizeDecision::Split {
+                    chunk,
+                    parts,
+                    duplicated,
+                } => writeln!(
+                    description,
+                    "- split {} into {} chunks of {} bytes, duplicating {} bytes of shared \
+                     dependencies",
+                    chunk,
+                    parts.len(),
+                    parts
+                        .iter()
+                        .map(|size| size.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    duplicated
+                )?,
+                ChunkSizeDecision::Oversized { chunk, size } => writeln!(
+                    description,
+                    "- {} has {} bytes, but can't be split since it has a single entry",
+                    chunk, size
+                )?,
+            }
+        }
+        Ok(StringVc::cell(description))
+    }
+}
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct FileSystemPathKey(FileSystemPathVc);
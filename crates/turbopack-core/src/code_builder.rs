@@ -5,14 +5,15 @@ use std::{
 };
 
 use anyhow::Result;
-use turbo_tasks::primitives::U64Vc;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use turbo_tasks::primitives::{StringVc, U64Vc};
 use turbo_tasks_fs::rope::{Rope, RopeBuilder};
 use turbo_tasks_hash::hash_xxh3_hash64;
 
 use crate::{
     source_map::{
         GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc, SourceMap, SourceMapSection,
-        SourceMapVc,
+        SourceMapVc, SourceMapsType, SourceMapsTypeVc,
     },
     source_pos::SourcePos,
 };
@@ -200,4 +201,30 @@ impl CodeVc {
         let hash = hash_xxh3_hash64(code.source_code());
         Ok(U64Vc::cell(hash))
     }
+
+    /// The url for the `sourceMappingURL` comment at the end of a file named
+    /// `file_name` with this code. Either the name of the `.map` file next to
+    /// it, or a data URI of the source map, depending on `source_maps_type`.
+    /// The code must not contain the comment yet.
+    #[turbo_tasks::function]
+    pub async fn source_mapping_url(
+        self,
+        file_name: &str,
+        source_maps_type: SourceMapsTypeVc,
+    ) -> Result<StringVc> {
+        Ok(StringVc::cell(match *source_maps_type.await? {
+            SourceMapsType::External => format!("{file_name}.map"),
+            SourceMapsType::Inline => {
+                let map = match *self.generate_source_map().await? {
+                    Some(map) => map,
+                    None => SourceMapVc::empty(),
+                };
+                let map = map.to_rope().await?;
+                format!(
+                    "data:application/json;charset=utf-8;base64,{}",
+                    STANDARD.encode(map.to_bytes()?)
+                )
+            }
+        }))
+    }
 }
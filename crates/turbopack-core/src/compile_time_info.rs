@@ -195,6 +195,18 @@ impl CompileTimeInfoVc {
     pub async fn environment(self) -> Result<EnvironmentVc> {
         Ok(self.await?.environment)
     }
+
+    /// Returns the same compile-time information for a different environment.
+    #[turbo_tasks::function]
+    pub async fn with_environment(self, environment: EnvironmentVc) -> Result<Self> {
+        let this = self.await?;
+        Ok(CompileTimeInfo {
+            environment,
+            defines: this.defines,
+            free_var_references: this.free_var_references,
+        }
+        .cell())
+    }
 }
 
 pub struct CompileTimeInfoBuilder {
@@ -5,6 +5,7 @@ use turbo_tasks_fs::FileSystemPathVc;
 use crate::{
     asset::AssetVc,
     compile_time_info::CompileTimeInfoVc,
+    environment::EnvironmentVc,
     reference_type::ReferenceType,
     resolve::{options::ResolveOptionsVc, parse::RequestVc, ResolveResultVc},
 };
@@ -34,4 +35,8 @@ pub trait AssetContext {
         reference_type: Value<ReferenceType>,
     ) -> ResolveResultVc;
     fn with_transition(&self, transition: &str) -> AssetContextVc;
+    /// Returns a context that resolves and processes assets for `environment`
+    /// instead. This allows a single asset graph to contain the variants of a
+    /// module for multiple environments, e.g. a browser and an edge variant.
+    fn with_environment(&self, environment: EnvironmentVc) -> AssetContextVc;
 }
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+use turbo_tasks::{primitives::BoolVc, RawVc, ReadRef, TransientInstance, TransientValue};
+
+use crate::issue::{CapturedIssues, IssueReporter, IssueReporterVc, IssueSeverity};
+
+/// A structured event of a compilation. Allows embedders to follow a
+/// compilation, e.g. for IDE integrations or build dashboards, without parsing
+/// the logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DiagnosticEvent {
+    /// An asset was written to the output file system. `path` is relative to
+    /// the root of the output file system.
+    #[serde(rename_all = "camelCase")]
+    AssetEmitted { path: String, size: Option<usize> },
+    /// An emitted asset is a chunk. Follows the [DiagnosticEvent::AssetEmitted]
+    /// of the chunk.
+    #[serde(rename_all = "camelCase")]
+    ChunkBuilt { path: String },
+    /// An issue was reported.
+    #[serde(rename_all = "camelCase")]
+    IssueRaised {
+        severity: IssueSeverity,
+        context: String,
+        category: String,
+        title: String,
+    },
+}
+
+/// Receives the [DiagnosticEvent]s of a compilation, e.g. to forward them over
+/// a channel.
+///
+/// Events are reported from within turbo tasks, so they are only reported
+/// again when the task reporting them is recomputed, e.g. an asset is reported
+/// as emitted again when its content changed.
+pub trait DiagnosticsSink: Send + Sync + 'static {
+    fn report(&self, event: DiagnosticEvent);
+}
+
+impl<T> DiagnosticsSink for T
+where
+    T: Fn(DiagnosticEvent) + Send + Sync + 'static,
+{
+    fn report(&self, event: DiagnosticEvent) {
+        self(event)
+    }
+}
+
+/// A [DiagnosticsSink] that can be passed to turbo tasks.
+#[turbo_tasks::value(shared, serialization = "none", eq = "manual", cell = "new")]
+pub struct Diagnostics {
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    sink: Arc<dyn DiagnosticsSink>,
+}
+
+impl PartialEq for Diagnostics {
+    fn eq(&self, other: &Self) -> bool {
+        // Only the data pointers are compared, the vtable of the same sink can
+        // differ between codegen units
+        std::ptr::eq(
+            Arc::as_ptr(&self.sink) as *const u8,
+            Arc::as_ptr(&other.sink) as *const u8,
+        )
+    }
+}
+
+impl DiagnosticsVc {
+    pub fn new(sink: impl DiagnosticsSink) -> Self {
+        Diagnostics {
+            sink: Arc::new(sink),
+        }
+        .cell()
+    }
+
+    pub async fn report(self, event: DiagnosticEvent) -> Result<()> {
+        self.await?.sink.report(event);
+        Ok(())
+    }
+}
+
+/// Reports every captured issue as a [DiagnosticEvent::IssueRaised], so the
+/// sink can be used wherever issues are reported. Never fails the operation
+/// the issues were captured from.
+#[turbo_tasks::value_impl]
+impl IssueReporter for Diagnostics {
+    #[turbo_tasks::function]
+    async fn report_issues(
+        &self,
+        issues: TransientInstance<ReadRef<CapturedIssues>>,
+        _source: TransientValue<RawVc>,
+    ) -> Result<BoolVc> {
+        for issue in issues.get_plain_issues().await? {
+            self.sink.report(DiagnosticEvent::IssueRaised {
+                severity: issue.severity,
+                context: issue.context.clone(),
+                category: issue.category.clone(),
+                title: issue.title.clone(),
+            });
+        }
+        Ok(BoolVc::cell(false))
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct OptionDiagnostics(Option<DiagnosticsVc>);
+
+#[turbo_tasks::value_impl]
+impl OptionDiagnosticsVc {
+    #[turbo_tasks::function]
+    pub fn none() -> Self {
+        OptionDiagnosticsVc::cell(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiagnosticEvent;
+
+    #[test]
+    fn serialize_event() {
+        let event = DiagnosticEvent::AssetEmitted {
+            path: "chunks/index.js".to_string(),
+            size: Some(42),
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"type":"assetEmitted","path":"chunks/index.js","size":42}"#
+        );
+    }
+}
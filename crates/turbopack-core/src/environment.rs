@@ -210,6 +210,31 @@ pub enum ExecutionEnvironment {
     Custom(u8),
 }
 
+/// Matches a kind of [ExecutionEnvironment], e.g. to select the variant of a
+/// module for an environment.
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(PartialOrd, Ord, Debug, Hash, Clone, Copy)]
+pub enum EnvironmentCondition {
+    Browser,
+    NodeJs,
+    EdgeWorker,
+}
+
+impl EnvironmentCondition {
+    pub fn matches(&self, execution: &ExecutionEnvironment) -> bool {
+        match self {
+            EnvironmentCondition::Browser => matches!(execution, ExecutionEnvironment::Browser(_)),
+            EnvironmentCondition::NodeJs => matches!(
+                execution,
+                ExecutionEnvironment::NodeJsBuildTime(_) | ExecutionEnvironment::NodeJsLambda(_)
+            ),
+            EnvironmentCondition::EdgeWorker => {
+                matches!(execution, ExecutionEnvironment::EdgeWorker(_))
+            }
+        }
+    }
+}
+
 #[turbo_tasks::value_impl]
 impl EnvironmentVc {
     #[turbo_tasks::function]
@@ -286,6 +311,11 @@ impl EnvironmentVc {
         })
     }
 
+    #[turbo_tasks::function]
+    pub async fn matches(self, condition: Value<EnvironmentCondition>) -> Result<BoolVc> {
+        Ok(BoolVc::cell(condition.matches(&self.await?.execution)))
+    }
+
     #[turbo_tasks::function]
     pub async fn resolve_conditions(self) -> Result<StringsVc> {
         let env = self.await?;
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use turbo_tasks::{primitives::StringVc, CompletionVc, State, ValueToString, ValueToStringVc};
+use turbo_tasks_fs::{
+    DirectoryContentVc, FileContent, FileContentVc, FileMetaVc, FileSystem, FileSystemPathVc,
+    FileSystemVc, LinkContentVc,
+};
+
+/// The I/O done on the paths below one prefix of an [InstrumentedFileSystem]
+#[turbo_tasks::value(shared)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IoStats {
+    /// Files and symlinks read
+    pub reads: u64,
+    /// Directories listed
+    pub dir_reads: u64,
+    /// Metadata requested
+    pub stats: u64,
+    /// Files and symlinks written or removed
+    pub writes: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+/// The [IoStats] of an [InstrumentedFileSystem] by path prefix
+#[turbo_tasks::value(transparent)]
+pub struct IoReport(BTreeMap<String, IoStats>);
+
+/// A wrapper [FileSystem] that counts the reads, writes and metadata calls
+/// made through it, and the bytes read and written, grouped by path prefix.
+/// The report helps to find the directories that dominate I/O in slow
+/// builds.
+///
+/// Only calls that reach the wrapped [FileSystem] are counted. Reads that
+/// turbo-tasks answers from its cache aren't, since they don't do any I/O.
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+pub struct InstrumentedFileSystem {
+    inner: FileSystemVc,
+    /// The number of leading directories of a path that make up its prefix
+    prefix_depth: u32,
+    stats: State<BTreeMap<String, IoStats>>,
+}
+
+#[turbo_tasks::value_impl]
+impl InstrumentedFileSystemVc {
+    /// Wraps `inner`, grouping paths by their first `prefix_depth`
+    /// directories. Files closer to the root are grouped by their directory.
+    #[turbo_tasks::function]
+    pub fn new(inner: FileSystemVc, prefix_depth: u32) -> Self {
+        Self::cell(InstrumentedFileSystem {
+            inner,
+            prefix_depth,
+            stats: State::new(BTreeMap::new()),
+        })
+    }
+
+    /// The I/O done so far. Recomputed whenever more I/O is done.
+    #[turbo_tasks::function]
+    pub async fn report(self) -> Result<IoReportVc> {
+        let this = self.await?;
+        let stats = this.stats.get().clone();
+        Ok(IoReportVc::cell(stats))
+    }
+}
+
+impl InstrumentedFileSystem {
+    fn inner_path(&self, path: &str) -> FileSystemPathVc {
+        self.inner.root().join(path)
+    }
+
+    /// The prefix that `path`, a file or directory, is counted under
+    fn prefix<'a>(&self, path: &'a str, is_dir: bool) -> &'a str {
+        let dir = if is_dir {
+            path
+        } else {
+            path.rsplit_once('/').map_or("", |(dir, _)| dir)
+        };
+        if self.prefix_depth == 0 {
+            return "";
+        }
+        // The separator after the last directory that belongs to the prefix
+        match dir.match_indices('/').nth(self.prefix_depth as usize - 1) {
+            Some((index, _)) => &dir[..index],
+            None => dir,
+        }
+    }
+
+    fn count(&self, path: &str, is_dir: bool, update: impl FnOnce(&mut IoStats)) {
+        let prefix = self.prefix(path, is_dir).to_string();
+        self.stats.update_conditionally(|stats| {
+            update(stats.entry(prefix).or_default());
+            true
+        });
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for InstrumentedFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        let path = fs_path.await?.path.clone();
+        let content = self.inner_path(&path).read();
+        let bytes = match &*content.await? {
+            FileContent::Content(file) => file.content().len() as u64,
+            FileContent::NotFound => 0,
+        };
+        self.count(&path, false, |stats| {
+            stats.reads += 1;
+            stats.bytes_read += bytes;
+        });
+        Ok(content)
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(&self, fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
+        let path = fs_path.await?.path.clone();
+        self.count(&path, false, |stats| stats.reads += 1);
+        Ok(self.inner_path(&path).read_link())
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let path = fs_path.await?.path.clone();
+        self.count(&path, true, |stats| stats.dir_reads += 1);
+        let dir_content = self.inner_path(&path).read_dir().await?;
+        Ok(dir_content.rebase(fs_path))
+    }
+
+    #[turbo_tasks::function]
+    async fn track(&self, fs_path: FileSystemPathVc) -> Result<CompletionVc> {
+        Ok(self.inner_path(&fs_path.await?.path).track())
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let path = fs_path.await?.path.clone();
+        let bytes = match &*content.await? {
+            FileContent::Content(file) => file.content().len() as u64,
+            FileContent::NotFound => 0,
+        };
+        self.count(&path, false, |stats| {
+            stats.writes += 1;
+            stats.bytes_written += bytes;
+        });
+        Ok(self.inner_path(&path).write(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        let path = fs_path.await?.path.clone();
+        self.count(&path, false, |stats| stats.writes += 1);
+        Ok(self.inner_path(&path).write_link(target))
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let path = fs_path.await?.path.clone();
+        self.count(&path, false, |stats| stats.stats += 1);
+        Ok(self.inner_path(&path).metadata())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for InstrumentedFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "{}-instrumented",
+            self.inner.to_string().await?
+        )))
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashSet, ops::Deref};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{IssueSeverity, PlainIssue, PlainIssueReadRef};
+use crate::source_pos::SourcePos;
+
+/// A machine-readable export of all issues of a build, e.g. for CI to gate on
+/// specific issues.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuesExport<'a> {
+    /// Issues at least as severe as this fail the build
+    pub fail_on: IssueSeverity,
+    /// Whether any issue is at least as severe as [IssuesExport::fail_on]
+    pub failed: bool,
+    pub issues: Vec<IssueExport<'a>>,
+}
+
+impl<'a> IssuesExport<'a> {
+    /// Exports the `issues`, deduplicated and sorted by severity, context,
+    /// code and title, so the export is stable between builds
+    pub fn new(issues: &'a [PlainIssueReadRef], fail_on: IssueSeverity) -> Self {
+        let mut seen = HashSet::new();
+        let mut issues: Vec<&PlainIssue> = issues
+            .iter()
+            .map(|issue| issue.deref())
+            .filter(|issue| seen.insert(issue.internal_hash(false)))
+            .collect();
+        issues.sort_by(|a, b| {
+            (a.severity, &a.context, &a.code, &a.title)
+                .cmp(&(b.severity, &b.context, &b.code, &b.title))
+        });
+
+        IssuesExport {
+            fail_on,
+            failed: issues
+                .iter()
+                .any(|issue| issue.severity.is_at_least(fail_on)),
+            issues: issues.into_iter().map(IssueExport::from).collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueExport<'a> {
+    pub severity: IssueSeverity,
+    /// See [super::Issue::code], `None` when the issue doesn't have one
+    pub code: Option<&'a str>,
+    pub category: &'a str,
+    pub context: &'a str,
+
+    pub title: &'a str,
+    pub description: &'a str,
+    pub detail: &'a str,
+    pub documentation_link: &'a str,
+
+    pub source: Option<IssueSourceExport<'a>>,
+    pub sub_issues: Vec<IssueExport<'a>>,
+}
+
+/// The location of an issue, with 0-based lines and columns
+#[derive(Serialize)]
+pub struct IssueSourceExport<'a> {
+    pub ident: &'a str,
+    pub start: SourcePos,
+    pub end: SourcePos,
+}
+
+impl<'a> From<&'a PlainIssue> for IssueExport<'a> {
+    fn from(plain: &'a PlainIssue) -> Self {
+        IssueExport {
+            severity: plain.severity,
+            code: (!plain.code.is_empty()).then_some(plain.code.as_str()),
+            category: &plain.category,
+            context: &plain.context,
+            title: &plain.title,
+            description: &plain.description,
+            detail: &plain.detail,
+            documentation_link: &plain.documentation_link,
+            source: plain.source.as_deref().map(|source| IssueSourceExport {
+                ident: &source.asset.ident,
+                start: source.start,
+                end: source.end,
+            }),
+            sub_issues: plain.sub_issues.iter().map(|p| p.deref().into()).collect(),
+        }
+    }
+}
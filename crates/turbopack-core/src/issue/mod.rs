@@ -1,5 +1,6 @@
 pub mod analyze;
 pub mod code_gen;
+pub mod export;
 pub mod resolve;
 pub mod unsupported_module;
 
@@ -68,6 +69,12 @@ impl IssueSeverity {
             IssueSeverity::Info => "detail that is worth telling",
         }
     }
+
+    /// Whether this severity is equal to or more severe than `threshold`, e.g.
+    /// an error is at least a warning.
+    pub fn is_at_least(&self, threshold: IssueSeverity) -> bool {
+        *self <= threshold
+    }
 }
 
 impl Display for IssueSeverity {
@@ -94,6 +101,14 @@ pub trait Issue {
         StringVc::empty()
     }
 
+    /// A stable identifier of the kind of issue (eg "module-not-resolved").
+    /// Unlike the title, it doesn't change with the wording or the details of
+    /// the issue, so tools can filter and gate on specific issues. Empty when
+    /// the issue doesn't have a code.
+    fn code(&self) -> StringVc {
+        StringVc::empty()
+    }
+
     /// The issue title should be descriptive of the issue, but should be a
     /// single line. This is displayed to the user directly under the issue
     /// header.
@@ -491,6 +506,7 @@ pub struct PlainIssue {
     pub severity: IssueSeverity,
     pub context: String,
     pub category: String,
+    pub code: String,
 
     pub title: String,
     pub description: String,
@@ -506,6 +522,7 @@ fn hash_plain_issue(issue: &PlainIssue, hasher: &mut Xxh3Hash64Hasher, full: boo
     hasher.write_ref(&issue.severity);
     hasher.write_ref(&issue.context);
     hasher.write_ref(&issue.category);
+    hasher.write_ref(&issue.code);
     hasher.write_ref(&issue.title);
     hasher.write_ref(
         // Normalize syspaths from Windows. These appear in stack traces.
@@ -577,6 +594,7 @@ impl IssueVc {
             severity: *self.severity().await?,
             context: self.context().to_string().await?.clone_value(),
             category: self.category().await?.clone_value(),
+            code: self.code().await?.clone_value(),
             title: self.title().await?.clone_value(),
             description: self.description().await?.clone_value(),
             detail: self.detail().await?.clone_value(),
@@ -667,6 +685,8 @@ pub struct PlainIssueProcessingPathItem {
 
 #[turbo_tasks::value_trait]
 pub trait IssueReporter {
+    /// Reports the captured issues. Returns true when the issues should fail
+    /// the operation they were captured from, e.g. when there's a fatal issue.
     fn report_issues(
         &self,
         issues: TransientInstance<ReadRef<CapturedIssues>>,
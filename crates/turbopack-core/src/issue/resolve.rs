@@ -42,6 +42,11 @@ impl Issue for ResolvingIssue {
         StringVc::cell("resolve".to_string())
     }
 
+    #[turbo_tasks::function]
+    fn code(&self) -> StringVc {
+        StringVc::cell("module-not-resolved".to_string())
+    }
+
     #[turbo_tasks::function]
     fn context(&self) -> FileSystemPathVc {
         self.context
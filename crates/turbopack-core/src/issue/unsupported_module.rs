@@ -23,6 +23,11 @@ impl Issue for UnsupportedModuleIssue {
         StringVc::cell("resolve".to_string())
     }
 
+    #[turbo_tasks::function]
+    fn code(&self) -> StringVc {
+        StringVc::cell("unsupported-module".to_string())
+    }
+
     #[turbo_tasks::function]
     fn title(&self) -> StringVc {
         StringVc::cell("Unsupported module".into())
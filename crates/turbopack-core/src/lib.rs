@@ -5,24 +5,33 @@
 #![feature(assert_matches)]
 #![feature(lint_reasons)]
 
+pub mod archive_fs;
 pub mod asset;
+pub mod asset_graph;
 pub mod changed;
 pub mod chunk;
 pub mod code_builder;
 pub mod compile_time_info;
 pub mod context;
+pub mod diagnostics;
 pub mod environment;
 pub mod error;
 pub mod ident;
+pub mod instrumented_fs;
 pub mod introspect;
 pub mod issue;
+pub mod output_manifest;
+pub mod overlay_fs;
 pub mod package_json;
 pub mod plugin;
 pub mod proxied_asset;
+pub mod quota_fs;
+pub mod read_only_fs;
 pub mod reference;
 pub mod reference_type;
 pub mod resolve;
 pub mod server_fs;
+pub mod snapshot_fs;
 pub mod source_asset;
 pub mod source_map;
 pub mod source_pos;
@@ -0,0 +1,133 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::Serialize;
+use turbo_tasks_fs::{File, FileContent, FileSystemPath, FileSystemPathVc};
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64};
+
+use crate::{
+    asset::{Asset, AssetContent, AssetContentVc, AssetVc, AssetsVc},
+    ident::AssetIdentVc,
+    reference::all_assets,
+};
+
+/// An entry of a build, named e.g. after its route, and the output assets of
+/// its chunk group
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub struct OutputEntry {
+    pub name: String,
+    pub chunk_group: AssetsVc,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct OutputEntries(Vec<OutputEntry>);
+
+/// A JSON manifest of the files emitted for a set of entries, so deploy
+/// tooling and servers can map an entry to the files it needs without
+/// scraping the output directory.
+///
+/// Lists the chunks of every entry and all files they reference, e.g. source
+/// maps and static assets, with their sizes and content hashes. Paths are
+/// relative to the output directory, assets outside of it are left out.
+#[turbo_tasks::value]
+pub struct OutputManifestAsset {
+    path: FileSystemPathVc,
+    output_dir: FileSystemPathVc,
+    entries: OutputEntriesVc,
+}
+
+#[turbo_tasks::value_impl]
+impl OutputManifestAssetVc {
+    #[turbo_tasks::function]
+    pub fn new(
+        path: FileSystemPathVc,
+        output_dir: FileSystemPathVc,
+        entries: OutputEntriesVc,
+    ) -> Self {
+        Self::cell(OutputManifestAsset {
+            path,
+            output_dir,
+            entries,
+        })
+    }
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct OutputManifest {
+    entries: BTreeMap<String, EntryManifest>,
+    files: BTreeMap<String, FileManifest>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryManifest {
+    /// The chunks to load for the entry, in order
+    chunks: Vec<String>,
+    /// All files needed by the entry, including its chunks
+    files: BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileManifest {
+    size: usize,
+    /// Hex encoded xxh3 hash of the content
+    hash: String,
+}
+
+/// The path of the asset relative to `output_dir`, `None` when it's emitted
+/// somewhere else
+async fn output_path(asset: AssetVc, output_dir: &FileSystemPath) -> Result<Option<String>> {
+    let path = asset.ident().path().await?;
+    Ok(output_dir.get_path_to(&path).map(|path| path.to_string()))
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for OutputManifestAsset {
+    #[turbo_tasks::function]
+    fn ident(&self) -> AssetIdentVc {
+        AssetIdentVc::from_path(self.path)
+    }
+
+    #[turbo_tasks::function]
+    async fn content(&self) -> Result<AssetContentVc> {
+        let output_dir = self.output_dir.await?;
+        let mut manifest = OutputManifest::default();
+        for entry in self.entries.await?.iter() {
+            let mut chunks = Vec::new();
+            let mut files = BTreeSet::new();
+            for chunk in entry.chunk_group.await?.iter() {
+                if let Some(path) = output_path(*chunk, &output_dir).await? {
+                    chunks.push(path);
+                }
+                for asset in all_assets(*chunk).await?.iter() {
+                    let Some(path) = output_path(*asset, &output_dir).await? else {
+                        continue;
+                    };
+                    if !manifest.files.contains_key(&path) {
+                        let AssetContent::File(content) = &*asset.content().await? else {
+                            continue;
+                        };
+                        let FileContent::Content(file) = &*content.await? else {
+                            continue;
+                        };
+                        manifest.files.insert(
+                            path.clone(),
+                            FileManifest {
+                                size: file.content().len(),
+                                hash: encode_hex(hash_xxh3_hash64(file.content())),
+                            },
+                        );
+                    }
+                    files.insert(path);
+                }
+            }
+            manifest
+                .entries
+                .insert(entry.name.clone(), EntryManifest { chunks, files });
+        }
+        Ok(File::from(serde_json::to_string_pretty(&manifest)?).into())
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{bail, Result};
+use auto_hash_map::AutoMap;
+use turbo_tasks::{
+    primitives::StringVc, CompletionVc, CompletionsVc, ValueToString, ValueToStringVc,
+};
+use turbo_tasks_fs::{
+    DirectoryContent, DirectoryContentVc, FileContent, FileContentVc, FileMetaVc, FileSystem,
+    FileSystemEntryType, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc,
+};
+
+/// A [FileSystem] that layers multiple [FileSystem]s on top of each other, e.g.
+/// a [VirtualFileSystem](crate::virtual_fs::VirtualFileSystem) with generated
+/// or patched files on top of the project on disk.
+///
+/// Reads see the entry from the topmost layer that has anything at the path,
+/// and directories list the entries of all layers. Writes always go to the top
+/// layer.
+///
+/// Caveat: Removing a file only removes it from the top layer, so a file with
+/// the same path in a lower layer becomes visible again.
+#[turbo_tasks::value]
+pub struct OverlayFileSystem {
+    /// The layers from top to bottom
+    layers: Vec<FileSystemVc>,
+}
+
+#[turbo_tasks::value_impl]
+impl OverlayFileSystemVc {
+    /// Creates a new [OverlayFileSystem] from `layers`, ordered from top to
+    /// bottom
+    #[turbo_tasks::function]
+    pub fn new(layers: Vec<FileSystemVc>) -> Result<Self> {
+        if layers.is_empty() {
+            bail!("an overlay file system needs at least one layer");
+        }
+        Ok(OverlayFileSystem { layers }.cell())
+    }
+}
+
+impl OverlayFileSystemVc {
+    /// Resolves the path in each layer, from top to bottom, from a path on the
+    /// [OverlayFileSystem]
+    async fn layer_paths(self, path: FileSystemPathVc) -> Result<Vec<FileSystemPathVc>> {
+        let this = self.await?;
+        let path = path.await?;
+        let self_fs: FileSystemVc = self.into();
+
+        if path.fs != self_fs {
+            bail!(
+                "path fs does not match (expected {}, got {})",
+                self_fs.to_string().await?,
+                path.fs.to_string().await?
+            )
+        }
+
+        let mut layer_paths = Vec::with_capacity(this.layers.len());
+        for layer in &this.layers {
+            layer_paths.push(layer.root().resolve().await?.join(&path.path));
+        }
+        Ok(layer_paths)
+    }
+
+    /// Returns the path in the topmost layer that has anything at `path`. An
+    /// entry of any type shadows the entries of lower layers, so e.g. a
+    /// directory hides a file with the same path below it.
+    async fn existing_layer_path(self, path: FileSystemPathVc) -> Result<Option<FileSystemPathVc>> {
+        for layer_path in self.layer_paths(path).await? {
+            if *layer_path.get_type().await? != FileSystemEntryType::NotFound {
+                return Ok(Some(layer_path));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn top_layer_path(self, path: FileSystemPathVc) -> Result<FileSystemPathVc> {
+        let layer_paths = self.layer_paths(path).await?;
+        Ok(layer_paths[0])
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for OverlayFileSystem {
+    #[turbo_tasks::function]
+    async fn read(self_vc: OverlayFileSystemVc, path: FileSystemPathVc) -> Result<FileContentVc> {
+        Ok(match self_vc.existing_layer_path(path).await? {
+            Some(layer_path) => layer_path.read(),
+            None => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(
+        self_vc: OverlayFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<LinkContentVc> {
+        Ok(match self_vc.existing_layer_path(path).await? {
+            Some(layer_path) => layer_path.read_link(),
+            None => LinkContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(
+        self_vc: OverlayFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<DirectoryContentVc> {
+        let mut found = false;
+        let mut entries = AutoMap::new();
+        for layer_path in self_vc.layer_paths(path).await? {
+            let dir_content = layer_path.read_dir().await?;
+            let DirectoryContent::Entries(layer_entries) = &*dir_content else {
+                continue;
+            };
+            found = true;
+            for (name, entry) in layer_entries {
+                // Upper layers shadow entries of lower layers
+                if !entries.contains_key(name) {
+                    entries.insert(name.clone(), entry.with_path(path.join(name)));
+                }
+            }
+        }
+
+        Ok(if found {
+            DirectoryContentVc::new(entries)
+        } else {
+            DirectoryContentVc::not_found()
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn track(self_vc: OverlayFileSystemVc, path: FileSystemPathVc) -> Result<CompletionVc> {
+        let completions = self_vc
+            .layer_paths(path)
+            .await?
+            .into_iter()
+            .map(|layer_path| layer_path.track())
+            .collect();
+        Ok(CompletionsVc::cell(completions).completed())
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        self_vc: OverlayFileSystemVc,
+        path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        Ok(self_vc.top_layer_path(path).await?.write(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        self_vc: OverlayFileSystemVc,
+        path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        Ok(self_vc.top_layer_path(path).await?.write_link(target))
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(self_vc: OverlayFileSystemVc, path: FileSystemPathVc) -> Result<FileMetaVc> {
+        Ok(match self_vc.existing_layer_path(path).await? {
+            Some(layer_path) => layer_path.metadata(),
+            // Let the bottom layer report that the path doesn't exist
+            None => self_vc.layer_paths(path).await?.pop().unwrap().metadata(),
+        })
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for OverlayFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        let mut names = Vec::with_capacity(self.layers.len());
+        for layer in &self.layers {
+            names.push(layer.to_string().await?.to_string());
+        }
+        Ok(StringVc::cell(names.join("-over-")))
+    }
+}
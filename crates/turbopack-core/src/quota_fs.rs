@@ -0,0 +1,242 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use turbo_tasks::{
+    mark_stateful, primitives::StringVc, CompletionVc, ValueToString, ValueToStringVc,
+};
+use turbo_tasks_fs::{
+    DirectoryContentVc, FileContent, FileContentVc, FileMetaVc, FileSystem, FileSystemPathVc,
+    FileSystemVc, LinkContent, LinkContentVc,
+};
+
+use crate::issue::{Issue, IssueVc};
+
+/// The budgets of a [QuotaFileSystem]. Limits that are `None` aren't enforced.
+#[turbo_tasks::value(shared)]
+#[derive(Default, Clone, Copy)]
+pub struct QuotaLimits {
+    /// The maximum total size of all files, in bytes
+    pub max_bytes: Option<u64>,
+    /// The maximum number of files and symlinks
+    pub max_files: Option<u64>,
+}
+
+/// A wrapper [FileSystem] for an output target, e.g. the server chunks
+/// directory, that tracks how much is written to it. Every write that leaves
+/// the output over one of its [QuotaLimits] emits a [QuotaExceededIssue], so
+/// runaway asset emission is caught during the build.
+///
+/// Writes still go through when the quota is exceeded. Only writes through
+/// this [FileSystem] are counted, files that were in the wrapped
+/// [FileSystem] before aren't.
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+pub struct QuotaFileSystem {
+    inner: FileSystemVc,
+    limits: QuotaLimitsVc,
+    /// The size of every file written so far, by path
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    written: Mutex<HashMap<String, u64>>,
+}
+
+/// The totals of everything written to a [QuotaFileSystem]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+#[turbo_tasks::value_impl]
+impl QuotaFileSystemVc {
+    #[turbo_tasks::function]
+    pub fn new(inner: FileSystemVc, limits: QuotaLimitsVc) -> Self {
+        // The totals live in this cell, so it must not be evicted and
+        // recreated empty
+        mark_stateful();
+        Self::cell(QuotaFileSystem {
+            inner,
+            limits,
+            written: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+impl QuotaFileSystemVc {
+    /// Returns the totals of everything written so far. Untracked, so it
+    /// should only be used for reporting.
+    pub async fn usage(self) -> Result<QuotaUsage> {
+        Ok(self.await?.usage())
+    }
+}
+
+impl QuotaFileSystem {
+    fn inner_path(&self, path: &str) -> FileSystemPathVc {
+        self.inner.root().join(path)
+    }
+
+    fn usage(&self) -> QuotaUsage {
+        let written = self.written.lock().unwrap();
+        QuotaUsage {
+            bytes: written.values().sum(),
+            files: written.len() as u64,
+        }
+    }
+
+    /// Records that `path` now has `size` bytes, or was removed, and emits a
+    /// [QuotaExceededIssue] when that exceeds the limits
+    async fn record_write(&self, fs_path: FileSystemPathVc, size: Option<u64>) -> Result<()> {
+        let path = fs_path.await?.path.clone();
+        {
+            let mut written = self.written.lock().unwrap();
+            match size {
+                Some(size) => written.insert(path, size),
+                None => written.remove(&path),
+            };
+        }
+        // Removing files only ever reduces the usage
+        if size.is_none() {
+            return Ok(());
+        }
+
+        let usage = self.usage();
+        let limits = *self.limits.await?;
+        let exceeds =
+            |limit: Option<u64>, value: u64| matches!(limit, Some(limit) if value > limit);
+        if exceeds(limits.max_bytes, usage.bytes) || exceeds(limits.max_files, usage.files) {
+            QuotaExceededIssue {
+                path: fs_path,
+                usage_bytes: usage.bytes,
+                usage_files: usage.files,
+                limits,
+            }
+            .cell()
+            .as_issue()
+            .emit();
+        }
+        Ok(())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for QuotaFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        Ok(self.inner_path(&fs_path.await?.path).read())
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(&self, fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
+        Ok(self.inner_path(&fs_path.await?.path).read_link())
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let dir_content = self.inner_path(&fs_path.await?.path).read_dir().await?;
+        Ok(dir_content.rebase(fs_path))
+    }
+
+    #[turbo_tasks::function]
+    async fn track(&self, fs_path: FileSystemPathVc) -> Result<CompletionVc> {
+        Ok(self.inner_path(&fs_path.await?.path).track())
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let size = match &*content.await? {
+            FileContent::Content(file) => Some(file.content().len() as u64),
+            FileContent::NotFound => None,
+        };
+        self.record_write(fs_path, size).await?;
+        Ok(self.inner_path(&fs_path.await?.path).write(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        // Links only count towards the number of files
+        let size = match &*target.await? {
+            LinkContent::NotFound => None,
+            _ => Some(0),
+        };
+        self.record_write(fs_path, size).await?;
+        Ok(self.inner_path(&fs_path.await?.path).write_link(target))
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        Ok(self.inner_path(&fs_path.await?.path).metadata())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for QuotaFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "{}-with-quota",
+            self.inner.to_string().await?
+        )))
+    }
+}
+
+/// A write that left a [QuotaFileSystem] over its limits
+#[turbo_tasks::value(shared)]
+pub struct QuotaExceededIssue {
+    /// The path whose write exceeded the limits
+    pub path: FileSystemPathVc,
+    /// The total size of the output after the write
+    pub usage_bytes: u64,
+    /// The number of files in the output after the write
+    pub usage_files: u64,
+    pub limits: QuotaLimits,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for QuotaExceededIssue {
+    #[turbo_tasks::function]
+    fn category(&self) -> StringVc {
+        StringVc::cell("write".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> StringVc {
+        StringVc::cell("Output exceeds its quota".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn context(&self) -> FileSystemPathVc {
+        self.path
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<StringVc> {
+        let mut exceeded = Vec::new();
+        if let Some(max_bytes) = self.limits.max_bytes {
+            if self.usage_bytes > max_bytes {
+                exceeded.push(format!(
+                    "{} bytes (limit {} bytes)",
+                    self.usage_bytes, max_bytes
+                ));
+            }
+        }
+        if let Some(max_files) = self.limits.max_files {
+            if self.usage_files > max_files {
+                exceeded.push(format!(
+                    "{} files (limit {} files)",
+                    self.usage_files, max_files
+                ));
+            }
+        }
+        Ok(StringVc::cell(format!(
+            "Writing {} brought the output to {}.",
+            self.path.to_string().await?,
+            exceeded.join(" and ")
+        )))
+    }
+}
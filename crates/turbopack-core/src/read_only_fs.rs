@@ -0,0 +1,170 @@
+use anyhow::{bail, Result};
+use turbo_tasks::{primitives::StringVc, CompletionVc, ValueToString, ValueToStringVc};
+use turbo_tasks_fs::{
+    DirectoryContentVc, FileContent, FileContentVc, FileMetaVc, FileSystem, FileSystemPathVc,
+    FileSystemVc, LinkContent, LinkContentVc,
+};
+
+use crate::issue::{Issue, IssueVc};
+
+/// A wrapper [FileSystem] that forwards reads to the wrapped [FileSystem] and
+/// rejects all writes. Every rejected write emits a [ReadOnlyWriteIssue]
+/// naming the path and what the write would have done, which makes it easy
+/// to find code that accidentally writes into e.g. source directories.
+#[turbo_tasks::value]
+pub struct ReadOnlyFileSystem {
+    inner: FileSystemVc,
+}
+
+#[turbo_tasks::value_impl]
+impl ReadOnlyFileSystemVc {
+    #[turbo_tasks::function]
+    pub fn new(inner: FileSystemVc) -> Self {
+        ReadOnlyFileSystem { inner }.cell()
+    }
+
+    /// Resolves the path on the wrapped [FileSystem] from a path on the
+    /// [ReadOnlyFileSystem]
+    #[turbo_tasks::function]
+    pub async fn get_inner_fs_path(self, path: FileSystemPathVc) -> Result<FileSystemPathVc> {
+        let this = self.await?;
+        let path = path.await?;
+        let self_fs: FileSystemVc = self.into();
+
+        if path.fs != self_fs {
+            bail!(
+                "path fs does not match (expected {}, got {})",
+                self_fs.to_string().await?,
+                path.fs.to_string().await?
+            )
+        }
+
+        Ok(this.inner.root().resolve().await?.join(&path.path))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for ReadOnlyFileSystem {
+    #[turbo_tasks::function]
+    fn read(self_vc: ReadOnlyFileSystemVc, path: FileSystemPathVc) -> FileContentVc {
+        self_vc.get_inner_fs_path(path).read()
+    }
+
+    #[turbo_tasks::function]
+    fn read_link(self_vc: ReadOnlyFileSystemVc, path: FileSystemPathVc) -> LinkContentVc {
+        self_vc.get_inner_fs_path(path).read_link()
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(
+        self_vc: ReadOnlyFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<DirectoryContentVc> {
+        let dir_content = self_vc.get_inner_fs_path(path).read_dir().await?;
+        Ok(dir_content.rebase(path))
+    }
+
+    #[turbo_tasks::function]
+    fn track(self_vc: ReadOnlyFileSystemVc, path: FileSystemPathVc) -> CompletionVc {
+        self_vc.get_inner_fs_path(path).track()
+    }
+
+    #[turbo_tasks::function]
+    async fn write(&self, path: FileSystemPathVc, content: FileContentVc) -> Result<CompletionVc> {
+        ReadOnlyWriteIssueVc::emit_for_write(path, content).await?;
+        Ok(CompletionVc::immutable())
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        ReadOnlyWriteIssueVc::emit_for_write_link(path, target).await?;
+        Ok(CompletionVc::immutable())
+    }
+
+    #[turbo_tasks::function]
+    fn metadata(self_vc: ReadOnlyFileSystemVc, path: FileSystemPathVc) -> FileMetaVc {
+        self_vc.get_inner_fs_path(path).metadata()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for ReadOnlyFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "{}-read-only",
+            self.inner.to_string().await?
+        )))
+    }
+}
+
+/// A write to a [ReadOnlyFileSystem] that was rejected
+#[turbo_tasks::value(shared)]
+pub struct ReadOnlyWriteIssue {
+    /// The path that was written to
+    pub path: FileSystemPathVc,
+    /// What the write would have done, e.g. "Removing a file"
+    pub attempted: String,
+}
+
+impl ReadOnlyWriteIssueVc {
+    /// Emits an issue for writing `content` to `path` on a read-only file
+    /// system
+    pub async fn emit_for_write(path: FileSystemPathVc, content: FileContentVc) -> Result<()> {
+        let attempted = match &*content.await? {
+            FileContent::Content(_) => "Writing a file".to_string(),
+            FileContent::NotFound => "Removing a file".to_string(),
+        };
+        ReadOnlyWriteIssue { path, attempted }
+            .cell()
+            .as_issue()
+            .emit();
+        Ok(())
+    }
+
+    /// Emits an issue for writing the symlink `target` to `path` on a
+    /// read-only file system
+    pub async fn emit_for_write_link(path: FileSystemPathVc, target: LinkContentVc) -> Result<()> {
+        let attempted = match &*target.await? {
+            LinkContent::Link { target, .. } => format!("Creating a symlink to {}", target),
+            LinkContent::Invalid => "Creating an invalid symlink".to_string(),
+            LinkContent::NotFound => "Removing a symlink".to_string(),
+        };
+        ReadOnlyWriteIssue { path, attempted }
+            .cell()
+            .as_issue()
+            .emit();
+        Ok(())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ReadOnlyWriteIssue {
+    #[turbo_tasks::function]
+    fn category(&self) -> StringVc {
+        StringVc::cell("write".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> StringVc {
+        StringVc::cell("Write to a read-only file system".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn context(&self) -> FileSystemPathVc {
+        self.path
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "{} at {} was rejected, as the file system is read-only. Nothing was written.",
+            self.attempted,
+            self.path.to_string().await?
+        )))
+    }
+}
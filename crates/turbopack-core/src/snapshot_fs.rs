@@ -0,0 +1,184 @@
+use std::{any::Any, collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::StringVc, turbo_tasks, CompletionVc, RawVc, ReadRef, State, ValueToString,
+    ValueToStringVc,
+};
+use turbo_tasks_fs::{
+    DirectoryContent, DirectoryContentVc, FileContent, FileContentVc, FileMeta, FileMetaVc,
+    FileSystem, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc,
+};
+
+/// A wrapper [FileSystem] that serves a consistent view of the wrapped
+/// [FileSystem] for one generation, e.g. one compilation in watch mode.
+///
+/// The first read of a path in a generation is taken from the wrapped
+/// [FileSystem] and all later reads of that path in the same generation see
+/// the same content, even when the file changes in the meantime. Changes only
+/// become visible after [SnapshotFileSystemVc::next_generation], which
+/// invalidates all reads. Reads whose content didn't change produce the same
+/// cells again, so only tasks depending on changed files are recomputed.
+///
+/// Writes are forwarded to the wrapped [FileSystem] right away, so they are
+/// only seen by the next generation, too.
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+pub struct SnapshotFileSystem {
+    inner: FileSystemVc,
+    /// Every read depends on the generation, so bumping it invalidates them
+    generation: State<u32>,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    snapshot: Mutex<Snapshot>,
+}
+
+/// The results of the reads in the current generation, by path
+#[derive(Default)]
+struct Snapshot {
+    files: HashMap<String, ReadRef<FileContent>>,
+    links: HashMap<String, ReadRef<LinkContent>>,
+    dirs: HashMap<String, ReadRef<DirectoryContent>>,
+    metas: HashMap<String, ReadRef<FileMeta>>,
+}
+
+#[turbo_tasks::value_impl]
+impl SnapshotFileSystemVc {
+    #[turbo_tasks::function]
+    pub fn new(inner: FileSystemVc) -> Self {
+        Self::cell(SnapshotFileSystem {
+            inner,
+            generation: State::new(0),
+            snapshot: Mutex::new(Snapshot::default()),
+        })
+    }
+}
+
+impl SnapshotFileSystemVc {
+    /// Drops the snapshot of the current generation and invalidates all
+    /// reads, so that they see the current state of the wrapped
+    /// [FileSystem]. Should be called between compilations.
+    pub async fn next_generation(self) -> Result<()> {
+        let this = self.await?;
+        // Readers check the generation before the snapshot, so holding the
+        // lock ensures nothing is read from the old snapshot for the new
+        // generation
+        let mut snapshot = this.snapshot.lock().unwrap();
+        *snapshot = Snapshot::default();
+        this.generation.update_conditionally(|generation| {
+            *generation = generation.wrapping_add(1);
+            true
+        });
+        Ok(())
+    }
+}
+
+impl SnapshotFileSystem {
+    fn inner_path(&self, path: &str) -> FileSystemPathVc {
+        self.inner.root().join(path)
+    }
+
+    /// Returns the result of `read` for `path` from the snapshot, reading it
+    /// from the wrapped [FileSystem] on the first access in this generation.
+    /// Has to be called within a turbo-tasks function.
+    async fn snapshot<T: Any + Send + Sync>(
+        &self,
+        path: &str,
+        select: impl Fn(&mut Snapshot) -> &mut HashMap<String, ReadRef<T>>,
+        read: impl FnOnce() -> RawVc,
+    ) -> Result<ReadRef<T>> {
+        // Only the generation is tracked, changes to the wrapped file system
+        // must not invalidate reads before the next generation
+        self.generation.get();
+        if let Some(value) = select(&mut self.snapshot.lock().unwrap()).get(path) {
+            return Ok(value.clone());
+        }
+        let value = read()
+            .into_strongly_consistent_read_untracked::<T>(&*turbo_tasks())
+            .await?;
+        // Another read of the same path might have finished first, everyone
+        // has to see the value that made it into the snapshot
+        Ok(select(&mut self.snapshot.lock().unwrap())
+            .entry(path.to_string())
+            .or_insert(value)
+            .clone())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for SnapshotFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        let path = fs_path.await?.path.clone();
+        let inner_path = self.inner_path(&path);
+        let content = self
+            .snapshot(&path, |s| &mut s.files, || inner_path.read().into())
+            .await?;
+        Ok(ReadRef::cell(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(&self, fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
+        let path = fs_path.await?.path.clone();
+        let inner_path = self.inner_path(&path);
+        let content = self
+            .snapshot(&path, |s| &mut s.links, || inner_path.read_link().into())
+            .await?;
+        Ok(ReadRef::cell(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let path = fs_path.await?.path.clone();
+        let inner_path = self.inner_path(&path);
+        let dir_content = self
+            .snapshot(&path, |s| &mut s.dirs, || inner_path.read_dir().into())
+            .await?;
+        Ok(dir_content.rebase(fs_path))
+    }
+
+    #[turbo_tasks::function]
+    fn track(&self, _fs_path: FileSystemPathVc) -> CompletionVc {
+        self.generation.get();
+        CompletionVc::new()
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let path = fs_path.await?.path.clone();
+        Ok(self.inner_path(&path).write(content))
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        let path = fs_path.await?.path.clone();
+        Ok(self.inner_path(&path).write_link(target))
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let path = fs_path.await?.path.clone();
+        let inner_path = self.inner_path(&path);
+        let meta = self
+            .snapshot(&path, |s| &mut s.metas, || inner_path.metadata().into())
+            .await?;
+        Ok(ReadRef::cell(meta))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for SnapshotFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        Ok(StringVc::cell(format!(
+            "{}-snapshot",
+            self.inner.to_string().await?
+        )))
+    }
+}
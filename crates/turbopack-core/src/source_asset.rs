@@ -5,6 +5,9 @@ use crate::{
     asset::{Asset, AssetContent, AssetContentVc, AssetVc},
     ident::AssetIdentVc,
     reference::AssetReferencesVc,
+    source_map::{
+        find_source_mapping_url, InputSourceMap, InputSourceMapVc, OptionSourceMapVc, SourceMapVc,
+    },
 };
 
 /// The raw [Asset]. It represents raw content from a path without any
@@ -56,3 +59,22 @@ impl Asset for SourceAsset {
         AssetReferencesVc::empty()
     }
 }
+
+#[turbo_tasks::value_impl]
+impl InputSourceMap for SourceAsset {
+    /// The source map that a `sourceMappingURL` comment at the end of the file
+    /// points to, e.g. for compiled files in `node_modules`.
+    #[turbo_tasks::function]
+    async fn input_source_map(&self) -> Result<OptionSourceMapVc> {
+        let FileContent::Content(file) = &*self.path.read().await? else {
+            return Ok(OptionSourceMapVc::cell(None));
+        };
+        let Ok(code) = file.content().to_str() else {
+            return Ok(OptionSourceMapVc::cell(None));
+        };
+        Ok(match find_source_mapping_url(&code) {
+            Some(url) => SourceMapVc::from_source_mapping_url(self.path, url),
+            None => OptionSourceMapVc::cell(None),
+        })
+    }
+}
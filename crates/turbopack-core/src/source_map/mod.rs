@@ -1,11 +1,15 @@
-use std::{io::Write, ops::Deref, sync::Arc};
+use std::{collections::HashSet, io::Write, ops::Deref, sync::Arc};
 
 use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use indexmap::IndexMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use sourcemap::{SourceMap as CrateMap, SourceMapBuilder};
+use sourcemap::{DecodedMap, SourceMap as CrateMap, SourceMapBuilder};
 use turbo_tasks::TryJoinIterExt;
-use turbo_tasks_fs::rope::{Rope, RopeBuilder, RopeVc};
+use turbo_tasks_fs::{
+    rope::{Rope, RopeBuilder, RopeVc},
+    FileContent, FileContentVc, FileSystemPathVc,
+};
 
 use crate::source_pos::SourcePos;
 
@@ -25,6 +29,27 @@ pub trait GenerateSourceMap {
     }
 }
 
+/// Allows assets whose content was produced by an earlier transformation, e.g.
+/// by a webpack loader or by a compiler before the package was published, to
+/// provide the source map of that transformation. Source maps generated from
+/// the content are composed with it (see [SourceMapVc::with_input_map]), so
+/// they point to the original sources.
+#[turbo_tasks::value_trait]
+pub trait InputSourceMap {
+    fn input_source_map(&self) -> OptionSourceMapVc;
+}
+
+/// How the source map of a generated file is emitted.
+#[turbo_tasks::value(shared, serialization = "auto_for_input")]
+#[derive(Debug, Default, Clone, Copy, PartialOrd, Ord, Hash)]
+pub enum SourceMapsType {
+    /// As a separate `.map` file next to the file.
+    #[default]
+    External,
+    /// As a data URI in the `sourceMappingURL` comment of the file itself.
+    Inline,
+}
+
 /// The source map spec lists 2 formats, a regular format where a single map
 /// covers the entire file, and an "index" sectioned format where multiple maps
 /// cover different regions of the file.
@@ -125,6 +150,104 @@ impl SourceMapVc {
         builder.add(0, 0, 0, 0, None, None);
         SourceMap::new_regular(builder.into_sourcemap()).cell()
     }
+
+    /// Parses a source map file. Index source maps are flattened into a
+    /// regular source map. Returns `None` when the file doesn't exist or isn't
+    /// a valid source map.
+    #[turbo_tasks::function]
+    pub async fn from_file(file: FileContentVc) -> Result<OptionSourceMapVc> {
+        let FileContent::Content(file) = &*file.await? else {
+            return Ok(OptionSourceMapVc::cell(None));
+        };
+        let map = decode_source_map(&file.content().to_bytes()?);
+        Ok(OptionSourceMapVc::cell(
+            map.map(|map| SourceMap::new_regular(map).cell()),
+        ))
+    }
+
+    /// The source map that a `sourceMappingURL` comment of the file at
+    /// `origin` points to, either a base64 data URI or a path relative to the
+    /// file.
+    #[turbo_tasks::function]
+    pub async fn from_source_mapping_url(
+        origin: FileSystemPathVc,
+        url: &str,
+    ) -> Result<OptionSourceMapVc> {
+        if let Some(data) = url.strip_prefix("data:") {
+            let map = data
+                .split_once(";base64,")
+                .and_then(|(_, data)| STANDARD.decode(data).ok())
+                .and_then(|bytes| decode_source_map(&bytes));
+            return Ok(OptionSourceMapVc::cell(
+                map.map(|map| SourceMap::new_regular(map).cell()),
+            ));
+        }
+        if url.contains("://") {
+            return Ok(OptionSourceMapVc::cell(None));
+        }
+        Ok(match *origin.parent().try_join(url).await? {
+            Some(path) => SourceMapVc::from_file(path.read()),
+            None => OptionSourceMapVc::cell(None),
+        })
+    }
+}
+
+/// Decodes a source map, flattening index source maps
+fn decode_source_map(bytes: &[u8]) -> Option<CrateMap> {
+    match sourcemap::decode_slice(bytes).ok()? {
+        DecodedMap::Regular(map) => Some(map),
+        DecodedMap::Index(map) => map.flatten().ok(),
+        _ => None,
+    }
+}
+
+/// Finds the url of a `sourceMappingURL` comment in the last line of `code`,
+/// in either the line or the block comment syntax.
+pub fn find_source_mapping_url(code: &str) -> Option<&str> {
+    let last_line = code.trim_end().rsplit('\n').next()?.trim();
+    let comment = last_line
+        .strip_prefix("//")
+        .or_else(|| last_line.strip_prefix("/*")?.strip_suffix("*/"))?;
+    let url = comment
+        .trim_start()
+        .strip_prefix(['#', '@'])?
+        .trim_start()
+        .strip_prefix("sourceMappingURL=")?
+        .trim();
+    (!url.is_empty()).then_some(url)
+}
+
+/// Maps the original positions of `map` through `input`, the source map of the
+/// code that `map` was generated from.
+fn compose(map: &CrateMap, input: &CrateMap) -> CrateMap {
+    let mut builder = SourceMapBuilder::new(map.get_file());
+    let mut with_contents = HashSet::new();
+    for token in map.tokens() {
+        if !token.has_source() {
+            continue;
+        }
+        let Some(original) = input
+            .lookup_token(token.get_src_line(), token.get_src_col())
+            // The sourcemap crate returns a previous line's token when there's no match on
+            // this line, see [SourceMapVc::lookup_token]
+            .filter(|t| t.get_dst_line() == token.get_src_line() && t.has_source())
+        else {
+            continue;
+        };
+        let raw = builder.add(
+            token.get_dst_line(),
+            token.get_dst_col(),
+            original.get_src_line(),
+            original.get_src_col(),
+            original.get_source(),
+            original.get_name().or(token.get_name()),
+        );
+        if with_contents.insert(raw.src_id) {
+            builder
+                .set_source_contents(raw.src_id, input.get_source_contents(original.get_src_id()));
+        }
+    }
+    builder.into_sourcemap()
 }
 
 #[turbo_tasks::value_impl]
@@ -241,6 +364,31 @@ impl SourceMapVc {
         };
         Ok(OptionToken(token).cell())
     }
+
+    /// Composes the source map with `input`, the source map of the code this
+    /// source map was generated from, so it maps to the original sources of
+    /// `input` instead. Mappings that `input` doesn't cover are dropped.
+    #[turbo_tasks::function]
+    pub async fn with_input_map(self, input: SourceMapVc) -> Result<SourceMapVc> {
+        let SourceMap::Regular(input_map) = &*input.await? else {
+            // Parsed source maps are always regular, see [SourceMapVc::from_file]
+            return Ok(self);
+        };
+        Ok(match &*self.await? {
+            SourceMap::Regular(map) => SourceMap::new_regular(compose(map, input_map)).cell(),
+            // The sections cover different parts of the generated code, but all of them
+            // map into the code that `input` is the source map of
+            SourceMap::Sectioned(map) => SourceMap::new_sectioned(
+                map.sections
+                    .iter()
+                    .map(|section| {
+                        SourceMapSection::new(section.offset, section.map.with_input_map(input))
+                    })
+                    .collect(),
+            )
+            .cell(),
+        })
+    }
 }
 
 /// A regular source map covers an entire file.
@@ -331,3 +479,108 @@ impl SourceMapSection {
         Self { offset, map }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sourcemap::{SourceMap as CrateMap, SourceMapBuilder};
+
+    use super::{compose, decode_source_map, find_source_mapping_url};
+
+    type TokenTuple<'a> = (u32, u32, Option<&'a str>, u32, u32, Option<&'a str>);
+
+    fn tokens(map: &CrateMap) -> Vec<TokenTuple<'_>> {
+        map.tokens()
+            .map(|token| {
+                (
+                    token.get_dst_line(),
+                    token.get_dst_col(),
+                    token.get_source(),
+                    token.get_src_line(),
+                    token.get_src_col(),
+                    token.get_name(),
+                )
+            })
+            .collect()
+    }
+
+    /// The map of `transpiled.js`, generated from `original.ts`
+    fn input_map() -> CrateMap {
+        let mut builder = SourceMapBuilder::new(Some("transpiled.js"));
+        let raw = builder.add(0, 0, 0, 0, Some("original.ts"), Some("foo"));
+        builder.set_source_contents(raw.src_id, Some("original content"));
+        builder.add(1, 4, 2, 2, Some("original.ts"), None);
+        builder.into_sourcemap()
+    }
+
+    #[test]
+    fn compose_maps_through_input() {
+        let mut builder = SourceMapBuilder::new(Some("bundle.js"));
+        builder.add(5, 0, 0, 0, Some("transpiled.js"), None);
+        builder.add(6, 2, 1, 4, Some("transpiled.js"), Some("bar"));
+        // Not covered by the input map, which only has tokens on earlier lines
+        builder.add(7, 0, 3, 0, Some("transpiled.js"), None);
+        let composed = compose(&builder.into_sourcemap(), &input_map());
+
+        assert_eq!(
+            tokens(&composed),
+            [
+                (5, 0, Some("original.ts"), 0, 0, Some("foo")),
+                (6, 2, Some("original.ts"), 2, 2, Some("bar")),
+            ]
+        );
+        assert_eq!(composed.get_file(), Some("bundle.js"));
+        assert_eq!(composed.get_source_count(), 1);
+        assert_eq!(composed.get_source_contents(0), Some("original content"));
+    }
+
+    #[test]
+    fn decode_flattens_index_maps() {
+        let index = br#"{
+            "version": 3,
+            "sections": [
+                {
+                    "offset": { "line": 0, "column": 0 },
+                    "map": { "version": 3, "sources": ["a.js"], "names": [], "mappings": "AAAA" }
+                },
+                {
+                    "offset": { "line": 1, "column": 0 },
+                    "map": { "version": 3, "sources": ["b.js"], "names": [], "mappings": "AAAA" }
+                }
+            ]
+        }"#;
+        let map = decode_source_map(index).unwrap();
+        assert_eq!(
+            tokens(&map),
+            [
+                (0, 0, Some("a.js"), 0, 0, None),
+                (1, 0, Some("b.js"), 0, 0, None),
+            ]
+        );
+
+        assert!(decode_source_map(b"not a source map").is_none());
+    }
+
+    #[test]
+    fn source_mapping_url() {
+        assert_eq!(
+            find_source_mapping_url("a();\n//# sourceMappingURL=a.js.map\n"),
+            Some("a.js.map")
+        );
+        assert_eq!(
+            find_source_mapping_url(".a {}\n/*# sourceMappingURL=a.css.map */"),
+            Some("a.css.map")
+        );
+        assert_eq!(
+            find_source_mapping_url("//@ sourceMappingURL=data:application/json;base64,e30="),
+            Some("data:application/json;base64,e30=")
+        );
+        assert_eq!(
+            find_source_mapping_url("//# sourceMappingURL=a.js.map\na();"),
+            None
+        );
+        assert_eq!(
+            find_source_mapping_url("// sourceMappingURL=a.js.map"),
+            None
+        );
+    }
+}
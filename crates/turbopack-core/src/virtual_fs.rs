@@ -1,60 +1,278 @@
-use anyhow::{bail, Result};
-use turbo_tasks::{primitives::StringVc, CompletionVc, ValueToString, ValueToStringVc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::take,
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use auto_hash_map::{AutoMap, AutoSet};
+use turbo_tasks::{
+    get_invalidator, mark_stateful, primitives::StringVc, CompletionVc, Invalidator, ValueToString,
+    ValueToStringVc,
+};
 use turbo_tasks_fs::{
-    DirectoryContentVc, FileContentVc, FileMetaVc, FileSystem, FileSystemPathVc, FileSystemVc,
-    LinkContentVc,
+    util::{follow_links, split_parent},
+    DirectoryContentVc, DirectoryEntry, File, FileContent, FileContentVc, FileMeta, FileMetaVc,
+    FileSystem, FileSystemEntryType, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc,
+    LinkType,
 };
 
-#[turbo_tasks::value]
-pub struct VirtualFileSystem;
+/// A writable file system that only lives in memory. Files, symlinks and
+/// directories are created by writing to it, and reads are invalidated when
+/// the entries they read are written.
+///
+/// Directories are created implicitly when writing a file or link inside of
+/// them and are never removed. Symlinks are followed when reading, but only
+/// when the link is the last component of the path.
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+pub struct VirtualFileSystem {
+    name: String,
+    /// The entries by their path, along with the tasks that read them. Paths
+    /// that were read but don't exist have a node without an entry.
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    nodes: Mutex<HashMap<String, Node>>,
+}
+
+#[derive(Default)]
+struct Node {
+    entry: Option<VirtualEntry>,
+    invalidators: AutoSet<Invalidator>,
+}
+
+impl Node {
+    fn invalidate(&mut self) {
+        for invalidator in take(&mut self.invalidators) {
+            invalidator.invalidate();
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum VirtualEntry {
+    File(File),
+    Symlink {
+        target: String,
+        link_type: LinkType,
+    },
+    /// The names of the entries in the directory and their types
+    Directory(BTreeMap<String, FileSystemEntryType>),
+}
+
+impl VirtualEntry {
+    fn entry_type(&self) -> FileSystemEntryType {
+        match self {
+            VirtualEntry::File(_) => FileSystemEntryType::File,
+            VirtualEntry::Symlink { .. } => FileSystemEntryType::Symlink,
+            VirtualEntry::Directory(_) => FileSystemEntryType::Directory,
+        }
+    }
+
+    fn as_link(&self) -> Option<(&str, LinkType)> {
+        match self {
+            VirtualEntry::Symlink { target, link_type } => Some((target, *link_type)),
+            _ => None,
+        }
+    }
+}
 
 #[turbo_tasks::value_impl]
 impl VirtualFileSystemVc {
     #[turbo_tasks::function]
     pub fn new() -> Self {
-        Self::cell(VirtualFileSystem)
+        Self::new_named("virtual file system".to_string())
+    }
+
+    /// Creates an empty virtual file system. File systems with the same name
+    /// are the same instance.
+    #[turbo_tasks::function]
+    pub fn new_named(name: String) -> Self {
+        mark_stateful();
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            String::new(),
+            Node {
+                entry: Some(VirtualEntry::Directory(BTreeMap::new())),
+                invalidators: AutoSet::new(),
+            },
+        );
+        Self::cell(VirtualFileSystem {
+            name,
+            nodes: Mutex::new(nodes),
+        })
+    }
+}
+
+impl VirtualFileSystem {
+    /// Returns the entry at `path` and registers the current task to be
+    /// invalidated when it changes, has to be called within a turbo-tasks
+    /// function
+    fn get(&self, path: &str) -> Option<VirtualEntry> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = nodes.entry(path.to_string()).or_default();
+        node.invalidators.insert(get_invalidator());
+        node.entry.clone()
+    }
+
+    /// Like `get`, but follows the symlink at `path`, if any
+    fn get_following_links(&self, path: &str) -> Result<Option<VirtualEntry>> {
+        follow_links(path, |path| self.get(path), VirtualEntry::as_link)
+            .with_context(|| format!("reading [{}]/{}", self.name, path))
+    }
+
+    /// Replaces the entry at `path`, creating its parent directories, and
+    /// invalidates the tasks that read anything that changed. Returns whether
+    /// anything changed.
+    fn set(&self, path: &str, entry: Option<VirtualEntry>) -> Result<bool> {
+        let mut nodes = self.nodes.lock().unwrap();
+
+        // Check everything up front, so that nothing is changed on errors
+        let current = nodes.get(path).and_then(|node| node.entry.as_ref());
+        if current == entry.as_ref() {
+            return Ok(false);
+        }
+        if let Some(VirtualEntry::Directory(_)) = current {
+            bail!("[{}]/{} is a directory", self.name, path);
+        }
+        let mut ancestor = path;
+        while let Some((parent, _)) = split_parent(ancestor) {
+            match nodes.get(parent).and_then(|node| node.entry.as_ref()) {
+                Some(VirtualEntry::Directory(_)) | None => {}
+                Some(_) => bail!("[{}]/{} is not a directory", self.name, parent),
+            }
+            ancestor = parent;
+        }
+
+        let mut entry_type = entry.as_ref().map(VirtualEntry::entry_type);
+        let node = nodes.entry(path.to_string()).or_default();
+        node.entry = entry;
+        node.invalidate();
+
+        let mut child = path;
+        while let Some((parent, name)) = split_parent(child) {
+            let node = nodes.entry(parent.to_string()).or_default();
+            let created = node.entry.is_none();
+            let VirtualEntry::Directory(children) = node
+                .entry
+                .get_or_insert_with(|| VirtualEntry::Directory(BTreeMap::new()))
+            else {
+                unreachable!("ancestors were checked to be directories");
+            };
+            let changed = match entry_type {
+                Some(entry_type) => {
+                    children.insert(name.to_string(), entry_type) != Some(entry_type)
+                }
+                None => children.remove(name).is_some(),
+            };
+            if created || changed {
+                node.invalidate();
+            }
+            // The ancestors of an existing directory exist already
+            if !created {
+                break;
+            }
+            entry_type = Some(FileSystemEntryType::Directory);
+            child = parent;
+        }
+        Ok(true)
     }
 }
 
 #[turbo_tasks::value_impl]
 impl FileSystem for VirtualFileSystem {
     #[turbo_tasks::function]
-    fn read(&self, _fs_path: FileSystemPathVc) -> Result<FileContentVc> {
-        bail!("Reading is not possible on the virtual file system")
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        let fs_path = fs_path.await?;
+        Ok(match self.get_following_links(&fs_path.path)? {
+            Some(VirtualEntry::File(file)) => FileContent::Content(file).cell(),
+            _ => FileContent::NotFound.cell(),
+        })
     }
 
     #[turbo_tasks::function]
-    fn read_link(&self, _fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
-        bail!("Reading is not possible on the virtual file system")
+    async fn read_link(&self, fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
+        let fs_path = fs_path.await?;
+        Ok(match self.get(&fs_path.path) {
+            Some(VirtualEntry::Symlink { target, link_type }) => {
+                LinkContent::Link { target, link_type }.cell()
+            }
+            _ => LinkContent::NotFound.cell(),
+        })
     }
 
     #[turbo_tasks::function]
-    fn read_dir(&self, _fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
-        bail!("Reading is not possible on the virtual file system")
+    async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let path = fs_path.await?.path.clone();
+        let Some(VirtualEntry::Directory(children)) = self.get_following_links(&path)? else {
+            return Ok(DirectoryContentVc::not_found());
+        };
+        let entries: AutoMap<_, _> = children
+            .into_iter()
+            .map(|(name, entry_type)| {
+                let child = fs_path.join(&name);
+                let entry = match entry_type {
+                    FileSystemEntryType::File => DirectoryEntry::File(child),
+                    FileSystemEntryType::Directory => DirectoryEntry::Directory(child),
+                    FileSystemEntryType::Symlink => DirectoryEntry::Symlink(child),
+                    _ => DirectoryEntry::Other(child),
+                };
+                (name, entry)
+            })
+            .collect();
+        Ok(DirectoryContentVc::new(entries))
     }
 
     #[turbo_tasks::function]
-    fn track(&self, _fs_path: FileSystemPathVc) -> Result<CompletionVc> {
-        bail!("Tracking is not possible on the virtual file system")
+    async fn track(&self, fs_path: FileSystemPathVc) -> Result<CompletionVc> {
+        self.get(&fs_path.await?.path);
+        Ok(CompletionVc::new())
     }
 
     #[turbo_tasks::function]
-    fn write(&self, _fs_path: FileSystemPathVc, _content: FileContentVc) -> Result<CompletionVc> {
-        bail!("Writing is not possible on the virtual file system")
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let entry = match &*content.await? {
+            FileContent::Content(file) => Some(VirtualEntry::File(file.clone())),
+            FileContent::NotFound => None,
+        };
+        Ok(if self.set(&fs_path.await?.path, entry)? {
+            CompletionVc::new()
+        } else {
+            CompletionVc::unchanged()
+        })
     }
 
     #[turbo_tasks::function]
-    fn write_link(
+    async fn write_link(
         &self,
-        _fs_path: FileSystemPathVc,
-        _target: LinkContentVc,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
     ) -> Result<CompletionVc> {
-        bail!("Writing is not possible on the virtual file system")
+        let path = fs_path.await?.path.clone();
+        let entry = match &*target.await? {
+            LinkContent::Link { target, link_type } => Some(VirtualEntry::Symlink {
+                target: target.clone(),
+                link_type: *link_type,
+            }),
+            LinkContent::Invalid => bail!("invalid symlink target: [{}]/{}", self.name, path),
+            LinkContent::NotFound => None,
+        };
+        Ok(if self.set(&path, entry)? {
+            CompletionVc::new()
+        } else {
+            CompletionVc::unchanged()
+        })
     }
 
     #[turbo_tasks::function]
-    fn metadata(&self, _fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
-        bail!("Reading is not possible on the virtual file system")
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let path = fs_path.await?.path.clone();
+        if self.get_following_links(&path)?.is_none() {
+            bail!("reading metadata for [{}]/{}: not found", self.name, path);
+        }
+        Ok(FileMeta::default().cell())
     }
 }
 
@@ -62,6 +280,6 @@ impl FileSystem for VirtualFileSystem {
 impl ValueToString for VirtualFileSystem {
     #[turbo_tasks::function]
     fn to_string(&self) -> StringVc {
-        StringVc::cell("virtual file system".to_string())
+        StringVc::cell(self.name.clone())
     }
 }
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use turbo_tasks::Value;
+use turbo_tasks_fs::{File, FileSystem, FileSystemPathVc, FileSystemVc};
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    asset::{Asset, AssetContentVc, AssetVc, AssetsVc},
+    chunk::naming::{ContentHashNamingVc, ContentHashPlacement},
+    virtual_asset::VirtualAssetVc,
+    virtual_fs::VirtualFileSystemVc,
+};
+
+register!();
+
+const CONTENT: &str = "console.log('hello')";
+
+/// The first 8 hex digits of the content hash of [CONTENT]
+fn hash() -> String {
+    encode_hex(hash_xxh3_hash64(File::from(CONTENT).content()))[..8].to_string()
+}
+
+fn asset(path: &str) -> AssetVc {
+    let fs: FileSystemVc = VirtualFileSystemVc::new().into();
+    VirtualAssetVc::new(
+        fs.root().join(path),
+        AssetContentVc::from(File::from(CONTENT)),
+    )
+    .into()
+}
+
+async fn hashed_path(placement: ContentHashPlacement, path: &str) -> Result<String> {
+    let naming = ContentHashNamingVc::new(8, Value::new(placement));
+    let hashed_path: FileSystemPathVc = naming.hashed_path(asset(path));
+    Ok(hashed_path.await?.path.clone())
+}
+
+#[tokio::test]
+async fn placements() {
+    run! {
+        turbopack_core::register();
+        let hash = hash();
+        assert_eq!(
+            hashed_path(ContentHashPlacement::BeforeExtension, "static/index.js").await?,
+            format!("static/index.{hash}.js")
+        );
+        assert_eq!(
+            hashed_path(ContentHashPlacement::BeforeExtension, "static/LICENSE").await?,
+            format!("static/LICENSE.{hash}")
+        );
+        assert_eq!(
+            hashed_path(ContentHashPlacement::ReplaceName, "static/index.js").await?,
+            format!("static/{hash}.js")
+        );
+        assert_eq!(
+            hashed_path(ContentHashPlacement::ReplaceName, "static/LICENSE").await?,
+            format!("static/{hash}")
+        );
+        assert_eq!(
+            hashed_path(ContentHashPlacement::Directory, "static/index.js").await?,
+            format!("static/{hash}/index.js")
+        );
+
+        let naming = ContentHashNamingVc::new(0, Value::new(ContentHashPlacement::Directory));
+        assert!(naming.await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn manifest() {
+    run! {
+        turbopack_core::register();
+        let hash = hash();
+        let naming = ContentHashNamingVc::new(8, Value::new(ContentHashPlacement::BeforeExtension));
+        let assets = AssetsVc::cell(vec![asset("static/index.js"), asset("static/app.css")]);
+
+        let manifest = naming.manifest(assets).await?;
+        let entries: Vec<_> = manifest
+            .iter()
+            .map(|(from, to)| (from.as_str(), to.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("static/index.js", format!("static/index.{hash}.js").as_str()),
+                ("static/app.css", format!("static/app.{hash}.css").as_str()),
+            ]
+        );
+
+        // The applied assets are exposed at the paths from the manifest
+        for (asset, (_, to)) in naming.apply_all(assets).await?.iter().zip(manifest.iter()) {
+            assert_eq!(&asset.ident().path().await?.path, to);
+        }
+    }
+}
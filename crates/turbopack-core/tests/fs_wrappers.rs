@@ -0,0 +1,201 @@
+use anyhow::Result;
+use turbo_tasks::CompletionVc;
+use turbo_tasks_fs::{
+    rebased::RebasedFileSystemVc, DirectoryContent, DirectoryEntry, File, FileContent, FileSystem,
+    FileSystemPathVc, FileSystemVc, LinkContent, LinkType,
+};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    instrumented_fs::InstrumentedFileSystemVc,
+    issue::{Issue, IssueVc},
+    overlay_fs::OverlayFileSystemVc,
+    quota_fs::{QuotaFileSystemVc, QuotaLimits},
+    read_only_fs::ReadOnlyFileSystemVc,
+    snapshot_fs::SnapshotFileSystemVc,
+    virtual_fs::VirtualFileSystemVc,
+};
+
+register!();
+
+async fn read_str(path: FileSystemPathVc) -> Result<Option<String>> {
+    Ok(match &*path.read().strongly_consistent().await? {
+        FileContent::Content(file) => Some(file.content().to_str()?.to_string()),
+        FileContent::NotFound => None,
+    })
+}
+
+async fn dir_names(path: FileSystemPathVc) -> Result<Option<Vec<String>>> {
+    Ok(match &*path.read_dir().strongly_consistent().await? {
+        DirectoryContent::Entries(entries) => {
+            let mut names: Vec<_> = entries.iter().map(|(name, _)| name.clone()).collect();
+            names.sort();
+            Some(names)
+        }
+        DirectoryContent::NotFound => None,
+    })
+}
+
+/// The titles of the issues emitted while writing
+async fn issue_titles(write: CompletionVc) -> Result<Vec<String>> {
+    let issues = IssueVc::peek_issues_with_path(write).await?.await?;
+    let mut titles = Vec::new();
+    for issue in issues.iter() {
+        titles.push(issue.title().await?.to_string());
+    }
+    Ok(titles)
+}
+
+/// A virtual file system with a file with the content "content" at each of
+/// `paths`
+async fn inner_fs(paths: &[&str]) -> Result<FileSystemVc> {
+    named_fs("inner", paths).await
+}
+
+async fn named_fs(name: &str, paths: &[&str]) -> Result<FileSystemVc> {
+    let fs: FileSystemVc = VirtualFileSystemVc::new_named(name.to_string()).into();
+    for path in paths {
+        fs.root()
+            .join(path)
+            .write(File::from("content").into())
+            .await?;
+    }
+    Ok(fs)
+}
+
+#[tokio::test]
+async fn read_only_rejects_writes() {
+    run! {
+        turbopack_core::register();
+        let inner = inner_fs(&["file.txt"]).await?;
+        let fs: FileSystemVc = ReadOnlyFileSystemVc::new(inner).into();
+        let file = fs.root().join("file.txt");
+        assert_eq!(read_str(file).await?.as_deref(), Some("content"));
+
+        let write = file.write(File::from("changed").into());
+        assert_eq!(issue_titles(write).await?, ["Write to a read-only file system"]);
+        let remove = file.write(FileContent::NotFound.cell());
+        assert_eq!(issue_titles(remove).await?, ["Write to a read-only file system"]);
+        assert_eq!(read_str(file).await?.as_deref(), Some("content"));
+        assert_eq!(read_str(inner.root().join("file.txt")).await?.as_deref(), Some("content"));
+    }
+}
+
+#[tokio::test]
+async fn overlay_shadows_any_entry_type() {
+    run! {
+        turbopack_core::register();
+        let upper = named_fs("upper", &["dir/file.txt", "target.txt"]).await?;
+        let lower = named_fs("lower", &["dir", "link", "lower.txt"]).await?;
+        upper
+            .root()
+            .join("link")
+            .write_link(LinkContent::Link { target: "target.txt".to_string(), link_type: LinkType::empty() }.cell())
+            .await?;
+        let root = FileSystemVc::from(OverlayFileSystemVc::new(vec![upper, lower])).root();
+
+        // The directory in the upper layer hides the file in the lower one
+        assert_eq!(read_str(root.join("dir")).await?, None);
+        assert_eq!(dir_names(root.join("dir")).await?, Some(vec!["file.txt".to_string()]));
+        // The link in the upper layer hides the file in the lower one, for
+        // reads and link reads alike
+        assert!(matches!(&*root.join("link").read_link().strongly_consistent().await?, LinkContent::Link { target, .. } if target == "target.txt"));
+        assert_eq!(read_str(root.join("link")).await?.as_deref(), Some("content"));
+        assert!(matches!(&*root.join("lower.txt").read_link().strongly_consistent().await?, LinkContent::NotFound));
+        assert_eq!(read_str(root.join("lower.txt")).await?.as_deref(), Some("content"));
+    }
+}
+
+#[tokio::test]
+async fn quota_reports_exceeded_limits() {
+    run! {
+        turbopack_core::register();
+        let inner = inner_fs(&[]).await?;
+        let limits = QuotaLimits {
+            max_bytes: Some(8),
+            max_files: None,
+        };
+        let quota = QuotaFileSystemVc::new(inner, limits.cell());
+        let root = FileSystemVc::from(quota).root();
+
+        let first = root.join("a.txt").write(File::from("12345").into());
+        assert!(issue_titles(first).await?.is_empty());
+        let second = root.join("b.txt").write(File::from("12345").into());
+        assert_eq!(issue_titles(second).await?, ["Output exceeds its quota"]);
+
+        // The write isn't rejected, only reported
+        assert_eq!(read_str(inner.root().join("b.txt")).await?.as_deref(), Some("12345"));
+        let usage = quota.usage().await?;
+        assert_eq!((usage.bytes, usage.files), (10, 2));
+
+        root.join("a.txt").write(FileContent::NotFound.cell()).await?;
+        let usage = quota.usage().await?;
+        assert_eq!((usage.bytes, usage.files), (5, 1));
+    }
+}
+
+#[tokio::test]
+async fn snapshot_isolates_generations() {
+    run! {
+        turbopack_core::register();
+        let inner = inner_fs(&["file.txt"]).await?;
+        let inner_file = inner.root().join("file.txt");
+        let snapshot = SnapshotFileSystemVc::new(inner);
+        let file = FileSystemVc::from(snapshot).root().join("file.txt");
+        assert_eq!(read_str(file).await?.as_deref(), Some("content"));
+
+        inner_file.write(File::from("changed").into()).await?;
+        assert_eq!(read_str(inner_file).await?.as_deref(), Some("changed"));
+        assert_eq!(read_str(file).await?.as_deref(), Some("content"));
+
+        snapshot.next_generation().await?;
+        assert_eq!(read_str(file).await?.as_deref(), Some("changed"));
+    }
+}
+
+#[tokio::test]
+async fn instrumented_groups_by_prefix() {
+    run! {
+        turbopack_core::register();
+        let paths = ["packages/a/index.js", "packages/b/lib/index.js", "README.md"];
+        let inner = inner_fs(&paths).await?;
+        let instrumented = InstrumentedFileSystemVc::new(inner, 1);
+        let root = FileSystemVc::from(instrumented).root();
+        for path in paths {
+            read_str(root.join(path)).await?;
+        }
+        assert_eq!(dir_names(root.join("packages/a")).await?, Some(vec!["index.js".to_string()]));
+
+        let report = instrumented.report().strongly_consistent().await?;
+        let stats: Vec<_> = report
+            .iter()
+            .map(|(prefix, stats)| (prefix.as_str(), stats.reads, stats.dir_reads, stats.bytes_read))
+            .collect();
+        assert_eq!(stats, [("", 1, 0, 7), ("packages", 2, 1, 14)]);
+    }
+}
+
+#[tokio::test]
+async fn rebased_lists_mount_ancestors() {
+    run! {
+        turbopack_core::register();
+        let inner = inner_fs(&["src/index.js"]).await?;
+        let fs: FileSystemVc =
+            RebasedFileSystemVc::new(inner.root(), "node_modules/pkg".to_string()).into();
+        let root = fs.root();
+        assert_eq!(dir_names(root).await?, Some(vec!["node_modules".to_string()]));
+        assert_eq!(dir_names(root.join("node_modules")).await?, Some(vec!["pkg".to_string()]));
+        assert_eq!(dir_names(root.join("node_modules/pkg")).await?, Some(vec!["src".to_string()]));
+        assert_eq!(dir_names(root.join("other")).await?, None);
+
+        // Entries point into the rebased file system
+        let DirectoryContent::Entries(entries) = &*root.join("node_modules/pkg/src").read_dir().strongly_consistent().await? else {
+            panic!("node_modules/pkg/src should be a directory");
+        };
+        let Some(DirectoryEntry::File(file)) = entries.get("index.js") else {
+            panic!("node_modules/pkg/src/index.js should be a file");
+        };
+        assert_eq!(file.await?.path, "node_modules/pkg/src/index.js");
+        assert_eq!(file.await?.fs, fs.resolve().await?);
+        assert_eq!(read_str(*file).await?.as_deref(), Some("content"));
+    }
+}
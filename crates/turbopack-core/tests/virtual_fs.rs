@@ -0,0 +1,115 @@
+use anyhow::Result;
+use turbo_tasks::ValueToString;
+use turbo_tasks_fs::{
+    DirectoryContent, DirectoryEntry, File, FileContent, FileSystem, FileSystemEntryType,
+    FileSystemPathVc, FileSystemVc, LinkContent, LinkType,
+};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::virtual_fs::VirtualFileSystemVc;
+
+register!();
+
+async fn read_str(path: FileSystemPathVc) -> Result<Option<String>> {
+    Ok(match &*path.read().strongly_consistent().await? {
+        FileContent::Content(file) => Some(file.content().to_str()?.to_string()),
+        FileContent::NotFound => None,
+    })
+}
+
+async fn dir_names(path: FileSystemPathVc) -> Result<Option<Vec<String>>> {
+    Ok(match &*path.read_dir().strongly_consistent().await? {
+        DirectoryContent::Entries(entries) => {
+            let mut names: Vec<_> = entries.iter().map(|(name, _)| name.clone()).collect();
+            names.sort();
+            Some(names)
+        }
+        DirectoryContent::NotFound => None,
+    })
+}
+
+#[tokio::test]
+async fn write_invalidates_reads() {
+    run! {
+        turbopack_core::register();
+        let fs: FileSystemVc = VirtualFileSystemVc::new().into();
+        let file = fs.root().join("file.txt");
+        let other = fs.root().join("other.txt");
+        assert_eq!(read_str(file).await?, None);
+
+        file.write(File::from("one").into()).await?;
+        other.write(File::from("other").into()).await?;
+        assert_eq!(read_str(file).await?.as_deref(), Some("one"));
+
+        file.write(File::from("two").into()).await?;
+        assert_eq!(read_str(file).await?.as_deref(), Some("two"));
+        assert_eq!(read_str(other).await?.as_deref(), Some("other"));
+    }
+}
+
+#[tokio::test]
+async fn write_creates_parent_directories() {
+    run! {
+        turbopack_core::register();
+        let fs: FileSystemVc = VirtualFileSystemVc::new().into();
+        let root = fs.root();
+        assert_eq!(dir_names(root).await?, Some(vec![]));
+        assert_eq!(dir_names(root.join("a")).await?, None);
+
+        root.join("a/b/file.txt").write(File::from("content").into()).await?;
+        assert_eq!(dir_names(root).await?, Some(vec!["a".to_string()]));
+        assert_eq!(dir_names(root.join("a")).await?, Some(vec!["b".to_string()]));
+        assert_eq!(*root.join("a/b").get_type().strongly_consistent().await?, FileSystemEntryType::Directory);
+        let DirectoryContent::Entries(entries) = &*root.join("a/b").read_dir().strongly_consistent().await? else {
+            panic!("a/b should be a directory");
+        };
+        let Some(DirectoryEntry::File(file)) = entries.get("file.txt") else {
+            panic!("a/b/file.txt should be a file");
+        };
+        assert_eq!(&*file.to_string().await?, "[virtual file system]/a/b/file.txt");
+
+        // Files can't be used as directories
+        assert!(root.join("a/b/file.txt/nested").write(File::from("content").into()).await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn write_not_found_removes_entries() {
+    run! {
+        turbopack_core::register();
+        let fs: FileSystemVc = VirtualFileSystemVc::new().into();
+        let dir = fs.root().join("dir");
+        let file = dir.join("file.txt");
+        let link = dir.join("link");
+        file.write(File::from("content").into()).await?;
+        link.write_link(LinkContent::Link { target: "file.txt".to_string(), link_type: LinkType::empty() }.cell()).await?;
+        assert_eq!(dir_names(dir).await?, Some(vec!["file.txt".to_string(), "link".to_string()]));
+        assert_eq!(read_str(link).await?.as_deref(), Some("content"));
+
+        file.write(FileContent::NotFound.cell()).await?;
+        assert_eq!(read_str(file).await?, None);
+        assert_eq!(read_str(link).await?, None);
+        assert_eq!(dir_names(dir).await?, Some(vec!["link".to_string()]));
+
+        link.write_link(LinkContent::NotFound.cell()).await?;
+        assert!(matches!(&*link.read_link().strongly_consistent().await?, LinkContent::NotFound));
+        // Directories are never removed
+        assert_eq!(dir_names(dir).await?, Some(vec![]));
+    }
+}
+
+#[tokio::test]
+async fn symlink_loops_are_limited() {
+    run! {
+        turbopack_core::register();
+        let fs: FileSystemVc = VirtualFileSystemVc::new().into();
+        let a = fs.root().join("a");
+        let b = fs.root().join("b");
+        a.write_link(LinkContent::Link { target: "b".to_string(), link_type: LinkType::empty() }.cell()).await?;
+        b.write_link(LinkContent::Link { target: "a".to_string(), link_type: LinkType::empty() }.cell()).await?;
+
+        let error = a.read().strongly_consistent().await.unwrap_err();
+        assert!(format!("{error:?}").contains("too many levels of symbolic links"));
+        // The links themselves can still be read
+        assert!(matches!(&*a.read_link().strongly_consistent().await?, LinkContent::Link { target, .. } if target == "b"));
+    }
+}
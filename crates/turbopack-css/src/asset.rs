@@ -241,6 +241,7 @@ impl CssChunkItem for ModuleChunkItem {
         if let ParseResult::Ok {
             stylesheet,
             source_map,
+            input_source_map,
             ..
         } = &*parsed
         {
@@ -280,7 +281,8 @@ impl CssChunkItem for ModuleChunkItem {
 
             code_gen.emit(&stylesheet)?;
 
-            let srcmap = ParseResultSourceMap::new(source_map.clone(), srcmap).cell();
+            let srcmap =
+                ParseResultSourceMap::new(source_map.clone(), srcmap, *input_source_map).cell();
 
             Ok(CssChunkItemContent {
                 inner_code: code_string.into(),
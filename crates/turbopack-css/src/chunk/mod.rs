@@ -7,12 +7,17 @@ use std::fmt::Write;
 use anyhow::{anyhow, Result};
 use indexmap::IndexSet;
 use turbo_tasks::{primitives::StringVc, TryJoinIterExt, Value, ValueToString};
-use turbo_tasks_fs::{rope::Rope, File, FileSystemPathOptionVc};
+use turbo_tasks_fs::{
+    rope::{Rope, RopeBuilder},
+    File, FileSystemPathOptionVc,
+};
 use turbopack_core::{
     asset::{Asset, AssetContentVc, AssetVc, AssetsVc},
     chunk::{
-        availability_info::AvailabilityInfo, chunk_content, chunk_content_split, Chunk,
-        ChunkContentResult, ChunkGroupReferenceVc, ChunkItem, ChunkItemVc, ChunkVc,
+        availability_info::AvailabilityInfo,
+        chunk_content, chunk_content_split,
+        graph::{ChunkItemSize, ChunkItemSizesVc},
+        Chunk, ChunkContentResult, ChunkGroupReferenceVc, ChunkItem, ChunkItemVc, ChunkVc,
         ChunkableAssetVc, ChunkingContext, ChunkingContextVc, ChunksVc, FromChunkableAsset,
         ModuleId, ModuleIdVc, ModuleIdsVc, OutputChunk, OutputChunkRuntimeInfo,
         OutputChunkRuntimeInfoVc, OutputChunkVc,
@@ -25,7 +30,7 @@ use turbopack_core::{
     },
     reference::{AssetReference, AssetReferenceVc, AssetReferencesVc},
     resolve::PrimaryResolveResult,
-    source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc},
+    source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc, SourceMapsType},
 };
 use writer::expand_imports;
 
@@ -165,28 +170,36 @@ impl CssChunkContentVc {
 
         code.push_code(&body.build());
 
-        if *this
-            .context
-            .reference_chunk_source_maps(this.chunk.into())
-            .await?
-            && code.has_source_map()
-        {
-            let chunk_path = this.chunk.path().await?;
-            write!(
-                code,
-                "\n/*# sourceMappingURL={}.map*/",
-                chunk_path.file_name()
-            )?;
-        }
-
         let c = code.build().cell();
         Ok(c)
     }
 
     #[turbo_tasks::function]
     async fn content(self) -> Result<AssetContentVc> {
-        let code = self.code().await?;
-        Ok(File::from(code.source_code().clone()).into())
+        use std::io::Write;
+
+        let this = self.await?;
+        let code_vc = self.code();
+        let code = code_vc.await?;
+        let mut source_code = RopeBuilder::default();
+        source_code += code.source_code();
+
+        let source_maps_type = this.context.source_maps_type();
+        if code.has_source_map()
+            && (*source_maps_type.await? == SourceMapsType::Inline
+                || *this
+                    .context
+                    .reference_chunk_source_maps(this.chunk.into())
+                    .await?)
+        {
+            let chunk_path = this.chunk.path().await?;
+            let url = code_vc
+                .source_mapping_url(chunk_path.file_name(), source_maps_type)
+                .await?;
+            write!(source_code, "\n/*# sourceMappingURL={}*/", url)?;
+        }
+
+        Ok(File::from(source_code.build()).into())
     }
 }
 
@@ -300,6 +313,28 @@ impl Chunk for CssChunk {
         }
         Ok(ChunksVc::cell(chunks))
     }
+
+    #[turbo_tasks::function]
+    async fn chunk_item_sizes(&self) -> Result<ChunkItemSizesVc> {
+        let content = css_chunk_content(
+            self.context,
+            self.main_entries,
+            Value::new(self.availability_info),
+        )
+        .await?;
+        let sizes = content
+            .chunk_items
+            .iter()
+            .map(|&chunk_item| async move {
+                Ok(ChunkItemSize {
+                    ident: chunk_item.asset_ident(),
+                    size: chunk_item.content().await?.inner_code.len() as u64,
+                })
+            })
+            .try_join()
+            .await?;
+        Ok(ChunkItemSizesVc::cell(sizes))
+    }
 }
 
 #[turbo_tasks::value_impl]
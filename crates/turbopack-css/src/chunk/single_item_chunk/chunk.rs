@@ -2,7 +2,7 @@ use std::fmt::Write;
 
 use anyhow::Result;
 use turbo_tasks::{primitives::StringVc, ValueToString};
-use turbo_tasks_fs::File;
+use turbo_tasks_fs::{rope::RopeBuilder, File};
 use turbopack_core::{
     asset::{Asset, AssetContentVc, AssetVc},
     chunk::{Chunk, ChunkItem, ChunkVc, ChunkingContext, ChunkingContextVc},
@@ -10,7 +10,7 @@ use turbopack_core::{
     ident::AssetIdentVc,
     introspect::{Introspectable, IntrospectableVc},
     reference::AssetReferencesVc,
-    source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc},
+    source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc, SourceMapsType},
 };
 
 use super::source_map::SingleItemCssChunkSourceMapAssetReferenceVc;
@@ -52,20 +52,6 @@ impl SingleItemCssChunkVc {
             content.source_map.map(|sm| sm.as_generate_source_map()),
         );
 
-        if *this
-            .context
-            .reference_chunk_source_maps(self.into())
-            .await?
-            && code.has_source_map()
-        {
-            let chunk_path = self.path().await?;
-            write!(
-                code,
-                "\n/*# sourceMappingURL={}.map*/",
-                chunk_path.file_name()
-            )?;
-        }
-
         let c = code.build().cell();
         Ok(c)
     }
@@ -100,8 +86,30 @@ impl Asset for SingleItemCssChunk {
 
     #[turbo_tasks::function]
     async fn content(self_vc: SingleItemCssChunkVc) -> Result<AssetContentVc> {
-        let code = self_vc.code().await?;
-        Ok(File::from(code.source_code().clone()).into())
+        use std::io::Write;
+
+        let this = self_vc.await?;
+        let code_vc = self_vc.code();
+        let code = code_vc.await?;
+        let mut source_code = RopeBuilder::default();
+        source_code += code.source_code();
+
+        let source_maps_type = this.context.source_maps_type();
+        if code.has_source_map()
+            && (*source_maps_type.await? == SourceMapsType::Inline
+                || *this
+                    .context
+                    .reference_chunk_source_maps(self_vc.into())
+                    .await?)
+        {
+            let chunk_path = self_vc.path().await?;
+            let url = code_vc
+                .source_mapping_url(chunk_path.file_name(), source_maps_type)
+                .await?;
+            write!(source_code, "\n/*# sourceMappingURL={}*/", url)?;
+        }
+
+        Ok(File::from(source_code.build()).into())
     }
 
     #[turbo_tasks::function]
@@ -25,6 +25,7 @@ use turbopack_core::{
         parse::RequestVc,
         ResolveResult, ResolveResultVc,
     },
+    source_map::OptionSourceMapVc,
 };
 use turbopack_ecmascript::{
     chunk::{
@@ -535,7 +536,7 @@ fn generate_minimal_source_map(filename: String, source: String) -> ParseResultS
     }
     let sm: Arc<SourceMap> = Default::default();
     sm.new_source_file(FileName::Custom(filename), source);
-    let map = ParseResultSourceMap::new(sm, mappings);
+    let map = ParseResultSourceMap::new(sm, mappings, OptionSourceMapVc::cell(None));
     map.cell()
 }
 
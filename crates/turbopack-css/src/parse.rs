@@ -19,7 +19,10 @@ use turbo_tasks::{Value, ValueToString};
 use turbo_tasks_fs::{FileContent, FileSystemPath};
 use turbopack_core::{
     asset::{Asset, AssetContent, AssetVc},
-    source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc},
+    source_map::{
+        GenerateSourceMap, GenerateSourceMapVc, InputSourceMap, InputSourceMapVc,
+        OptionSourceMapVc, SourceMap as TurbopackSourceMap,
+    },
     SOURCE_MAP_ROOT_NAME,
 };
 use turbopack_swc_utils::emitter::IssueEmitter;
@@ -39,6 +42,8 @@ pub enum ParseResult {
         stylesheet: Stylesheet,
         #[turbo_tasks(debug_ignore, trace_ignore)]
         source_map: Arc<SourceMap>,
+        /// See [turbopack_core::source_map::InputSourceMap]
+        input_source_map: OptionSourceMapVc,
         #[turbo_tasks(debug_ignore, trace_ignore)]
         imports: Vec<JsWord>,
         #[turbo_tasks(debug_ignore, trace_ignore)]
@@ -66,19 +71,30 @@ pub struct ParseResultSourceMap {
     /// SourceMap.
     #[turbo_tasks(debug_ignore, trace_ignore)]
     mappings: Vec<(BytePos, LineCol)>,
+
+    /// The source map of the parsed source code, which the generated source
+    /// map is composed with.
+    input_source_map: OptionSourceMapVc,
 }
 
 impl PartialEq for ParseResultSourceMap {
     fn eq(&self, other: &Self) -> bool {
-        Arc::ptr_eq(&self.source_map, &other.source_map) && self.mappings == other.mappings
+        Arc::ptr_eq(&self.source_map, &other.source_map)
+            && self.mappings == other.mappings
+            && self.input_source_map == other.input_source_map
     }
 }
 
 impl ParseResultSourceMap {
-    pub fn new(source_map: Arc<SourceMap>, mappings: Vec<(BytePos, LineCol)>) -> Self {
+    pub fn new(
+        source_map: Arc<SourceMap>,
+        mappings: Vec<(BytePos, LineCol)>,
+        input_source_map: OptionSourceMapVc,
+    ) -> Self {
         ParseResultSourceMap {
             source_map,
             mappings,
+            input_source_map,
         }
     }
 }
@@ -86,15 +102,21 @@ impl ParseResultSourceMap {
 #[turbo_tasks::value_impl]
 impl GenerateSourceMap for ParseResultSourceMap {
     #[turbo_tasks::function]
-    fn generate_source_map(&self) -> OptionSourceMapVc {
-        let map = self.source_map.build_source_map_with_config(
+    async fn generate_source_map(&self) -> Result<OptionSourceMapVc> {
+        // The swc source map isn't `Send`, so it must not be held across the
+        // await below
+        let map = TurbopackSourceMap::new_regular(self.source_map.build_source_map_with_config(
             &self.mappings,
             None,
             InlineSourcesContentConfig {},
-        );
-        OptionSourceMapVc::cell(Some(
-            turbopack_core::source_map::SourceMap::new_regular(map).cell(),
         ))
+        .cell();
+        Ok(OptionSourceMapVc::cell(Some(
+            match *self.input_source_map.await? {
+                Some(input) => map.with_input_map(input),
+                None => map,
+            },
+        )))
     }
 }
 
@@ -232,9 +254,15 @@ async fn parse_content(
         }
     };
 
+    let input_source_map = match InputSourceMapVc::resolve_from(source).await? {
+        Some(input) => input.input_source_map(),
+        None => OptionSourceMapVc::cell(None),
+    };
+
     Ok(ParseResult::Ok {
         stylesheet: parsed_stylesheet,
         source_map,
+        input_source_map,
         imports,
         exports,
     }
@@ -176,6 +176,7 @@ impl<'a> From<&'a PlainIssue> for Issue<'a> {
                     show_all: true,
                     log_detail: true,
                     log_level: IssueSeverity::Info,
+                    fail_on: IssueSeverity::Fatal,
                 },
             ),
         }
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use indexmap::IndexSet;
 use turbo_tasks::{
     graph::{GraphTraversal, ReverseTopological},
@@ -9,11 +9,13 @@ use turbo_tasks_fs::FileSystemPathVc;
 use turbopack_core::{
     asset::{Asset, AssetVc, AssetsVc},
     chunk::{
-        Chunk, ChunkVc, ChunkableAsset, ChunkingContext, ChunkingContextVc, ChunksVc,
-        EvaluatableAssetsVc,
+        optimize::{ChunkSizeConstraintsVc, ChunkSizeDecisionsIssue},
+        Chunk, ChunkGroupsVc, ChunkVc, ChunkableAsset, ChunkingContext, ChunkingContextVc,
+        ChunksVc, EvaluatableAssetsVc, EvaluatedEntriesVc,
     },
     environment::EnvironmentVc,
     ident::AssetIdentVc,
+    source_map::{SourceMapsType, SourceMapsTypeVc},
 };
 use turbopack_css::chunk::{CssChunkVc, CssChunksVc};
 use turbopack_ecmascript::chunk::{
@@ -27,7 +29,8 @@ use crate::{
         chunk::EcmascriptDevChunkVc,
         evaluate::chunk::EcmascriptDevEvaluateChunkVc,
         list::asset::{EcmascriptDevChunkListSource, EcmascriptDevChunkListVc},
-        optimize::optimize_ecmascript_chunks,
+        optimize::{apply_ecmascript_chunk_size_constraints, optimize_ecmascript_chunks},
+        shared::{get_shared_entries, shared_entry_chunks, without_shared_entries},
     },
 };
 
@@ -56,11 +59,21 @@ impl DevChunkingContextBuilder {
         self
     }
 
+    pub fn source_maps_type(mut self, source_maps_type: SourceMapsType) -> Self {
+        self.context.source_maps_type = source_maps_type;
+        self
+    }
+
     pub fn runtime_type(mut self, runtime_type: RuntimeType) -> Self {
         self.context.runtime_type = runtime_type;
         self
     }
 
+    pub fn chunk_size_constraints(mut self, constraints: ChunkSizeConstraintsVc) -> Self {
+        self.context.chunk_size_constraints = Some(constraints);
+        self
+    }
+
     pub fn build(self) -> ChunkingContextVc {
         DevChunkingContextVc::new(Value::new(self.context)).into()
     }
@@ -85,6 +98,8 @@ pub struct DevChunkingContext {
     reference_chunk_source_maps: bool,
     /// Css chunks reference source maps assets
     reference_css_chunk_source_maps: bool,
+    /// Whether source maps are separate files or inlined into the chunks
+    source_maps_type: SourceMapsType,
     /// Static assets are placed at this path
     asset_root_path: FileSystemPathVc,
     /// Layer name within this context
@@ -95,6 +110,8 @@ pub struct DevChunkingContext {
     environment: EnvironmentVc,
     /// The kind of runtime to include in the output.
     runtime_type: RuntimeType,
+    /// Size budgets applied to the ecmascript chunks of every chunk group
+    chunk_size_constraints: Option<ChunkSizeConstraintsVc>,
 }
 
 impl DevChunkingContextVc {
@@ -112,11 +129,13 @@ impl DevChunkingContextVc {
                 chunk_root_path,
                 reference_chunk_source_maps: true,
                 reference_css_chunk_source_maps: true,
+                source_maps_type: SourceMapsType::External,
                 asset_root_path,
                 layer: None,
                 enable_hot_module_replacement: false,
                 environment,
                 runtime_type: Default::default(),
+                chunk_size_constraints: None,
             },
         }
     }
@@ -203,6 +222,9 @@ impl ChunkingContext for DevChunkingContext {
 
     #[turbo_tasks::function]
     async fn reference_chunk_source_maps(&self, chunk: AssetVc) -> Result<BoolVc> {
+        if self.source_maps_type == SourceMapsType::Inline {
+            return Ok(BoolVc::cell(false));
+        }
         let mut source_maps = self.reference_chunk_source_maps;
         let path = chunk.ident().path().await?;
         let extension = path.extension().unwrap_or_default();
@@ -216,6 +238,11 @@ impl ChunkingContext for DevChunkingContext {
         Ok(BoolVc::cell(source_maps))
     }
 
+    #[turbo_tasks::function]
+    fn source_maps_type(&self) -> SourceMapsTypeVc {
+        self.source_maps_type.cell()
+    }
+
     #[turbo_tasks::function]
     async fn can_be_in_same_chunk(&self, asset_a: AssetVc, asset_b: AssetVc) -> Result<BoolVc> {
         let parent_dir = asset_a.ident().path().parent().await?;
@@ -273,7 +300,7 @@ impl ChunkingContext for DevChunkingContext {
     async fn chunk_group(self_vc: DevChunkingContextVc, entry_chunk: ChunkVc) -> Result<AssetsVc> {
         let parallel_chunks = get_parallel_chunks([entry_chunk]).await?;
 
-        let optimized_chunks = get_optimized_chunks(parallel_chunks).await?;
+        let optimized_chunks = get_optimized_chunks(&*self_vc.await?, parallel_chunks).await?;
 
         let mut assets: Vec<AssetVc> = optimized_chunks
             .await?
@@ -296,6 +323,83 @@ impl ChunkingContext for DevChunkingContext {
         entry_chunk: ChunkVc,
         evaluatable_assets: EvaluatableAssetsVc,
     ) -> Result<AssetsVc> {
+        let evaluatable_assets = evaluatable_assets.for_chunking_context(self_vc.into());
+        let chunks = self_vc
+            .get_evaluated_parallel_chunks(entry_chunk, evaluatable_assets)
+            .await?;
+
+        self_vc
+            .generate_evaluated_chunk_group(entry_chunk, evaluatable_assets, chunks, &[])
+            .await
+    }
+
+    #[turbo_tasks::function]
+    async fn evaluated_chunk_groups(
+        self_vc: DevChunkingContextVc,
+        entries: EvaluatedEntriesVc,
+        min_shared_entries: usize,
+    ) -> Result<ChunkGroupsVc> {
+        if min_shared_entries < 2 {
+            bail!(
+                "chunks can only be shared by at least 2 entries, got {}",
+                min_shared_entries
+            );
+        }
+
+        let mut entry_chunks = Vec::new();
+        for entry in entries.await?.iter() {
+            let evaluatable_assets = entry
+                .evaluatable_assets
+                .for_chunking_context(self_vc.into());
+            let chunks = self_vc
+                .get_evaluated_parallel_chunks(entry.entry_chunk, evaluatable_assets)
+                .await?;
+            entry_chunks.push((entry.entry_chunk, evaluatable_assets, chunks));
+        }
+
+        // The iterators are collected first, futures holding closures across
+        // awaits aren't accepted by `value_impl`
+        let chunk_groups: Vec<&[ChunkVc]> = entry_chunks
+            .iter()
+            .map(|(_, _, chunks)| chunks.as_slice())
+            .collect();
+        let shared_entries = get_shared_entries(chunk_groups, min_shared_entries).await?;
+        let shared_entry_chunks: Vec<_> = shared_entry_chunks(&shared_entries).collect();
+        let shared_chunks = get_parallel_chunks(shared_entry_chunks).await?;
+        let shared_assets: Vec<AssetVc> = get_optimized_chunks(&*self_vc.await?, shared_chunks)
+            .await?
+            .await?
+            .iter()
+            .map(|chunk| self_vc.generate_chunk(*chunk))
+            .collect();
+
+        let mut chunk_groups = Vec::new();
+        for (entry_chunk, evaluatable_assets, chunks) in entry_chunks {
+            let chunks = without_shared_entries(chunks, &shared_entries).await?;
+            chunk_groups.push(
+                self_vc
+                    .generate_evaluated_chunk_group(
+                        entry_chunk,
+                        evaluatable_assets,
+                        chunks,
+                        &shared_assets,
+                    )
+                    .await?,
+            );
+        }
+
+        Ok(ChunkGroupsVc::cell(chunk_groups))
+    }
+}
+
+impl DevChunkingContextVc {
+    /// Returns the chunks needed to evaluate the `evaluatable_assets` and the
+    /// `entry_chunk`, before optimization
+    async fn get_evaluated_parallel_chunks(
+        self,
+        entry_chunk: ChunkVc,
+        evaluatable_assets: EvaluatableAssetsVc,
+    ) -> Result<Vec<ChunkVc>> {
         let evaluatable_assets_ref = evaluatable_assets.await?;
 
         let mut entry_assets: IndexSet<_> = evaluatable_assets_ref
@@ -303,7 +407,7 @@ impl ChunkingContext for DevChunkingContext {
             .map({
                 move |evaluatable_asset| async move {
                     Ok(evaluatable_asset
-                        .as_root_chunk(self_vc.into())
+                        .as_root_chunk(self.into())
                         .resolve()
                         .await?)
                 }
@@ -315,25 +419,37 @@ impl ChunkingContext for DevChunkingContext {
 
         entry_assets.insert(entry_chunk.resolve().await?);
 
-        let parallel_chunks = get_parallel_chunks(entry_assets).await?;
+        Ok(get_parallel_chunks(entry_assets).await?.collect())
+    }
 
-        let optimized_chunks = get_optimized_chunks(parallel_chunks).await?;
+    /// Generates the output assets of an evaluated chunk group from its chunks.
+    /// The `shared_assets` are loaded along with the chunk group's own chunks.
+    async fn generate_evaluated_chunk_group(
+        self,
+        entry_chunk: ChunkVc,
+        evaluatable_assets: EvaluatableAssetsVc,
+        chunks: Vec<ChunkVc>,
+        shared_assets: &[AssetVc],
+    ) -> Result<AssetsVc> {
+        let optimized_chunks = get_optimized_chunks(&*self.await?, chunks).await?;
 
-        let mut assets: Vec<AssetVc> = optimized_chunks
-            .await?
-            .iter()
-            .map(|chunk| self_vc.generate_chunk(*chunk))
-            .collect();
+        let mut assets: Vec<AssetVc> = shared_assets.to_vec();
+        assets.extend(
+            optimized_chunks
+                .await?
+                .iter()
+                .map(|chunk| self.generate_chunk(*chunk)),
+        );
 
         let other_assets = AssetsVc::cell(assets.clone());
 
-        assets.push(self_vc.generate_chunk_list_register_chunk(
+        assets.push(self.generate_chunk_list_register_chunk(
             entry_chunk,
             other_assets,
             Value::new(EcmascriptDevChunkListSource::Entry),
         ));
 
-        assets.push(self_vc.generate_evaluate_chunk(entry_chunk, other_assets, evaluatable_assets));
+        assets.push(self.generate_evaluate_chunk(entry_chunk, other_assets, evaluatable_assets));
 
         Ok(AssetsVc::cell(assets))
     }
@@ -368,7 +484,7 @@ where
         .into_iter())
 }
 
-async fn get_optimized_chunks<I>(chunks: I) -> Result<ChunksVc>
+async fn get_optimized_chunks<I>(context: &DevChunkingContext, chunks: I) -> Result<ChunksVc>
 where
     I: IntoIterator<Item = ChunkVc>,
 {
@@ -386,8 +502,23 @@ where
         }
     }
 
-    let ecmascript_chunks =
-        optimize_ecmascript_chunks(EcmascriptChunksVc::cell(ecmascript_chunks)).await?;
+    let mut ecmascript_chunks =
+        optimize_ecmascript_chunks(EcmascriptChunksVc::cell(ecmascript_chunks));
+    if let Some(constraints) = context.chunk_size_constraints {
+        let constrained =
+            apply_ecmascript_chunk_size_constraints(ecmascript_chunks, constraints).await?;
+        if !constrained.decisions.await?.is_empty() {
+            ChunkSizeDecisionsIssue {
+                context: context.chunk_root_path,
+                decisions: constrained.decisions,
+            }
+            .cell()
+            .as_issue()
+            .emit();
+        }
+        ecmascript_chunks = constrained.chunks;
+    }
+    let ecmascript_chunks = ecmascript_chunks.await?;
     let css_chunks = optimize_css_chunks(CssChunksVc::cell(css_chunks)).await?;
 
     let chunks = ecmascript_chunks
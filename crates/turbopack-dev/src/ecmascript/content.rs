@@ -2,7 +2,7 @@ use std::io::Write;
 
 use anyhow::{bail, Result};
 use indoc::writedoc;
-use turbo_tasks_fs::File;
+use turbo_tasks_fs::{rope::RopeBuilder, File};
 use turbopack_core::{
     asset::{Asset, AssetContentVc},
     chunk::{ChunkingContext, ModuleId},
@@ -99,11 +99,6 @@ impl EcmascriptDevChunkContentVc {
 
         write!(code, "\n}}]);")?;
 
-        if code.has_source_map() {
-            let filename = chunk_path.file_name();
-            write!(code, "\n\n//# sourceMappingURL={}.map", filename)?;
-        }
-
         Ok(code.build().cell())
     }
 }
@@ -112,8 +107,24 @@ impl EcmascriptDevChunkContentVc {
 impl VersionedContent for EcmascriptDevChunkContent {
     #[turbo_tasks::function]
     async fn content(self_vc: EcmascriptDevChunkContentVc) -> Result<AssetContentVc> {
-        let code = self_vc.code().await?;
-        Ok(File::from(code.source_code().clone()).into())
+        let this = self_vc.await?;
+        let code_vc = self_vc.code();
+        let code = code_vc.await?;
+        let mut source_code = RopeBuilder::default();
+        source_code += code.source_code();
+
+        if code.has_source_map() {
+            let chunk_path = this.chunk.ident().path().await?;
+            let url = code_vc
+                .source_mapping_url(
+                    chunk_path.file_name(),
+                    this.chunking_context.source_maps_type(),
+                )
+                .await?;
+            write!(source_code, "\n\n//# sourceMappingURL={}", url)?;
+        }
+
+        Ok(File::from(source_code.build()).into())
     }
 
     #[turbo_tasks::function]
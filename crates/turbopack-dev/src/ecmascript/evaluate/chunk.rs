@@ -4,7 +4,7 @@ use anyhow::{bail, Result};
 use indoc::writedoc;
 use serde::Serialize;
 use turbo_tasks::{primitives::StringVc, TryJoinIterExt, Value, ValueToString, ValueToStringVc};
-use turbo_tasks_fs::File;
+use turbo_tasks_fs::{rope::RopeBuilder, File};
 use turbopack_core::{
     asset::{Asset, AssetContentVc, AssetVc, AssetsVc},
     chunk::{
@@ -149,11 +149,6 @@ impl EcmascriptDevEvaluateChunkVc {
             }
         }
 
-        if code.has_source_map() {
-            let filename = chunk_path.file_name();
-            write!(code, "\n\n//# sourceMappingURL={}.map", filename)?;
-        }
-
         Ok(CodeVc::cell(code.build()))
     }
 }
@@ -218,8 +213,24 @@ impl Asset for EcmascriptDevEvaluateChunk {
 
     #[turbo_tasks::function]
     async fn content(self_vc: EcmascriptDevEvaluateChunkVc) -> Result<AssetContentVc> {
-        let code = self_vc.code().await?;
-        Ok(File::from(code.source_code().clone()).into())
+        let this = self_vc.await?;
+        let code_vc = self_vc.code();
+        let code = code_vc.await?;
+        let mut source_code = RopeBuilder::default();
+        source_code += code.source_code();
+
+        if code.has_source_map() {
+            let chunk_path = self_vc.ident().path().await?;
+            let url = code_vc
+                .source_mapping_url(
+                    chunk_path.file_name(),
+                    this.chunking_context.source_maps_type(),
+                )
+                .await?;
+            write!(source_code, "\n\n//# sourceMappingURL={}", url)?;
+        }
+
+        Ok(File::from(source_code.build()).into())
     }
 }
 
@@ -6,7 +6,16 @@ use anyhow::Result;
 use indexmap::{IndexMap, IndexSet};
 use turbo_tasks::{TryJoinIterExt, Value};
 use turbo_tasks_fs::FileSystemPathOptionVc;
-use turbopack_core::chunk::optimize::optimize_by_common_parent;
+use turbopack_core::{
+    chunk::{
+        optimize::{
+            optimize_by_common_parent, ChunkSizeConstraintsVc, ChunkSizeDecision,
+            ChunkSizeDecisionsVc,
+        },
+        Chunk,
+    },
+    ident::AssetIdentVc,
+};
 use turbopack_ecmascript::chunk::{
     EcmascriptChunkPlaceablesVc, EcmascriptChunkVc, EcmascriptChunkingContextVc, EcmascriptChunksVc,
 };
@@ -445,3 +454,212 @@ async fn optimize_ecmascript(
 
     Ok(EcmascriptChunksVc::cell(chunks))
 }
+
+/// The chunks of a chunk group after applying the chunk size constraints, with
+/// the decisions that were made
+#[turbo_tasks::value]
+pub struct ConstrainedEcmascriptChunks {
+    pub chunks: EcmascriptChunksVc,
+    pub decisions: ChunkSizeDecisionsVc,
+}
+
+/// The total size of the chunk items' code in `chunk`
+async fn chunk_size(chunk: EcmascriptChunkVc) -> Result<u64> {
+    Ok(chunk
+        .as_chunk()
+        .chunk_item_sizes()
+        .await?
+        .iter()
+        .map(|item| item.size)
+        .sum())
+}
+
+async fn chunk_name(chunk: EcmascriptChunkVc) -> Result<String> {
+    Ok(chunk.as_chunk().path().await?.path.clone())
+}
+
+/// Splits chunks above the maximum size by their entries and merges chunks
+/// below the minimum size.
+///
+/// Chunks are only merged with chunks of the same chunking context and
+/// availability, and splitting only distributes the entries of a chunk, so
+/// no chunk item ever crosses an async chunk group boundary.
+#[turbo_tasks::function]
+pub async fn apply_ecmascript_chunk_size_constraints(
+    chunks: EcmascriptChunksVc,
+    constraints: ChunkSizeConstraintsVc,
+) -> Result<ConstrainedEcmascriptChunksVc> {
+    let constraints = *constraints.await?;
+    let mut decisions = Vec::new();
+
+    let mut sized_chunks = Vec::new();
+    for &chunk in chunks.await?.iter() {
+        let size = chunk_size(chunk).await?;
+        match constraints.max_size {
+            Some(max_size) if size > max_size => {
+                let (parts, duplicated) = split_chunk(chunk, max_size).await?;
+                if parts.len() > 1 {
+                    decisions.push(ChunkSizeDecision::Split {
+                        chunk: chunk_name(chunk).await?,
+                        parts: parts.iter().map(|&(_, size)| size).collect(),
+                        duplicated,
+                    });
+                    // Parts are not merged again, that would undo the split
+                    sized_chunks.extend(parts.into_iter().map(|(part, size)| (part, size, false)));
+                } else {
+                    decisions.push(ChunkSizeDecision::Oversized {
+                        chunk: chunk_name(chunk).await?,
+                        size,
+                    });
+                    sized_chunks.push((chunk, size, false));
+                }
+            }
+            _ => sized_chunks.push((chunk, size, true)),
+        }
+    }
+
+    let Some(min_size) = constraints.min_size else {
+        return Ok(ConstrainedEcmascriptChunks {
+            chunks: EcmascriptChunksVc::cell(
+                sized_chunks.into_iter().map(|(chunk, ..)| chunk).collect(),
+            ),
+            decisions: ChunkSizeDecisionsVc::cell(decisions),
+        }
+        .cell());
+    };
+
+    // Small chunks that can be merged, by chunking context and availability. The
+    // merged chunk takes the place of the first small chunk to keep the order.
+    let mut result = Vec::new();
+    let mut small_chunks: IndexMap<_, (usize, Vec<EcmascriptChunkVc>, u64)> = IndexMap::new();
+    for (chunk, size, mergeable) in sized_chunks {
+        if !mergeable || size >= min_size {
+            result.push(Some(chunk));
+            continue;
+        }
+        let content = chunk.await?;
+        let key = (content.context.resolve().await?, content.availability_info);
+        let (_, group, group_size) = small_chunks.entry(key).or_insert_with(|| {
+            result.push(None);
+            (result.len() - 1, Vec::new(), 0)
+        });
+        let exceeds_max =
+            matches!(constraints.max_size, Some(max_size) if *group_size + size > max_size);
+        if !group.is_empty() && (exceeds_max || *group_size >= min_size) {
+            // The group is complete, start a new one
+            let (position, group, group_size) = small_chunks.remove(&key).unwrap();
+            result[position] = Some(merge_group(group, group_size, &mut decisions).await?);
+            result.push(None);
+            small_chunks.insert(key, (result.len() - 1, vec![chunk], size));
+        } else {
+            group.push(chunk);
+            *group_size += size;
+        }
+    }
+    for (position, group, group_size) in small_chunks.into_values() {
+        result[position] = Some(merge_group(group, group_size, &mut decisions).await?);
+    }
+
+    Ok(ConstrainedEcmascriptChunks {
+        chunks: EcmascriptChunksVc::cell(result.into_iter().flatten().collect()),
+        decisions: ChunkSizeDecisionsVc::cell(decisions),
+    }
+    .cell())
+}
+
+/// Merges a group of small chunks into one chunk, recording the decision
+async fn merge_group(
+    group: Vec<EcmascriptChunkVc>,
+    size: u64,
+    decisions: &mut Vec<ChunkSizeDecision>,
+) -> Result<EcmascriptChunkVc> {
+    if let [chunk] = group[..] {
+        return Ok(chunk);
+    }
+    decisions.push(ChunkSizeDecision::Merged {
+        chunks: group
+            .iter()
+            .map(|&chunk| chunk_name(chunk))
+            .try_join()
+            .await?,
+        size,
+    });
+    merge_chunks(group[0], &group).await
+}
+
+/// Splits a chunk into parts with subsets of its entries that stay below
+/// `max_size` where possible. Returns the parts with their sizes, or the
+/// chunk itself when it has a single entry, along with the size of the chunk
+/// items that are shared between entries and duplicated across parts.
+async fn split_chunk(
+    chunk: EcmascriptChunkVc,
+    max_size: u64,
+) -> Result<(Vec<(EcmascriptChunkVc, u64)>, u64)> {
+    let content = chunk.await?;
+    let entries = content.main_entries.await?;
+    if entries.len() <= 1 {
+        return Ok((vec![(chunk, chunk_size(chunk).await?)], 0));
+    }
+
+    let new_chunk = |entries: Vec<_>| {
+        EcmascriptChunkVc::new_normalized(
+            content.context,
+            EcmascriptChunkPlaceablesVc::cell(entries),
+            content.omit_entries,
+            Value::new(content.availability_info),
+        )
+    };
+    // The chunk items of each entry are only computed once, parts are sized by
+    // the union of the chunk items of their entries
+    let entry_items = entries
+        .iter()
+        .map(|&entry| chunk_item_sizes_by_ident(new_chunk(vec![entry])))
+        .try_join()
+        .await?;
+
+    let mut parts = Vec::new();
+    let mut current = Vec::new();
+    let mut current_items = HashSet::new();
+    let mut current_size = 0;
+    for (&entry, items) in entries.iter().zip(entry_items.iter()) {
+        let added_size: u64 = items
+            .iter()
+            .filter(|(ident, _)| !current_items.contains(*ident))
+            .map(|(_, size)| size)
+            .sum();
+        if current_size + added_size > max_size && !current.is_empty() {
+            // The entry doesn't fit anymore, it starts the next part
+            parts.push((new_chunk(std::mem::take(&mut current)), current_size));
+            current_items.clear();
+            current_size = items.values().sum();
+        } else {
+            current_size += added_size;
+        }
+        current.push(entry);
+        current_items.extend(items.keys().copied());
+    }
+    parts.push((new_chunk(current), current_size));
+
+    let mut all_items = HashSet::new();
+    let mut total_size = 0;
+    for items in entry_items.iter() {
+        for (&ident, &size) in items.iter() {
+            if all_items.insert(ident) {
+                total_size += size;
+            }
+        }
+    }
+    let parts_size: u64 = parts.iter().map(|&(_, size)| size).sum();
+    Ok((parts, parts_size - total_size))
+}
+
+/// The sizes of the chunk items of `chunk` by their ident
+async fn chunk_item_sizes_by_ident(
+    chunk: EcmascriptChunkVc,
+) -> Result<IndexMap<AssetIdentVc, u64>> {
+    let mut sizes = IndexMap::new();
+    for item in chunk.as_chunk().chunk_item_sizes().await?.iter() {
+        sizes.insert(item.ident.resolve().await?, item.size);
+    }
+    Ok(sizes)
+}
@@ -0,0 +1,97 @@
+//! Extraction of Ecmascript chunk entries shared by multiple chunk groups.
+
+use anyhow::Result;
+use indexmap::{IndexMap, IndexSet};
+use turbo_tasks::Value;
+use turbopack_core::chunk::{availability_info::AvailabilityInfo, ChunkVc, ChunkableAsset};
+use turbopack_ecmascript::chunk::{
+    EcmascriptChunkPlaceableVc, EcmascriptChunkPlaceablesVc, EcmascriptChunkVc,
+    EcmascriptChunkingContextVc,
+};
+
+/// The main entry of an Ecmascript chunk with the chunking context of the
+/// chunk
+pub(crate) type SharedEntry = (EcmascriptChunkingContextVc, EcmascriptChunkPlaceableVc);
+
+/// Returns the main entries of the Ecmascript chunks that appear in at least
+/// `min_shared_groups` of the chunk groups.
+///
+/// The chunks of a chunk group are split where the chunking context doesn't
+/// put assets into the same chunk, e.g. at node_modules, so those are the
+/// assets that can be shared. Assets that are placed into the chunks of their
+/// importers are never shared.
+pub(crate) async fn get_shared_entries<'a>(
+    chunk_groups: impl IntoIterator<Item = &'a [ChunkVc]>,
+    min_shared_groups: usize,
+) -> Result<IndexSet<SharedEntry>> {
+    let mut counts: IndexMap<SharedEntry, usize> = IndexMap::new();
+    for chunks in chunk_groups {
+        let mut group_entries = IndexSet::new();
+        for &chunk in chunks {
+            let Some(chunk) = EcmascriptChunkVc::resolve_from(chunk).await? else {
+                continue;
+            };
+            let chunk = chunk.await?;
+            let context = chunk.context.resolve().await?;
+            for &entry in chunk.main_entries.await?.iter() {
+                group_entries.insert((context, entry.resolve().await?));
+            }
+        }
+        for entry in group_entries {
+            *counts.entry(entry).or_default() += 1;
+        }
+    }
+    Ok(counts
+        .into_iter()
+        .filter(|&(_, count)| count >= min_shared_groups)
+        .map(|(entry, _)| entry)
+        .collect())
+}
+
+/// Creates the chunks of the shared entries. They don't belong to a single
+/// chunk group, so their availability isn't tracked.
+pub(crate) fn shared_entry_chunks(
+    shared_entries: &IndexSet<SharedEntry>,
+) -> impl Iterator<Item = ChunkVc> + '_ {
+    shared_entries.iter().map(|&(context, entry)| {
+        entry.as_chunk(context.into(), Value::new(AvailabilityInfo::Untracked))
+    })
+}
+
+/// Removes the shared entries from the main entries of the chunks. Chunks
+/// that only contain shared entries are removed entirely.
+pub(crate) async fn without_shared_entries(
+    chunks: Vec<ChunkVc>,
+    shared_entries: &IndexSet<SharedEntry>,
+) -> Result<Vec<ChunkVc>> {
+    let mut result = Vec::new();
+    for chunk in chunks {
+        let Some(ecmascript_chunk) = EcmascriptChunkVc::resolve_from(chunk).await? else {
+            result.push(chunk);
+            continue;
+        };
+        let content = ecmascript_chunk.await?;
+        let context = content.context.resolve().await?;
+        let main_entries = content.main_entries.await?;
+        let mut remaining_entries = Vec::new();
+        for &entry in main_entries.iter() {
+            if !shared_entries.contains(&(context, entry.resolve().await?)) {
+                remaining_entries.push(entry);
+            }
+        }
+        if remaining_entries.len() == main_entries.len() {
+            result.push(chunk);
+        } else if !remaining_entries.is_empty() {
+            result.push(
+                EcmascriptChunkVc::new_normalized(
+                    content.context,
+                    EcmascriptChunkPlaceablesVc::cell(remaining_entries),
+                    content.omit_entries,
+                    Value::new(content.availability_info),
+                )
+                .into(),
+            );
+        }
+    }
+    Ok(result)
+}
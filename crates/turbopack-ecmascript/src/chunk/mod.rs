@@ -16,8 +16,9 @@ use turbo_tasks_fs::FileSystemPathOptionVc;
 use turbopack_core::{
     asset::{Asset, AssetContentVc, AssetVc},
     chunk::{
-        availability_info::AvailabilityInfo, Chunk, ChunkGroupReferenceVc, ChunkItem, ChunkVc,
-        ChunkingContextVc, ChunksVc, ModuleIdsVc,
+        availability_info::AvailabilityInfo,
+        graph::{ChunkItemSize, ChunkItemSizesVc},
+        Chunk, ChunkGroupReferenceVc, ChunkItem, ChunkVc, ChunkingContextVc, ChunksVc, ModuleIdsVc,
     },
     ident::{AssetIdent, AssetIdentVc},
     introspect::{
@@ -240,6 +241,29 @@ impl Chunk for EcmascriptChunk {
         }
         Ok(ChunksVc::cell(chunks))
     }
+
+    #[turbo_tasks::function]
+    async fn chunk_item_sizes(&self) -> Result<ChunkItemSizesVc> {
+        let content = ecmascript_chunk_content(
+            self.context,
+            self.main_entries,
+            self.omit_entries,
+            Value::new(self.availability_info),
+        )
+        .await?;
+        let sizes = content
+            .chunk_items
+            .iter()
+            .map(|&chunk_item| async move {
+                Ok(ChunkItemSize {
+                    ident: chunk_item.asset_ident(),
+                    size: chunk_item.content().await?.inner_code.len() as u64,
+                })
+            })
+            .try_join()
+            .await?;
+        Ok(ChunkItemSizesVc::cell(sizes))
+    }
 }
 
 #[turbo_tasks::value_impl]
@@ -599,6 +599,7 @@ async fn gen_content_with_visitors(
     if let ParseResult::Ok {
         program,
         source_map,
+        input_source_map,
         globals,
         eval_context,
         ..
@@ -641,7 +642,8 @@ async fn gen_content_with_visitors(
 
         emitter.emit_program(&program)?;
 
-        let srcmap = ParseResultSourceMap::new(source_map.clone(), srcmap).cell();
+        let srcmap =
+            ParseResultSourceMap::new(source_map.clone(), srcmap, *input_source_map).cell();
 
         Ok(EcmascriptModuleContent {
             inner_code: bytes.into(),
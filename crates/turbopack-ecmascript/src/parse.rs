@@ -1,6 +1,6 @@
 use std::{future::Future, sync::Arc};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use swc_core::{
     base::SwcComments,
     common::{
@@ -20,6 +20,7 @@ use swc_core::{
     },
 };
 use turbo_tasks::{
+    is_cancelled,
     primitives::{StringVc, U64Vc},
     util::WrapFuture,
     Value, ValueToString,
@@ -30,7 +31,10 @@ use turbopack_core::{
     asset::{Asset, AssetContent, AssetVc},
     error::PrettyPrintError,
     issue::{Issue, IssueSeverity, IssueSeverityVc, IssueVc},
-    source_map::{GenerateSourceMap, GenerateSourceMapVc, OptionSourceMapVc},
+    source_map::{
+        GenerateSourceMap, GenerateSourceMapVc, InputSourceMap, InputSourceMapVc,
+        OptionSourceMapVc, SourceMap as TurbopackSourceMap,
+    },
     SOURCE_MAP_ROOT_NAME,
 };
 use turbopack_swc_utils::emitter::IssueEmitter;
@@ -56,6 +60,8 @@ pub enum ParseResult {
         globals: Arc<Globals>,
         #[turbo_tasks(debug_ignore, trace_ignore)]
         source_map: Arc<swc_core::common::SourceMap>,
+        /// See [turbopack_core::source_map::InputSourceMap]
+        input_source_map: OptionSourceMapVc,
     },
     Unparseable,
     NotFound,
@@ -82,11 +88,17 @@ pub struct ParseResultSourceMap {
     /// SourceMap.
     #[turbo_tasks(debug_ignore, trace_ignore)]
     mappings: Vec<(BytePos, LineCol)>,
+
+    /// The source map of the parsed source code, which the generated source
+    /// map is composed with.
+    input_source_map: OptionSourceMapVc,
 }
 
 impl PartialEq for ParseResultSourceMap {
     fn eq(&self, other: &Self) -> bool {
-        Arc::ptr_eq(&self.source_map, &other.source_map) && self.mappings == other.mappings
+        Arc::ptr_eq(&self.source_map, &other.source_map)
+            && self.mappings == other.mappings
+            && self.input_source_map == other.input_source_map
     }
 }
 
@@ -94,10 +106,12 @@ impl ParseResultSourceMap {
     pub fn new(
         source_map: Arc<swc_core::common::SourceMap>,
         mappings: Vec<(BytePos, LineCol)>,
+        input_source_map: OptionSourceMapVc,
     ) -> Self {
         ParseResultSourceMap {
             source_map,
             mappings,
+            input_source_map,
         }
     }
 }
@@ -105,15 +119,21 @@ impl ParseResultSourceMap {
 #[turbo_tasks::value_impl]
 impl GenerateSourceMap for ParseResultSourceMap {
     #[turbo_tasks::function]
-    fn generate_source_map(&self) -> OptionSourceMapVc {
-        let map = self.source_map.build_source_map_with_config(
+    async fn generate_source_map(&self) -> Result<OptionSourceMapVc> {
+        // The swc source map isn't `Send`, so it must not be held across the
+        // await below
+        let map = TurbopackSourceMap::new_regular(self.source_map.build_source_map_with_config(
             &self.mappings,
             None,
             InlineSourcesContentConfig {},
-        );
-        OptionSourceMapVc::cell(Some(
-            turbopack_core::source_map::SourceMap::new_regular(map).cell(),
         ))
+        .cell();
+        Ok(OptionSourceMapVc::cell(Some(
+            match *self.input_source_map.await? {
+                Some(input) => map.with_input_map(input),
+                None => map,
+            },
+        )))
     }
 }
 
@@ -182,6 +202,10 @@ async fn parse_internal(
             FileContent::Content(file) => match file.content().to_str() {
                 Ok(string) => {
                     let transforms = &*transforms.await?;
+                    if is_cancelled() {
+                        // An input changed while waiting, the result would be discarded anyway
+                        bail!("parsing has been cancelled");
+                    }
                     match parse_content(
                         string.into_owned(),
                         fs_path_vc,
@@ -229,6 +253,10 @@ async fn parse_content(
     ty: EcmascriptModuleAssetType,
     transforms: &[EcmascriptInputTransform],
 ) -> Result<ParseResultVc> {
+    let input_source_map = match InputSourceMapVc::resolve_from(source).await? {
+        Some(input) => input.input_source_map(),
+        None => OptionSourceMapVc::cell(None),
+    };
     let source_map: Arc<swc_core::common::SourceMap> = Default::default();
     let handler = Handler::with_emitter(
         true,
@@ -358,6 +386,7 @@ async fn parse_content(
                 // borrowed
                 globals: Arc::new(Globals::new()),
                 source_map,
+                input_source_map,
             })
         },
     )
@@ -313,6 +313,7 @@ pub(super) async fn split(path: FileSystemPathVc, parsed: ParseResultVc) -> Resu
             comments,
             eval_context,
             source_map,
+            input_source_map,
             globals,
             ..
         } => {
@@ -337,6 +338,7 @@ pub(super) async fn split(path: FileSystemPathVc, parsed: ParseResultVc) -> Resu
                         globals: globals.clone(),
                         comments: comments.clone(),
                         source_map: source_map.clone(),
+                        input_source_map: *input_source_map,
                         eval_context,
                     })
                 })
@@ -156,14 +156,11 @@ pub async fn get_evaluate_pool(
             bail!("Internal module is not evaluatable");
         };
 
-        let mut entries = vec![globals_module];
-        if let Some(runtime_entries) = runtime_entries {
-            for &entry in &*runtime_entries.await? {
-                entries.push(entry)
-            }
+        let entries = EvaluatableAssetsVc::one(globals_module);
+        match runtime_entries {
+            Some(runtime_entries) => entries.concat(runtime_entries),
+            None => entries,
         }
-
-        EvaluatableAssetsVc::cell(entries)
     };
 
     let bootstrap = NodeJsBootstrapAsset {
@@ -10,6 +10,7 @@ use turbopack_core::{
     ident::AssetIdentVc,
     reference_type::{InnerAssetsVc, ReferenceType},
     source_asset::SourceAssetVc,
+    source_map::{InputSourceMap, InputSourceMapVc, OptionSourceMapVc, SourceMapVc},
     source_transform::{SourceTransform, SourceTransformVc},
     virtual_asset::VirtualAssetVc,
 };
@@ -107,9 +108,21 @@ impl Asset for WebpackLoadersProcessedAsset {
     }
 }
 
+#[turbo_tasks::value_impl]
+impl InputSourceMap for WebpackLoadersProcessedAsset {
+    /// The source map returned by the loaders
+    #[turbo_tasks::function]
+    async fn input_source_map(
+        self_vc: WebpackLoadersProcessedAssetVc,
+    ) -> Result<OptionSourceMapVc> {
+        Ok(self_vc.process().await?.source_map)
+    }
+}
+
 #[turbo_tasks::value]
 struct ProcessWebpackLoadersResult {
     content: AssetContentVc,
+    source_map: OptionSourceMapVc,
     assets: Vec<VirtualAssetVc>,
 }
 
@@ -142,6 +155,7 @@ impl WebpackLoadersProcessedAssetVc {
         let FileContent::Content(content) = &*file.await? else {
             return Ok(ProcessWebpackLoadersResult {
                 content: AssetContent::File(FileContent::NotFound.cell()).cell(),
+                source_map: OptionSourceMapVc::cell(None),
                 assets: Vec::new()
             }.cell());
         };
@@ -174,6 +188,7 @@ impl WebpackLoadersProcessedAssetVc {
             // An error happened, which has already been converted into an issue.
             return Ok(ProcessWebpackLoadersResult {
                 content: AssetContent::File(FileContent::NotFound.cell()).cell(),
+                source_map: OptionSourceMapVc::cell(None),
                 assets: Vec::new()
             }.cell());
         };
@@ -182,10 +197,18 @@ impl WebpackLoadersProcessedAssetVc {
         )
         .context("Unable to deserializate response from webpack loaders transform operation")?;
 
-        // TODO handle SourceMap
         let file = File::from(processed.source);
+        let source_map = match processed.map {
+            Some(map) => SourceMapVc::from_file(FileContent::Content(File::from(map)).cell()),
+            None => OptionSourceMapVc::cell(None),
+        };
         let assets = emitted_assets_to_virtual_assets(processed.assets);
         let content = AssetContent::File(FileContent::Content(file).cell()).cell();
-        Ok(ProcessWebpackLoadersResult { content, assets }.cell())
+        Ok(ProcessWebpackLoadersResult {
+            content,
+            source_map,
+            assets,
+        }
+        .cell())
     }
 }
@@ -27,13 +27,17 @@ use turbo_tasks::{
     primitives::{BoolVc, StringVc},
     CompletionVc, Value,
 };
-use turbo_tasks_fs::FileSystemPathVc;
+use turbo_tasks_fs::{FileContent, FileSystemPathVc};
 use turbopack_core::{
-    asset::{Asset, AssetVc},
+    asset::{Asset, AssetContent, AssetVc},
+    chunk::ChunkVc,
     compile_time_info::CompileTimeInfoVc,
     context::{AssetContext, AssetContextVc},
+    diagnostics::{DiagnosticEvent, DiagnosticsVc, OptionDiagnosticsVc},
+    environment::EnvironmentVc,
     ident::AssetIdentVc,
     issue::{Issue, IssueVc},
+    output_manifest::{OutputEntriesVc, OutputManifestAssetVc},
     plugin::CustomModuleType,
     reference::all_referenced_assets,
     reference_type::{EcmaScriptModulesReferenceSubType, InnerAssetsVc, ReferenceType},
@@ -409,10 +413,13 @@ impl AssetContext for ModuleAssetContext {
         } else {
             self_vc
         };
+        let context = context.await?;
         // TODO move `apply_commonjs/esm_resolve_options` etc. to here
         Ok(resolve_options(
             origin_path.parent().resolve().await?,
-            context.await?.resolve_options_context,
+            context
+                .resolve_options_context
+                .for_environment(context.compile_time_info.environment()),
         ))
     }
 
@@ -494,53 +501,154 @@ impl AssetContext for ModuleAssetContext {
             },
         )
     }
+
+    #[turbo_tasks::function]
+    async fn with_environment(&self, environment: EnvironmentVc) -> Result<AssetContextVc> {
+        let mut module_options_context = self.module_options_context.await?.clone_value();
+        if module_options_context.preset_env_versions.is_some() {
+            module_options_context.preset_env_versions = Some(environment);
+        }
+        Ok(ModuleAssetContext {
+            transitions: self.transitions,
+            compile_time_info: self.compile_time_info.with_environment(environment),
+            module_options_context: module_options_context.cell(),
+            resolve_options_context: self.resolve_options_context.with_environment(environment),
+            transition: self.transition,
+        }
+        .cell()
+        .into())
+    }
 }
 
 #[turbo_tasks::function]
 pub async fn emit_with_completion(asset: AssetVc, output_dir: FileSystemPathVc) -> CompletionVc {
-    emit_assets_aggregated(asset, output_dir)
+    emit_assets_aggregated(asset, output_dir, OptionDiagnosticsVc::none())
+}
+
+/// Like [emit_with_completion], but also reports every emitted asset and chunk
+/// to `diagnostics`.
+#[turbo_tasks::function]
+pub async fn emit_with_diagnostics(
+    asset: AssetVc,
+    output_dir: FileSystemPathVc,
+    diagnostics: DiagnosticsVc,
+) -> CompletionVc {
+    emit_assets_aggregated(
+        asset,
+        output_dir,
+        OptionDiagnosticsVc::cell(Some(diagnostics)),
+    )
 }
 
 #[turbo_tasks::function]
-async fn emit_assets_aggregated(asset: AssetVc, output_dir: FileSystemPathVc) -> CompletionVc {
+async fn emit_assets_aggregated(
+    asset: AssetVc,
+    output_dir: FileSystemPathVc,
+    diagnostics: OptionDiagnosticsVc,
+) -> CompletionVc {
     let aggregated = aggregate(asset);
-    emit_aggregated_assets(aggregated, output_dir)
+    emit_aggregated_assets(aggregated, output_dir, diagnostics)
 }
 
 #[turbo_tasks::function]
 async fn emit_aggregated_assets(
     aggregated: AggregatedGraphVc,
     output_dir: FileSystemPathVc,
+    diagnostics: OptionDiagnosticsVc,
 ) -> Result<CompletionVc> {
     Ok(match &*aggregated.content().await? {
-        AggregatedGraphNodeContent::Asset(asset) => emit_asset_into_dir(*asset, output_dir),
+        AggregatedGraphNodeContent::Asset(asset) => {
+            emit_asset_into_dir_with_diagnostics(*asset, output_dir, diagnostics)
+        }
         AggregatedGraphNodeContent::Children(children) => {
             for aggregated in children {
-                emit_aggregated_assets(*aggregated, output_dir).await?;
+                emit_aggregated_assets(*aggregated, output_dir, diagnostics).await?;
             }
             CompletionVc::new()
         }
     })
 }
 
+/// Emits the chunk groups of the `entries` into `output_dir`, followed by a
+/// manifest of the emitted files at `manifest_path`. The manifest is written
+/// last, so it never refers to files that haven't been written yet.
+#[turbo_tasks::function]
+pub async fn emit_with_manifest(
+    entries: OutputEntriesVc,
+    output_dir: FileSystemPathVc,
+    manifest_path: FileSystemPathVc,
+) -> Result<CompletionVc> {
+    let mut emits = Vec::new();
+    for entry in entries.await?.iter() {
+        for asset in entry.chunk_group.await?.iter() {
+            emits.push(emit_with_completion(*asset, output_dir));
+        }
+    }
+    for emit in emits {
+        emit.await?;
+    }
+    Ok(emit_asset(
+        OutputManifestAssetVc::new(manifest_path, output_dir, entries).into(),
+    ))
+}
+
 #[turbo_tasks::function]
 pub async fn emit_asset(asset: AssetVc) -> CompletionVc {
     asset.content().write(asset.ident().path())
 }
 
 #[turbo_tasks::function]
-pub async fn emit_asset_into_dir(
+pub async fn emit_asset_into_dir(asset: AssetVc, output_dir: FileSystemPathVc) -> CompletionVc {
+    emit_asset_into_dir_with_diagnostics(asset, output_dir, OptionDiagnosticsVc::none())
+}
+
+#[turbo_tasks::function]
+async fn emit_asset_into_dir_with_diagnostics(
     asset: AssetVc,
     output_dir: FileSystemPathVc,
+    diagnostics: OptionDiagnosticsVc,
 ) -> Result<CompletionVc> {
     let dir = &*output_dir.await?;
     Ok(if asset.ident().path().await?.is_inside(dir) {
-        emit_asset(asset)
+        emit_asset_with_diagnostics(asset, diagnostics)
     } else {
         CompletionVc::new()
     })
 }
 
+#[turbo_tasks::function]
+async fn emit_asset_with_diagnostics(
+    asset: AssetVc,
+    diagnostics: OptionDiagnosticsVc,
+) -> Result<CompletionVc> {
+    let completion = emit_asset(asset);
+    let Some(diagnostics) = *diagnostics.await? else {
+        return Ok(completion);
+    };
+    completion.await?;
+
+    let path = asset.ident().path().await?.path.clone();
+    let size = match &*asset.content().await? {
+        AssetContent::File(content) => match &*content.await? {
+            FileContent::Content(file) => Some(file.content().len()),
+            FileContent::NotFound => None,
+        },
+        AssetContent::Redirect { .. } => None,
+    };
+    diagnostics
+        .report(DiagnosticEvent::AssetEmitted {
+            path: path.clone(),
+            size,
+        })
+        .await?;
+    if ChunkVc::resolve_from(asset).await?.is_some() {
+        diagnostics
+            .report(DiagnosticEvent::ChunkBuilt { path })
+            .await?;
+    }
+    Ok(completion)
+}
+
 #[turbo_tasks::value(shared)]
 struct ReferencesList {
     referenced_by: HashMap<AssetVc, HashSet<AssetVc>>,
@@ -1,7 +1,8 @@
 use anyhow::Result;
+use turbo_tasks::Value;
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack_core::{
-    environment::EnvironmentVc,
+    environment::{EnvironmentCondition, EnvironmentVc},
     resolve::{
         options::{ImportMapVc, ResolvedMapVc},
         plugin::ResolvePluginVc,
@@ -61,6 +62,12 @@ pub struct ResolveOptionsContext {
     /// context paths. The first matching is used.
     pub rules: Vec<(ContextCondition, ResolveOptionsContextVc)>,
     #[serde(default)]
+    /// A list of rules to use a different resolve option context when
+    /// resolving for certain environments, e.g. to alias a module to its edge
+    /// variant. The first matching is used. See
+    /// [ResolveOptionsContextVc::for_environment].
+    pub environment_rules: Vec<(EnvironmentCondition, ResolveOptionsContextVc)>,
+    #[serde(default)]
     /// A list of plugins which get applied before (in the future) and after
     /// resolving.
     pub plugins: Vec<ResolvePluginVc>,
@@ -83,6 +90,39 @@ impl ResolveOptionsContextVc {
         Ok(Self::cell(clone))
     }
 
+    /// Returns a new [ResolveOptionsContextVc] that resolves for `environment`
+    /// instead, i.e. with the "browser" field and export condition only enabled
+    /// for browsers. Only contexts that emulate an environment follow it, the
+    /// others, including those of rules, keep their settings.
+    #[turbo_tasks::function]
+    pub async fn with_environment(self, environment: EnvironmentVc) -> Result<Self> {
+        let mut resolve_options_context = self.await?.clone_value();
+        if resolve_options_context.emulate_environment.is_some() {
+            resolve_options_context.emulate_environment = Some(environment);
+            resolve_options_context.browser = *environment
+                .matches(Value::new(EnvironmentCondition::Browser))
+                .await?;
+        }
+        // Each rule's context decides for itself whether it follows
+        for (_, context) in resolve_options_context.rules.iter_mut() {
+            *context = context.with_environment(environment);
+        }
+        Ok(resolve_options_context.into())
+    }
+
+    /// Returns the resolve options context of the first environment rule
+    /// matching `environment`, or this context when none matches.
+    #[turbo_tasks::function]
+    pub async fn for_environment(self, environment: EnvironmentVc) -> Result<Self> {
+        let this = self.await?;
+        for (condition, context) in this.environment_rules.iter() {
+            if *environment.matches(Value::new(*condition)).await? {
+                return Ok(*context);
+            }
+        }
+        Ok(self)
+    }
+
     /// Returns a new [ResolveOptionsContextVc] with its import map extended to
     /// include the given import map.
     #[turbo_tasks::function]
@@ -270,19 +270,19 @@ fn node_file_trace_memory(#[case] input: CaseInput) {
 
 #[cfg(feature = "test_persistent_cache")]
 #[apply(test_cases)]
-fn node_file_trace_rocksdb(#[case] input: CaseInput) {
+fn node_file_trace_persistent_cache(#[case] input: CaseInput) {
+    use turbo_tasks_file_store::FileStorePersistedGraph;
     use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
-    use turbo_tasks_rocksdb::RocksDbPersistedGraph;
 
     node_file_trace(
         input,
-        "rockdb",
+        "persistent_cache",
         false,
         2,
         240,
         |directory_path| {
             TurboTasks::new(MemoryBackendWithPersistedGraph::new(
-                RocksDbPersistedGraph::new(directory_path.join(".db")).unwrap(),
+                FileStorePersistedGraph::new(directory_path.join(".cache")).unwrap(),
             ))
         },
         |_| {},
@@ -3,16 +3,22 @@ use std::backtrace::Backtrace;
 use reqwest::header::ToStrError;
 use thiserror::Error;
 
-use crate::CachingStatus;
+use crate::{retry::should_retry_request, CachingStatus};
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
     #[error("Error making HTTP request: {0}")]
     ReqwestError(#[from] reqwest::Error),
     #[error("skipping HTTP Request, too many failures have occurred.\nLast error: {0}")]
     TooManyFailures(#[from] Box<reqwest::Error>),
     #[error("Error parsing header: {0}")]
     InvalidHeader(#[from] ToStrError),
+    #[error("invalid custom header: {0}")]
+    InvalidCustomHeader(String),
+    #[error("TLS options were set, but turbo was built without a TLS backend")]
+    TlsUnavailable,
     #[error("Error parsing URL: {0}")]
     InvalidUrl(#[from] url::ParseError),
     #[error("unknown caching status: {0}")]
@@ -31,4 +37,22 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Whether retrying the request that failed with this error might succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::ReqwestError(err) => {
+                err.is_timeout() || err.is_connect() || err.is_body() || should_retry_request(err)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the request failed because the server couldn't be reached at
+    /// all, as opposed to the server returning an error
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, Error::ReqwestError(err) if err.is_connect() || err.is_timeout())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -2,9 +2,16 @@
 #![feature(provide_any)]
 #![feature(error_generic_member_access)]
 
-use std::env;
-
-use reqwest::RequestBuilder;
+use std::{
+    env,
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+};
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    RequestBuilder, StatusCode,
+};
 use serde::{Deserialize, Serialize};
 
 pub use crate::error::{Error, Result};
@@ -111,6 +118,102 @@ pub struct UserResponse {
     pub user: User,
 }
 
+/// An artifact downloaded from the remote cache
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub body: Vec<u8>,
+    /// Time it took to produce the artifact, in milliseconds
+    pub duration: Option<u64>,
+    /// Signature tag attached to the artifact when it was uploaded
+    pub tag: Option<String>,
+    /// SHA-256 digest of the artifact recorded when it was uploaded
+    pub digest: Option<String>,
+}
+
+/// Metadata of an artifact downloaded to a file with
+/// `APIClient::download_artifact`
+#[derive(Debug, Clone)]
+pub struct ArtifactDownload {
+    /// Time it took to produce the artifact, in milliseconds
+    pub duration: Option<u64>,
+    /// Signature tag attached to the artifact when it was uploaded
+    pub tag: Option<String>,
+    /// SHA-256 digest of the artifact recorded when it was uploaded
+    pub digest: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteArtifactsResponse {
+    /// Number of artifacts that were deleted
+    pub deleted: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SignedUrlMethod {
+    Get,
+    Put,
+}
+
+/// A request for a pre-signed artifact URL. Uploads describe the artifact up
+/// front, since object storage doesn't keep turbo's artifact headers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedUrlRequest {
+    pub method: SignedUrlMethod,
+    /// Time it took to produce the artifact, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    /// Signature tag of the artifact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// SHA-256 digest of the artifact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl SignedUrlRequest {
+    pub fn get() -> Self {
+        SignedUrlRequest {
+            method: SignedUrlMethod::Get,
+            duration: None,
+            tag: None,
+            digest: None,
+        }
+    }
+}
+
+/// A pre-signed URL for uploading or downloading an artifact directly from
+/// the remote cache's object storage. For downloads, the remote cache also
+/// returns the metadata recorded when the artifact was uploaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedArtifactUrl {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+/// Settings for talking to a remote cache server other than the Vercel API,
+/// e.g. a self-hosted implementation behind a proxy or with a private CA.
+#[derive(Debug, Clone, Default)]
+pub struct APIClientOptions {
+    /// Request timeout in seconds, 0 disables the timeout
+    pub timeout: u64,
+    /// Timeout for establishing a connection in seconds, 0 disables the
+    /// timeout. Lets an unreachable server fail fast even when `timeout`
+    /// allows for slow transfers of large artifacts.
+    pub connect_timeout: u64,
+    /// Headers sent with every request
+    pub custom_headers: Vec<(String, String)>,
+    /// PEM encoded certificate to trust in addition to the system roots
+    pub root_certificate: Option<Vec<u8>>,
+    /// Disables TLS certificate validation. Only use this for testing.
+    pub accept_invalid_certs: bool,
+}
+
 pub struct APIClient {
     client: reqwest::Client,
     base_url: String,
@@ -240,15 +343,368 @@ impl APIClient {
         })
     }
 
-    pub fn new(base_url: impl AsRef<str>, timeout: u64, version: &str) -> Result<Self> {
-        let client = if timeout != 0 {
-            reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(timeout))
-                .build()?
-        } else {
-            reqwest::Client::builder().build()?
+    #[allow(clippy::too_many_arguments)]
+    pub async fn put_artifact(
+        &self,
+        hash: &str,
+        artifact_body: &[u8],
+        duration: u64,
+        tag: Option<&str>,
+        digest: Option<&str>,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<()> {
+        let mut request_builder = self
+            .client
+            .put(self.make_url(&format!("/v8/artifacts/{}", hash)))
+            .header("User-Agent", self.user_agent.clone())
+            .header("Content-Type", "application/octet-stream")
+            .header("Authorization", format!("Bearer {}", token))
+            .header("x-artifact-duration", duration.to_string())
+            .body(artifact_body.to_vec());
+
+        if let Some(tag) = tag {
+            request_builder = request_builder.header("x-artifact-tag", tag);
+        }
+        if let Some(digest) = digest {
+            request_builder = request_builder.header("x-artifact-digest", digest);
+        }
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        retry::make_retryable_request(request_builder)
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Deletes the artifact for `hash`, returning `false` if the remote cache
+    /// didn't have it
+    pub async fn delete_artifact(
+        &self,
+        hash: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<bool> {
+        let request_builder = self
+            .client
+            .delete(self.make_url(&format!("/v8/artifacts/{}", hash)))
+            .header("User-Agent", self.user_agent.clone())
+            .header("Authorization", format!("Bearer {}", token));
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        let response = retry::make_retryable_request(request_builder).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+
+        Ok(true)
+    }
+
+    /// Deletes every artifact whose hash starts with `prefix`, returning how
+    /// many were deleted
+    pub async fn delete_artifacts_with_prefix(
+        &self,
+        prefix: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<u64> {
+        let request_builder = self
+            .client
+            .delete(self.make_url("/v8/artifacts"))
+            .query(&[("prefix", prefix)])
+            .header("User-Agent", self.user_agent.clone())
+            .header("Authorization", format!("Bearer {}", token));
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        let response: DeleteArtifactsResponse = retry::make_retryable_request(request_builder)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response.deleted)
+    }
+
+    pub async fn artifact_exists(
+        &self,
+        hash: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<bool> {
+        let request_builder = self
+            .client
+            .head(self.make_url(&format!("/v8/artifacts/{}", hash)))
+            .header("User-Agent", self.user_agent.clone())
+            .header("Authorization", format!("Bearer {}", token));
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        let response = retry::make_retryable_request(request_builder).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+
+        Ok(true)
+    }
+
+    /// Downloads the artifact for `hash`, returning `None` if the remote cache
+    /// doesn't have it.
+    pub async fn fetch_artifact(
+        &self,
+        hash: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<Option<Artifact>> {
+        let request_builder = self
+            .client
+            .get(self.make_url(&format!("/v8/artifacts/{}", hash)))
+            .header("User-Agent", self.user_agent.clone())
+            .header("Authorization", format!("Bearer {}", token));
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        let response = retry::make_retryable_request(request_builder).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+
+        let metadata = Self::artifact_metadata(response.headers())?;
+        let body = response.bytes().await?.to_vec();
+
+        Ok(Some(Artifact {
+            body,
+            duration: metadata.duration,
+            tag: metadata.tag,
+            digest: metadata.digest,
+        }))
+    }
+
+    /// Downloads the artifact for `hash` into `file`. If `file` already holds
+    /// the start of the artifact from an interrupted download, only the
+    /// remaining bytes are requested. Returns `None` if the remote cache
+    /// doesn't have the artifact.
+    ///
+    /// If the download fails partway through, the bytes received so far are
+    /// left in `file` so that the next call can pick up where this one
+    /// stopped.
+    pub async fn download_artifact(
+        &self,
+        hash: &str,
+        file: &mut File,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<Option<ArtifactDownload>> {
+        let headers = self
+            .download_to_file(file, || {
+                let request_builder = self
+                    .client
+                    .get(self.make_url(&format!("/v8/artifacts/{}", hash)))
+                    .header("User-Agent", self.user_agent.clone())
+                    .header("Authorization", format!("Bearer {}", token));
+                Self::add_team_params(request_builder, team_id, team_slug)
+            })
+            .await?;
+        let Some(headers) = headers else {
+            return Ok(None);
         };
 
+        Ok(Some(Self::artifact_metadata(&headers)?))
+    }
+
+    /// Asks the remote cache for a pre-signed URL to upload or download the
+    /// artifact for `hash` directly from its object storage. Returns `None`
+    /// if the remote cache doesn't support signed URLs, in which case the
+    /// artifact has to go through the regular endpoints.
+    pub async fn signed_artifact_url(
+        &self,
+        hash: &str,
+        request: &SignedUrlRequest,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<Option<SignedArtifactUrl>> {
+        let request_builder = self
+            .client
+            .post(self.make_url(&format!("/v8/artifacts/{}/signed-url", hash)))
+            .header("User-Agent", self.user_agent.clone())
+            .header("Authorization", format!("Bearer {}", token))
+            .json(request);
+
+        let request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        let response = retry::make_retryable_request(request_builder).await?;
+        if matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Uploads an artifact to a URL from `signed_artifact_url`. The URL
+    /// carries its own authorization, so no credentials are sent.
+    pub async fn put_signed_artifact(&self, url: &str, artifact_body: &[u8]) -> Result<()> {
+        let request_builder = self
+            .client
+            .put(url)
+            .header("Content-Type", "application/octet-stream")
+            .body(artifact_body.to_vec());
+
+        retry::make_retryable_request(request_builder)
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Downloads an artifact from a URL from `signed_artifact_url`, returning
+    /// `None` if the artifact doesn't exist.
+    pub async fn fetch_signed_artifact(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let response = retry::make_retryable_request(self.client.get(url)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+
+        Ok(Some(response.bytes().await?.to_vec()))
+    }
+
+    /// Like `download_artifact`, but downloads from a URL from
+    /// `signed_artifact_url`. Returns `false` if the artifact doesn't exist.
+    pub async fn download_signed_artifact(&self, url: &str, file: &mut File) -> Result<bool> {
+        let headers = self.download_to_file(file, || self.client.get(url)).await?;
+        Ok(headers.is_some())
+    }
+
+    // Downloads the response to `make_request` into `file`, resuming from the
+    // bytes already in `file` where the server supports range requests.
+    // Returns the headers of the response, or `None` on a 404.
+    async fn download_to_file(
+        &self,
+        file: &mut File,
+        make_request: impl Fn() -> RequestBuilder,
+    ) -> Result<Option<HeaderMap>> {
+        let mut response = loop {
+            let offset = file.seek(SeekFrom::End(0))?;
+            let mut request_builder = make_request();
+            if offset > 0 {
+                request_builder = request_builder.header("Range", format!("bytes={}-", offset));
+            }
+
+            let response = retry::make_retryable_request(request_builder).await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => return Ok(None),
+                StatusCode::PARTIAL_CONTENT => break response,
+                // What we have on disk is longer than the artifact, so it
+                // can't be a prefix of it. Start over.
+                StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                    file.set_len(0)?;
+                }
+                _ => {
+                    // The server ignored the range and is sending the whole
+                    // artifact.
+                    let response = response.error_for_status()?;
+                    file.set_len(0)?;
+                    file.seek(SeekFrom::Start(0))?;
+                    break response;
+                }
+            }
+        };
+
+        let headers = response.headers().clone();
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+        }
+        file.flush()?;
+
+        Ok(Some(headers))
+    }
+
+    fn artifact_metadata(headers: &HeaderMap) -> Result<ArtifactDownload> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .map(|value| value.to_str().map(|value| value.to_string()))
+                .transpose()
+        };
+        let duration = header("x-artifact-duration")?.and_then(|duration| duration.parse().ok());
+
+        Ok(ArtifactDownload {
+            duration,
+            tag: header("x-artifact-tag")?,
+            digest: header("x-artifact-digest")?,
+        })
+    }
+
+    pub fn new(base_url: impl AsRef<str>, timeout: u64, version: &str) -> Result<Self> {
+        Self::with_options(
+            base_url,
+            version,
+            APIClientOptions {
+                timeout,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn with_options(
+        base_url: impl AsRef<str>,
+        version: &str,
+        options: APIClientOptions,
+    ) -> Result<Self> {
+        let mut client_builder = reqwest::Client::builder();
+        if options.timeout != 0 {
+            client_builder =
+                client_builder.timeout(std::time::Duration::from_secs(options.timeout));
+        }
+        if options.connect_timeout != 0 {
+            client_builder = client_builder
+                .connect_timeout(std::time::Duration::from_secs(options.connect_timeout));
+        }
+
+        if !options.custom_headers.is_empty() {
+            let mut headers = HeaderMap::new();
+            for (name, value) in &options.custom_headers {
+                let header_name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| Error::InvalidCustomHeader(name.clone()))?;
+                let header_value = HeaderValue::from_str(value)
+                    .map_err(|_| Error::InvalidCustomHeader(name.clone()))?;
+                headers.insert(header_name, header_value);
+            }
+            client_builder = client_builder.default_headers(headers);
+        }
+
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        {
+            if let Some(root_certificate) = &options.root_certificate {
+                client_builder = client_builder
+                    .add_root_certificate(reqwest::Certificate::from_pem(root_certificate)?);
+            }
+            client_builder =
+                client_builder.danger_accept_invalid_certs(options.accept_invalid_certs);
+        }
+        #[cfg(not(any(feature = "native-tls", feature = "rustls-tls")))]
+        if options.root_certificate.is_some() || options.accept_invalid_certs {
+            return Err(Error::TlsUnavailable);
+        }
+
+        let client = client_builder.build()?;
+
         let user_agent = format!(
             "turbo {} {} {} {}",
             version,
@@ -267,3 +723,34 @@ impl APIClient {
         format!("{}{}", self.base_url, endpoint)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{APIClient, APIClientOptions, Error};
+
+    #[test]
+    fn test_with_options_tls() {
+        let tls_options = [
+            APIClientOptions {
+                accept_invalid_certs: true,
+                ..Default::default()
+            },
+            APIClientOptions {
+                root_certificate: Some(b"not a certificate".to_vec()),
+                ..Default::default()
+            },
+        ];
+        for options in tls_options {
+            let result = APIClient::with_options("https://example.com", "1.0.0", options);
+            if cfg!(any(feature = "native-tls", feature = "rustls-tls")) {
+                assert!(!matches!(result, Err(Error::TlsUnavailable)));
+            } else {
+                assert!(matches!(result, Err(Error::TlsUnavailable)));
+            }
+        }
+
+        let result =
+            APIClient::with_options("https://example.com", "1.0.0", APIClientOptions::default());
+        assert!(result.is_ok());
+    }
+}
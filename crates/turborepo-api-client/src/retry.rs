@@ -1,4 +1,7 @@
-use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
 use tokio::time::sleep;
 
 use crate::Error;
@@ -11,6 +14,10 @@ const RETRY_MAX: u32 = 2;
 /// function returns false, or the future succeeds. Uses an exponential backoff
 /// with a base of 2 to delay between retries.
 ///
+/// Responses with a 429 status are retried too, after the delay the server
+/// asks for in `Retry-After` if there is one. Those delays are jittered so
+/// that many clients throttled at the same time don't retry in lockstep.
+///
 /// # Arguments
 ///
 /// * `request_builder`: The request builder with everything, i.e. headers and
@@ -24,6 +31,14 @@ pub(crate) async fn make_retryable_request(
     for retry_count in 0..RETRY_MAX {
         let builder = request_builder.try_clone().expect("cannot clone request");
         match builder.send().await {
+            Ok(response)
+                if response.status() == StatusCode::TOO_MANY_REQUESTS
+                    && retry_count + 1 < RETRY_MAX =>
+            {
+                let delay = retry_after(&response).unwrap_or_else(|| backoff(retry_count));
+                sleep(with_jitter(delay)).await;
+                continue;
+            }
             Ok(value) => return Ok(value),
             Err(err) => {
                 if !should_retry_request(&err) {
@@ -33,16 +48,38 @@ pub(crate) async fn make_retryable_request(
             }
         }
 
-        let sleep_period = (2_u64)
-            .pow(retry_count)
-            .clamp(MIN_SLEEP_TIME_SECS, MAX_SLEEP_TIME_SECS);
-        sleep(std::time::Duration::from_secs(sleep_period)).await;
+        sleep(backoff(retry_count)).await;
     }
 
     Err(Error::TooManyFailures(Box::new(last_error.unwrap())))
 }
 
-fn should_retry_request(error: &reqwest::Error) -> bool {
+fn backoff(retry_count: u32) -> Duration {
+    let sleep_period = (2_u64)
+        .pow(retry_count)
+        .clamp(MIN_SLEEP_TIME_SECS, MAX_SLEEP_TIME_SECS);
+    Duration::from_secs(sleep_period)
+}
+
+// Only the delay-seconds form of `Retry-After` is supported. Delays longer
+// than we'd ever back off for are capped.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds.min(MAX_SLEEP_TIME_SECS)))
+}
+
+// Adds a random delay of up to half of `delay`
+fn with_jitter(delay: Duration) -> Duration {
+    delay + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+}
+
+pub(crate) fn should_retry_request(error: &reqwest::Error) -> bool {
     if let Some(status) = error.status() {
         if status == StatusCode::TOO_MANY_REQUESTS {
             return true;
@@ -0,0 +1,247 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    sync::Mutex,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use turbopath::AbsoluteSystemPathBuf;
+
+use crate::{fs_cache::now_millis, CacheError, CacheSource};
+
+const EVENTS_FILE: &str = "cache-events.jsonl";
+const ROTATED_EVENTS_FILE: &str = "cache-events.1.jsonl";
+
+/// The outcome of a single artifact lookup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEvent {
+    pub hash: String,
+    /// Where the artifact was found, or `None` on a miss
+    pub source: Option<CacheSource>,
+    /// Size of the artifact that was read, in bytes
+    pub bytes: u64,
+    /// Time the lookup took, in milliseconds
+    pub latency: u64,
+    /// Milliseconds since the unix epoch at which the lookup finished
+    pub timestamp: u64,
+}
+
+impl CacheEvent {
+    pub fn new(hash: &str, source: Option<CacheSource>, bytes: u64, latency: Duration) -> Self {
+        CacheEvent {
+            hash: hash.to_string(),
+            source,
+            bytes,
+            latency: latency.as_millis() as u64,
+            timestamp: now_millis(),
+        }
+    }
+
+    pub fn is_hit(&self) -> bool {
+        self.source.is_some()
+    }
+}
+
+/// Totals over a set of cache events
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub local_hits: u64,
+    pub remote_hits: u64,
+    pub misses: u64,
+    pub local_bytes: u64,
+    pub remote_bytes: u64,
+    /// Total time spent on lookups, in milliseconds
+    pub total_latency: u64,
+}
+
+impl CacheStats {
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a CacheEvent>) -> Self {
+        let mut stats = CacheStats::default();
+        for event in events {
+            match event.source {
+                Some(CacheSource::Local) => {
+                    stats.local_hits += 1;
+                    stats.local_bytes += event.bytes;
+                }
+                Some(CacheSource::Remote) => {
+                    stats.remote_hits += 1;
+                    stats.remote_bytes += event.bytes;
+                }
+                None => stats.misses += 1,
+            }
+            stats.total_latency += event.latency;
+        }
+        stats
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.local_hits + self.remote_hits
+    }
+
+    pub fn lookups(&self) -> u64 {
+        self.hits() + self.misses
+    }
+
+    /// Fraction of lookups that were hits, or `None` if there were no lookups
+    pub fn hit_rate(&self) -> Option<f64> {
+        match self.lookups() {
+            0 => None,
+            lookups => Some(self.hits() as f64 / lookups as f64),
+        }
+    }
+}
+
+/// Records cache events to a rolling log in a local directory.
+///
+/// Events are appended as JSON lines. Once the log reaches `max_size` bytes it
+/// replaces the previous generation, so at most two generations are kept on
+/// disk and aggregations cover roughly the last `2 * max_size` bytes of
+/// events.
+#[derive(Debug)]
+pub struct CacheAnalytics {
+    events_path: AbsoluteSystemPathBuf,
+    rotated_events_path: AbsoluteSystemPathBuf,
+    max_size: u64,
+    // Serializes appends with rotation
+    lock: Mutex<()>,
+}
+
+impl CacheAnalytics {
+    pub fn new(directory: AbsoluteSystemPathBuf, max_size: u64) -> Result<Self, CacheError> {
+        directory.create_dir_all()?;
+        Ok(CacheAnalytics {
+            events_path: directory.join_component(EVENTS_FILE),
+            rotated_events_path: directory.join_component(ROTATED_EVENTS_FILE),
+            max_size,
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn record(&self, event: &CacheEvent) -> Result<(), CacheError> {
+        let _guard = self.lock.lock().unwrap();
+
+        let size = match fs::metadata(self.events_path.as_path()) {
+            Ok(metadata) => metadata.len(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+        if size >= self.max_size {
+            fs::rename(
+                self.events_path.as_path(),
+                self.rotated_events_path.as_path(),
+            )?;
+        }
+
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.events_path.as_path())?
+            .write_all(&line)?;
+        Ok(())
+    }
+
+    /// Returns the recorded events, oldest first
+    pub fn events(&self) -> Result<Vec<CacheEvent>, CacheError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut events = Vec::new();
+        for path in [&self.rotated_events_path, &self.events_path] {
+            let file = match fs::File::open(path.as_path()) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            for line in BufReader::new(file).lines() {
+                // A line can be cut short if turbo was killed mid-write, which
+                // shouldn't make the rest of the log unreadable.
+                if let Ok(event) = serde_json::from_str(&line?) {
+                    events.push(event);
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, CacheError> {
+        Ok(CacheStats::from_events(&self.events()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn event(hash: &str, source: Option<CacheSource>, bytes: u64) -> CacheEvent {
+        CacheEvent::new(hash, source, bytes, Duration::from_millis(10))
+    }
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let dir = tempdir()?;
+        let analytics = CacheAnalytics::new(AbsoluteSystemPathBuf::new(dir.path())?, 1024)?;
+        assert_eq!(analytics.stats()?.hit_rate(), None);
+
+        analytics.record(&event("one", Some(CacheSource::Local), 100))?;
+        analytics.record(&event("two", Some(CacheSource::Remote), 200))?;
+        analytics.record(&event("three", None, 0))?;
+        analytics.record(&event("four", Some(CacheSource::Local), 50))?;
+
+        let stats = analytics.stats()?;
+        assert_eq!(
+            stats,
+            CacheStats {
+                local_hits: 2,
+                remote_hits: 1,
+                misses: 1,
+                local_bytes: 150,
+                remote_bytes: 200,
+                total_latency: 40,
+            }
+        );
+        assert_eq!(stats.hit_rate(), Some(0.75));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_rotation() -> Result<()> {
+        let dir = tempdir()?;
+        let analytics = CacheAnalytics::new(AbsoluteSystemPathBuf::new(dir.path())?, 1)?;
+
+        // Every event fills the log, so only the last two survive
+        for hash in ["one", "two", "three"] {
+            analytics.record(&event(hash, None, 0))?;
+        }
+        let hashes: Vec<_> = analytics
+            .events()?
+            .into_iter()
+            .map(|event| event.hash)
+            .collect();
+        assert_eq!(hashes, ["two", "three"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skips_truncated_events() -> Result<()> {
+        let dir = tempdir()?;
+        let analytics = CacheAnalytics::new(AbsoluteSystemPathBuf::new(dir.path())?, 1024)?;
+
+        analytics.record(&event("one", Some(CacheSource::Local), 100))?;
+        OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(EVENTS_FILE))?
+            .write_all(br#"{"hash":"two","sou"#)?;
+
+        assert_eq!(analytics.stats()?.lookups(), 1);
+
+        Ok(())
+    }
+}
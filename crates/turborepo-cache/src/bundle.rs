@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use tar::{Archive, Builder, Header};
+use turbopath::AbsoluteSystemPath;
+
+use crate::{
+    artifact_digest,
+    fs_cache::{CacheMetadata, FSCache},
+    CacheError,
+};
+
+const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_PATH: &str = "manifest.json";
+const ARTIFACTS_DIRECTORY: &str = "artifacts/";
+
+/// Describes the artifacts in a bundle. It is the first entry of the bundle,
+/// so that importing can validate artifacts as they are read.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleManifest {
+    version: u32,
+    artifacts: Vec<CacheMetadata>,
+}
+
+/// Writes the artifacts for `hashes` from `cache` into a single portable
+/// bundle file, e.g. to seed the cache of an air-gapped CI agent. Hashes that
+/// aren't in the cache are skipped. Returns the hashes that were exported.
+///
+/// The bundle is a tar archive holding a manifest followed by the artifacts.
+/// Artifacts are already compressed, so the archive itself isn't.
+pub fn export_bundle(
+    cache: &FSCache,
+    hashes: &[impl AsRef<str>],
+    bundle_path: impl AsRef<AbsoluteSystemPath>,
+) -> Result<Vec<String>, CacheError> {
+    let mut artifacts = Vec::new();
+    let mut bodies = Vec::new();
+    for hash in hashes {
+        let Some((body, metadata)) = cache.fetch(hash.as_ref())? else {
+            continue;
+        };
+        artifacts.push(CacheMetadata {
+            digest: Some(artifact_digest(&body)),
+            ..metadata
+        });
+        bodies.push(body);
+    }
+
+    let mut builder = Builder::new(BufWriter::new(File::create(
+        bundle_path.as_ref().as_path(),
+    )?));
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        artifacts,
+    };
+    append_file(&mut builder, MANIFEST_PATH, &serde_json::to_vec(&manifest)?)?;
+    for (metadata, body) in manifest.artifacts.iter().zip(&bodies) {
+        append_file(
+            &mut builder,
+            &format!("{}{}", ARTIFACTS_DIRECTORY, metadata.hash),
+            body,
+        )?;
+    }
+    builder.into_inner()?.flush()?;
+
+    Ok(manifest
+        .artifacts
+        .into_iter()
+        .map(|metadata| metadata.hash)
+        .collect())
+}
+
+/// Adds the artifacts in the bundle at `bundle_path` to `cache`, replacing
+/// any existing artifacts with the same hashes. Every artifact is checked
+/// against the digest in the manifest before it is stored. Returns the hashes
+/// that were imported.
+pub fn import_bundle(
+    cache: &FSCache,
+    bundle_path: impl AsRef<AbsoluteSystemPath>,
+) -> Result<Vec<String>, CacheError> {
+    let mut archive = Archive::new(BufReader::new(File::open(bundle_path.as_ref().as_path())?));
+    let mut entries = archive.entries()?;
+
+    let manifest: BundleManifest = match entries.next() {
+        Some(entry) => {
+            let entry = entry?;
+            if entry.path()?.to_str() != Some(MANIFEST_PATH) {
+                return Err(CacheError::InvalidBundle(
+                    "bundle doesn't start with a manifest".to_string(),
+                ));
+            }
+            serde_json::from_reader(entry)?
+        }
+        None => return Err(CacheError::InvalidBundle("bundle is empty".to_string())),
+    };
+    if manifest.version != BUNDLE_VERSION {
+        return Err(CacheError::InvalidBundle(format!(
+            "unsupported bundle version {}",
+            manifest.version
+        )));
+    }
+    let mut artifacts: HashMap<String, CacheMetadata> = manifest
+        .artifacts
+        .into_iter()
+        .map(|metadata| (metadata.hash.clone(), metadata))
+        .collect();
+
+    let mut imported = Vec::new();
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry.path()?;
+        let Some(hash) = path
+            .to_str()
+            .and_then(|path| path.strip_prefix(ARTIFACTS_DIRECTORY))
+            .map(|hash| hash.to_string())
+        else {
+            continue;
+        };
+        let Some(metadata) = artifacts.remove(&hash) else {
+            return Err(CacheError::InvalidBundle(format!(
+                "artifact {} is missing from the manifest",
+                hash
+            )));
+        };
+
+        let mut body = Vec::new();
+        entry.read_to_end(&mut body)?;
+        if metadata.digest.as_deref() != Some(artifact_digest(&body).as_str()) {
+            return Err(CacheError::DigestMismatch(hash));
+        }
+        cache.put(&hash, &body, &metadata.artifact)?;
+        imported.push(hash);
+    }
+
+    Ok(imported)
+}
+
+fn append_file(
+    builder: &mut Builder<impl Write>,
+    path: &str,
+    contents: &[u8],
+) -> Result<(), CacheError> {
+    let mut header = Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::*;
+    use crate::ArtifactMetadata;
+
+    #[test]
+    fn test_export_and_import() -> Result<()> {
+        let dir = tempdir()?;
+        let root = AbsoluteSystemPathBuf::new(dir.path())?;
+        let source = FSCache::new(root.join_component("source"), None)?;
+        let metadata = ArtifactMetadata::new(12, "1.10.0", None);
+        source.put("one", b"first artifact", &metadata)?;
+        source.put("two", b"second artifact", &metadata)?;
+        source.put("three", b"not exported", &metadata)?;
+
+        let bundle_path = root.join_component("cache.bundle");
+        let exported = export_bundle(&source, &["one", "two", "missing"], &bundle_path)?;
+        assert_eq!(exported, ["one", "two"]);
+
+        let destination = FSCache::new(root.join_component("destination"), None)?;
+        let imported = import_bundle(&destination, &bundle_path)?;
+        assert_eq!(imported, ["one", "two"]);
+
+        let (body, imported_metadata) = destination.fetch("two")?.unwrap();
+        assert_eq!(body, b"second artifact");
+        assert_eq!(imported_metadata.artifact, metadata);
+        assert!(!destination.exists("three")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_corrupt_bundles() -> Result<()> {
+        let dir = tempdir()?;
+        let root = AbsoluteSystemPathBuf::new(dir.path())?;
+        let cache = FSCache::new(root.join_component("cache"), None)?;
+
+        // An artifact that doesn't match the digest in the manifest
+        let bundle_path = root.join_component("corrupt.bundle");
+        let mut builder = Builder::new(File::create(bundle_path.as_path())?);
+        let manifest = BundleManifest {
+            version: BUNDLE_VERSION,
+            artifacts: vec![CacheMetadata {
+                hash: "one".to_string(),
+                artifact: ArtifactMetadata::default(),
+                size: 3,
+                last_accessed: 0,
+                digest: Some(artifact_digest(b"one")),
+            }],
+        };
+        append_file(&mut builder, MANIFEST_PATH, &serde_json::to_vec(&manifest)?)?;
+        append_file(&mut builder, "artifacts/one", b"eno")?;
+        builder.finish()?;
+        drop(builder);
+
+        assert!(matches!(
+            import_bundle(&cache, &bundle_path),
+            Err(CacheError::DigestMismatch(_))
+        ));
+        assert!(!cache.exists("one")?);
+
+        Ok(())
+    }
+}
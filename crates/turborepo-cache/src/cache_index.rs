@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use turbopath::AbsoluteSystemPathBuf;
+
+use crate::{
+    fs_cache::{now_millis, CacheMetadata},
+    CacheError,
+};
+
+/// Logs with fewer records than this are never compacted
+const MIN_COMPACTION_RECORDS: usize = 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum IndexRecord {
+    /// First record of every log. Compaction replaces the log with one that
+    /// has a new generation, which tells other processes to reload it.
+    Open {
+        generation: u64,
+    },
+    Put(CacheMetadata),
+    #[serde(rename_all = "camelCase")]
+    Access {
+        hash: String,
+        last_accessed: u64,
+    },
+    Remove {
+        hash: String,
+    },
+}
+
+/// The metadata of every artifact in the local cache, kept in memory and
+/// persisted as an append-only log of JSON records.
+///
+/// Several processes can share a cache directory, so every mutation is an
+/// append to the log followed by reading whatever was appended since this
+/// process last looked, including records written by other processes. Reads
+/// do the same, which only costs reading the first record when nothing has
+/// changed.
+#[derive(Debug)]
+pub(crate) struct CacheIndex {
+    path: AbsoluteSystemPathBuf,
+    state: Mutex<IndexState>,
+}
+
+#[derive(Debug, Default)]
+struct IndexState {
+    entries: HashMap<String, CacheMetadata>,
+    generation: u64,
+    // How far into the log we've read. Only complete lines are consumed, so a
+    // record that is still being appended is picked up on the next read.
+    offset: u64,
+    records: usize,
+}
+
+impl CacheIndex {
+    /// Opens the index at `path`, creating it if it doesn't exist and
+    /// compacting it if it is mostly made up of superseded records.
+    pub fn open(path: AbsoluteSystemPathBuf) -> Result<Self, CacheError> {
+        let index = CacheIndex {
+            path,
+            state: Mutex::new(IndexState::default()),
+        };
+
+        let mut state = index.state.lock().unwrap();
+        index.refresh(&mut state)?;
+        // Records another process appends to the old log while it is being
+        // replaced are lost. That only costs us cache hits, since their
+        // artifacts become invisible.
+        if state.offset == 0
+            || (state.records > MIN_COMPACTION_RECORDS && state.records > 2 * state.entries.len())
+        {
+            index.rewrite(&mut state)?;
+        }
+        drop(state);
+
+        Ok(index)
+    }
+
+    pub fn get(&self, hash: &str) -> Result<Option<CacheMetadata>, CacheError> {
+        let mut state = self.state.lock().unwrap();
+        self.refresh(&mut state)?;
+        Ok(state.entries.get(hash).cloned())
+    }
+
+    pub fn entries(&self) -> Result<Vec<CacheMetadata>, CacheError> {
+        let mut state = self.state.lock().unwrap();
+        self.refresh(&mut state)?;
+        Ok(state.entries.values().cloned().collect())
+    }
+
+    pub fn insert(&self, metadata: CacheMetadata) -> Result<(), CacheError> {
+        self.append(&IndexRecord::Put(metadata))
+    }
+
+    /// Records that `hash` was just read
+    pub fn touch(&self, hash: &str) -> Result<(), CacheError> {
+        self.append(&IndexRecord::Access {
+            hash: hash.to_string(),
+            last_accessed: now_millis(),
+        })
+    }
+
+    pub fn remove(&self, hash: &str) -> Result<(), CacheError> {
+        self.append(&IndexRecord::Remove {
+            hash: hash.to_string(),
+        })
+    }
+
+    fn append(&self, record: &IndexRecord) -> Result<(), CacheError> {
+        let mut state = self.state.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(self.path.as_path())?;
+        // A writer that crashed can leave an incomplete record at the end of
+        // the log. Terminate it, so that it is skipped as malformed instead of
+        // swallowing our record.
+        let mut line = Vec::new();
+        let length = file.metadata()?.len();
+        if length > 0 {
+            let mut last_byte = [0];
+            file.seek(SeekFrom::Start(length - 1))?;
+            file.read_exact(&mut last_byte)?;
+            if last_byte[0] != b'\n' {
+                line.push(b'\n');
+            }
+        }
+        serde_json::to_writer(&mut line, record)?;
+        line.push(b'\n');
+        // A single write to a file opened for appending isn't interleaved
+        // with appends from other processes
+        file.write_all(&line)?;
+        self.refresh(&mut state)
+    }
+
+    // Applies the records appended to the log since we last read it
+    fn refresh(&self, state: &mut IndexState) -> Result<(), CacheError> {
+        let mut file = match File::open(self.path.as_path()) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                *state = IndexState::default();
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let length = file.metadata()?.len();
+        let mut reader = BufReader::new(&mut file);
+        let mut header = Vec::new();
+        reader.read_until(b'\n', &mut header)?;
+        let generation = match serde_json::from_slice(&header) {
+            Ok(IndexRecord::Open { generation }) => generation,
+            _ => 0,
+        };
+        // The log was compacted by another process, so start over
+        if generation != state.generation || length < state.offset {
+            *state = IndexState {
+                generation,
+                ..Default::default()
+            };
+        }
+        if length == state.offset {
+            return Ok(());
+        }
+
+        file.seek(SeekFrom::Start(state.offset))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        let mut consumed = 0;
+        for line in contents.split_inclusive(|byte| *byte == b'\n') {
+            if !line.ends_with(b"\n") {
+                break;
+            }
+            consumed += line.len();
+            state.records += 1;
+            // A malformed record can only come from a write that was cut
+            // short, and it must not make the rest of the log unreadable
+            let Ok(record) = serde_json::from_slice::<IndexRecord>(line) else {
+                continue;
+            };
+            match record {
+                IndexRecord::Open { .. } => {}
+                IndexRecord::Put(metadata) => {
+                    state.entries.insert(metadata.hash.clone(), metadata);
+                }
+                IndexRecord::Access {
+                    hash,
+                    last_accessed,
+                } => {
+                    if let Some(metadata) = state.entries.get_mut(&hash) {
+                        metadata.last_accessed = last_accessed;
+                    }
+                }
+                IndexRecord::Remove { hash } => {
+                    state.entries.remove(&hash);
+                }
+            }
+        }
+        state.offset += consumed as u64;
+
+        Ok(())
+    }
+
+    // Replaces the log with one holding a single record per artifact
+    fn rewrite(&self, state: &mut IndexState) -> Result<(), CacheError> {
+        let generation = now_millis().max(state.generation + 1);
+        let mut contents = Vec::new();
+        let records = std::iter::once(IndexRecord::Open { generation })
+            .chain(state.entries.values().cloned().map(IndexRecord::Put));
+        for record in records {
+            serde_json::to_writer(&mut contents, &record)?;
+            contents.push(b'\n');
+        }
+
+        let temporary_path = self.path.as_path().with_extension("tmp");
+        fs::write(&temporary_path, &contents)?;
+        fs::rename(&temporary_path, self.path.as_path())?;
+
+        state.generation = generation;
+        state.offset = contents.len() as u64;
+        state.records = state.entries.len() + 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::ArtifactMetadata;
+
+    fn metadata(hash: &str) -> CacheMetadata {
+        CacheMetadata {
+            hash: hash.to_string(),
+            artifact: ArtifactMetadata::default(),
+            size: 1,
+            last_accessed: 0,
+            digest: None,
+        }
+    }
+
+    #[test]
+    fn test_shared_between_instances() -> Result<()> {
+        let dir = tempdir()?;
+        let path = AbsoluteSystemPathBuf::new(dir.path().join("index.log"))?;
+        let first = CacheIndex::open(path.clone())?;
+        let second = CacheIndex::open(path.clone())?;
+
+        first.insert(metadata("one"))?;
+        second.insert(metadata("two"))?;
+        second.touch("one")?;
+        first.remove("two")?;
+
+        for index in [&first, &second, &CacheIndex::open(path)?] {
+            assert!(index.get("one")?.unwrap().last_accessed > 0);
+            assert!(index.get("two")?.is_none());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction() -> Result<()> {
+        let dir = tempdir()?;
+        let path = AbsoluteSystemPathBuf::new(dir.path().join("index.log"))?;
+        let index = CacheIndex::open(path.clone())?;
+        index.insert(metadata("kept"))?;
+        for _ in 0..MIN_COMPACTION_RECORDS {
+            index.touch("kept")?;
+        }
+        let other = CacheIndex::open(path)?;
+        assert_eq!(other.state.lock().unwrap().records, 2);
+
+        // The instance that was open during compaction carries on with the new log
+        index.insert(metadata("one"))?;
+        other.insert(metadata("two"))?;
+
+        let mut hashes: Vec<_> = index
+            .entries()?
+            .into_iter()
+            .map(|metadata| metadata.hash)
+            .collect();
+        hashes.sort();
+        assert_eq!(hashes, ["kept", "one", "two"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_detects_compaction_to_same_length() -> Result<()> {
+        let dir = tempdir()?;
+        let path = AbsoluteSystemPathBuf::new(dir.path().join("index.log"))?;
+        let index = CacheIndex::open(path.clone())?;
+        index.insert(metadata("one"))?;
+
+        // Compact the log into one of exactly the same length, holding a
+        // different artifact
+        let other = CacheIndex::open(path)?;
+        {
+            let mut state = other.state.lock().unwrap();
+            state.entries.clear();
+            state.entries.insert("two".to_string(), metadata("two"));
+            other.rewrite(&mut state)?;
+            assert_eq!(state.offset, index.state.lock().unwrap().offset);
+        }
+
+        assert!(index.get("one")?.is_none());
+        assert!(index.get("two")?.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skips_incomplete_records() -> Result<()> {
+        let dir = tempdir()?;
+        let path = AbsoluteSystemPathBuf::new(dir.path().join("index.log"))?;
+        let index = CacheIndex::open(path.clone())?;
+        index.insert(metadata("one"))?;
+        OpenOptions::new()
+            .append(true)
+            .open(path.as_path())?
+            .write_all(br#"{"op":"put","hash":"tw"#)?;
+
+        let reopened = CacheIndex::open(path.clone())?;
+        assert!(reopened.get("one")?.is_some());
+
+        // Records appended after the incomplete one aren't lost
+        index.insert(metadata("three"))?;
+        index.remove("one")?;
+        for index in [&index, &reopened, &CacheIndex::open(path)?] {
+            assert!(index.get("three")?.is_some());
+            assert!(index.get("one")?.is_none());
+        }
+
+        Ok(())
+    }
+}
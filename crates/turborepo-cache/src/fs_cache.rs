@@ -0,0 +1,520 @@
+use std::{
+    fs, io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{field, Span};
+use turbopath::AbsoluteSystemPathBuf;
+
+use crate::{
+    artifact_digest, cache_index::CacheIndex, validate_hash, ArtifactMetadata, CacheError,
+};
+
+const ARTIFACT_SUFFIX: &str = ".tar.zst";
+const METADATA_SUFFIX: &str = "-meta.json";
+const BLOB_DIRECTORY: &str = "blobs";
+const INDEX_FILE: &str = "index.log";
+const STAGING_DIRECTORY: &str = ".staging";
+const TEMPORARY_SUFFIX: &str = ".tmp";
+
+/// A content-addressed artifact cache in a local directory.
+///
+/// Every artifact is stored as `blobs/<hash>.tar.zst`, and an index records
+/// how each one was produced, so lookups never have to scan the directory.
+/// The record includes a digest of the artifact, and artifacts that no longer
+/// match it are treated as misses and removed. Reads bump the record's access
+/// time, so that when a size budget is configured the least recently used
+/// artifacts are evicted first.
+#[derive(Debug)]
+pub struct FSCache {
+    cache_directory: AbsoluteSystemPathBuf,
+    blob_directory: AbsoluteSystemPathBuf,
+    index: CacheIndex,
+    max_size: Option<u64>,
+}
+
+// The outcome of reading an artifact from the blob directory
+enum StoredArtifact {
+    Valid(Vec<u8>, CacheMetadata),
+    // The index has no record of the artifact
+    Unknown,
+    // The blob was deleted out from under the index
+    Missing,
+    // The blob doesn't match the digest recorded in the index
+    Corrupt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMetadata {
+    pub hash: String,
+    #[serde(flatten)]
+    pub artifact: ArtifactMetadata,
+    /// Size of the stored artifact, in bytes
+    pub size: u64,
+    /// Milliseconds since the unix epoch at which the artifact was last
+    /// written or read
+    pub last_accessed: u64,
+    /// SHA-256 digest of the stored artifact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl FSCache {
+    /// Opens the cache at `cache_directory`, creating the directory if it
+    /// doesn't exist yet. If `max_size` is set, every `put` evicts least
+    /// recently used artifacts until the cache fits within that many bytes.
+    pub fn new(
+        cache_directory: AbsoluteSystemPathBuf,
+        max_size: Option<u64>,
+    ) -> Result<Self, CacheError> {
+        let blob_directory = cache_directory.join_component(BLOB_DIRECTORY);
+        blob_directory.create_dir_all()?;
+        let index_path = cache_directory.join_component(INDEX_FILE);
+        let needs_migration = !index_path.exists();
+        let cache = FSCache {
+            index: CacheIndex::open(index_path)?,
+            cache_directory,
+            blob_directory,
+            max_size,
+        };
+        if needs_migration {
+            cache.migrate_flat_layout()?;
+        }
+        Ok(cache)
+    }
+
+    pub fn cache_directory(&self) -> &AbsoluteSystemPathBuf {
+        &self.cache_directory
+    }
+
+    /// A directory inside the cache for in-progress downloads, so that they
+    /// can be resumed by a later invocation
+    pub fn staging_directory(&self) -> Result<AbsoluteSystemPathBuf, CacheError> {
+        let staging_directory = self.cache_directory.join_component(STAGING_DIRECTORY);
+        staging_directory.create_dir_all()?;
+        Ok(staging_directory)
+    }
+
+    /// Removes artifacts that were being written when a `put` was
+    /// interrupted. Partial downloads are kept so that they can be resumed.
+    pub fn remove_temporary_files(&self) -> Result<(), CacheError> {
+        let staging_directory = self.staging_directory()?;
+        for dir_entry in fs::read_dir(staging_directory.as_path())? {
+            let dir_entry = dir_entry?;
+            let file_name = dir_entry.file_name();
+            if matches!(file_name.to_str(), Some(name) if name.ends_with(TEMPORARY_SUFFIX)) {
+                match fs::remove_file(dir_entry.path()) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), fields(backend = "local"))]
+    pub fn exists(&self, hash: &str) -> Result<bool, CacheError> {
+        validate_hash(hash)?;
+        Ok(self.index.get(hash)?.is_some())
+    }
+
+    /// Reads the artifact for `hash`, returning `None` on a cache miss.
+    /// A hit updates the artifact's access time. An artifact that doesn't
+    /// match its recorded digest is removed and reported as a miss.
+    #[tracing::instrument(skip(self), fields(backend = "local", bytes = field::Empty))]
+    pub fn fetch(&self, hash: &str) -> Result<Option<(Vec<u8>, CacheMetadata)>, CacheError> {
+        match self.read_artifact(hash)? {
+            StoredArtifact::Valid(body, mut metadata) => {
+                self.index.touch(hash)?;
+                metadata.last_accessed = now_millis();
+                Span::current().record("bytes", body.len());
+                Ok(Some((body, metadata)))
+            }
+            StoredArtifact::Unknown => Ok(None),
+            StoredArtifact::Missing => {
+                self.index.remove(hash)?;
+                Ok(None)
+            }
+            StoredArtifact::Corrupt => {
+                self.remove(hash)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads the artifact for `hash` like `fetch`, but never writes to the
+    /// cache directory: the access time isn't updated, and missing or corrupt
+    /// artifacts are reported as misses without being removed.
+    #[tracing::instrument(skip(self), fields(backend = "local", bytes = field::Empty))]
+    pub fn read(&self, hash: &str) -> Result<Option<(Vec<u8>, CacheMetadata)>, CacheError> {
+        match self.read_artifact(hash)? {
+            StoredArtifact::Valid(body, metadata) => {
+                Span::current().record("bytes", body.len());
+                Ok(Some((body, metadata)))
+            }
+            StoredArtifact::Unknown | StoredArtifact::Missing | StoredArtifact::Corrupt => Ok(None),
+        }
+    }
+
+    fn read_artifact(&self, hash: &str) -> Result<StoredArtifact, CacheError> {
+        let artifact_path = self.artifact_path(hash)?;
+        let Some(metadata) = self.index.get(hash)? else {
+            return Ok(StoredArtifact::Unknown);
+        };
+        let body = match fs::read(artifact_path.as_path()) {
+            Ok(body) => body,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(StoredArtifact::Missing);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if let Some(digest) = &metadata.digest {
+            if *digest != artifact_digest(&body) {
+                return Ok(StoredArtifact::Corrupt);
+            }
+        }
+        Ok(StoredArtifact::Valid(body, metadata))
+    }
+
+    /// Stores `body` as the artifact for `hash`, replacing any existing one.
+    /// The artifact is written to the staging directory first and then moved
+    /// into place, so an interrupted write never leaves a truncated artifact
+    /// behind.
+    #[tracing::instrument(
+        skip(self, body, metadata),
+        fields(backend = "local", bytes = body.len())
+    )]
+    pub fn put(
+        &self,
+        hash: &str,
+        body: &[u8],
+        metadata: &ArtifactMetadata,
+    ) -> Result<(), CacheError> {
+        let artifact_path = self.artifact_path(hash)?;
+        let temporary_path = self
+            .staging_directory()?
+            .join_component(&format!("{}{}", hash, TEMPORARY_SUFFIX));
+        fs::write(temporary_path.as_path(), body)?;
+        fs::rename(temporary_path.as_path(), artifact_path.as_path())?;
+        self.index.insert(CacheMetadata {
+            hash: hash.to_string(),
+            artifact: metadata.clone(),
+            size: body.len() as u64,
+            last_accessed: now_millis(),
+            digest: Some(artifact_digest(body)),
+        })?;
+
+        if let Some(max_size) = self.max_size {
+            self.evict(max_size)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_metadata(&self, hash: &str) -> Result<Option<CacheMetadata>, CacheError> {
+        validate_hash(hash)?;
+        self.index.get(hash)
+    }
+
+    #[tracing::instrument(skip(self), fields(backend = "local"))]
+    pub fn remove(&self, hash: &str) -> Result<(), CacheError> {
+        // Drop the index entry first, so that the artifact stops being a hit
+        // even if removing the blob fails
+        let artifact_path = self.artifact_path(hash)?;
+        self.index.remove(hash)?;
+        match artifact_path.remove() {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Removes every artifact whose hash starts with `prefix`, returning the
+    /// hashes of the removed artifacts
+    #[tracing::instrument(skip(self), fields(backend = "local"))]
+    pub fn remove_prefix(&self, prefix: &str) -> Result<Vec<String>, CacheError> {
+        validate_hash(prefix)?;
+        let mut removed = Vec::new();
+        for entry in self.entries()? {
+            if entry.hash.starts_with(prefix) {
+                self.remove(&entry.hash)?;
+                removed.push(entry.hash);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Returns the metadata of every artifact in the cache
+    pub fn entries(&self) -> Result<Vec<CacheMetadata>, CacheError> {
+        self.index.entries()
+    }
+
+    /// Total size in bytes of the artifacts in the cache
+    pub fn size(&self) -> Result<u64, CacheError> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    /// Removes least recently used artifacts until the cache holds at most
+    /// `max_size` bytes. Returns the hashes of the evicted artifacts.
+    #[tracing::instrument(skip(self), fields(backend = "local"))]
+    pub fn evict(&self, max_size: u64) -> Result<Vec<String>, CacheError> {
+        let mut entries = self.entries()?;
+        entries.sort_by(|a, b| {
+            a.last_accessed
+                .cmp(&b.last_accessed)
+                .then_with(|| a.hash.cmp(&b.hash))
+        });
+
+        let mut total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut evicted = Vec::new();
+        for entry in entries {
+            if total_size <= max_size {
+                break;
+            }
+            self.remove(&entry.hash)?;
+            total_size -= entry.size;
+            evicted.push(entry.hash);
+        }
+
+        Ok(evicted)
+    }
+
+    /// Moves artifacts stored by earlier versions as `<hash>.tar.zst` and
+    /// `<hash>-meta.json` directly in the cache directory into the blob
+    /// directory and the index
+    fn migrate_flat_layout(&self) -> Result<(), CacheError> {
+        for dir_entry in fs::read_dir(self.cache_directory.as_path())? {
+            let file_name = dir_entry?.file_name();
+            let Some(hash) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(ARTIFACT_SUFFIX))
+            else {
+                continue;
+            };
+            if validate_hash(hash).is_err() {
+                continue;
+            }
+
+            let legacy_artifact_path = self
+                .cache_directory
+                .join_component(file_name.to_str().unwrap());
+            let legacy_metadata_path = self
+                .cache_directory
+                .join_component(&format!("{}{}", hash, METADATA_SUFFIX));
+            let size = fs::metadata(legacy_artifact_path.as_path())?.len();
+            // Artifacts written without a metadata record are still valid
+            // hits, we just don't know how long they took to produce.
+            let metadata = match fs::read(legacy_metadata_path.as_path()) {
+                Ok(contents) => serde_json::from_slice(&contents).ok(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err.into()),
+            }
+            .unwrap_or_else(|| CacheMetadata {
+                hash: hash.to_string(),
+                artifact: ArtifactMetadata::default(),
+                size,
+                last_accessed: 0,
+                digest: None,
+            });
+
+            fs::rename(
+                legacy_artifact_path.as_path(),
+                self.artifact_path(hash)?.as_path(),
+            )?;
+            self.index.insert(metadata)?;
+            match legacy_metadata_path.remove() {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn artifact_path(&self, hash: &str) -> Result<AbsoluteSystemPathBuf, CacheError> {
+        validate_hash(hash)?;
+        Ok(self
+            .blob_directory
+            .join_component(&format!("{}{}", hash, ARTIFACT_SUFFIX)))
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn new_cache(max_size: Option<u64>) -> Result<(tempfile::TempDir, FSCache)> {
+        let dir = tempdir()?;
+        let cache_directory = AbsoluteSystemPathBuf::new(dir.path().join("cache"))?;
+        let cache = FSCache::new(cache_directory, max_size)?;
+        Ok((dir, cache))
+    }
+
+    #[test]
+    fn test_put_and_fetch() -> Result<()> {
+        let (_dir, cache) = new_cache(None)?;
+
+        assert!(!cache.exists("abc123")?);
+        assert!(cache.fetch("abc123")?.is_none());
+
+        let artifact_metadata = ArtifactMetadata::new(42, "1.10.0", Some("env".to_string()));
+        cache.put("abc123", b"artifact body", &artifact_metadata)?;
+        assert!(cache.exists("abc123")?);
+
+        let (body, metadata) = cache.fetch("abc123")?.unwrap();
+        assert_eq!(body, b"artifact body");
+        assert_eq!(metadata.hash, "abc123");
+        assert_eq!(metadata.artifact, artifact_metadata);
+        assert_eq!(metadata.size, 13);
+        assert_eq!(cache.size()?, 13);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_updates_access_time() -> Result<()> {
+        let (_dir, cache) = new_cache(None)?;
+
+        cache.put("abc123", b"body", &ArtifactMetadata::default())?;
+        let written = cache.read_metadata("abc123")?.unwrap().last_accessed;
+        thread::sleep(Duration::from_millis(5));
+        cache.fetch("abc123")?;
+        let read = cache.read_metadata("abc123")?.unwrap().last_accessed;
+        assert!(read > written);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() -> Result<()> {
+        let (_dir, cache) = new_cache(Some(10))?;
+
+        cache.put("first", b"1234", &ArtifactMetadata::default())?;
+        thread::sleep(Duration::from_millis(5));
+        cache.put("second", b"1234", &ArtifactMetadata::default())?;
+        thread::sleep(Duration::from_millis(5));
+        // Reading "first" makes "second" the least recently used artifact
+        cache.fetch("first")?;
+        thread::sleep(Duration::from_millis(5));
+        cache.put("third", b"1234", &ArtifactMetadata::default())?;
+
+        assert!(cache.exists("first")?);
+        assert!(!cache.exists("second")?);
+        assert!(cache.read_metadata("second")?.is_none());
+        assert!(cache.exists("third")?);
+        assert_eq!(cache.size()?, 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrates_flat_layout() -> Result<()> {
+        let dir = tempdir()?;
+        let cache_directory = AbsoluteSystemPathBuf::new(dir.path().join("cache"))?;
+        cache_directory.create_dir_all()?;
+
+        // Records written before the producer fields were added only have a
+        // duration
+        fs::write(
+            cache_directory.join_component("abc123.tar.zst").as_path(),
+            b"body",
+        )?;
+        fs::write(
+            cache_directory.join_component("abc123-meta.json").as_path(),
+            r#"{"hash":"abc123","duration":7,"size":4,"lastAccessed":0}"#,
+        )?;
+        // Artifacts from before metadata records existed
+        fs::write(
+            cache_directory.join_component("def456.tar.zst").as_path(),
+            b"other",
+        )?;
+
+        let cache = FSCache::new(cache_directory.clone(), None)?;
+        let (body, metadata) = cache.fetch("abc123")?.unwrap();
+        assert_eq!(body, b"body");
+        assert_eq!(metadata.artifact, ArtifactMetadata::from_duration(7));
+        let (_, metadata) = cache.fetch("def456")?.unwrap();
+        assert_eq!(metadata.size, 5);
+        assert!(!cache_directory.join_component("abc123-meta.json").exists());
+        assert!(!cache_directory.join_component("abc123.tar.zst").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_between_processes() -> Result<()> {
+        let (dir, cache) = new_cache(None)?;
+        let other = FSCache::new(AbsoluteSystemPathBuf::new(dir.path().join("cache"))?, None)?;
+
+        cache.put("abc123", b"body", &ArtifactMetadata::default())?;
+        assert!(other.exists("abc123")?);
+        other.remove("abc123")?;
+        assert!(cache.fetch("abc123")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_removes_corrupt_artifacts() -> Result<()> {
+        let (_dir, cache) = new_cache(None)?;
+
+        cache.put("abc123", b"artifact body", &ArtifactMetadata::default())?;
+        fs::write(cache.artifact_path("abc123")?.as_path(), b"corrupted")?;
+
+        assert!(cache.fetch("abc123")?.is_none());
+        assert!(!cache.exists("abc123")?);
+        assert!(cache.read_metadata("abc123")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_leaves_cache_untouched() -> Result<()> {
+        let (_dir, cache) = new_cache(None)?;
+
+        cache.put("abc123", b"artifact body", &ArtifactMetadata::default())?;
+        cache.put("def456", b"artifact body", &ArtifactMetadata::default())?;
+        let written = cache.read_metadata("abc123")?.unwrap();
+        thread::sleep(Duration::from_millis(5));
+        let (body, metadata) = cache.read("abc123")?.unwrap();
+        assert_eq!(body, b"artifact body");
+        assert_eq!(metadata, written);
+        assert_eq!(cache.read_metadata("abc123")?.unwrap(), written);
+
+        fs::write(cache.artifact_path("def456")?.as_path(), b"corrupted")?;
+        assert!(cache.read("def456")?.is_none());
+        assert!(cache.exists("def456")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_invalid_hashes() -> Result<()> {
+        let (_dir, cache) = new_cache(None)?;
+
+        for hash in ["", "../escape", "nested/hash", "dot.dot"] {
+            assert!(matches!(
+                cache.put(hash, b"body", &ArtifactMetadata::default()),
+                Err(CacheError::InvalidHash(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
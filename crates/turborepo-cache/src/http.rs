@@ -0,0 +1,568 @@
+use std::{fs, fs::OpenOptions, io};
+
+use tracing::{field, Span};
+use turbopath::AbsoluteSystemPath;
+use turborepo_api_client::{
+    APIClient, ArtifactDownload, CachingStatus, SignedArtifactUrl, SignedUrlMethod,
+    SignedUrlRequest,
+};
+
+use crate::{
+    artifact_digest, signature_authentication::ArtifactSignatureAuthenticator, validate_hash,
+    CacheError,
+};
+
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// A client for the turborepo remote cache protocol
+/// (`GET`/`PUT /v8/artifacts/:hash`). Works against the Vercel API as well as
+/// self-hosted servers implementing the same endpoints.
+///
+/// With `with_signed_urls`, artifacts are transferred through pre-signed URLs
+/// to the server's object storage instead, if the server hands them out.
+pub struct HTTPCache {
+    client: APIClient,
+    token: String,
+    team_id: String,
+    team_slug: Option<String>,
+    signer_verifier: Option<ArtifactSignatureAuthenticator>,
+    signed_urls: bool,
+}
+
+impl HTTPCache {
+    /// When `signer_verifier` is set, uploaded artifacts are tagged with a
+    /// signature and downloaded artifacts are rejected unless their tag
+    /// verifies.
+    pub fn new(
+        client: APIClient,
+        token: String,
+        team_id: String,
+        team_slug: Option<String>,
+        signer_verifier: Option<ArtifactSignatureAuthenticator>,
+    ) -> Self {
+        HTTPCache {
+            client,
+            token,
+            team_id,
+            team_slug,
+            signer_verifier,
+            signed_urls: false,
+        }
+    }
+
+    pub fn with_signed_urls(mut self) -> Self {
+        self.signed_urls = true;
+        self
+    }
+
+    #[tracing::instrument(skip(self, body), fields(backend = "remote", bytes = body.len()))]
+    pub async fn put(&self, hash: &str, body: &[u8], duration: u64) -> Result<(), CacheError> {
+        validate_hash(hash)?;
+        let tag = self
+            .signer_verifier
+            .as_ref()
+            .map(|signer| signer.generate_tag(hash.as_bytes(), body))
+            .transpose()?;
+        let digest = artifact_digest(body);
+
+        let request = SignedUrlRequest {
+            method: SignedUrlMethod::Put,
+            duration: Some(duration),
+            tag: tag.clone(),
+            digest: Some(digest.clone()),
+        };
+        if let Some(signed_url) = self.signed_url(hash, &request).await? {
+            self.client
+                .put_signed_artifact(&signed_url.url, body)
+                .await?;
+            return Ok(());
+        }
+
+        self.client
+            .put_artifact(
+                hash,
+                body,
+                duration,
+                tag.as_deref(),
+                Some(&digest),
+                &self.token,
+                &self.team_id,
+                self.team_slug.as_deref(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the artifact for `hash`, returning `false` if it didn't exist
+    #[tracing::instrument(skip(self), fields(backend = "remote"))]
+    pub async fn delete(&self, hash: &str) -> Result<bool, CacheError> {
+        validate_hash(hash)?;
+        Ok(self
+            .client
+            .delete_artifact(hash, &self.token, &self.team_id, self.team_slug.as_deref())
+            .await?)
+    }
+
+    /// Deletes every artifact whose hash starts with `prefix`, returning how
+    /// many were deleted
+    #[tracing::instrument(skip(self), fields(backend = "remote"))]
+    pub async fn delete_prefix(&self, prefix: &str) -> Result<u64, CacheError> {
+        validate_hash(prefix)?;
+        Ok(self
+            .client
+            .delete_artifacts_with_prefix(
+                prefix,
+                &self.token,
+                &self.team_id,
+                self.team_slug.as_deref(),
+            )
+            .await?)
+    }
+
+    /// Probes the remote cache, returning whether caching is enabled for the
+    /// team. Fails if the remote cache can't be reached.
+    #[tracing::instrument(skip(self), fields(backend = "remote"))]
+    pub async fn health_check(&self) -> Result<CachingStatus, CacheError> {
+        let response = self
+            .client
+            .get_caching_status(&self.token, &self.team_id, self.team_slug.as_deref())
+            .await?;
+        Ok(response.status)
+    }
+
+    #[tracing::instrument(skip(self), fields(backend = "remote"))]
+    pub async fn exists(&self, hash: &str) -> Result<bool, CacheError> {
+        validate_hash(hash)?;
+        Ok(self
+            .client
+            .artifact_exists(hash, &self.token, &self.team_id, self.team_slug.as_deref())
+            .await?)
+    }
+
+    /// Downloads the artifact for `hash` along with the time it took to
+    /// produce, returning `None` on a cache miss.
+    #[tracing::instrument(skip(self), fields(backend = "remote", bytes = field::Empty))]
+    pub async fn fetch(&self, hash: &str) -> Result<Option<(Vec<u8>, u64)>, CacheError> {
+        validate_hash(hash)?;
+        if let Some(signed_url) = self.signed_url(hash, &SignedUrlRequest::get()).await? {
+            let Some(body) = self.client.fetch_signed_artifact(&signed_url.url).await? else {
+                return Ok(None);
+            };
+            self.verify(
+                hash,
+                &body,
+                signed_url.tag.as_deref(),
+                signed_url.digest.as_deref(),
+            )?;
+            Span::current().record("bytes", body.len());
+            return Ok(Some((body, signed_url.duration.unwrap_or_default())));
+        }
+
+        let Some(artifact) = self
+            .client
+            .fetch_artifact(hash, &self.token, &self.team_id, self.team_slug.as_deref())
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        self.verify(
+            hash,
+            &artifact.body,
+            artifact.tag.as_deref(),
+            artifact.digest.as_deref(),
+        )?;
+        Span::current().record("bytes", artifact.body.len());
+
+        Ok(Some((artifact.body, artifact.duration.unwrap_or_default())))
+    }
+
+    /// Like `fetch`, but streams the artifact into
+    /// `<staging_directory>/<hash>.partial` as it arrives. Transient failures
+    /// are retried, and each retry (including one from a later invocation)
+    /// resumes from the bytes already on disk instead of starting over.
+    #[tracing::instrument(
+        skip(self, staging_directory),
+        fields(backend = "remote", bytes = field::Empty, attempts = field::Empty)
+    )]
+    pub async fn fetch_resumable(
+        &self,
+        hash: &str,
+        staging_directory: impl AsRef<AbsoluteSystemPath>,
+    ) -> Result<Option<(Vec<u8>, u64)>, CacheError> {
+        validate_hash(hash)?;
+        let partial_path = staging_directory
+            .as_ref()
+            .join_component(&format!("{}.partial", hash));
+
+        let signed_url = self.signed_url(hash, &SignedUrlRequest::get()).await?;
+        let mut attempts = 0;
+        let download = loop {
+            attempts += 1;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .read(true)
+                .write(true)
+                .open(partial_path.as_path())?;
+            let download = match &signed_url {
+                Some(signed_url) => self
+                    .client
+                    .download_signed_artifact(&signed_url.url, &mut file)
+                    .await
+                    .map(|found| {
+                        found.then(|| ArtifactDownload {
+                            duration: signed_url.duration,
+                            tag: signed_url.tag.clone(),
+                            digest: signed_url.digest.clone(),
+                        })
+                    }),
+                None => {
+                    self.client
+                        .download_artifact(
+                            hash,
+                            &mut file,
+                            &self.token,
+                            &self.team_id,
+                            self.team_slug.as_deref(),
+                        )
+                        .await
+                }
+            };
+            match download {
+                Ok(download) => {
+                    Span::current().record("attempts", attempts);
+                    break download;
+                }
+                Err(err) if err.is_transient() && attempts < MAX_DOWNLOAD_ATTEMPTS => {}
+                Err(err) => {
+                    Span::current().record("attempts", attempts);
+                    return Err(err.into());
+                }
+            }
+        };
+
+        let body = match download {
+            Some(_) => fs::read(partial_path.as_path())?,
+            None => Vec::new(),
+        };
+        // Whatever happens next, this partial download is finished with. In
+        // particular, a download that fails verification must not be resumed.
+        match partial_path.remove() {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        let Some(download) = download else {
+            return Ok(None);
+        };
+        self.verify(
+            hash,
+            &body,
+            download.tag.as_deref(),
+            download.digest.as_deref(),
+        )?;
+        Span::current().record("bytes", body.len());
+
+        Ok(Some((body, download.duration.unwrap_or_default())))
+    }
+
+    // Returns `None` if signed URLs are disabled or the server doesn't
+    // support them
+    async fn signed_url(
+        &self,
+        hash: &str,
+        request: &SignedUrlRequest,
+    ) -> Result<Option<SignedArtifactUrl>, CacheError> {
+        if !self.signed_urls {
+            return Ok(None);
+        }
+        Ok(self
+            .client
+            .signed_artifact_url(
+                hash,
+                request,
+                &self.token,
+                &self.team_id,
+                self.team_slug.as_deref(),
+            )
+            .await?)
+    }
+
+    // Artifacts uploaded by older clients have no digest, so only artifacts
+    // that have one are checked against it
+    fn verify(
+        &self,
+        hash: &str,
+        body: &[u8],
+        tag: Option<&str>,
+        digest: Option<&str>,
+    ) -> Result<(), CacheError> {
+        if let Some(signer_verifier) = &self.signer_verifier {
+            let tag = tag.ok_or(CacheError::ArtifactTagMissing)?;
+            if !signer_verifier.validate(hash.as_bytes(), body, tag)? {
+                return Err(CacheError::InvalidTag(tag.to_string()));
+            }
+        }
+        if let Some(digest) = digest {
+            if digest != artifact_digest(body) {
+                return Err(CacheError::DigestMismatch(hash.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use turbopath::AbsoluteSystemPathBuf;
+    use turborepo_api_client::APIClient;
+    use vercel_api_mock::start_test_server;
+
+    use super::*;
+
+    fn new_cache(port: u16, signer_verifier: Option<ArtifactSignatureAuthenticator>) -> HTTPCache {
+        let client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0").unwrap();
+        HTTPCache::new(
+            client,
+            vercel_api_mock::EXPECTED_TOKEN.to_string(),
+            vercel_api_mock::EXPECTED_TEAM_ID.to_string(),
+            None,
+            signer_verifier,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_put_and_fetch() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let cache = new_cache(port, None);
+
+        assert!(cache.fetch("missing").await?.is_none());
+        assert!(!cache.exists("abc123").await?);
+
+        cache.put("abc123", b"artifact body", 42).await?;
+        assert!(cache.exists("abc123").await?);
+        let (body, duration) = cache.fetch("abc123").await?.unwrap();
+        assert_eq!(body, b"artifact body");
+        assert_eq!(duration, 42);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_signed_artifacts() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let signer = |key: &[u8]| {
+            Some(ArtifactSignatureAuthenticator::new(
+                b"team_id".to_vec(),
+                Some(key.to_vec()),
+            ))
+        };
+        let cache = new_cache(port, signer(b"secret"));
+        cache.put("abc123", b"artifact body", 0).await?;
+        assert!(cache.fetch("abc123").await?.is_some());
+
+        // A client with a different key must reject the artifact
+        let other_cache = new_cache(port, signer(b"other secret"));
+        assert!(matches!(
+            other_cache.fetch("abc123").await,
+            Err(CacheError::InvalidTag(_))
+        ));
+
+        // Unsigned artifacts are rejected when verification is enabled
+        new_cache(port, None)
+            .put("unsigned", b"artifact body", 0)
+            .await?;
+        assert!(matches!(
+            cache.fetch("unsigned").await,
+            Err(CacheError::ArtifactTagMissing)
+        ));
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_signed_urls() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let signer = || {
+            Some(ArtifactSignatureAuthenticator::new(
+                b"team_id".to_vec(),
+                Some(b"secret".to_vec()),
+            ))
+        };
+        let cache = new_cache(port, signer()).with_signed_urls();
+        assert!(cache.fetch("abc123").await?.is_none());
+
+        cache.put("abc123", b"artifact body", 42).await?;
+        assert_eq!(
+            cache.fetch("abc123").await?,
+            Some((b"artifact body".to_vec(), 42))
+        );
+        let dir = tempdir()?;
+        assert_eq!(
+            cache
+                .fetch_resumable("abc123", AbsoluteSystemPathBuf::new(dir.path())?)
+                .await?,
+            Some((b"artifact body".to_vec(), 42))
+        );
+
+        // Artifacts uploaded through signed URLs are visible to clients that
+        // use the regular endpoints, along with their metadata
+        assert_eq!(
+            new_cache(port, signer()).fetch("abc123").await?,
+            Some((b"artifact body".to_vec(), 42))
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resumes_partial_downloads() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let cache = new_cache(port, None);
+        // Upload without a digest, so that resuming from a partial download
+        // with different bytes isn't rejected
+        cache
+            .client
+            .put_artifact(
+                "abc123",
+                b"artifact body",
+                7,
+                None,
+                None,
+                &cache.token,
+                &cache.team_id,
+                None,
+            )
+            .await?;
+
+        let dir = tempdir()?;
+        let staging_directory = AbsoluteSystemPathBuf::new(dir.path())?;
+        let partial_path = staging_directory.join_component("abc123.partial");
+
+        // Without a partial download, the whole artifact is fetched
+        let (body, duration) = cache
+            .fetch_resumable("abc123", &staging_directory)
+            .await?
+            .unwrap();
+        assert_eq!(body, b"artifact body");
+        assert_eq!(duration, 7);
+        assert!(!partial_path.exists());
+
+        // Only the bytes after the partial download are requested, which we
+        // can observe by seeding the partial file with different bytes
+        fs::write(partial_path.as_path(), b"ARTIFACT")?;
+        let (body, _) = cache
+            .fetch_resumable("abc123", &staging_directory)
+            .await?
+            .unwrap();
+        assert_eq!(body, b"ARTIFACT body");
+
+        // A partial download longer than the artifact is discarded
+        fs::write(
+            partial_path.as_path(),
+            b"a partial download that is too long",
+        )?;
+        let (body, _) = cache
+            .fetch_resumable("abc123", &staging_directory)
+            .await?
+            .unwrap();
+        assert_eq!(body, b"artifact body");
+
+        assert!(cache
+            .fetch_resumable("missing", &staging_directory)
+            .await?
+            .is_none());
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verifies_digest() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let cache = new_cache(port, None);
+        cache.put("abc123", b"artifact body", 0).await?;
+
+        let dir = tempdir()?;
+        let staging_directory = AbsoluteSystemPathBuf::new(dir.path())?;
+        let partial_path = staging_directory.join_component("abc123.partial");
+        fs::write(partial_path.as_path(), b"ARTIFACT")?;
+        assert!(matches!(
+            cache.fetch_resumable("abc123", &staging_directory).await,
+            Err(CacheError::DigestMismatch(_))
+        ));
+        assert!(!partial_path.exists());
+
+        // An artifact stored under a digest that doesn't match its contents is
+        // rejected
+        cache
+            .client
+            .put_artifact(
+                "corrupt",
+                b"artifact body",
+                0,
+                None,
+                Some(&artifact_digest(b"something else")),
+                &cache.token,
+                &cache.team_id,
+                None,
+            )
+            .await?;
+        assert!(matches!(
+            cache.fetch("corrupt").await,
+            Err(CacheError::DigestMismatch(_))
+        ));
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resumed_downloads_are_verified() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let cache = new_cache(
+            port,
+            Some(ArtifactSignatureAuthenticator::new(
+                b"team_id".to_vec(),
+                Some(b"secret".to_vec()),
+            )),
+        );
+        cache.put("abc123", b"artifact body", 0).await?;
+
+        let dir = tempdir()?;
+        let staging_directory = AbsoluteSystemPathBuf::new(dir.path())?;
+        let partial_path = staging_directory.join_component("abc123.partial");
+        fs::write(partial_path.as_path(), b"ARTIFACT")?;
+
+        assert!(matches!(
+            cache.fetch_resumable("abc123", &staging_directory).await,
+            Err(CacheError::InvalidTag(_))
+        ));
+        // The corrupt partial download was thrown away, so retrying succeeds
+        assert!(!partial_path.exists());
+        let (body, _) = cache
+            .fetch_resumable("abc123", &staging_directory)
+            .await?
+            .unwrap();
+        assert_eq!(body, b"artifact body");
+
+        handle.abort();
+        Ok(())
+    }
+}
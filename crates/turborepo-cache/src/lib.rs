@@ -1 +1,155 @@
+pub mod analytics;
+pub mod bundle;
+mod cache_index;
+pub mod fs_cache;
+pub mod http;
+pub mod multiplexer;
+pub mod namespace;
+pub mod pending_uploads;
+pub mod prefetch;
+pub mod rate_limit;
 pub mod signature_authentication;
+pub mod upload_manager;
+
+use std::{env, fmt::Write, time::Duration};
+
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::signature_authentication::SignatureError;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("path error: {0}")]
+    Path(#[from] turbopath::PathError),
+    #[error("invalid cache metadata: {0}")]
+    InvalidMetadata(#[from] serde_json::Error),
+    #[error("invalid artifact hash: {0}")]
+    InvalidHash(String),
+    #[error("invalid cache namespace: {0:?}")]
+    InvalidNamespace(String),
+    #[error(transparent)]
+    ApiClientError(#[from] turborepo_api_client::Error),
+    #[error(transparent)]
+    SignatureError(#[from] SignatureError),
+    #[error(
+        "artifact verification failed: downloaded artifact is missing required x-artifact-tag \
+         header"
+    )]
+    ArtifactTagMissing,
+    #[error("artifact verification failed: artifact tag {0} does not match")]
+    InvalidTag(String),
+    #[error("artifact verification failed: artifact {0} does not match its recorded digest")]
+    DigestMismatch(String),
+    #[error("invalid cache bundle: {0}")]
+    InvalidBundle(String),
+    #[error("cache operation timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("cache operation was cancelled")]
+    Cancelled,
+    #[error("cache is read-only")]
+    ReadOnly,
+}
+
+// Hashes become file names, so we only accept characters that can't be used
+// to escape the cache directory.
+pub(crate) fn validate_hash(hash: &str) -> Result<(), CacheError> {
+    let is_valid = !hash.is_empty()
+        && hash
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    if !is_valid {
+        return Err(CacheError::InvalidHash(hash.to_string()));
+    }
+    Ok(())
+}
+
+/// Hex encoded SHA-256 digest of an artifact, used to detect artifacts that
+/// were corrupted in storage or in transit
+pub(crate) fn artifact_digest(body: &[u8]) -> String {
+    let digest = digest::digest(&digest::SHA256, body);
+    let mut hex = String::with_capacity(digest.as_ref().len() * 2);
+    for byte in digest.as_ref() {
+        write!(hex, "{:02x}", byte).expect("writing to a String can't fail");
+    }
+    hex
+}
+
+impl CacheError {
+    /// Whether retrying the operation that failed with this error might
+    /// succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, CacheError::ApiClientError(err) if err.is_transient())
+    }
+
+    /// Whether this error means the remote cache couldn't be reached
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, CacheError::ApiClientError(err) if err.is_connection_error())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheSource {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheResponse {
+    pub source: CacheSource,
+    /// Time it originally took to produce the artifact, in milliseconds
+    pub time_saved: u64,
+}
+
+/// What happened to an artifact handed to the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheWriteStatus {
+    /// The artifact was stored locally and, if there is a remote cache,
+    /// queued for upload
+    Written,
+    /// Nothing was stored because the cache is read-only
+    SkippedReadOnly,
+}
+
+/// Information about how an artifact was produced. The local cache keeps it in
+/// a JSON sidecar next to the artifact.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactMetadata {
+    /// Time it took to produce the artifact, in milliseconds
+    pub duration: u64,
+    /// Version of turbo that produced the artifact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turbo_version: Option<String>,
+    /// `<os>-<arch>` of the machine that produced the artifact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// Hash of the environment variables the producing task depended on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_hash: Option<String>,
+}
+
+impl ArtifactMetadata {
+    /// Metadata for an artifact produced on this machine
+    pub fn new(duration: u64, turbo_version: &str, env_hash: Option<String>) -> Self {
+        ArtifactMetadata {
+            duration,
+            turbo_version: Some(turbo_version.to_string()),
+            platform: Some(format!("{}-{}", env::consts::OS, env::consts::ARCH)),
+            env_hash,
+        }
+    }
+
+    /// Metadata for an artifact where only the duration is known, e.g. one
+    /// downloaded from the remote cache
+    pub fn from_duration(duration: u64) -> Self {
+        ArtifactMetadata {
+            duration,
+            ..Default::default()
+        }
+    }
+}
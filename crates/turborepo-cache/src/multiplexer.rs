@@ -0,0 +1,1043 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::{stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tracing::{field, warn, Span};
+use turborepo_api_client::CachingStatus;
+
+use crate::{
+    analytics::{CacheAnalytics, CacheEvent},
+    fs_cache::FSCache,
+    http::HTTPCache,
+    namespace::CacheNamespace,
+    pending_uploads::{PendingUpload, PendingUploads},
+    prefetch::Prefetcher,
+    rate_limit::TransferLimits,
+    upload_manager::{UploadManager, UploadResult},
+    ArtifactMetadata, CacheError, CacheResponse, CacheSource, CacheWriteStatus,
+};
+
+/// Maximum number of artifacts `exists_all` checks at once
+const MAX_CONCURRENT_EXISTS_CHECKS: usize = 16;
+
+const PENDING_UPLOADS_FILE: &str = ".pending-uploads.json";
+
+/// Layers the local filesystem cache in front of the remote cache.
+///
+/// Reads check the local cache first and fall through to the remote cache,
+/// storing remote hits locally. Writes go to the local cache immediately and
+/// are uploaded to the remote cache in the background, with at most
+/// `max_concurrent_uploads` uploads in flight at once. Call `wait` before
+/// exiting to make sure every upload has finished.
+///
+/// Artifacts can be scoped with `with_namespaces`: writes then go to the
+/// primary namespace, and reads that miss in it fall back to the other
+/// namespaces in order (e.g. reading from `main` when a branch has no
+/// artifact yet).
+///
+/// If the remote cache can't be reached, the multiplexer goes offline for the
+/// rest of the invocation: it serves only the local cache, and artifacts that
+/// should have been uploaded are recorded in a queue in the local cache
+/// directory. A later invocation can upload them with `flush`. The same
+/// happens when `check_health` finds the remote cache unhealthy, or when more
+/// remote operations fail or time out than allowed by `with_error_budget`,
+/// so that a struggling remote cache doesn't slow down every task.
+///
+/// With `with_analytics`, the outcome of every lookup is recorded as a
+/// `CacheEvent`.
+///
+/// `with_timeout` bounds every remote operation; lookups that run out of time
+/// are treated as misses. `cancel` stops all remote work, e.g. on Ctrl-C.
+/// `with_transfer_limits` rate limits remote cache traffic and caps how many
+/// transfers run at once, across lookups, uploads and prefetches.
+///
+/// A read-only multiplexer (`with_read_only`) never writes artifacts to either
+/// cache, so that e.g. untrusted CI jobs can't pollute a shared cache: `put`
+/// reports the artifact as skipped, remote hits aren't copied into the local
+/// cache, and deleting artifacts fails with `CacheError::ReadOnly`.
+pub struct CacheMultiplexer {
+    fs: Option<Arc<FSCache>>,
+    http: Option<Arc<HTTPCache>>,
+    uploads: Option<UploadManager>,
+    prefetcher: Option<Prefetcher>,
+    pending_uploads: Option<PendingUploads>,
+    namespaces: Vec<CacheNamespace>,
+    analytics: Option<CacheAnalytics>,
+    timeout: Option<Duration>,
+    cancellation: CancellationToken,
+    limits: TransferLimits,
+    read_only: bool,
+    offline: AtomicBool,
+    error_budget: Option<u32>,
+    remote_errors: AtomicU32,
+}
+
+impl CacheMultiplexer {
+    pub fn new(
+        fs: Option<FSCache>,
+        http: Option<HTTPCache>,
+        max_concurrent_uploads: usize,
+    ) -> Self {
+        let fs = fs.map(Arc::new);
+        let http = http.map(Arc::new);
+        let cancellation = CancellationToken::new();
+        let uploads = http.as_ref().map(|http| {
+            UploadManager::new(http.clone(), max_concurrent_uploads)
+                .with_cancellation(cancellation.clone())
+        });
+        let prefetcher = match (&fs, &http) {
+            (Some(fs), Some(http)) => Some(Prefetcher::new(fs.clone(), http.clone())),
+            _ => None,
+        };
+        let pending_uploads = fs.as_ref().map(|fs| {
+            PendingUploads::new(fs.cache_directory().join_component(PENDING_UPLOADS_FILE))
+        });
+        CacheMultiplexer {
+            fs,
+            http,
+            uploads,
+            prefetcher,
+            pending_uploads,
+            namespaces: Vec::new(),
+            analytics: None,
+            timeout: None,
+            cancellation,
+            limits: TransferLimits::default(),
+            read_only: false,
+            offline: AtomicBool::new(false),
+            error_budget: None,
+            remote_errors: AtomicU32::new(0),
+        }
+    }
+
+    /// Whether the remote cache has been found to be unreachable or
+    /// unhealthy
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    // The remote cache, unless we've found it to be unreachable
+    fn remote(&self) -> Option<&Arc<HTTPCache>> {
+        if self.is_offline() {
+            return None;
+        }
+        self.http.as_ref()
+    }
+
+    // Goes offline if `result` failed because the remote cache couldn't be
+    // reached, turning that failure into `None`.
+    fn check_connection<T>(&self, result: Result<T, CacheError>) -> Result<Option<T>, CacheError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_connection_error() => {
+                self.go_offline(&err);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    // Stops using the remote cache for the rest of the invocation, warning
+    // only the first time
+    fn go_offline(&self, reason: impl Display) {
+        if !self.offline.swap(true, Ordering::Relaxed) {
+            warn!(
+                "remote caching disabled for the rest of this run: {}",
+                reason
+            );
+        }
+    }
+
+    // Counts failures that suggest the remote cache is struggling against the
+    // error budget
+    fn record_remote_error(&self, err: &CacheError) {
+        let Some(error_budget) = self.error_budget else {
+            return;
+        };
+        if !(matches!(err, CacheError::Timeout(_)) || err.is_transient()) {
+            return;
+        }
+        let remote_errors = self.remote_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if remote_errors > error_budget {
+            self.go_offline(format_args!(
+                "{} remote cache requests failed, the last with: {}",
+                remote_errors, err
+            ));
+        }
+    }
+
+    /// Probes the remote cache, going offline if it can't be reached or
+    /// caching is disabled for the team. Returns whether the remote cache is
+    /// usable.
+    pub async fn check_health(&self) -> bool {
+        let Some(http) = self.remote() else {
+            return false;
+        };
+        match self.remote_operation(http.health_check()).await {
+            Ok(CachingStatus::Enabled) => true,
+            Ok(status) => {
+                self.go_offline(format_args!("remote caching is {:?}", status));
+                false
+            }
+            Err(CacheError::Cancelled) => false,
+            Err(err) => {
+                self.go_offline(format_args!("health check failed: {}", err));
+                false
+            }
+        }
+    }
+
+    // Runs a remote operation within the transfer limits and the configured
+    // timeout, giving up early if the multiplexer is cancelled. Waiting for
+    // the transfer limits counts against the timeout.
+    async fn remote_operation<T>(
+        &self,
+        operation: impl Future<Output = Result<T, CacheError>>,
+    ) -> Result<T, CacheError> {
+        let operation = async {
+            let _transfer = self.limits.acquire().await;
+            operation.await
+        };
+        let operation = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, operation)
+                    .await
+                    .unwrap_or(Err(CacheError::Timeout(timeout))),
+                None => operation.await,
+            }
+        };
+        let result = tokio::select! {
+            result = operation => result,
+            _ = self.cancellation.cancelled() => Err(CacheError::Cancelled),
+        };
+        if let Err(err) = &result {
+            self.record_remote_error(err);
+        }
+        result
+    }
+
+    fn defer_upload(&self, hash: &str, duration: u64) -> Result<(), CacheError> {
+        if let Some(pending_uploads) = &self.pending_uploads {
+            pending_uploads.add(PendingUpload {
+                hash: hash.to_string(),
+                duration,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn with_namespaces(
+        mut self,
+        primary: CacheNamespace,
+        fallbacks: impl IntoIterator<Item = CacheNamespace>,
+    ) -> Self {
+        self.namespaces = std::iter::once(primary).chain(fallbacks).collect();
+        self
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.uploads = self.uploads.map(|uploads| uploads.with_timeout(timeout));
+        self
+    }
+
+    pub fn with_transfer_limits(mut self, limits: TransferLimits) -> Self {
+        self.uploads = self
+            .uploads
+            .map(|uploads| uploads.with_transfer_limits(limits.clone()));
+        self.prefetcher = self
+            .prefetcher
+            .map(|prefetcher| prefetcher.with_transfer_limits(limits.clone()));
+        self.limits = limits;
+        self
+    }
+
+    /// Goes offline once more than `error_budget` remote operations have
+    /// timed out or failed in a way that is worth retrying
+    pub fn with_error_budget(mut self, error_budget: u32) -> Self {
+        self.error_budget = Some(error_budget);
+        self
+    }
+
+    pub fn with_analytics(mut self, analytics: CacheAnalytics) -> Self {
+        self.analytics = Some(analytics);
+        self
+    }
+
+    // The keys to look `hash` up under, in order of preference. The first key
+    // is the one writes go to.
+    fn keys(&self, hash: &str) -> Vec<String> {
+        if self.namespaces.is_empty() {
+            return vec![hash.to_string()];
+        }
+        self.namespaces
+            .iter()
+            .map(|namespace| namespace.key(hash))
+            .collect()
+    }
+
+    fn primary_key(&self, hash: &str) -> String {
+        match self.namespaces.first() {
+            Some(namespace) => namespace.key(hash),
+            None => hash.to_string(),
+        }
+    }
+
+    #[tracing::instrument(skip(self, body, metadata), fields(bytes = body.len()))]
+    pub async fn put(
+        &self,
+        hash: &str,
+        body: Vec<u8>,
+        metadata: ArtifactMetadata,
+    ) -> Result<CacheWriteStatus, CacheError> {
+        if self.read_only {
+            return Ok(CacheWriteStatus::SkippedReadOnly);
+        }
+
+        let key = self.primary_key(hash);
+        if let Some(fs) = &self.fs {
+            fs.put(&key, &body, &metadata)?;
+        }
+
+        if let Some(uploads) = &self.uploads {
+            if self.is_offline() {
+                self.defer_upload(&key, metadata.duration)?;
+            } else {
+                uploads.queue(&key, body, metadata.duration);
+            }
+        }
+
+        Ok(CacheWriteStatus::Written)
+    }
+
+    /// Looks up `hash` in the local cache and then in the remote cache,
+    /// returning `None` if neither has it.
+    #[tracing::instrument(skip(self), fields(source = field::Empty, bytes = field::Empty))]
+    pub async fn fetch(&self, hash: &str) -> Result<Option<(CacheResponse, Vec<u8>)>, CacheError> {
+        let start = Instant::now();
+        let artifact = self.fetch_artifact(hash).await?;
+        if let Some((response, body)) = &artifact {
+            Span::current()
+                .record("source", field::debug(response.source))
+                .record("bytes", body.len());
+        }
+        if let Some(analytics) = &self.analytics {
+            let (source, bytes) = match &artifact {
+                Some((response, body)) => (Some(response.source), body.len() as u64),
+                None => (None, 0),
+            };
+            // Analytics are best-effort and must never fail a lookup
+            let _ = analytics.record(&CacheEvent::new(hash, source, bytes, start.elapsed()));
+        }
+        Ok(artifact)
+    }
+
+    async fn fetch_artifact(
+        &self,
+        hash: &str,
+    ) -> Result<Option<(CacheResponse, Vec<u8>)>, CacheError> {
+        let keys = self.keys(hash);
+
+        if let Some(prefetcher) = &self.prefetcher {
+            for key in &keys {
+                prefetcher.wait_for(key).await;
+            }
+        }
+
+        if let Some(fs) = &self.fs {
+            for key in &keys {
+                // Reads of a read-only cache must not update access times or
+                // remove corrupt artifacts
+                let artifact = if self.read_only {
+                    fs.read(key)?
+                } else {
+                    fs.fetch(key)?
+                };
+                if let Some((body, metadata)) = artifact {
+                    let response = CacheResponse {
+                        source: CacheSource::Local,
+                        time_saved: metadata.artifact.duration,
+                    };
+                    return Ok(Some((response, body)));
+                }
+            }
+        }
+
+        if let Some(http) = self.remote() {
+            for key in &keys {
+                let artifact = match &self.fs {
+                    // Resumable downloads stage partial artifacts in the local
+                    // cache, which a read-only cache must not write to
+                    Some(fs) if !self.read_only => {
+                        let staging_directory = fs.staging_directory()?;
+                        self.remote_operation(http.fetch_resumable(key, staging_directory))
+                            .await
+                    }
+                    _ => self.remote_operation(http.fetch(key)).await,
+                };
+                // A corrupt or slow remote artifact must not hold up the task,
+                // which can still be found in another namespace or rerun
+                let artifact = match artifact {
+                    Err(CacheError::DigestMismatch(_) | CacheError::Timeout(_)) => Ok(None),
+                    artifact => artifact,
+                };
+                let Some(artifact) = self.check_connection(artifact)? else {
+                    break;
+                };
+                if let Some((body, duration)) = artifact {
+                    if let (Some(fs), false) = (&self.fs, self.read_only) {
+                        fs.put(key, &body, &ArtifactMetadata::from_duration(duration))?;
+                    }
+                    let response = CacheResponse {
+                        source: CacheSource::Remote,
+                        time_saved: duration,
+                    };
+                    return Ok(Some((response, body)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Starts downloading the remote artifacts for `hashes` into the local
+    /// cache in the background, so that later lookups for them are local
+    /// hits. Does nothing unless both a local and a remote cache are
+    /// configured.
+    pub fn prefetch(&self, hashes: impl IntoIterator<Item = String>) {
+        if self.is_offline() || self.read_only {
+            return;
+        }
+        if let Some(prefetcher) = &self.prefetcher {
+            prefetcher.prefetch(hashes.into_iter().flat_map(|hash| self.keys(&hash)));
+        }
+    }
+
+    /// Reads the metadata recorded for `hash` in the local cache. Artifacts
+    /// downloaded from the remote cache only record their duration.
+    pub fn metadata(&self, hash: &str) -> Result<Option<ArtifactMetadata>, CacheError> {
+        let Some(fs) = &self.fs else {
+            return Ok(None);
+        };
+        for key in self.keys(hash) {
+            if let Some(metadata) = fs.read_metadata(&key)? {
+                return Ok(Some(metadata.artifact));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reports where the artifact for `hash` is available without downloading
+    /// it, preferring the local cache.
+    #[tracing::instrument(skip(self))]
+    pub async fn exists(&self, hash: &str) -> Result<Option<CacheSource>, CacheError> {
+        let keys = self.keys(hash);
+
+        if let Some(fs) = &self.fs {
+            for key in &keys {
+                if fs.exists(key)? {
+                    return Ok(Some(CacheSource::Local));
+                }
+            }
+        }
+
+        if let Some(http) = self.remote() {
+            for key in &keys {
+                let exists = match self.remote_operation(http.exists(key)).await {
+                    Err(CacheError::Timeout(_)) => Ok(false),
+                    exists => exists,
+                };
+                match self.check_connection(exists)? {
+                    Some(true) => return Ok(Some(CacheSource::Remote)),
+                    Some(false) => {}
+                    None => break,
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Checks the availability of every hash in `hashes` concurrently, so a
+    /// run can plan execution before any task starts. Results are returned in
+    /// the same order as `hashes`.
+    pub async fn exists_all(
+        &self,
+        hashes: &[impl AsRef<str>],
+    ) -> Vec<Result<Option<CacheSource>, CacheError>> {
+        stream::iter(hashes)
+            .map(|hash| self.exists(hash.as_ref()))
+            .buffered(MAX_CONCURRENT_EXISTS_CHECKS)
+            .collect()
+            .await
+    }
+
+    /// Deletes the artifact for `hash` from the local and remote caches, e.g.
+    /// to purge an artifact produced by a buggy toolchain. Only the primary
+    /// namespace is affected, the fallback namespaces belong to other branches
+    /// or configurations.
+    #[tracing::instrument(skip(self))]
+    pub async fn delete(&self, hash: &str) -> Result<(), CacheError> {
+        if self.read_only {
+            return Err(CacheError::ReadOnly);
+        }
+        let key = self.primary_key(hash);
+        if let Some(fs) = &self.fs {
+            fs.remove(&key)?;
+        }
+        if let Some(pending_uploads) = &self.pending_uploads {
+            pending_uploads.remove(std::slice::from_ref(&key))?;
+        }
+        if let Some(http) = &self.http {
+            self.remote_operation(http.delete(&key)).await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes every artifact in `namespace` from the local and remote caches
+    #[tracing::instrument(skip(self), fields(namespace = namespace.name()))]
+    pub async fn invalidate_prefix(&self, namespace: &CacheNamespace) -> Result<(), CacheError> {
+        if self.read_only {
+            return Err(CacheError::ReadOnly);
+        }
+        let prefix = namespace.key_prefix();
+        if let Some(fs) = &self.fs {
+            fs.remove_prefix(&prefix)?;
+        }
+        if let Some(pending_uploads) = &self.pending_uploads {
+            let invalidated: Vec<_> = pending_uploads
+                .list()?
+                .into_iter()
+                .map(|upload| upload.hash)
+                .filter(|hash| hash.starts_with(&prefix))
+                .collect();
+            pending_uploads.remove(&invalidated)?;
+        }
+        if let Some(http) = &self.http {
+            self.remote_operation(http.delete_prefix(&prefix)).await?;
+        }
+        Ok(())
+    }
+
+    /// Stops all remote work: in-flight uploads and prefetches are abandoned
+    /// and later remote operations fail with `CacheError::Cancelled`.
+    /// Artifacts that were only partially written to the local cache are
+    /// removed. Call `wait` afterwards to queue the abandoned uploads for a
+    /// later invocation.
+    pub fn cancel(&self) -> Result<(), CacheError> {
+        self.cancellation.cancel();
+        if let Some(prefetcher) = &self.prefetcher {
+            prefetcher.cancel();
+        }
+        if let Some(fs) = &self.fs {
+            fs.remove_temporary_files()?;
+        }
+        Ok(())
+    }
+
+    /// Waits for all background uploads started so far and returns the
+    /// outcome of each one. Uploads that failed because the remote cache
+    /// couldn't be reached, or that were cancelled, are queued for a later
+    /// `flush`.
+    #[tracing::instrument(skip(self))]
+    pub async fn wait(&self) -> Vec<UploadResult> {
+        let Some(uploads) = &self.uploads else {
+            return Vec::new();
+        };
+        let results = uploads.finish().await;
+
+        let mut uploaded = Vec::new();
+        for upload in &results {
+            match &upload.result {
+                Ok(()) => uploaded.push(upload.hash.clone()),
+                Err(err) if err.is_connection_error() || matches!(err, CacheError::Cancelled) => {
+                    if err.is_connection_error() {
+                        self.go_offline(err);
+                    }
+                    let duration = self
+                        .fs
+                        .as_ref()
+                        .and_then(|fs| fs.read_metadata(&upload.hash).ok().flatten())
+                        .map(|metadata| metadata.artifact.duration)
+                        .unwrap_or_default();
+                    // Failing to record the upload only means it won't be
+                    // retried later, which is no worse than not being
+                    // offline-aware at all, so it isn't worth failing for.
+                    let _ = self.defer_upload(&upload.hash, duration);
+                }
+                Err(_) => {}
+            }
+        }
+        if let Some(pending_uploads) = &self.pending_uploads {
+            let _ = pending_uploads.remove(&uploaded);
+        }
+
+        results
+    }
+
+    /// Uploads the artifacts that were queued while the remote cache was
+    /// unreachable, returning the outcome of each upload. Uploads that fail
+    /// to reach the remote cache again stay queued.
+    #[tracing::instrument(skip(self))]
+    pub async fn flush(&self) -> Result<Vec<UploadResult>, CacheError> {
+        if self.read_only {
+            return Err(CacheError::ReadOnly);
+        }
+        let (Some(fs), Some(uploads), Some(pending_uploads)) =
+            (&self.fs, &self.uploads, &self.pending_uploads)
+        else {
+            return Ok(Vec::new());
+        };
+
+        self.offline.store(false, Ordering::Relaxed);
+        let mut evicted = Vec::new();
+        for upload in pending_uploads.list()? {
+            match fs.fetch(&upload.hash)? {
+                Some((body, _)) => uploads.queue(&upload.hash, body, upload.duration),
+                // The artifact was evicted from the local cache since, so
+                // there is nothing left to upload
+                None => evicted.push(upload.hash),
+            }
+        }
+        pending_uploads.remove(&evicted)?;
+
+        Ok(self.wait().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use turbopath::AbsoluteSystemPathBuf;
+    use turborepo_api_client::APIClient;
+    use vercel_api_mock::start_test_server;
+
+    use super::*;
+
+    fn new_http_cache(port: u16) -> HTTPCache {
+        let client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0").unwrap();
+        HTTPCache::new(
+            client,
+            vercel_api_mock::EXPECTED_TOKEN.to_string(),
+            vercel_api_mock::EXPECTED_TEAM_ID.to_string(),
+            None,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_remote_hits_populate_local_cache() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let producer_dir = tempdir()?;
+        let producer = CacheMultiplexer::new(
+            Some(FSCache::new(
+                AbsoluteSystemPathBuf::new(producer_dir.path())?,
+                None,
+            )?),
+            Some(new_http_cache(port)),
+            2,
+        );
+        for hash in ["one", "two", "three"] {
+            producer
+                .put(
+                    hash,
+                    hash.as_bytes().to_vec(),
+                    ArtifactMetadata::new(10, "1.10.0", None),
+                )
+                .await?;
+        }
+        assert!(producer
+            .wait()
+            .await
+            .iter()
+            .all(|upload| upload.result.is_ok()));
+
+        let local_only = CacheMultiplexer::new(
+            Some(FSCache::new(
+                AbsoluteSystemPathBuf::new(producer_dir.path())?,
+                None,
+            )?),
+            None,
+            2,
+        );
+        let availability = local_only.exists_all(&["one", "four"]).await;
+        assert!(matches!(availability[0], Ok(Some(CacheSource::Local))));
+        assert!(matches!(availability[1], Ok(None)));
+
+        let consumer_dir = tempdir()?;
+        let consumer = CacheMultiplexer::new(
+            Some(FSCache::new(
+                AbsoluteSystemPathBuf::new(consumer_dir.path())?,
+                None,
+            )?),
+            Some(new_http_cache(port)),
+            2,
+        );
+
+        assert_eq!(consumer.exists("two").await?, Some(CacheSource::Remote));
+        let (response, body) = consumer.fetch("two").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Remote);
+        assert_eq!(response.time_saved, 10);
+        assert_eq!(body, b"two");
+
+        let (response, body) = consumer.fetch("two").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Local);
+        assert_eq!(body, b"two");
+        assert_eq!(consumer.exists("two").await?, Some(CacheSource::Local));
+        assert_eq!(
+            consumer.metadata("two")?,
+            Some(ArtifactMetadata::from_duration(10))
+        );
+        assert_eq!(
+            producer.metadata("two")?,
+            Some(ArtifactMetadata::new(10, "1.10.0", None))
+        );
+
+        // Prefetched artifacts are local hits
+        consumer.prefetch(["three".to_string()]);
+        let (response, body) = consumer.fetch("three").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Local);
+        assert_eq!(body, b"three");
+
+        assert!(consumer.fetch("four").await?.is_none());
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_namespace_fallback() -> Result<()> {
+        let dir = tempdir()?;
+        let new_cache = |primary: &str, fallbacks: &[&str]| -> Result<CacheMultiplexer> {
+            let fs = FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?;
+            Ok(CacheMultiplexer::new(Some(fs), None, 1).with_namespaces(
+                CacheNamespace::new(primary)?,
+                fallbacks
+                    .iter()
+                    .map(|name| CacheNamespace::new(*name))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ))
+        };
+
+        let main = new_cache("main", &[])?;
+        main.put("abc", b"from main".to_vec(), ArtifactMetadata::default())
+            .await?;
+
+        // A branch without its own artifact reads from main...
+        let branch = new_cache("feature/x", &["main"])?;
+        let (_, body) = branch.fetch("abc").await?.unwrap();
+        assert_eq!(body, b"from main");
+
+        // ...but writes only to its own namespace, which then takes precedence
+        branch
+            .put("abc", b"from branch".to_vec(), ArtifactMetadata::default())
+            .await?;
+        let (_, body) = branch.fetch("abc").await?.unwrap();
+        assert_eq!(body, b"from branch");
+        let (_, body) = main.fetch("abc").await?.unwrap();
+        assert_eq!(body, b"from main");
+
+        // Without a fallback, other namespaces are invisible
+        let isolated = new_cache("other", &[])?;
+        assert!(isolated.fetch("abc").await?.is_none());
+        assert_eq!(isolated.exists("abc").await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel() -> Result<()> {
+        // Nothing is listening on the port, so uploads keep retrying until
+        // they're cancelled
+        let port = port_scanner::request_open_port().unwrap();
+        let dir = tempdir()?;
+        let fs = FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?;
+        let staging_directory = fs.staging_directory()?;
+        let cache = CacheMultiplexer::new(Some(fs), Some(new_http_cache(port)), 2);
+
+        cache
+            .put("one", b"one".to_vec(), ArtifactMetadata::from_duration(3))
+            .await?;
+        // Left behind by a write that was interrupted
+        let temporary_path = staging_directory.join_component("two.tmp");
+        std::fs::write(temporary_path.as_path(), b"tw")?;
+
+        cache.cancel()?;
+        assert!(!temporary_path.exists());
+        let uploads = cache.wait().await;
+        assert_eq!(uploads.len(), 1);
+        assert!(matches!(uploads[0].result, Err(CacheError::Cancelled)));
+        assert!(matches!(
+            cache.exists("missing").await,
+            Err(CacheError::Cancelled)
+        ));
+
+        // The abandoned upload is picked up by the next invocation
+        let pending = PendingUploads::new(
+            AbsoluteSystemPathBuf::new(dir.path())?.join_component(PENDING_UPLOADS_FILE),
+        );
+        assert_eq!(
+            pending.list()?,
+            [PendingUpload {
+                hash: "one".to_string(),
+                duration: 3,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remote_timeout_is_a_miss() -> Result<()> {
+        // Connections to the listener succeed, but requests never get a
+        // response
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        let cache = CacheMultiplexer::new(None, Some(new_http_cache(port)), 1)
+            .with_timeout(Duration::from_millis(100));
+        assert!(cache.fetch("abc").await?.is_none());
+        assert_eq!(cache.exists("abc").await?, None);
+        assert!(!cache.is_offline());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_error_budget() -> Result<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        let cache = CacheMultiplexer::new(None, Some(new_http_cache(port)), 1)
+            .with_timeout(Duration::from_millis(100))
+            .with_error_budget(2);
+        for _ in 0..2 {
+            assert!(cache.fetch("abc").await?.is_none());
+            assert!(!cache.is_offline());
+        }
+        assert!(cache.fetch("abc").await?.is_none());
+        assert!(cache.is_offline());
+
+        // Later lookups don't wait on the remote cache at all
+        let start = Instant::now();
+        assert_eq!(cache.exists("abc").await?, None);
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_health() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let healthy = CacheMultiplexer::new(None, Some(new_http_cache(port)), 1);
+        let handle = tokio::spawn(start_test_server(port));
+        assert!(healthy.check_health().await);
+        assert!(!healthy.is_offline());
+        handle.abort();
+        let _ = handle.await;
+
+        let unreachable = CacheMultiplexer::new(None, Some(new_http_cache(port)), 1);
+        assert!(!unreachable.check_health().await);
+        assert!(unreachable.is_offline());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_records_lookups() -> Result<()> {
+        let dir = tempdir()?;
+        let analytics_dir = tempdir()?;
+        let cache = CacheMultiplexer::new(
+            Some(FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?),
+            None,
+            1,
+        )
+        .with_analytics(CacheAnalytics::new(
+            AbsoluteSystemPathBuf::new(analytics_dir.path())?,
+            1024 * 1024,
+        )?);
+
+        cache
+            .put("abc", b"body".to_vec(), ArtifactMetadata::default())
+            .await?;
+        cache.fetch("abc").await?;
+        cache.fetch("missing").await?;
+
+        let events =
+            CacheAnalytics::new(AbsoluteSystemPathBuf::new(analytics_dir.path())?, 0)?.events()?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].hash, "abc");
+        assert_eq!(events[0].source, Some(CacheSource::Local));
+        assert_eq!(events[0].bytes, 4);
+        assert_eq!(events[1].hash, "missing");
+        assert!(!events[1].is_hit());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_invalidate() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let dir = tempdir()?;
+        let new_cache = |primary: &str, fallbacks: &[&str]| -> Result<CacheMultiplexer> {
+            let fs = FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?;
+            Ok(
+                CacheMultiplexer::new(Some(fs), Some(new_http_cache(port)), 2).with_namespaces(
+                    CacheNamespace::new(primary)?,
+                    fallbacks
+                        .iter()
+                        .map(|name| CacheNamespace::new(*name))
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+            )
+        };
+
+        let main = new_cache("main", &[])?;
+        let branch = new_cache("branch", &["main"])?;
+        for cache in [&main, &branch] {
+            for hash in ["one", "two"] {
+                cache
+                    .put(hash, hash.as_bytes().to_vec(), ArtifactMetadata::default())
+                    .await?;
+            }
+            assert!(cache
+                .wait()
+                .await
+                .iter()
+                .all(|upload| upload.result.is_ok()));
+        }
+
+        // Deleting from the branch leaves the artifact of main alone, which
+        // the branch falls back to
+        branch.delete("one").await?;
+        let remote = new_http_cache(port);
+        assert!(remote.fetch("branch-one").await?.is_none());
+        assert!(main.fetch("one").await?.is_some());
+        assert!(branch.fetch("one").await?.is_some());
+        main.delete("one").await?;
+        assert!(branch.fetch("one").await?.is_none());
+
+        branch
+            .invalidate_prefix(&CacheNamespace::new("branch")?)
+            .await?;
+        assert!(remote.fetch("branch-two").await?.is_none());
+        assert!(remote.fetch("main-two").await?.is_some());
+        let (response, _) = branch.fetch("two").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Local);
+        assert_eq!(branch.metadata("two")?, main.metadata("two")?);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_only() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let remote = new_http_cache(port);
+        remote.put("remote", b"remote", 0).await?;
+
+        let dir = tempdir()?;
+        let cache = CacheMultiplexer::new(
+            Some(FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?),
+            Some(new_http_cache(port)),
+            2,
+        )
+        .with_read_only(true);
+
+        assert_eq!(
+            cache
+                .put("abc", b"abc".to_vec(), ArtifactMetadata::default())
+                .await?,
+            CacheWriteStatus::SkippedReadOnly
+        );
+        assert!(cache.wait().await.is_empty());
+        assert!(cache.fetch("abc").await?.is_none());
+        assert!(remote.fetch("abc").await?.is_none());
+
+        // Remote hits are served but neither copied nor staged in the local
+        // cache
+        let (response, _) = cache.fetch("remote").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Remote);
+        assert_eq!(cache.exists("remote").await?, Some(CacheSource::Remote));
+        assert!(!dir.path().join(".staging").exists());
+
+        // Local hits don't update the access time
+        let fs = FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?;
+        fs.put("local", b"local", &ArtifactMetadata::default())?;
+        let written = fs.read_metadata("local")?;
+        let (response, _) = cache.fetch("local").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Local);
+        assert_eq!(fs.read_metadata("local")?, written);
+
+        assert!(matches!(
+            cache.delete("remote").await,
+            Err(CacheError::ReadOnly)
+        ));
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_offline_uploads_are_flushed_later() -> Result<()> {
+        // Nothing is listening on the port yet, so the remote cache is
+        // unreachable
+        let port = port_scanner::request_open_port().unwrap();
+        let dir = tempdir()?;
+        let new_cache = || -> Result<CacheMultiplexer> {
+            let fs = FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?;
+            Ok(CacheMultiplexer::new(
+                Some(fs),
+                Some(new_http_cache(port)),
+                2,
+            ))
+        };
+
+        let offline = new_cache()?;
+        assert!(offline.fetch("one").await?.is_none());
+        assert!(offline.is_offline());
+        offline
+            .put("one", b"one".to_vec(), ArtifactMetadata::from_duration(7))
+            .await?;
+        assert!(offline.wait().await.is_empty());
+        let (response, _) = offline.fetch("one").await?.unwrap();
+        assert_eq!(response.source, CacheSource::Local);
+
+        let handle = tokio::spawn(start_test_server(port));
+        let online = new_cache()?;
+        let uploads = online.flush().await?;
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].hash, "one");
+        assert!(uploads[0].result.is_ok());
+        assert!(!online.is_offline());
+        assert!(online.flush().await?.is_empty());
+
+        let remote = new_http_cache(port);
+        assert_eq!(remote.fetch("one").await?, Some((b"one".to_vec(), 7)));
+
+        handle.abort();
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Write;
+
+use crate::CacheError;
+
+/// A scope for cache artifacts, such as a branch or a team.
+///
+/// Artifacts stored under one namespace are invisible to lookups in another,
+/// which is done by prefixing the artifact hash with the namespace when
+/// building cache keys. Namespace names can be arbitrary strings (e.g. branch
+/// names containing `/`), so they are encoded into characters that are safe
+/// to use in file names and URLs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheNamespace {
+    name: String,
+    encoded: String,
+}
+
+impl CacheNamespace {
+    pub fn new(name: impl Into<String>) -> Result<Self, CacheError> {
+        let name = name.into();
+        if name.is_empty() {
+            return Err(CacheError::InvalidNamespace(name));
+        }
+
+        // Alphanumerics are kept as-is and every other byte is written as
+        // `_<hex>`. In particular `-` is always encoded, so the first `-` in a
+        // key separates the namespace from the hash.
+        let mut encoded = String::with_capacity(name.len());
+        for byte in name.bytes() {
+            if byte.is_ascii_alphanumeric() {
+                encoded.push(byte as char);
+            } else {
+                write!(encoded, "_{:02x}", byte).expect("writing to a String can't fail");
+            }
+        }
+
+        Ok(CacheNamespace { name, encoded })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The cache key for `hash` within this namespace
+    pub fn key(&self, hash: &str) -> String {
+        format!("{}{}", self.key_prefix(), hash)
+    }
+
+    /// The prefix shared by every cache key within this namespace
+    pub fn key_prefix(&self) -> String {
+        format!("{}-", self.encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate_hash;
+
+    #[test]
+    fn test_namespace_keys() {
+        let main = CacheNamespace::new("main").unwrap();
+        assert_eq!(main.key("abc123"), "main-abc123");
+
+        let branch = CacheNamespace::new("feature/new-thing").unwrap();
+        assert_eq!(branch.name(), "feature/new-thing");
+        assert_eq!(branch.key("abc123"), "feature_2fnew_2dthing-abc123");
+
+        // Names that differ only in punctuation don't collide
+        let other = CacheNamespace::new("feature-new-thing").unwrap();
+        assert_ne!(branch.key("abc123"), other.key("abc123"));
+
+        for namespace in [main, branch, other] {
+            assert!(validate_hash(&namespace.key("abc123")).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_empty_namespace() {
+        assert!(matches!(
+            CacheNamespace::new(""),
+            Err(CacheError::InvalidNamespace(_))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fs, io, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use turbopath::AbsoluteSystemPathBuf;
+
+use crate::CacheError;
+
+/// An artifact that is in the local cache but still has to be uploaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingUpload {
+    pub hash: String,
+    /// Time it took to produce the artifact, in milliseconds
+    pub duration: u64,
+}
+
+/// A queue of uploads that couldn't be made because the remote cache was
+/// unreachable, persisted as JSON so that a later invocation can drain it.
+/// The artifacts themselves stay in the local cache.
+#[derive(Debug)]
+pub struct PendingUploads {
+    path: AbsoluteSystemPathBuf,
+    // Serializes read-modify-write cycles of the queue file
+    lock: Mutex<()>,
+}
+
+impl PendingUploads {
+    pub fn new(path: AbsoluteSystemPathBuf) -> Self {
+        PendingUploads {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<PendingUpload>, CacheError> {
+        let _guard = self.lock.lock().unwrap();
+        self.read()
+    }
+
+    /// Adds `upload` to the queue, replacing any queued upload with the same
+    /// hash
+    pub fn add(&self, upload: PendingUpload) -> Result<(), CacheError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut uploads = self.read()?;
+        uploads.retain(|pending| pending.hash != upload.hash);
+        uploads.push(upload);
+        self.write(&uploads)
+    }
+
+    pub fn remove(&self, hashes: &[String]) -> Result<(), CacheError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut uploads = self.read()?;
+        uploads.retain(|pending| !hashes.contains(&pending.hash));
+        self.write(&uploads)
+    }
+
+    fn read(&self) -> Result<Vec<PendingUpload>, CacheError> {
+        match fs::read(self.path.as_path()) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self, uploads: &[PendingUpload]) -> Result<(), CacheError> {
+        if uploads.is_empty() {
+            return match self.path.remove() {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(err.into()),
+            };
+        }
+        fs::write(self.path.as_path(), serde_json::to_vec(uploads)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_pending_uploads() -> Result<()> {
+        let dir = tempdir()?;
+        let path = AbsoluteSystemPathBuf::new(dir.path().join("pending.json"))?;
+        let pending = PendingUploads::new(path.clone());
+        assert!(pending.list()?.is_empty());
+
+        for (hash, duration) in [("one", 1), ("two", 2), ("one", 3)] {
+            pending.add(PendingUpload {
+                hash: hash.to_string(),
+                duration,
+            })?;
+        }
+
+        // The queue survives across instances
+        let reopened = PendingUploads::new(path.clone());
+        let hashes: Vec<_> = reopened
+            .list()?
+            .into_iter()
+            .map(|upload| (upload.hash, upload.duration))
+            .collect();
+        assert_eq!(hashes, [("two".to_string(), 2), ("one".to_string(), 3)]);
+
+        reopened.remove(&["one".to_string(), "two".to_string()])?;
+        assert!(reopened.list()?.is_empty());
+        assert!(!path.exists());
+
+        Ok(())
+    }
+}
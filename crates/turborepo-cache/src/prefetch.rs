@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures::FutureExt;
+use tokio::{
+    sync::{OwnedMutexGuard, Semaphore},
+    task::JoinSet,
+};
+use tracing::warn;
+
+use crate::{fs_cache::FSCache, http::HTTPCache, rate_limit::TransferLimits, ArtifactMetadata};
+
+const MAX_CONCURRENT_PREFETCHES: usize = 8;
+
+type InFlight = Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>;
+
+/// Speculatively downloads remote artifacts into the local cache.
+///
+/// Given the hashes of tasks that are about to run, the prefetcher starts
+/// downloading their artifacts while earlier tasks are still executing, so
+/// that by the time a task looks up its artifact it is a local hit. Failures
+/// are ignored: the task's own lookup will simply go to the remote cache.
+pub struct Prefetcher {
+    fs: Arc<FSCache>,
+    http: Arc<HTTPCache>,
+    download_permits: Arc<Semaphore>,
+    limits: TransferLimits,
+    // Each prefetch holds the lock for its hash until the download finishes,
+    // letting lookups wait for an in-flight download instead of starting a
+    // second one.
+    in_flight: InFlight,
+    downloads: Mutex<JoinSet<()>>,
+}
+
+impl Prefetcher {
+    pub fn new(fs: Arc<FSCache>, http: Arc<HTTPCache>) -> Self {
+        Prefetcher {
+            fs,
+            http,
+            download_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_PREFETCHES)),
+            limits: TransferLimits::default(),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            downloads: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    pub fn with_transfer_limits(mut self, limits: TransferLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Starts downloading the artifacts for `hashes` in the background.
+    /// Hashes that are already local or already being prefetched are skipped.
+    /// Must be called from within a tokio runtime.
+    pub fn prefetch(&self, hashes: impl IntoIterator<Item = String>) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let mut downloads = self.downloads.lock().unwrap();
+        reap_finished(&mut downloads);
+        for hash in hashes {
+            if in_flight.contains_key(&hash) || matches!(self.fs.exists(&hash), Ok(true)) {
+                continue;
+            }
+
+            let lock = Arc::new(tokio::sync::Mutex::new(()));
+            let guard = lock
+                .clone()
+                .try_lock_owned()
+                .expect("newly created lock is unlocked");
+            in_flight.insert(hash.clone(), lock.clone());
+            let prefetch = InFlightPrefetch {
+                in_flight: self.in_flight.clone(),
+                hash: hash.clone(),
+                lock,
+                _guard: guard,
+            };
+
+            let fs = self.fs.clone();
+            let http = self.http.clone();
+            let download_permits = self.download_permits.clone();
+            let limits = self.limits.clone();
+            downloads.spawn(async move {
+                let _prefetch = prefetch;
+                let _permit = download_permits
+                    .acquire_owned()
+                    .await
+                    .expect("download semaphore is never closed");
+                let Ok(staging_directory) = fs.staging_directory() else {
+                    return;
+                };
+                let _transfer = limits.acquire().await;
+                if let Ok(Some((body, duration))) =
+                    http.fetch_resumable(&hash, &staging_directory).await
+                {
+                    let _ = fs.put(&hash, &body, &ArtifactMetadata::from_duration(duration));
+                }
+            });
+        }
+    }
+
+    /// Aborts every prefetch that is still running. Their partial downloads
+    /// are kept, so a later prefetch or lookup resumes them.
+    pub fn cancel(&self) {
+        self.downloads.lock().unwrap().abort_all();
+    }
+
+    /// Waits for an in-flight prefetch of `hash`, if there is one
+    pub async fn wait_for(&self, hash: &str) {
+        let lock = self.in_flight.lock().unwrap().get(hash).cloned();
+        if let Some(lock) = lock {
+            let _ = lock.lock().await;
+        }
+    }
+}
+
+/// Joins the prefetches that already finished, so that `downloads` doesn't
+/// keep growing over a long run. Polls `join_next` without waiting, as our
+/// tokio version has no `try_join_next`.
+fn reap_finished(downloads: &mut JoinSet<()>) {
+    while let Some(Some(result)) = downloads.join_next().now_or_never() {
+        if let Err(err) = result {
+            // Cancelled prefetches are expected, see `Prefetcher::cancel`
+            if err.is_panic() {
+                warn!("prefetch failed: {}", err);
+            }
+        }
+    }
+}
+
+/// Removes a prefetch from `in_flight` once it finished, failed or was
+/// aborted, so that a later prefetch of the same hash can retry it
+struct InFlightPrefetch {
+    in_flight: InFlight,
+    hash: String,
+    lock: Arc<tokio::sync::Mutex<()>>,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for InFlightPrefetch {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if matches!(in_flight.get(&self.hash), Some(lock) if Arc::ptr_eq(lock, &self.lock)) {
+            in_flight.remove(&self.hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use turbopath::AbsoluteSystemPathBuf;
+    use turborepo_api_client::APIClient;
+    use vercel_api_mock::start_test_server;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prefetch_populates_local_cache() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0")?;
+        let http = Arc::new(HTTPCache::new(
+            client,
+            vercel_api_mock::EXPECTED_TOKEN.to_string(),
+            vercel_api_mock::EXPECTED_TEAM_ID.to_string(),
+            None,
+            None,
+        ));
+        http.put("one", b"one", 5).await?;
+        http.put("two", b"two", 5).await?;
+
+        let dir = tempdir()?;
+        let fs = Arc::new(FSCache::new(AbsoluteSystemPathBuf::new(dir.path())?, None)?);
+        let prefetcher = Prefetcher::new(fs.clone(), http.clone());
+
+        prefetcher.prefetch(["one", "two", "missing"].map(String::from));
+        for hash in ["one", "two", "missing"] {
+            prefetcher.wait_for(hash).await;
+        }
+
+        assert_eq!(fs.fetch("one")?.unwrap().0, b"one");
+        assert_eq!(fs.fetch("two")?.unwrap().1.artifact.duration, 5);
+        assert!(!fs.exists("missing")?);
+        assert!(prefetcher.in_flight.lock().unwrap().is_empty());
+
+        // A failed prefetch is retried once the artifact is available
+        http.put("missing", b"missing", 5).await?;
+        prefetcher.prefetch(["missing".to_string()]);
+        prefetcher.wait_for("missing").await;
+        assert_eq!(fs.fetch("missing")?.unwrap().0, b"missing");
+
+        // Finished prefetches are joined before new ones are started
+        prefetcher.prefetch(["one".to_string()]);
+        assert!(prefetcher.downloads.lock().unwrap().is_empty());
+
+        handle.abort();
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+/// A token bucket limiting how often requests are made to the remote cache.
+///
+/// Up to `burst` requests can be made back to back, after which requests are
+/// spread out to `requests_per_second`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// `requests_per_second` must be positive.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        assert!(
+            requests_per_second > 0.0,
+            "rate limit must allow some requests"
+        );
+        let burst = f64::from(burst.max(1));
+        RateLimiter {
+            requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request may be made
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let refilled = (now - bucket.last_refill).as_secs_f64() * self.requests_per_second;
+                bucket.tokens = (bucket.tokens + refilled).min(self.burst);
+                bucket.last_refill = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.requests_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Limits on remote cache traffic, shared by everything that talks to the
+/// remote cache so that large CI fan-outs stay within what the cache provider
+/// allows.
+#[derive(Debug, Clone, Default)]
+pub struct TransferLimits {
+    rate_limiter: Option<Arc<RateLimiter>>,
+    transfer_permits: Option<Arc<Semaphore>>,
+}
+
+impl TransferLimits {
+    pub fn with_rate_limit(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+        self
+    }
+
+    pub fn with_max_concurrent_transfers(mut self, max_concurrent_transfers: usize) -> Self {
+        self.transfer_permits = Some(Arc::new(Semaphore::new(max_concurrent_transfers.max(1))));
+        self
+    }
+
+    /// Waits until a request may be made. The request counts against the
+    /// concurrent transfer budget until the returned permit is dropped.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = match &self.transfer_permits {
+            Some(transfer_permits) => Some(
+                transfer_permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("transfer semaphore is never closed"),
+            ),
+            None => None,
+        };
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let rate_limiter = RateLimiter::new(50.0, 2);
+
+        // The burst is available right away...
+        let start = Instant::now();
+        rate_limiter.acquire().await;
+        rate_limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(20));
+
+        // ...after which requests are spaced out
+        let start = Instant::now();
+        for _ in 0..3 {
+            rate_limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(55));
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_transfers() {
+        let limits = TransferLimits::default().with_max_concurrent_transfers(1);
+        let permit = limits.acquire().await;
+
+        let waiting = tokio::spawn({
+            let limits = limits.clone();
+            async move { limits.acquire().await.is_some() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(permit);
+        assert!(waiting.await.unwrap());
+    }
+}
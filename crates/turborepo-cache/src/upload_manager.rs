@@ -0,0 +1,237 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rand::Rng;
+use tokio::{sync::Semaphore, task::JoinSet};
+use tokio_util::sync::CancellationToken;
+use tracing::{field, Instrument, Span};
+
+use crate::{http::HTTPCache, rate_limit::TransferLimits, CacheError};
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// The outcome of uploading a single artifact
+#[derive(Debug)]
+pub struct UploadResult {
+    pub hash: String,
+    /// Number of upload attempts made, including the first one
+    pub attempts: u32,
+    pub result: Result<(), CacheError>,
+}
+
+/// Uploads the artifacts produced during a run to the remote cache in the
+/// background.
+///
+/// At most `max_concurrent_uploads` uploads run at once, and uploads that fail
+/// with a transient error (timeouts, connection failures, 429s and 5xxs) are
+/// retried with an exponential backoff. `finish` reports what happened to each
+/// artifact so the run summary can show which tasks were persisted remotely.
+///
+/// Each upload, including its retries, can be bounded with `with_timeout`, and
+/// every attempt counts against the `TransferLimits` from
+/// `with_transfer_limits`.
+/// Cancelling the token passed to `with_cancellation` stops every upload that
+/// hasn't finished yet.
+pub struct UploadManager {
+    http: Arc<HTTPCache>,
+    upload_permits: Arc<Semaphore>,
+    max_retries: u32,
+    timeout: Option<Duration>,
+    cancellation: CancellationToken,
+    limits: TransferLimits,
+    uploads: Mutex<JoinSet<UploadResult>>,
+}
+
+impl UploadManager {
+    pub fn new(http: Arc<HTTPCache>, max_concurrent_uploads: usize) -> Self {
+        Self::with_max_retries(http, max_concurrent_uploads, DEFAULT_MAX_RETRIES)
+    }
+
+    pub fn with_max_retries(
+        http: Arc<HTTPCache>,
+        max_concurrent_uploads: usize,
+        max_retries: u32,
+    ) -> Self {
+        UploadManager {
+            http,
+            upload_permits: Arc::new(Semaphore::new(max_concurrent_uploads.max(1))),
+            max_retries,
+            timeout: None,
+            cancellation: CancellationToken::new(),
+            limits: TransferLimits::default(),
+            uploads: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn with_transfer_limits(mut self, limits: TransferLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Queues `body` for upload. Must be called from within a tokio runtime.
+    pub fn queue(&self, hash: &str, body: Vec<u8>, duration: u64) {
+        let http = self.http.clone();
+        let upload_permits = self.upload_permits.clone();
+        let max_retries = self.max_retries;
+        let timeout = self.timeout;
+        let cancellation = self.cancellation.clone();
+        let limits = self.limits.clone();
+        let span = tracing::info_span!("upload", hash, bytes = body.len(), attempts = field::Empty);
+        let hash = hash.to_string();
+        self.uploads.lock().unwrap().spawn(
+            async move {
+                let mut attempts = 0;
+                let result = {
+                    let upload = async {
+                        let _permit = upload_permits
+                            .acquire_owned()
+                            .await
+                            .expect("upload semaphore is never closed");
+                        loop {
+                            attempts += 1;
+                            let transfer = limits.acquire().await;
+                            let result = http.put(&hash, &body, duration).await;
+                            drop(transfer);
+                            match result {
+                                Err(err) if err.is_transient() && attempts <= max_retries => {
+                                    tokio::time::sleep(retry_delay(attempts)).await;
+                                }
+                                result => return result,
+                            }
+                        }
+                    };
+                    let upload = async {
+                        match timeout {
+                            Some(timeout) => tokio::time::timeout(timeout, upload)
+                                .await
+                                .unwrap_or(Err(CacheError::Timeout(timeout))),
+                            None => upload.await,
+                        }
+                    };
+                    tokio::select! {
+                        result = upload => result,
+                        _ = cancellation.cancelled() => Err(CacheError::Cancelled),
+                    }
+                };
+
+                Span::current().record("attempts", attempts);
+                UploadResult {
+                    hash,
+                    attempts,
+                    result,
+                }
+            }
+            .instrument(span),
+        );
+    }
+
+    /// Waits for every upload queued so far and returns their results in
+    /// completion order.
+    pub async fn finish(&self) -> Vec<UploadResult> {
+        let mut uploads = std::mem::replace(&mut *self.uploads.lock().unwrap(), JoinSet::new());
+        let mut results = Vec::with_capacity(uploads.len());
+        while let Some(upload) = uploads.join_next().await {
+            results.push(upload.expect("cache upload task panicked"));
+        }
+        results
+    }
+}
+
+// Exponential backoff with jitter, so that uploads throttled at the same time
+// don't all retry at the same time
+fn retry_delay(attempts: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY * 2_u32.pow(attempts - 1);
+    delay + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use turborepo_api_client::APIClient;
+    use vercel_api_mock::start_test_server;
+
+    use super::*;
+
+    fn new_http_cache(port: u16) -> Arc<HTTPCache> {
+        let client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0").unwrap();
+        Arc::new(HTTPCache::new(
+            client,
+            vercel_api_mock::EXPECTED_TOKEN.to_string(),
+            vercel_api_mock::EXPECTED_TEAM_ID.to_string(),
+            None,
+            None,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_reports_upload_results() {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let http = new_http_cache(port);
+        let manager = UploadManager::new(http.clone(), 2);
+        for hash in ["one", "two", "three"] {
+            manager.queue(hash, hash.as_bytes().to_vec(), 0);
+        }
+
+        let mut results = manager.finish().await;
+        results.sort_by(|a, b| a.hash.cmp(&b.hash));
+        let hashes: Vec<_> = results.iter().map(|result| result.hash.as_str()).collect();
+        assert_eq!(hashes, ["one", "three", "two"]);
+        for result in &results {
+            assert!(result.result.is_ok());
+            assert_eq!(result.attempts, 1);
+            assert!(http.fetch(&result.hash).await.unwrap().is_some());
+        }
+
+        // Everything has been drained
+        assert!(manager.finish().await.is_empty());
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_reports_failed_uploads() {
+        // Nothing is listening on this port, so every upload fails
+        let port = port_scanner::request_open_port().unwrap();
+        let manager = UploadManager::with_max_retries(new_http_cache(port), 2, 0);
+        manager.queue("hash", b"body".to_vec(), 0);
+
+        let results = manager.finish().await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].hash, "hash");
+        assert_eq!(results[0].attempts, 1);
+        assert!(results[0].result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancels_uploads() {
+        let port = port_scanner::request_open_port().unwrap();
+        let cancellation = CancellationToken::new();
+        // Uploads to an unreachable server would be retried for a while, but
+        // cancelling stops them right away
+        let manager = UploadManager::with_max_retries(new_http_cache(port), 1, 10)
+            .with_cancellation(cancellation.clone());
+        manager.queue("one", b"one".to_vec(), 0);
+        manager.queue("two", b"two".to_vec(), 0);
+        cancellation.cancel();
+
+        let results = manager.finish().await;
+        assert_eq!(results.len(), 2);
+        for result in results {
+            assert!(matches!(result.result, Err(CacheError::Cancelled)));
+        }
+    }
+}
@@ -1,8 +1,7 @@
 use std::fs::{self, DirBuilder, Metadata};
 
 use anyhow::Result;
-use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf};
-use walkdir::WalkDir;
+use turbopath::{walk, AbsoluteSystemPath, FileKind, PathError};
 
 pub fn recursive_copy(
     src: impl AsRef<AbsoluteSystemPath>,
@@ -10,55 +9,47 @@ pub fn recursive_copy(
 ) -> Result<()> {
     let src = src.as_ref();
     let dst = dst.as_ref();
-    let src_metadata = src.symlink_metadata()?;
-    if src_metadata.is_dir() {
-        let walker = WalkDir::new(src.as_path()).follow_links(false);
-        for entry in walker.into_iter() {
+    let src_kind = src.symlink_metadata_typed()?.kind();
+    if src_kind == FileKind::Dir {
+        for entry in walk(src) {
             match entry {
-                Err(e) => {
-                    if e.io_error().is_some() {
-                        // Matches go behavior where we translate path errors
-                        // into skipping the path we're currently walking
-                        continue;
-                    } else {
-                        return Err(e.into());
-                    }
-                }
-                Ok(entry) => {
-                    let path = entry.path();
-                    let path = AbsoluteSystemPath::new(path)?;
-                    let file_type = entry.file_type();
+                // Matches go behavior where we translate path errors
+                // into skipping the path we're currently walking
+                Err(PathError::IO { .. }) => continue,
+                Err(e) => return Err(e.into()),
+                Ok((suffix, kind)) => {
+                    let path = src.resolve(&suffix);
+                    let path = path.as_absolute_path();
                     // currently we support symlinked files, but not symlinked directories:
                     // For copying, we Mkdir and bail if we encounter a symlink to a directoy
                     // For finding packages, we enumerate the symlink, but don't follow inside
                     // Note that we also don't currently copy broken symlinks
-                    let is_dir_or_symlink_to_dir = if file_type.is_dir() {
-                        true
-                    } else if file_type.is_symlink() {
-                        if let Ok(metadata) = path.stat() {
-                            metadata.is_dir()
-                        } else {
-                            // If we have a broken link, skip this entry
-                            continue;
+                    let is_dir_or_symlink_to_dir = match kind {
+                        FileKind::Dir => true,
+                        FileKind::Symlink => {
+                            if let Ok(metadata) = path.stat() {
+                                metadata.is_dir()
+                            } else {
+                                // If we have a broken link, skip this entry
+                                continue;
+                            }
                         }
-                    } else {
-                        false
+                        FileKind::File | FileKind::Other => false,
                     };
 
-                    let suffix = AnchoredSystemPathBuf::new(src, &path)?;
                     let target = dst.resolve(&suffix);
                     if is_dir_or_symlink_to_dir {
-                        let src_metadata = entry.metadata()?;
+                        let src_metadata = path.symlink_metadata()?;
                         make_dir_copy(&target, &src_metadata)?;
                     } else {
-                        copy_file_with_type(&path, file_type, &target)?;
+                        copy_file_with_kind(path, kind, &target)?;
                     }
                 }
             }
         }
         Ok(())
     } else {
-        copy_file_with_type(src, src_metadata.file_type(), dst)
+        copy_file_with_kind(src, src_kind, dst)
     }
 }
 
@@ -80,18 +71,18 @@ pub fn copy_file(
     to: impl AsRef<AbsoluteSystemPath>,
 ) -> Result<()> {
     let from = from.as_ref();
-    let metadata = from.symlink_metadata()?;
-    copy_file_with_type(from, metadata.file_type(), to)
+    let kind = from.symlink_metadata_typed()?.kind();
+    copy_file_with_kind(from, kind, to)
 }
 
-fn copy_file_with_type(
+fn copy_file_with_kind(
     from: impl AsRef<AbsoluteSystemPath>,
-    from_type: fs::FileType,
+    from_kind: FileKind,
     to: impl AsRef<AbsoluteSystemPath>,
 ) -> Result<()> {
     let from = from.as_ref();
     let to = to.as_ref();
-    if from_type.is_symlink() {
+    if from_kind == FileKind::Symlink {
         let target = from.read_link()?;
         to.ensure_dir()?;
         if to.symlink_metadata().is_ok() {
@@ -110,7 +101,7 @@ fn copy_file_with_type(
 mod tests {
     use std::{io, path::Path};
 
-    use turbopath::{AbsoluteSystemPathBuf, PathError};
+    use turbopath::AbsoluteSystemPathBuf;
 
     use super::*;
 
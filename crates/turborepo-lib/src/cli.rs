@@ -394,6 +394,10 @@ pub enum GenerateCommand {
 
 #[derive(Parser, Clone, Debug, Default, Serialize, PartialEq)]
 pub struct RunArgs {
+    /// Only run the tasks of workspaces affected by changes since the merge
+    /// base with TURBO_SCM_BASE (default main), and of their dependents.
+    #[clap(long)]
+    pub affected: bool,
     /// Override the filesystem cache directory.
     #[clap(long)]
     pub cache_dir: Option<String>,
@@ -496,6 +500,14 @@ pub struct RunArgs {
     /// to identify which task produced a log.
     #[clap(long, value_enum)]
     pub log_prefix: Option<LogPrefix>,
+    /// Keep running and re-run the tasks of workspaces whose inputs
+    /// changed, and of their dependents. Requires the daemon
+    #[clap(long, conflicts_with = "no_daemon")]
+    pub watch: bool,
+    /// Milliseconds to wait for further changes before re-running tasks in
+    /// watch mode
+    #[clap(long, value_name = "MS", default_value_t = 100, requires = "watch")]
+    pub watch_debounce: u64,
     // NOTE: The following two are hidden because clap displays them in the help text incorrectly:
     // > Usage: turbo [OPTIONS] [TASKS]... [-- <FORWARDED_ARGS>...] [COMMAND]
     #[clap(hide = true)]
@@ -734,6 +746,7 @@ mod test {
             cache_workers: 10,
             output_logs: None,
             framework_inference: true,
+            watch_debounce: 100,
             ..RunArgs::default()
         }
     }
@@ -1299,6 +1312,41 @@ mod test {
             }
         );
 
+        assert_eq!(
+            Args::try_parse_from(["turbo", "run", "build", "--affected"]).unwrap(),
+            Args {
+                command: Some(Command::Run(Box::new(RunArgs {
+                    tasks: vec!["build".to_string()],
+                    affected: true,
+                    ..get_default_run_args()
+                }))),
+                ..Args::default()
+            }
+        );
+
+        assert_eq!(
+            Args::try_parse_from([
+                "turbo",
+                "run",
+                "build",
+                "--watch",
+                "--watch-debounce",
+                "500"
+            ])
+            .unwrap(),
+            Args {
+                command: Some(Command::Run(Box::new(RunArgs {
+                    tasks: vec!["build".to_string()],
+                    watch: true,
+                    watch_debounce: 500,
+                    ..get_default_run_args()
+                }))),
+                ..Args::default()
+            }
+        );
+
+        assert!(Args::try_parse_from(["turbo", "run", "build", "--watch", "--no-daemon"]).is_err());
+
         assert_eq!(
             Args::try_parse_from(["turbo", "build"]).unwrap(),
             Args {
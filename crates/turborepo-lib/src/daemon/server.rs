@@ -306,6 +306,25 @@ impl<T: Watcher + Send + 'static> proto::turbod_server::Turbod for DaemonServer<
             }
         }
     }
+
+    async fn unwatch_globs(
+        &self,
+        request: tonic::Request<proto::UnwatchGlobsRequest>,
+    ) -> Result<tonic::Response<proto::UnwatchGlobsResponse>, tonic::Status> {
+        let inner = request.into_inner();
+
+        {
+            let mut times_saved = self.times_saved.lock().expect("times saved lock poisoned");
+            for hash in &inner.hashes {
+                times_saved.remove(hash);
+            }
+        }
+        self.watcher
+            .unwatch_globs(inner.hashes.into_iter().map(Arc::new))
+            .await;
+
+        Ok(tonic::Response::new(proto::UnwatchGlobsResponse {}))
+    }
 }
 
 impl<T: Watcher> NamedService for DaemonServer<T> {
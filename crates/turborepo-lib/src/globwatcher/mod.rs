@@ -222,6 +222,35 @@ impl<T: Watcher> HashGlobWatcher<T> {
         }
     }
 
+    /// stops tracking the globs registered for the given hashes, and stops
+    /// watching globs that no other hash is tracking
+    pub async fn unwatch_globs(&self, hashes: impl IntoIterator<Item = Hash>) {
+        let hashes: HashSet<Hash> = hashes.into_iter().collect();
+
+        // put these in a block so we can drop the locks before we await
+        let globs_to_exclude = {
+            let mut glob_statuses = self.glob_statuses.lock().expect("only fails if poisoned");
+            let mut hash_globs = self.hash_globs.lock().expect("only fails if poisoned");
+            hash_globs.retain(|hash, _| !hashes.contains(hash));
+
+            let mut globs_to_exclude = vec![];
+            glob_statuses.retain(|glob, hash_status| {
+                hash_status.retain(|hash| !hashes.contains(hash));
+                if hash_status.is_empty() {
+                    globs_to_exclude.push(glob.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            globs_to_exclude
+        };
+
+        for glob in globs_to_exclude {
+            self.config.exclude(&self.relative_to, &glob).await;
+        }
+    }
+
     /// given a hash and a set of candidates, return the subset of candidates
     /// that have changed.
     pub async fn changed_globs(
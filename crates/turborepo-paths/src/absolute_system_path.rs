@@ -9,14 +9,15 @@ use std::{
     fmt, fs,
     fs::Metadata,
     io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf, Prefix},
 };
 
 use path_clean::PathClean;
 use path_slash::CowExt;
 
 use crate::{
-    AbsoluteSystemPathBuf, AnchoredSystemPathBuf, IntoSystem, PathError, RelativeUnixPath,
+    AbsoluteSystemPathBuf, AnchoredSystemPath, AnchoredSystemPathBuf, IntoSystem, PathError,
+    PathOperation, RelativeUnixPath,
 };
 
 pub struct AbsoluteSystemPath(Path);
@@ -111,6 +112,51 @@ impl AbsoluteSystemPath {
         &self.0
     }
 
+    /// Whether this is a UNC path to a network share, e.g.
+    /// `\\server\share\dir`, in either its regular or its extended-length
+    /// form
+    pub fn is_unc(&self) -> bool {
+        matches!(
+            self.0.components().next(),
+            Some(Component::Prefix(prefix))
+                if matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+        )
+    }
+
+    /// Returns the extended-length (`\\?\`) form of this path on Windows,
+    /// which lifts the `MAX_PATH` limit of 260 characters for Windows APIs.
+    /// Extended-length paths aren't normalized by Windows, so this should
+    /// only be used right before handing the path to the OS. On other
+    /// platforms the path is returned unchanged.
+    pub fn to_extended_length_path(&self) -> PathBuf {
+        let mut components = self.0.components();
+        let Some(Component::Prefix(prefix)) = components.next() else {
+            return self.0.to_path_buf();
+        };
+        let mut extended = match prefix.kind() {
+            Prefix::Disk(drive) => PathBuf::from(format!(r"\\?\{}:\", drive as char)),
+            Prefix::UNC(server, share) => {
+                let mut extended = PathBuf::from(r"\\?\UNC\");
+                extended.push(server);
+                extended.push(share);
+                extended
+            }
+            // Already extended-length, or a device path that has no such form
+            _ => return self.0.to_path_buf(),
+        };
+        // Windows doesn't resolve `.` and `..` in extended-length paths
+        for component in components {
+            match component {
+                Component::Normal(component) => extended.push(component),
+                Component::ParentDir => {
+                    extended.pop();
+                }
+                _ => {}
+            }
+        }
+        extended
+    }
+
     pub fn ancestors(&self) -> impl Iterator<Item = &AbsoluteSystemPath> {
         self.0
             .ancestors()
@@ -143,6 +189,80 @@ impl AbsoluteSystemPath {
         Ok(AbsoluteSystemPathBuf(self.0.join(tail.as_path()).clean()))
     }
 
+    /// Joins `path` onto this directory, making sure the result can't be used
+    /// to reach a file outside of it, e.g. when writing files from a cache
+    /// artifact. Besides `..` components, this follows every existing
+    /// symlink along the way, including the last component, and errors if
+    /// one of them resolves to somewhere outside this directory.
+    ///
+    /// Returns the joined path with symlinks left in place, except where a
+    /// `..` follows a symlink: the OS applies that `..` to the symlink's
+    /// target, so the returned path continues from the resolved target.
+    /// Nothing stops a symlink from being created after this check, so this
+    /// only protects against the contents of the directory at the time of the
+    /// call.
+    pub fn safe_join(
+        &self,
+        path: impl AsRef<AnchoredSystemPath>,
+    ) -> Result<AbsoluteSystemPathBuf, PathError> {
+        let path = path.as_ref();
+        let escapes = || PathError::Escapes {
+            base: self.0.to_path_buf(),
+            path: path.as_path().to_path_buf(),
+        };
+        let base = resolve_existing(&self.0);
+        // Where `path` really points so far, with symlinks resolved
+        let mut resolved = base.clone();
+        // The path we hand back, and whether each of the components pushed
+        // onto it was a symlink
+        let mut joined = self.0.to_path_buf();
+        let mut symlinks = Vec::new();
+        // Once a component doesn't exist, none of the ones after it can
+        let mut exists = true;
+        for component in path.components() {
+            match component {
+                Component::Normal(component) => {
+                    resolved.push(component);
+                    joined.push(component);
+                    let metadata = if exists {
+                        match fs::symlink_metadata(&resolved) {
+                            Ok(metadata) => Some(metadata),
+                            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                            Err(err) => {
+                                return Err(PathError::io(PathOperation::Stat, resolved)(err))
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    exists = metadata.is_some();
+                    let is_symlink = matches!(metadata, Some(metadata) if metadata.is_symlink());
+                    if is_symlink {
+                        let target = fs::read_link(&resolved)
+                            .map_err(PathError::io(PathOperation::Read, &resolved))?;
+                        resolved.pop();
+                        resolved = resolve_existing(&resolved.join(target));
+                    }
+                    symlinks.push(is_symlink);
+                }
+                Component::ParentDir => {
+                    resolved.pop();
+                    if symlinks.pop().unwrap_or(false) {
+                        joined = resolved.clone();
+                        symlinks.clear();
+                    } else {
+                        joined.pop();
+                    }
+                }
+                _ => {}
+            }
+            if !resolved.starts_with(&base) {
+                return Err(escapes());
+            }
+        }
+        Ok(AbsoluteSystemPathBuf(joined))
+    }
+
     pub fn anchor(&self, path: &AbsoluteSystemPath) -> Result<AnchoredSystemPathBuf, PathError> {
         AnchoredSystemPathBuf::new(self, path)
     }
@@ -158,15 +278,23 @@ impl AbsoluteSystemPath {
     pub fn symlink_to_file<P: AsRef<Path>>(&self, to: P) -> Result<(), PathError> {
         let system_path = to.as_ref();
         let system_path = system_path.into_system()?;
-        symlink_file(system_path, &self.0)?;
-        Ok(())
+        symlink_file(&system_path, &self.0).map_err(|source| PathError::IO {
+            operation: PathOperation::Symlink,
+            path: self.0.to_path_buf(),
+            other: Some(system_path),
+            source,
+        })
     }
 
     pub fn symlink_to_dir<P: AsRef<Path>>(&self, to: P) -> Result<(), PathError> {
         let system_path = to.as_ref();
         let system_path = system_path.into_system()?;
-        symlink_dir(&system_path, &self.0)?;
-        Ok(())
+        symlink_dir(&system_path, &self.0).map_err(|source| PathError::IO {
+            operation: PathOperation::Symlink,
+            path: self.0.to_path_buf(),
+            other: Some(system_path),
+            source,
+        })
     }
 
     pub fn resolve(&self, path: &AnchoredSystemPathBuf) -> AbsoluteSystemPathBuf {
@@ -177,13 +305,13 @@ impl AbsoluteSystemPath {
     // note that this is *not* lstat. If this is a symlink, it
     // will return metadata for the target.
     pub fn stat(&self) -> Result<Metadata, PathError> {
-        Ok(fs::metadata(&self.0)?)
+        fs::metadata(&self.0).map_err(PathError::io(PathOperation::Stat, &self.0))
     }
 
     // The equivalent of lstat. Returns the metadata for this file,
     // even if it is a symlink
     pub fn symlink_metadata(&self) -> Result<Metadata, PathError> {
-        Ok(fs::symlink_metadata(&self.0)?)
+        fs::symlink_metadata(&self.0).map_err(PathError::io(PathOperation::Stat, &self.0))
     }
 
     pub fn read_link(&self) -> Result<PathBuf, io::Error> {
@@ -195,8 +323,16 @@ impl AbsoluteSystemPath {
     }
 }
 
+// Resolves symlinks in `path` if it exists, or just cleans it up otherwise,
+// e.g. for a dangling symlink target
+fn resolve_existing(path: &Path) -> PathBuf {
+    dunce::canonicalize(path).unwrap_or_else(|_| path.clean())
+}
+
 #[cfg(test)]
 mod tests {
+    use std::assert_matches::assert_matches;
+
     use anyhow::Result;
 
     use super::*;
@@ -217,4 +353,56 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_safe_join() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let outside = AbsoluteSystemPathBuf::new(dir.path())?;
+        let base = outside.join_component("repo");
+        fs::create_dir_all(base.join_components(&["packages", "ui"]))?;
+        let anchored = |path: &str| AnchoredSystemPathBuf::from_raw(path).unwrap();
+
+        assert_eq!(
+            base.safe_join(anchored("packages/ui/../ui/dist/index.js"))?,
+            base.join_components(&["packages", "ui", "dist", "index.js"])
+        );
+        assert_matches!(
+            base.safe_join(anchored("packages/../../secret")),
+            Err(PathError::Escapes { .. })
+        );
+
+        // Symlinks that stay inside the base are fine, even when their
+        // target doesn't exist yet
+        base.join_component("docs")
+            .symlink_to_dir(["packages", "ui"].join(std::path::MAIN_SEPARATOR_STR))?;
+        base.join_component("dangling")
+            .symlink_to_file("not-there-yet")?;
+        assert!(base.safe_join(anchored("docs/README.md")).is_ok());
+        assert!(base.safe_join(anchored("dangling")).is_ok());
+
+        // A symlink in the middle of the path, or as the last component
+        base.join_component("escape")
+            .symlink_to_dir(outside.as_path())?;
+        base.join_component("passwd")
+            .symlink_to_file(["..", "..", "passwd"].join(std::path::MAIN_SEPARATOR_STR))?;
+        assert_matches!(
+            base.safe_join(anchored("escape/repo/../secret")),
+            Err(PathError::Escapes { .. })
+        );
+        assert_matches!(
+            base.safe_join(anchored("passwd")),
+            Err(PathError::Escapes { .. })
+        );
+        // `..` after a symlink is relative to where the symlink points
+        assert_eq!(
+            base.safe_join(anchored("docs/../../index.js"))?,
+            AbsoluteSystemPathBuf::new(dunce::canonicalize(&base)?.join("index.js"))?
+        );
+        assert_matches!(
+            base.safe_join(anchored("docs/../../../secret")),
+            Err(PathError::Escapes { .. })
+        );
+
+        Ok(())
+    }
 }
@@ -7,11 +7,13 @@ use std::{
 };
 
 use path_clean::PathClean;
-use serde::Serialize;
 
-use crate::{AbsoluteSystemPath, AnchoredSystemPathBuf, IntoSystem, PathError, RelativeUnixPath};
+use crate::{
+    AbsoluteSystemPath, AnchoredSystemPath, AnchoredSystemPathBuf, IntoSystem, PathError,
+    PathOperation, RelativeUnixPath,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct AbsoluteSystemPathBuf(pub(crate) PathBuf);
 
 impl Borrow<AbsoluteSystemPath> for AbsoluteSystemPathBuf {
@@ -30,7 +32,9 @@ impl AsRef<AbsoluteSystemPath> for AbsoluteSystemPathBuf {
 impl AbsoluteSystemPathBuf {
     /// Create a new AbsoluteSystemPathBuf from `unchecked_path`.
     /// Confirms that `unchecked_path` is absolute and converts it to a system
-    /// path.
+    /// path. On Windows, extended-length paths (`\\?\C:\dir`) are
+    /// converted to their regular form (`C:\dir`) whenever that form refers
+    /// to the same file, so that they compare equal and join as expected.
     ///
     /// # Arguments
     ///
@@ -63,14 +67,32 @@ impl AbsoluteSystemPathBuf {
         }
 
         let system_path = unchecked_path.into_system()?;
-        Ok(AbsoluteSystemPathBuf(system_path))
+        Ok(AbsoluteSystemPathBuf(
+            dunce::simplified(&system_path).to_path_buf(),
+        ))
+    }
+
+    /// Like `new`, but also cleans the path
+    pub fn new_clean(unchecked_path: impl Into<PathBuf>) -> Result<Self, PathError> {
+        Ok(Self::new(unchecked_path)?.clean())
+    }
+
+    /// Resolves `.` and `..` components and removes repeated separators,
+    /// purely lexically, like Go's `filepath.Clean`. `..` at the root stays
+    /// at the root.
+    ///
+    /// Unlike `to_realpath`, this doesn't touch the filesystem, so it works
+    /// for paths that don't exist yet, but it may change which file a path
+    /// refers to when it goes through a symlink.
+    pub fn clean(&self) -> Self {
+        Self(self.0.clean())
     }
 
     pub fn from_unknown(base: &AbsoluteSystemPath, unknown: impl Into<PathBuf>) -> Self {
         // we have an absolute system path and an unknown kind of system path.
         let unknown: PathBuf = unknown.into();
         if unknown.is_absolute() {
-            Self(unknown)
+            Self(dunce::simplified(&unknown).to_path_buf())
         } else {
             Self(base.as_path().join(unknown).clean())
         }
@@ -82,7 +104,8 @@ impl AbsoluteSystemPathBuf {
     }
 
     pub fn cwd() -> Result<Self, PathError> {
-        Ok(Self(std::env::current_dir()?))
+        let cwd = std::env::current_dir().map_err(PathError::io(PathOperation::Resolve, "."))?;
+        Ok(Self(cwd))
     }
 
     pub fn ancestors(&self) -> impl Iterator<Item = &AbsoluteSystemPath> {
@@ -196,6 +219,13 @@ impl AbsoluteSystemPathBuf {
         self.as_absolute_path().join_unix_path(unix_path)
     }
 
+    pub fn safe_join(
+        &self,
+        path: impl AsRef<AnchoredSystemPath>,
+    ) -> Result<AbsoluteSystemPathBuf, PathError> {
+        self.as_absolute_path().safe_join(path)
+    }
+
     pub fn ensure_dir(&self) -> Result<(), io::Error> {
         if let Some(parent) = self.0.parent() {
             fs::create_dir_all(parent)
@@ -213,15 +243,15 @@ impl AbsoluteSystemPathBuf {
     }
 
     pub fn set_readonly(&self) -> Result<(), PathError> {
-        let metadata = fs::symlink_metadata(self)?;
-        let mut perms = metadata.permissions();
+        let mut perms = self.as_absolute_path().symlink_metadata()?.permissions();
         perms.set_readonly(true);
-        fs::set_permissions(self.0.as_path(), perms)?;
-        Ok(())
+        fs::set_permissions(self.0.as_path(), perms)
+            .map_err(PathError::io(PathOperation::SetPermissions, &self.0))
     }
 
     pub fn is_readonly(&self) -> Result<bool, PathError> {
-        Ok(self.0.symlink_metadata()?.permissions().readonly())
+        let metadata = self.as_absolute_path().symlink_metadata()?;
+        Ok(metadata.permissions().readonly())
     }
 
     pub fn create_with_contents(&self, contents: &str) -> Result<(), io::Error> {
@@ -253,11 +283,12 @@ impl AbsoluteSystemPathBuf {
     }
 
     pub fn open(&self) -> Result<fs::File, PathError> {
-        Ok(fs::File::open(&self.0)?)
+        fs::File::open(&self.0).map_err(PathError::io(PathOperation::Read, &self.0))
     }
 
     pub fn to_realpath(&self) -> Result<Self, PathError> {
-        let realpath = dunce::canonicalize(&self.0)?;
+        let realpath =
+            dunce::canonicalize(&self.0).map_err(PathError::io(PathOperation::Resolve, &self.0))?;
         Ok(Self(realpath))
     }
 
@@ -290,9 +321,9 @@ impl AsRef<Path> for AbsoluteSystemPathBuf {
 
 #[cfg(test)]
 mod tests {
-    use std::assert_matches::assert_matches;
+    use std::{assert_matches::assert_matches, error::Error, io};
 
-    use crate::{AbsoluteSystemPathBuf, PathError, RelativeUnixPathBuf};
+    use crate::{AbsoluteSystemPathBuf, PathError, PathOperation, RelativeUnixPathBuf};
 
     #[cfg(not(windows))]
     #[test]
@@ -318,6 +349,52 @@ mod tests {
                 .unwrap(),
             AbsoluteSystemPathBuf::new("/some/other").unwrap(),
         );
+
+        assert_eq!(
+            AbsoluteSystemPathBuf::new_clean("/some/./dir//../../../other/").unwrap(),
+            AbsoluteSystemPathBuf::new("/other").unwrap(),
+        );
+
+        let path = AbsoluteSystemPathBuf::new("/some/dir").unwrap();
+        assert!(!path.as_absolute_path().is_unc());
+        assert_eq!(
+            path.as_absolute_path().to_extended_length_path(),
+            path.as_path()
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_extended_length_and_unc_paths() {
+        use std::path::Path;
+
+        let path = AbsoluteSystemPathBuf::new(r"\\?\C:\repo\packages").unwrap();
+        assert_eq!(
+            path,
+            AbsoluteSystemPathBuf::new(r"C:\repo\packages").unwrap()
+        );
+        assert!(!path.as_absolute_path().is_unc());
+        assert_eq!(
+            path.as_absolute_path().to_extended_length_path(),
+            Path::new(r"\\?\C:\repo\packages")
+        );
+
+        let share = AbsoluteSystemPathBuf::new(r"\\?\UNC\server\share\repo").unwrap();
+        assert_eq!(share.as_path(), Path::new(r"\\server\share\repo"));
+        assert!(share.as_absolute_path().is_unc());
+        assert_eq!(
+            share.join_component("packages").as_path(),
+            Path::new(r"\\server\share\repo\packages")
+        );
+        assert_eq!(
+            share.as_absolute_path().to_extended_length_path(),
+            Path::new(r"\\?\UNC\server\share\repo")
+        );
+
+        // The share itself is the root of the path
+        let root = share.parent().unwrap();
+        assert_eq!(root.as_path(), Path::new(r"\\server\share\"));
+        assert!(root.parent().is_none());
     }
 
     #[cfg(windows)]
@@ -348,4 +425,19 @@ mod tests {
             AbsoluteSystemPathBuf::new("C:\\some\\other").unwrap(),
         );
     }
+
+    #[test]
+    fn test_io_error_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = AbsoluteSystemPathBuf::new(dir.path())
+            .unwrap()
+            .join_component("missing.json");
+
+        let err = missing.open().unwrap_err();
+        assert_eq!(err.operation(), Some(PathOperation::Read));
+        assert!(err.is_io_error(io::ErrorKind::NotFound));
+        assert_eq!(err.to_string(), format!("Failed to read {}", missing));
+        let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::NotFound);
+    }
 }
@@ -0,0 +1,339 @@
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fmt,
+    path::{Component, Components, Path},
+};
+
+use path_slash::CowExt;
+
+#[cfg(not(unix))]
+use crate::AnchoredUnixPathBuf;
+use crate::{AnchoredSystemPathBuf, AnchoredUnixPath, PathError, PathOperation, RelativeUnixPathBuf};
+
+#[repr(transparent)]
+pub struct AnchoredSystemPath(Path);
+
+impl ToOwned for AnchoredSystemPath {
+    type Owned = AnchoredSystemPathBuf;
+
+    fn to_owned(&self) -> Self::Owned {
+        AnchoredSystemPathBuf(self.0.to_owned())
+    }
+}
+
+impl AsRef<AnchoredSystemPath> for AnchoredSystemPath {
+    fn as_ref(&self) -> &AnchoredSystemPath {
+        self
+    }
+}
+
+impl AsRef<Path> for AnchoredSystemPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl fmt::Display for AnchoredSystemPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display().fmt(f)
+    }
+}
+
+impl fmt::Debug for AnchoredSystemPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl AnchoredSystemPath {
+    /// Creates a path that is known to be relative and a system path.
+    /// If either of these conditions are not met, we error.
+    /// Does *not* do automatic conversion like
+    /// `AnchoredSystemPathBuf::from_raw` does
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turbopath::AnchoredSystemPath;
+    /// #[cfg(unix)]
+    /// {
+    ///   assert!(AnchoredSystemPath::new("foo/bar").is_ok());
+    ///   assert!(AnchoredSystemPath::new("/foo/bar").is_err());
+    /// }
+    ///
+    /// #[cfg(windows)]
+    /// {
+    ///   assert!(AnchoredSystemPath::new("foo\\bar").is_ok());
+    ///   assert!(AnchoredSystemPath::new("foo/bar").is_err());
+    ///   assert!(AnchoredSystemPath::new("C:\\foo\\bar").is_err());
+    /// }
+    /// ```
+    pub fn new<P: AsRef<Path> + ?Sized>(value: &P) -> Result<&Self, PathError> {
+        let path = value.as_ref();
+        if path.is_absolute() || path.has_root() {
+            return Err(PathError::NotRelative(path.display().to_string()));
+        }
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| PathError::InvalidUnicode(path.to_string_lossy().to_string()))?;
+
+        match Cow::from_slash(path_str) {
+            Cow::Owned(path) => Err(PathError::NotSystem(path.to_string_lossy().to_string())),
+            Cow::Borrowed(path) => Ok(Self::new_unchecked(Path::new(path))),
+        }
+    }
+
+    pub(crate) fn new_unchecked(path: &Path) -> &Self {
+        // copied from stdlib path.rs: relies on the representation of
+        // AnchoredSystemPath being just a Path, the same way Path relies on
+        // just being an OsStr
+        unsafe { &*(path as *const Path as *const Self) }
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn to_str(&self) -> Result<&str, PathError> {
+        self.0
+            .to_str()
+            .ok_or_else(|| PathError::InvalidUnicode(self.0.to_string_lossy().to_string()))
+    }
+
+    /// Converts this path to a unix path. On unix this borrows the path
+    /// rather than copying it, since the bytes are already the same.
+    pub fn to_unix(&self) -> Result<Cow<'_, AnchoredUnixPath>, PathError> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            // Anchored paths are relative, so this is a valid unix path
+            return Ok(Cow::Borrowed(AnchoredUnixPath::new_unchecked(
+                self.0.as_os_str().as_bytes(),
+            )));
+        }
+        #[cfg(not(unix))]
+        {
+            use crate::IntoUnix;
+            let unix_buf = (&self.0).into_unix()?;
+            let unix_str = unix_buf
+                .to_str()
+                .ok_or_else(|| PathError::InvalidUnicode(unix_buf.to_string_lossy().to_string()))?;
+            return Ok(Cow::Owned(AnchoredUnixPathBuf::new(unix_str.as_bytes())?));
+        }
+    }
+
+    /// Appends `tail` to this path. Both paths are relative, so the result is
+    /// too.
+    pub fn join(&self, tail: impl AsRef<AnchoredSystemPath>) -> AnchoredSystemPathBuf {
+        AnchoredSystemPathBuf(self.0.join(&tail.as_ref().0))
+    }
+
+    /// Returns the path without its final component. The parent of a single
+    /// component path is the empty path, i.e. the anchor itself, which has no
+    /// parent.
+    pub fn parent(&self) -> Option<&AnchoredSystemPath> {
+        self.0.parent().map(Self::new_unchecked)
+    }
+
+    pub fn components(&self) -> Components<'_> {
+        self.0.components()
+    }
+
+    /// Returns the rest of this path after `prefix`, comparing whole
+    /// components
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turbopath::AnchoredSystemPath;
+    /// #[cfg(unix)]
+    /// {
+    ///   let path = AnchoredSystemPath::new("packages/ui/package.json").unwrap();
+    ///   let prefix = AnchoredSystemPath::new("packages").unwrap();
+    ///   assert_eq!(path.strip_prefix(prefix).unwrap().to_str().unwrap(), "ui/package.json");
+    ///   assert!(path.strip_prefix(AnchoredSystemPath::new("pack").unwrap()).is_err());
+    /// }
+    /// ```
+    pub fn strip_prefix(
+        &self,
+        prefix: impl AsRef<AnchoredSystemPath>,
+    ) -> Result<&AnchoredSystemPath, PathError> {
+        let prefix = prefix.as_ref();
+        self.0
+            .strip_prefix(&prefix.0)
+            .map(Self::new_unchecked)
+            .map_err(|_| PathError::NotParent {
+                operation: PathOperation::Strip,
+                parent: prefix.0.to_path_buf(),
+                path: self.0.to_path_buf(),
+            })
+    }
+
+    /// Returns the path that leads from the directory `base` to this path,
+    /// e.g. for a symlink at `base` pointing at this path. Both paths are
+    /// anchored at the same directory. The result is computed lexically, so
+    /// it fails if `base` climbs out of a directory with `..` where this path
+    /// doesn't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turbopath::AnchoredSystemPathBuf;
+    /// let path = AnchoredSystemPathBuf::from_raw("packages/ui/dist/index.js").unwrap();
+    /// let base = AnchoredSystemPathBuf::from_raw("packages/app/node_modules").unwrap();
+    /// assert_eq!(
+    ///     path.relative_to(&base).unwrap().as_str().unwrap(),
+    ///     "../../ui/dist/index.js"
+    /// );
+    /// ```
+    pub fn relative_to(
+        &self,
+        base: impl AsRef<AnchoredSystemPath>,
+    ) -> Result<RelativeUnixPathBuf, PathError> {
+        let base = base.as_ref();
+        let components = |path: &AnchoredSystemPath| -> Result<Vec<String>, PathError> {
+            path.components()
+                .filter(|component| *component != Component::CurDir)
+                .map(|component| {
+                    component
+                        .as_os_str()
+                        .to_str()
+                        .map(|component| component.to_string())
+                        .ok_or_else(|| PathError::InvalidUnicode(path.to_string()))
+                })
+                .collect()
+        };
+        let path_components = components(self)?;
+        let base_components = components(base)?;
+
+        let common = path_components
+            .iter()
+            .zip(&base_components)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let climbs = &base_components[common..];
+        if climbs.iter().any(|component| component == "..") {
+            return Err(PathError::NoRelativePath {
+                base: base.0.to_path_buf(),
+                path: self.0.to_path_buf(),
+            });
+        }
+
+        let relative: Vec<&str> = climbs
+            .iter()
+            .map(|_| "..")
+            .chain(path_components[common..].iter().map(String::as_str))
+            .collect();
+        RelativeUnixPathBuf::new(relative.join("/"))
+    }
+
+    pub fn starts_with(&self, base: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.0.starts_with(&base.as_ref().0)
+    }
+
+    pub fn ends_with(&self, child: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.0.ends_with(&child.as_ref().0)
+    }
+
+    pub fn file_name(&self) -> Option<&OsStr> {
+        self.0.file_name()
+    }
+
+    pub fn extension(&self) -> Option<&OsStr> {
+        self.0.extension()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_to_unix_borrows() -> Result<()> {
+        let path = AnchoredSystemPath::new("packages/ui/package.json")?;
+        let unix = path.to_unix()?;
+        assert_matches!(unix, Cow::Borrowed(_));
+        assert_eq!(unix.as_str()?, "packages/ui/package.json");
+        assert_eq!(unix.as_relative_path().file_name().unwrap(), "package.json");
+        assert_eq!(
+            RelativeUnixPathBuf::from(unix.into_owned()),
+            path.to_owned().to_unix()?
+        );
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_to_unix_converts_separators() -> Result<()> {
+        let path = AnchoredSystemPath::new("packages\\ui\\package.json")?;
+        let unix = path.to_unix()?;
+        assert_matches!(unix, Cow::Owned(_));
+        assert_eq!(unix.as_str()?, "packages/ui/package.json");
+        assert_eq!(
+            RelativeUnixPathBuf::from(unix.into_owned()),
+            path.to_owned().to_unix()?
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_anchored_system_path() -> Result<()> {
+        assert_matches!(
+            AnchoredSystemPath::new("/foo"),
+            Err(PathError::NotRelative(_))
+        );
+
+        let path = AnchoredSystemPath::new("packages/ui/package.json")?;
+        assert_eq!(path.file_name(), Some(OsStr::new("package.json")));
+        assert_eq!(path.extension(), Some(OsStr::new("json")));
+        assert_eq!(path.components().count(), 3);
+
+        let parent = path.parent().unwrap();
+        assert_eq!(parent.to_str()?, "packages/ui");
+        assert!(path.starts_with(parent));
+        assert!(!path.starts_with(AnchoredSystemPath::new("packages/u")?));
+        assert!(path.ends_with(AnchoredSystemPath::new("ui/package.json")?));
+
+        let tail = path.strip_prefix(parent)?;
+        assert_eq!(tail.to_str()?, "package.json");
+        assert_eq!(
+            parent.join(tail).as_anchored_path().to_str()?,
+            path.to_str()?
+        );
+        assert_matches!(parent.strip_prefix(path), Err(PathError::NotParent { .. }));
+
+        assert_eq!(
+            path.relative_to(AnchoredSystemPath::new("packages/app")?)?
+                .as_str()?,
+            "../ui/package.json"
+        );
+        assert_eq!(path.relative_to(parent)?.as_str()?, "package.json");
+        assert_eq!(parent.relative_to(path)?.as_str()?, "..");
+        assert_eq!(path.relative_to(path)?.as_str()?, "");
+        assert_eq!(
+            AnchoredSystemPath::new("../shared")?
+                .relative_to(AnchoredSystemPath::new("packages")?)?
+                .as_str()?,
+            "../../shared"
+        );
+        assert_matches!(
+            path.relative_to(AnchoredSystemPath::new("../elsewhere")?),
+            Err(PathError::NoRelativePath { .. })
+        );
+
+        let root = AnchoredSystemPath::new("packages")?.parent().unwrap();
+        assert_eq!(root.to_str()?, "");
+        assert!(root.parent().is_none());
+
+        Ok(())
+    }
+}
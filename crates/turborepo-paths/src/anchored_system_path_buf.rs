@@ -1,11 +1,30 @@
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Borrow,
+    ffi::OsStr,
+    path::{Components, Path, PathBuf},
+};
 
-use serde::{Deserialize, Serialize};
+use path_clean::PathClean;
 
-use crate::{AbsoluteSystemPath, IntoSystem, PathError, RelativeUnixPathBuf};
+use crate::{
+    AbsoluteSystemPath, AnchoredSystemPath, IntoSystem, PathError, PathOperation,
+    RelativeUnixPathBuf,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
-pub struct AnchoredSystemPathBuf(PathBuf);
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct AnchoredSystemPathBuf(pub(crate) PathBuf);
+
+impl Borrow<AnchoredSystemPath> for AnchoredSystemPathBuf {
+    fn borrow(&self) -> &AnchoredSystemPath {
+        AnchoredSystemPath::new_unchecked(self.0.as_path())
+    }
+}
+
+impl AsRef<AnchoredSystemPath> for AnchoredSystemPathBuf {
+    fn as_ref(&self) -> &AnchoredSystemPath {
+        self.borrow()
+    }
+}
 
 impl TryFrom<&Path> for AnchoredSystemPathBuf {
     type Error = PathError;
@@ -30,7 +49,11 @@ impl AnchoredSystemPathBuf {
         let stripped_path = path
             .as_path()
             .strip_prefix(root.as_path())
-            .map_err(|_| PathError::NotParent(root.to_string(), path.to_string()))?
+            .map_err(|_| PathError::NotParent {
+                operation: PathOperation::Anchor,
+                parent: root.as_path().to_path_buf(),
+                path: path.as_path().to_path_buf(),
+            })?
             .to_path_buf();
 
         Ok(AnchoredSystemPathBuf(stripped_path))
@@ -42,10 +65,78 @@ impl AnchoredSystemPathBuf {
         Ok(Self(system_path))
     }
 
+    /// Like `from_raw`, but also cleans the path
+    pub fn from_raw_clean<P: AsRef<Path>>(raw: P) -> Result<Self, PathError> {
+        Ok(Self::from_raw(raw)?.clean())
+    }
+
+    /// Resolves `.` and `..` components and removes repeated separators,
+    /// purely lexically, like Go's `filepath.Clean`. `..` components that
+    /// would climb above the anchor are kept. Cleaning a path that refers to
+    /// the anchor itself gives the empty path.
+    ///
+    /// This doesn't touch the filesystem, so it works for paths that don't
+    /// exist yet, but it may change which file a path refers to when it goes
+    /// through a symlink.
+    pub fn clean(&self) -> Self {
+        let cleaned = self.0.clean();
+        if cleaned.as_os_str() == "." {
+            return Self::default();
+        }
+        Self(cleaned)
+    }
+
     pub(crate) fn as_path(&self) -> &Path {
         self.0.as_path()
     }
 
+    pub fn as_anchored_path(&self) -> &AnchoredSystemPath {
+        self.borrow()
+    }
+
+    pub fn push(&mut self, tail: impl AsRef<AnchoredSystemPath>) {
+        self.0.push(tail.as_ref().as_path());
+    }
+
+    pub fn join(&self, tail: impl AsRef<AnchoredSystemPath>) -> Self {
+        self.as_anchored_path().join(tail)
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        self.as_anchored_path().parent().map(ToOwned::to_owned)
+    }
+
+    pub fn components(&self) -> Components<'_> {
+        self.0.components()
+    }
+
+    pub fn strip_prefix(&self, prefix: impl AsRef<AnchoredSystemPath>) -> Result<Self, PathError> {
+        Ok(self.as_anchored_path().strip_prefix(prefix)?.to_owned())
+    }
+
+    pub fn relative_to(
+        &self,
+        base: impl AsRef<AnchoredSystemPath>,
+    ) -> Result<RelativeUnixPathBuf, PathError> {
+        self.as_anchored_path().relative_to(base)
+    }
+
+    pub fn starts_with(&self, base: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.as_anchored_path().starts_with(base)
+    }
+
+    pub fn ends_with(&self, child: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.as_anchored_path().ends_with(child)
+    }
+
+    pub fn file_name(&self) -> Option<&OsStr> {
+        self.0.file_name()
+    }
+
+    pub fn extension(&self) -> Option<&OsStr> {
+        self.0.extension()
+    }
+
     pub fn to_str(&self) -> Result<&str, PathError> {
         self.0
             .to_str()
@@ -53,21 +144,7 @@ impl AnchoredSystemPathBuf {
     }
 
     pub fn to_unix(&self) -> Result<RelativeUnixPathBuf, PathError> {
-        #[cfg(unix)]
-        {
-            use std::os::unix::ffi::OsStrExt;
-            let bytes = self.0.as_os_str().as_bytes();
-            return RelativeUnixPathBuf::new(bytes);
-        }
-        #[cfg(not(unix))]
-        {
-            use crate::IntoUnix;
-            let unix_buf = self.0.as_path().into_unix()?;
-            let unix_str = unix_buf
-                .to_str()
-                .ok_or_else(|| PathError::InvalidUnicode(unix_buf.to_string_lossy().to_string()))?;
-            return RelativeUnixPathBuf::new(unix_str.as_bytes());
-        }
+        Ok(self.as_anchored_path().to_unix()?.into_owned().into())
     }
 }
 
@@ -76,3 +153,45 @@ impl From<AnchoredSystemPathBuf> for PathBuf {
         path.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_anchored_system_path_buf() -> Result<()> {
+        let mut path = AnchoredSystemPathBuf::from_raw("packages")?;
+        path.push(AnchoredSystemPath::new("ui")?);
+        let manifest = path.join(AnchoredSystemPath::new("package.json")?);
+        assert_eq!(manifest.to_str()?, "packages/ui/package.json");
+        assert_eq!(manifest.parent(), Some(path.clone()));
+        assert_eq!(manifest.file_name(), Some(OsStr::new("package.json")));
+        assert_eq!(manifest.extension(), Some(OsStr::new("json")));
+        assert!(manifest.starts_with(&path));
+        assert!(manifest.ends_with(AnchoredSystemPath::new("ui/package.json")?));
+        assert_eq!(
+            manifest.strip_prefix(&path)?,
+            AnchoredSystemPathBuf::from_raw("package.json")?
+        );
+        assert!(path.strip_prefix(&manifest).is_err());
+
+        for (raw, cleaned) in [
+            (
+                "packages/./ui//src/../package.json",
+                "packages/ui/package.json",
+            ),
+            ("../shared/../../vendor", "../../vendor"),
+            ("packages/..", ""),
+        ] {
+            assert_eq!(
+                AnchoredSystemPathBuf::from_raw_clean(raw)?,
+                AnchoredSystemPathBuf::from_raw(cleaned)?
+            );
+        }
+
+        Ok(())
+    }
+}
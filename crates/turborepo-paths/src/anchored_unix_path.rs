@@ -0,0 +1,97 @@
+use std::fmt;
+
+use bstr::{BStr, ByteSlice};
+
+use crate::{AnchoredSystemPathBuf, AnchoredUnixPathBuf, PathError, RelativeUnixPath};
+
+/// A path relative to a specific directory that uses `/` as its separator.
+/// This is the borrowed counterpart of `AnchoredUnixPathBuf`, and the
+/// platform-independent form of an `AnchoredSystemPath`.
+#[repr(transparent)]
+pub struct AnchoredUnixPath {
+    inner: BStr,
+}
+
+impl AnchoredUnixPath {
+    /// Validates that `value` is a relative path. Does *not* convert `\` to
+    /// `/`; use `AnchoredSystemPath::to_unix` for that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turbopath::AnchoredUnixPath;
+    /// assert!(AnchoredUnixPath::new("packages/ui").is_ok());
+    /// assert!(AnchoredUnixPath::new("/packages/ui").is_err());
+    /// ```
+    pub fn new<P: AsRef<[u8]> + ?Sized>(value: &P) -> Result<&Self, PathError> {
+        let path = value.as_ref();
+        if path.first() == Some(&b'/') {
+            return Err(PathError::not_relative_error(path));
+        }
+        Ok(Self::new_unchecked(path))
+    }
+
+    pub(crate) fn new_unchecked(bytes: &[u8]) -> &Self {
+        let path = bytes.as_bstr();
+        // copied from stdlib path.rs: relies on the representation of
+        // AnchoredUnixPath being just a BStr, the same way Path relies on
+        // just being an OsStr
+        unsafe { &*(path as *const BStr as *const Self) }
+    }
+
+    pub fn as_str(&self) -> Result<&str, PathError> {
+        self.inner
+            .to_str()
+            .map_err(|_| PathError::InvalidUnicode(self.inner.to_str_lossy().to_string()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+
+    /// Borrows this path as a `RelativeUnixPath`, without copying it
+    pub fn as_relative_path(&self) -> &RelativeUnixPath {
+        RelativeUnixPath::new_unchecked(&self.inner)
+    }
+
+    pub fn to_anchored_system_path_buf(&self) -> Result<AnchoredSystemPathBuf, PathError> {
+        self.as_relative_path().to_anchored_system_path_buf()
+    }
+}
+
+impl ToOwned for AnchoredUnixPath {
+    type Owned = AnchoredUnixPathBuf;
+
+    fn to_owned(&self) -> Self::Owned {
+        AnchoredUnixPathBuf(self.inner.to_owned())
+    }
+}
+
+impl PartialEq for AnchoredUnixPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl Eq for AnchoredUnixPath {}
+
+impl AsRef<AnchoredUnixPath> for AnchoredUnixPath {
+    fn as_ref(&self) -> &AnchoredUnixPath {
+        self
+    }
+}
+
+impl AsRef<RelativeUnixPath> for AnchoredUnixPath {
+    fn as_ref(&self) -> &RelativeUnixPath {
+        self.as_relative_path()
+    }
+}
+
+impl fmt::Debug for AnchoredUnixPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_str() {
+            Ok(s) => write!(f, "{}", s),
+            Err(_) => write!(f, "Non-utf8 {:?}", &self.inner),
+        }
+    }
+}
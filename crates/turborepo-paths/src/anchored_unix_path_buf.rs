@@ -0,0 +1,61 @@
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+use bstr::BString;
+
+use crate::{AnchoredUnixPath, PathError, RelativeUnixPath, RelativeUnixPathBuf};
+
+/// The owned counterpart of `AnchoredUnixPath`
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct AnchoredUnixPathBuf(pub(crate) BString);
+
+impl AnchoredUnixPathBuf {
+    pub fn new(path: impl Into<Vec<u8>>) -> Result<Self, PathError> {
+        let bytes: Vec<u8> = path.into();
+        if bytes.first() == Some(&b'/') {
+            return Err(PathError::not_relative_error(&bytes));
+        }
+        Ok(Self(BString::new(bytes)))
+    }
+
+    pub fn as_anchored_path(&self) -> &AnchoredUnixPath {
+        self.borrow()
+    }
+}
+
+impl Borrow<AnchoredUnixPath> for AnchoredUnixPathBuf {
+    fn borrow(&self) -> &AnchoredUnixPath {
+        AnchoredUnixPath::new_unchecked(&self.0)
+    }
+}
+
+impl Deref for AnchoredUnixPathBuf {
+    type Target = AnchoredUnixPath;
+
+    fn deref(&self) -> &Self::Target {
+        self.borrow()
+    }
+}
+
+impl AsRef<AnchoredUnixPath> for AnchoredUnixPathBuf {
+    fn as_ref(&self) -> &AnchoredUnixPath {
+        self.borrow()
+    }
+}
+
+impl AsRef<RelativeUnixPath> for AnchoredUnixPathBuf {
+    fn as_ref(&self) -> &RelativeUnixPath {
+        self.as_relative_path()
+    }
+}
+
+impl From<AnchoredUnixPathBuf> for RelativeUnixPathBuf {
+    fn from(path: AnchoredUnixPathBuf) -> Self {
+        RelativeUnixPathBuf(path.0)
+    }
+}
+
+impl fmt::Debug for AnchoredUnixPathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_anchored_path().fmt(f)
+    }
+}
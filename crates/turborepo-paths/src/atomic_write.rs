@@ -0,0 +1,154 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::Serialize;
+
+use crate::{AbsoluteSystemPath, AbsoluteSystemPathBuf, PathError, PathOperation};
+
+// Distinguishes temporary files created by concurrent writes in this process
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl AbsoluteSystemPath {
+    /// Replaces the contents of this file with `contents`, such that readers,
+    /// and the file after a crash, see either the old or the new contents but
+    /// never a mix of both. The contents are written to a temporary file in
+    /// the same directory, synced to disk, and renamed over this path.
+    ///
+    /// If the file already exists its permissions are kept.
+    pub fn write_atomic(&self, contents: impl AsRef<[u8]>) -> Result<(), PathError> {
+        write_atomic(self.as_path(), contents.as_ref())
+            .map_err(PathError::io(PathOperation::Write, self.as_path()))
+    }
+
+    /// Like `write_atomic`, but writes `value` as pretty-printed JSON
+    pub fn write_json_atomic<T: Serialize + ?Sized>(&self, value: &T) -> Result<(), PathError> {
+        let contents = serde_json::to_vec_pretty(value)
+            .map_err(|err| PathError::io(PathOperation::Write, self.as_path())(err.into()))?;
+        self.write_atomic(contents)
+    }
+}
+
+impl AbsoluteSystemPathBuf {
+    pub fn write_atomic(&self, contents: impl AsRef<[u8]>) -> Result<(), PathError> {
+        self.as_absolute_path().write_atomic(contents)
+    }
+
+    pub fn write_json_atomic<T: Serialize + ?Sized>(&self, value: &T) -> Result<(), PathError> {
+        self.as_absolute_path().write_json_atomic(value)
+    }
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path has no file name",
+        ));
+    };
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = dir.join(temp_name);
+
+    let result = write_and_rename(path, &temp_path, contents);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    // The rename itself is only durable once the directory is synced. This
+    // isn't possible on Windows, where renames are journaled instead.
+    #[cfg(unix)]
+    fs::File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+fn write_and_rename(path: &Path, temp_path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp_path)?;
+    file.write_all(contents)?;
+    match fs::metadata(path) {
+        Ok(metadata) => file.set_permissions(metadata.permissions())?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::rename(temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?;
+        let config = dir.join_component("config.json");
+
+        config.write_atomic("{}")?;
+        assert_eq!(fs::read_to_string(&config)?, "{}");
+
+        let mut value = HashMap::new();
+        value.insert("teamId", "team_123");
+        config.write_json_atomic(&value)?;
+        assert_eq!(
+            fs::read_to_string(&config)?,
+            "{\n  \"teamId\": \"team_123\"\n}"
+        );
+
+        // Only the file itself is left behind
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?;
+        let token = dir.join_component("token");
+        token.write_atomic("old")?;
+        fs::set_permissions(&token, fs::Permissions::from_mode(0o600))?;
+
+        token.write_atomic("new")?;
+        let permissions = fs::metadata(&token)?.permissions();
+        assert_eq!(permissions.mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&token)?, "new");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic_missing_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?;
+        let err = dir
+            .join_components(&["missing", "config.json"])
+            .write_atomic("{}")
+            .unwrap_err();
+        assert!(err.is_io_error(io::ErrorKind::NotFound));
+        assert_eq!(err.operation(), Some(PathOperation::Write));
+
+        Ok(())
+    }
+}
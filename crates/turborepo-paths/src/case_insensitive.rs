@@ -0,0 +1,184 @@
+use std::{
+    collections::{hash_map, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::{AnchoredSystemPath, AnchoredSystemPathBuf};
+
+// Windows and macOS file systems are case-insensitive by default, so
+// `Packages/UI` and `packages/ui` are the same file there. Paths are compared
+// by their lowercased form, which covers the case folding those file systems
+// do for all but a handful of special cases.
+
+impl AnchoredSystemPath {
+    fn case_folded(&self) -> String {
+        self.as_path().to_string_lossy().to_lowercase()
+    }
+
+    /// Whether this path and `other` name the same file on a
+    /// case-insensitive file system
+    pub fn eq_ignore_case(&self, other: impl AsRef<AnchoredSystemPath>) -> bool {
+        let other = other.as_ref();
+        self.as_path() == other.as_path() || self.case_folded() == other.case_folded()
+    }
+
+    /// Hashes this path such that paths that are `eq_ignore_case` hash the
+    /// same
+    pub fn hash_ignore_case<H: Hasher>(&self, state: &mut H) {
+        self.case_folded().hash(state);
+    }
+}
+
+impl AnchoredSystemPathBuf {
+    pub fn eq_ignore_case(&self, other: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.as_anchored_path().eq_ignore_case(other)
+    }
+
+    pub fn hash_ignore_case<H: Hasher>(&self, state: &mut H) {
+        self.as_anchored_path().hash_ignore_case(state)
+    }
+}
+
+/// A map keyed by anchored paths that treats paths differing only in case as
+/// the same key, for tracking files on case-insensitive file systems. Each
+/// entry keeps the spelling of the path it was first inserted with.
+#[derive(Debug, Clone)]
+pub struct CaseInsensitivePathMap<V> {
+    entries: HashMap<String, (AnchoredSystemPathBuf, V)>,
+}
+
+impl<V> Default for CaseInsensitivePathMap<V> {
+    fn default() -> Self {
+        CaseInsensitivePathMap {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<V> CaseInsensitivePathMap<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Inserts `value` for `path`, returning the value previously stored for
+    /// any spelling of `path`
+    pub fn insert(&mut self, path: AnchoredSystemPathBuf, value: V) -> Option<V> {
+        match self.entries.entry(path.as_anchored_path().case_folded()) {
+            hash_map::Entry::Occupied(mut entry) => {
+                Some(std::mem::replace(&mut entry.get_mut().1, value))
+            }
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert((path, value));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, path: impl AsRef<AnchoredSystemPath>) -> Option<&V> {
+        self.entries
+            .get(&path.as_ref().case_folded())
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, path: impl AsRef<AnchoredSystemPath>) -> Option<&mut V> {
+        self.entries
+            .get_mut(&path.as_ref().case_folded())
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the spelling `path` was stored with
+    pub fn get_key(&self, path: impl AsRef<AnchoredSystemPath>) -> Option<&AnchoredSystemPathBuf> {
+        self.entries
+            .get(&path.as_ref().case_folded())
+            .map(|(path, _)| path)
+    }
+
+    pub fn contains_key(&self, path: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.entries.contains_key(&path.as_ref().case_folded())
+    }
+
+    pub fn remove(&mut self, path: impl AsRef<AnchoredSystemPath>) -> Option<V> {
+        self.entries
+            .remove(&path.as_ref().case_folded())
+            .map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&AnchoredSystemPathBuf, &V)> {
+        self.entries.values().map(|(path, value)| (path, value))
+    }
+}
+
+impl<V> FromIterator<(AnchoredSystemPathBuf, V)> for CaseInsensitivePathMap<V> {
+    fn from_iter<I: IntoIterator<Item = (AnchoredSystemPathBuf, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        for (path, value) in iter {
+            map.insert(path, value);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+    use anyhow::Result;
+
+    use super::*;
+
+    fn hash_ignore_case(state: &RandomState, path: &AnchoredSystemPathBuf) -> u64 {
+        let mut hasher = state.build_hasher();
+        path.hash_ignore_case(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_case_insensitive_comparison() -> Result<()> {
+        let lower = AnchoredSystemPathBuf::from_raw("packages/ui/README.md")?;
+        let upper = AnchoredSystemPathBuf::from_raw("Packages/UI/readme.md")?;
+        assert_ne!(lower, upper);
+        assert!(lower.eq_ignore_case(&upper));
+        let state = RandomState::new();
+        assert_eq!(
+            hash_ignore_case(&state, &lower),
+            hash_ignore_case(&state, &upper)
+        );
+        assert!(!lower.eq_ignore_case(AnchoredSystemPathBuf::from_raw("packages/ui")?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_insensitive_path_map() -> Result<()> {
+        let mut map = CaseInsensitivePathMap::new();
+        assert_eq!(
+            map.insert(AnchoredSystemPathBuf::from_raw("src/Index.ts")?, 1),
+            None
+        );
+        assert_eq!(
+            map.insert(AnchoredSystemPathBuf::from_raw("src/index.ts")?, 2),
+            Some(1)
+        );
+        assert_eq!(map.len(), 1);
+
+        let lookup = AnchoredSystemPathBuf::from_raw("SRC/INDEX.TS")?;
+        assert_eq!(map.get(&lookup), Some(&2));
+        assert_eq!(
+            map.get_key(&lookup),
+            Some(&AnchoredSystemPathBuf::from_raw("src/Index.ts")?)
+        );
+        *map.get_mut(&lookup).unwrap() += 1;
+        assert_eq!(map.remove(&lookup), Some(3));
+        assert!(map.is_empty());
+
+        Ok(())
+    }
+}
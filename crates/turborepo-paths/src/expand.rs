@@ -0,0 +1,199 @@
+use std::{env, path::PathBuf};
+
+use crate::{AbsoluteSystemPathBuf, PathError};
+
+impl AbsoluteSystemPathBuf {
+    /// Expands a path from user configuration, e.g. a cache directory
+    /// override, into an absolute path:
+    ///
+    /// - a leading `~` becomes the current user's home directory, and a leading
+    ///   `~user` that user's home directory (unix only)
+    /// - `${VAR}` anywhere becomes the value of the environment variable `VAR`,
+    ///   which must be set
+    ///
+    /// `$` that isn't followed by `{` is kept as is. The expanded path must
+    /// be absolute.
+    pub fn expand(input: &str) -> Result<Self, PathError> {
+        let expanded = expand_with(input, |name| env::var(name).ok(), home_dir)?;
+        Self::new(expanded)
+    }
+}
+
+// Does the expansion for `expand`, with the environment and home directory
+// lookups passed in so they can be faked in tests
+fn expand_with(
+    input: &str,
+    var: impl Fn(&str) -> Option<String>,
+    home_dir: impl Fn(Option<&str>) -> Option<PathBuf>,
+) -> Result<String, PathError> {
+    let invalid = |reason: &str| PathError::InvalidExpansion(input.to_string(), reason.to_string());
+    let mut expanded = String::with_capacity(input.len());
+    let mut rest = input;
+
+    if let Some(tilde) = rest.strip_prefix('~') {
+        let user_end = tilde.find(is_separator).unwrap_or(tilde.len());
+        let user = &tilde[..user_end];
+        let user = (!user.is_empty()).then_some(user);
+        let home = home_dir(user).ok_or_else(|| match user {
+            Some(user) => PathError::UnknownUser(user.to_string()),
+            None => invalid("could not find the home directory"),
+        })?;
+        let home = home
+            .to_str()
+            .ok_or_else(|| PathError::InvalidUnicode(home.to_string_lossy().to_string()))?;
+        expanded.push_str(home);
+        rest = &tilde[user_end..];
+    }
+
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let name_start = start + 2;
+        let name_len = rest[name_start..]
+            .find('}')
+            .ok_or_else(|| invalid("unclosed '${'"))?;
+        let name = &rest[name_start..name_start + name_len];
+        if name.is_empty() {
+            return Err(invalid("empty variable name"));
+        }
+        let value = var(name).ok_or_else(|| PathError::UnsetVariable(name.to_string()))?;
+        expanded.push_str(&value);
+        rest = &rest[name_start + name_len + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == std::path::MAIN_SEPARATOR
+}
+
+fn home_dir(user: Option<&str>) -> Option<PathBuf> {
+    #[cfg(windows)]
+    let home_var = "USERPROFILE";
+    #[cfg(not(windows))]
+    let home_var = "HOME";
+
+    match user {
+        None => env::var_os(home_var)
+            .filter(|home| !home.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| passwd_home_dir(None)),
+        Some(user) => passwd_home_dir(Some(user)),
+    }
+}
+
+// Looks up the home directory of `user`, or of the current user, in the user
+// database
+#[cfg(unix)]
+fn passwd_home_dir(user: Option<&str>) -> Option<PathBuf> {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        mem::MaybeUninit,
+        os::unix::ffi::OsStrExt,
+        ptr,
+    };
+
+    let user = user.map(CString::new).transpose().ok()?;
+    let mut buffer = vec![0; 1024];
+    let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+    let mut result = ptr::null_mut();
+    loop {
+        // SAFETY: the buffers outlive the call, and their lengths are passed
+        // along with them
+        let status = unsafe {
+            match &user {
+                Some(user) => libc::getpwnam_r(
+                    user.as_ptr(),
+                    passwd.as_mut_ptr(),
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    &mut result,
+                ),
+                None => libc::getpwuid_r(
+                    libc::getuid(),
+                    passwd.as_mut_ptr(),
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    &mut result,
+                ),
+            }
+        };
+        if status == libc::ERANGE && buffer.len() < 1 << 20 {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        break;
+    }
+    if result.is_null() {
+        return None;
+    }
+    // SAFETY: a non-null result points at `passwd`, whose strings live in
+    // `buffer`
+    let home = unsafe { CStr::from_ptr((*result).pw_dir) };
+    Some(PathBuf::from(OsStr::from_bytes(home.to_bytes())))
+}
+
+#[cfg(not(unix))]
+fn passwd_home_dir(_user: Option<&str>) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+
+    fn expand(input: &str) -> Result<String, PathError> {
+        expand_with(
+            input,
+            |name| (name == "XDG_CACHE_HOME").then(|| "/home/turbo/.cache".to_string()),
+            |user| match user {
+                None => Some(PathBuf::from("/home/turbo")),
+                Some("vercel") => Some(PathBuf::from("/home/vercel")),
+                Some(_) => None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("~").unwrap(), "/home/turbo");
+        assert_eq!(expand("~/.turbo").unwrap(), "/home/turbo/.turbo");
+        assert_eq!(expand("~vercel/cache").unwrap(), "/home/vercel/cache");
+        assert_eq!(
+            expand("${XDG_CACHE_HOME}/turbo").unwrap(),
+            "/home/turbo/.cache/turbo"
+        );
+        // Only a leading `~` is expanded, and only `${...}` is a variable
+        assert_eq!(expand("/srv/~/$HOME").unwrap(), "/srv/~/$HOME");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_matches!(
+            expand("~nobody/cache"),
+            Err(PathError::UnknownUser(user)) if user == "nobody"
+        );
+        assert_matches!(
+            expand("${TURBO_UNSET}/cache"),
+            Err(PathError::UnsetVariable(name)) if name == "TURBO_UNSET"
+        );
+        assert_matches!(
+            expand("${XDG_CACHE_HOME"),
+            Err(PathError::InvalidExpansion(_, _))
+        );
+        assert_matches!(expand("${}"), Err(PathError::InvalidExpansion(_, _)));
+        assert_matches!(
+            AbsoluteSystemPathBuf::expand("relative/${TURBO_UNSET_TOO}"),
+            Err(PathError::UnsetVariable(_))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_current_user_home_dir() {
+        assert!(passwd_home_dir(None).is_some());
+    }
+}
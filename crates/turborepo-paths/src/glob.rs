@@ -0,0 +1,410 @@
+use std::path::Component;
+
+use crate::{AnchoredSystemPath, PathError, RelativeUnixPath};
+
+/// A glob pattern, compiled once and matched against typed paths.
+///
+/// Patterns always use `/` as the separator and are matched component by
+/// component, so the same pattern works for system paths on every platform.
+/// Supported syntax:
+///
+/// - `*` matches any characters within a component, `?` a single character
+/// - `**` as a whole component matches any number of components
+/// - `[abc]`, `[a-z]` and `[!a-z]` match a character class
+/// - `{a,b}` matches either alternative, and alternatives can nest
+/// - `\` escapes the character after it
+///
+/// # Examples
+///
+/// ```
+/// use turbopath::{Glob, RelativeUnixPathBuf};
+/// let glob = Glob::new("dist/**/*.{js,map}").unwrap();
+/// assert!(glob.is_match_unix(&RelativeUnixPathBuf::new("dist/chunks/main.js").unwrap()));
+/// assert!(!glob.is_match_unix(&RelativeUnixPathBuf::new("src/main.js").unwrap()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    // Brace expansion turns a pattern into several patterns without braces
+    alternatives: Vec<Vec<Segment>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    AnyComponents,
+    Component(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyChars,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, PathError> {
+        let invalid =
+            |reason: &str| PathError::InvalidGlob(pattern.to_string(), reason.to_string());
+        let alternatives = expand_braces(pattern)
+            .map_err(invalid)?
+            .iter()
+            .map(|pattern| compile(pattern))
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?;
+        Ok(Glob {
+            pattern: pattern.to_string(),
+            alternatives,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, path: impl AsRef<AnchoredSystemPath>) -> bool {
+        let mut components = Vec::new();
+        for component in path.as_ref().components() {
+            match component {
+                Component::Normal(component) => match component.to_str() {
+                    Some(component) => components.push(component),
+                    // Patterns are always valid UTF-8, so can't match
+                    None => return false,
+                },
+                Component::CurDir => {}
+                _ => components.push(".."),
+            }
+        }
+        self.is_match_components(&components)
+    }
+
+    pub fn is_match_unix(&self, path: impl AsRef<RelativeUnixPath>) -> bool {
+        let Ok(path) = path.as_ref().as_str() else {
+            return false;
+        };
+        let components: Vec<_> = path
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect();
+        self.is_match_components(&components)
+    }
+
+    fn is_match_components(&self, components: &[&str]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|segments| match_segments(segments, components))
+    }
+}
+
+/// A set of globs matched against a path in one call, e.g. all of a task's
+/// output globs
+#[derive(Debug, Clone, Default)]
+pub struct GlobSet {
+    globs: Vec<Glob>,
+}
+
+impl GlobSet {
+    pub fn new<S: AsRef<str>>(patterns: impl IntoIterator<Item = S>) -> Result<Self, PathError> {
+        let globs = patterns
+            .into_iter()
+            .map(|pattern| Glob::new(pattern.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(GlobSet { globs })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+
+    pub fn is_match(&self, path: impl AsRef<AnchoredSystemPath>) -> bool {
+        let path = path.as_ref();
+        self.globs.iter().any(|glob| glob.is_match(path))
+    }
+
+    pub fn is_match_unix(&self, path: impl AsRef<RelativeUnixPath>) -> bool {
+        let path = path.as_ref();
+        self.globs.iter().any(|glob| glob.is_match_unix(path))
+    }
+
+    /// Returns the indices of the globs that match `path`
+    pub fn matches(&self, path: impl AsRef<AnchoredSystemPath>) -> Vec<usize> {
+        let path = path.as_ref();
+        self.globs
+            .iter()
+            .enumerate()
+            .filter(|(_, glob)| glob.is_match(path))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl FromIterator<Glob> for GlobSet {
+    fn from_iter<I: IntoIterator<Item = Glob>>(iter: I) -> Self {
+        GlobSet {
+            globs: iter.into_iter().collect(),
+        }
+    }
+}
+
+// Expands the first top-level `{...}` group in `pattern`, recursing into each
+// alternative until no braces are left
+fn expand_braces(pattern: &str) -> Result<Vec<String>, &'static str> {
+    let mut open = None;
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut escaped = false;
+    for (index, c) in pattern.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '{' => {
+                if depth == 0 {
+                    open = Some(index);
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(index),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let open = open.unwrap();
+                    let prefix = &pattern[..open];
+                    let suffix = &pattern[index + 1..];
+                    let bounds: Vec<_> = std::iter::once(open)
+                        .chain(commas)
+                        .chain(std::iter::once(index))
+                        .collect();
+                    let mut expanded = Vec::new();
+                    for window in bounds.windows(2) {
+                        let alternative = &pattern[window[0] + 1..window[1]];
+                        let rest = format!("{}{}{}", prefix, alternative, suffix);
+                        expanded.extend(expand_braces(&rest)?);
+                    }
+                    return Ok(expanded);
+                }
+            }
+            '}' => return Err("unmatched '}'"),
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err("unclosed '{'");
+    }
+    Ok(vec![pattern.to_string()])
+}
+
+fn compile(pattern: &str) -> Result<Vec<Segment>, &'static str> {
+    pattern
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .map(|component| {
+            if component == "**" {
+                Ok(Segment::AnyComponents)
+            } else {
+                Ok(Segment::Component(tokenize(component)?))
+            }
+        })
+        .collect()
+}
+
+fn tokenize(component: &str) -> Result<Vec<Token>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut chars = component.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '*' => {
+                if tokens.last() == Some(&Token::AnyChars) {
+                    continue;
+                }
+                Token::AnyChars
+            }
+            '?' => Token::AnyChar,
+            '\\' => Token::Literal(chars.next().ok_or("trailing '\\'")?),
+            '[' => {
+                let negated = matches!(chars.peek(), Some('!' | '^'));
+                if negated {
+                    chars.next();
+                }
+                let mut ranges = Vec::new();
+                loop {
+                    let start = match chars.next().ok_or("unclosed '['")? {
+                        // A `]` right after the `[` is part of the class
+                        ']' if !ranges.is_empty() => break,
+                        '\\' => chars.next().ok_or("unclosed '['")?,
+                        c => c,
+                    };
+                    let end = if chars.peek() == Some(&'-') {
+                        chars.next();
+                        match chars.next().ok_or("unclosed '['")? {
+                            // A trailing `-` is a literal
+                            ']' => {
+                                ranges.push((start, start));
+                                ranges.push(('-', '-'));
+                                break;
+                            }
+                            '\\' => chars.next().ok_or("unclosed '['")?,
+                            end => end,
+                        }
+                    } else {
+                        start
+                    };
+                    if end < start {
+                        return Err("invalid character range");
+                    }
+                    ranges.push((start, end));
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Literal(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn match_segments(segments: &[Segment], components: &[&str]) -> bool {
+    let components: Vec<Vec<char>> = components
+        .iter()
+        .map(|component| component.chars().collect())
+        .collect();
+    match_wildcards(
+        segments,
+        &components,
+        |segment| *segment == Segment::AnyComponents,
+        |segment, component| match segment {
+            Segment::Component(tokens) => match_tokens(tokens, component),
+            Segment::AnyComponents => unreachable!(),
+        },
+    )
+}
+
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    match_wildcards(
+        tokens,
+        chars,
+        |token| *token == Token::AnyChars,
+        |token, c| match token {
+            Token::Literal(literal) => literal == c,
+            Token::AnyChar => true,
+            Token::Class { negated, ranges } => {
+                ranges
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(c))
+                    != *negated
+            }
+            Token::AnyChars => unreachable!(),
+        },
+    )
+}
+
+// Matches `items` against `patterns`, where a wildcard matches any run of
+// items and every other pattern exactly one item. On a mismatch only the most
+// recent wildcard needs to absorb another item, as it can also absorb anything
+// an earlier wildcard would have. That bounds matching to
+// O(patterns * items) steps, where trying every split would take exponential
+// time on patterns like `a*a*a*b`.
+fn match_wildcards<P, I>(
+    patterns: &[P],
+    items: &[I],
+    is_wildcard: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &I) -> bool,
+) -> bool {
+    let (mut pattern, mut item) = (0, 0);
+    // The pattern after the most recent wildcard, and the first item that
+    // wildcard hasn't absorbed
+    let mut backtrack = None;
+    while item < items.len() {
+        if pattern < patterns.len() && is_wildcard(&patterns[pattern]) {
+            pattern += 1;
+            backtrack = Some((pattern, item));
+        } else if pattern < patterns.len() && matches(&patterns[pattern], &items[item]) {
+            pattern += 1;
+            item += 1;
+        } else if let Some((wildcard_end, absorbed)) = backtrack {
+            pattern = wildcard_end;
+            item = absorbed + 1;
+            backtrack = Some((wildcard_end, item));
+        } else {
+            return false;
+        }
+    }
+    patterns[pattern..].iter().all(is_wildcard)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::{AnchoredSystemPathBuf, RelativeUnixPathBuf};
+
+    fn is_match(pattern: &str, path: &str) -> bool {
+        let glob = Glob::new(pattern).unwrap();
+        let unix_match = glob.is_match_unix(RelativeUnixPathBuf::new(path).unwrap());
+        let system_match = glob.is_match(AnchoredSystemPathBuf::from_raw(path).unwrap());
+        assert_eq!(unix_match, system_match, "{} against {}", pattern, path);
+        unix_match
+    }
+
+    #[test]
+    fn test_glob_syntax() {
+        assert!(is_match("dist/*.js", "dist/main.js"));
+        assert!(!is_match("dist/*.js", "dist/chunks/main.js"));
+        assert!(is_match("dist/**/*.js", "dist/main.js"));
+        assert!(is_match("dist/**/*.js", "dist/chunks/deep/main.js"));
+        assert!(is_match("dist/**", "dist/chunks/main.js"));
+        assert!(is_match(
+            "**/node_modules/**",
+            "packages/ui/node_modules/react/index.js"
+        ));
+        assert!(is_match("file?.txt", "file1.txt"));
+        assert!(!is_match("file?.txt", "file.txt"));
+        assert!(is_match("file[0-9].txt", "file7.txt"));
+        assert!(!is_match("file[!0-9].txt", "file7.txt"));
+        assert!(is_match("file[]a].txt", "file].txt"));
+        assert!(is_match("*.{js,{map,d.ts}}", "index.d.ts"));
+        assert!(!is_match("*.{js,map}", "index.ts"));
+        assert!(is_match("\\*.txt", "*.txt"));
+        assert!(!is_match("\\*.txt", "a.txt"));
+        assert!(is_match("./dist//main.js", "dist/main.js"));
+    }
+
+    #[test]
+    fn test_pathological_globs() {
+        // These take exponential time with naive backtracking
+        let path = ["a"; 64].join("");
+        assert!(!is_match("a*a*a*a*a*a*a*a*a*a*b", &path));
+        assert!(is_match("a*a*a*a*a*a*a*a*a*a*a", &path));
+
+        let path = ["a"; 64].join("/");
+        assert!(!is_match("**/**/**/**/**/**/**/**/x", &path));
+        assert!(is_match("**/**/**/**/**/**/**/**/a", &path));
+    }
+
+    #[test]
+    fn test_invalid_globs() {
+        for pattern in ["{a,b", "a}", "[a-", "[z-a]", "trailing\\"] {
+            assert_matches!(Glob::new(pattern), Err(PathError::InvalidGlob(_, _)));
+        }
+    }
+
+    #[test]
+    fn test_glob_set() -> Result<()> {
+        let globs = GlobSet::new([".next/**", "build/**", "dist/**"])?;
+        let path = AnchoredSystemPathBuf::from_raw("dist/index.js")?;
+        assert!(globs.is_match(&path));
+        assert_eq!(globs.matches(&path), [2]);
+        assert!(!globs.is_match(AnchoredSystemPathBuf::from_raw("src/index.ts")?));
+
+        Ok(())
+    }
+}
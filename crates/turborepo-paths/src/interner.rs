@@ -0,0 +1,116 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::AnchoredSystemPath;
+
+/// A handle to a path stored in a `PathInterner`. Ids are only meaningful for
+/// the interner that handed them out, and are cheap to copy, compare and hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathId(u32);
+
+impl PathId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Stores each distinct anchored path once and hands out `PathId`s for them,
+/// for structures such as file watchers, hashers and task graphs that hold
+/// many references to the same paths.
+///
+/// Paths are never removed, so an id stays valid for the lifetime of the
+/// interner.
+#[derive(Debug, Default)]
+pub struct PathInterner {
+    // The map and the list share each path's allocation
+    paths: Vec<Arc<Path>>,
+    ids: HashMap<Arc<Path>, PathId>,
+}
+
+impl PathInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the id for `path`, storing it if it hasn't been interned yet
+    pub fn intern(&mut self, path: impl AsRef<AnchoredSystemPath>) -> PathId {
+        let path = path.as_ref().as_path();
+        if let Some(id) = self.ids.get(path) {
+            return *id;
+        }
+        let id = PathId(
+            self.paths
+                .len()
+                .try_into()
+                .expect("more than u32::MAX interned paths"),
+        );
+        let path: Arc<Path> = Arc::from(path);
+        self.paths.push(path.clone());
+        self.ids.insert(path, id);
+        id
+    }
+
+    /// Returns the id for `path` if it has been interned
+    pub fn get(&self, path: impl AsRef<AnchoredSystemPath>) -> Option<PathId> {
+        self.ids.get(path.as_ref().as_path()).copied()
+    }
+
+    /// Returns the path for `id`.
+    ///
+    /// Panics if `id` was handed out by a different interner.
+    pub fn resolve(&self, id: PathId) -> &AnchoredSystemPath {
+        AnchoredSystemPath::new_unchecked(&self.paths[id.index()])
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PathId, &AnchoredSystemPath)> {
+        self.paths.iter().enumerate().map(|(index, path)| {
+            (
+                PathId(index as u32),
+                AnchoredSystemPath::new_unchecked(path),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::AnchoredSystemPathBuf;
+
+    #[test]
+    fn test_interning() -> Result<()> {
+        let mut interner = PathInterner::new();
+        let index = AnchoredSystemPathBuf::from_raw("packages/ui/index.ts")?;
+        let readme = AnchoredSystemPathBuf::from_raw("packages/ui/README.md")?;
+
+        let id = interner.intern(&index);
+        assert_eq!(interner.intern(&readme), PathId(1));
+        assert_eq!(interner.intern(index.clone()), id);
+        assert_eq!(interner.len(), 2);
+
+        assert_eq!(interner.resolve(id).to_owned(), index);
+        assert_eq!(interner.get(&readme), Some(PathId(1)));
+        assert_eq!(
+            interner.get(AnchoredSystemPathBuf::from_raw("packages/ui")?),
+            None
+        );
+        assert_eq!(
+            interner
+                .iter()
+                .map(|(_, path)| path.to_owned())
+                .collect::<Vec<_>>(),
+            [index, readme]
+        );
+
+        Ok(())
+    }
+}
@@ -12,6 +12,8 @@
 /// - `AnchoredSystemPath(Buf)`: a path that is relative to a specific directory
 ///   and uses the system's path separator. Used for handling files relative to
 ///   the repository root.
+/// - `AnchoredUnixPath(Buf)`: an anchored path that uses the unix path
+///   separator. Used when an anchored path is written to a cache.
 ///
 /// As in `std::path`, there are `Path` and `PathBuf` variants of each path
 /// type, that indicate whether the path is borrowed or owned.
@@ -28,21 +30,50 @@
 /// should be considered unsafe
 mod absolute_system_path;
 mod absolute_system_path_buf;
+mod anchored_system_path;
 mod anchored_system_path_buf;
+mod anchored_unix_path;
+mod anchored_unix_path_buf;
+mod atomic_write;
+mod case_insensitive;
+mod expand;
+mod glob;
+mod interner;
+mod macros;
+mod metadata;
+mod normalization;
+mod normalize;
 mod relative_unix_path;
 mod relative_unix_path_buf;
+mod serialization;
+mod trie;
+mod validation;
+mod walk;
 
 use std::{
-    io,
+    fmt, io,
     path::{Path, PathBuf},
 };
 
 pub use absolute_system_path::AbsoluteSystemPath;
 pub use absolute_system_path_buf::AbsoluteSystemPathBuf;
+pub use anchored_system_path::AnchoredSystemPath;
 pub use anchored_system_path_buf::AnchoredSystemPathBuf;
+pub use anchored_unix_path::AnchoredUnixPath;
+pub use anchored_unix_path_buf::AnchoredUnixPathBuf;
+pub use case_insensitive::CaseInsensitivePathMap;
+pub use glob::{Glob, GlobSet};
+pub use interner::{PathId, PathInterner};
+#[doc(hidden)]
+pub use macros::__private;
+pub use metadata::{FileKind, FileMetadata};
+pub use normalization::NormalizedPath;
 use path_slash::{PathBufExt, PathExt};
 pub use relative_unix_path::RelativeUnixPath;
 pub use relative_unix_path_buf::{RelativeUnixPathBuf, RelativeUnixPathBufTestExt};
+pub use trie::{PathTrie, PathTrieIter};
+pub use validation::{validate_output_spec, OutputSpecDiagnostic, OutputSpecError};
+pub use walk::{walk, Walk};
 
 #[derive(Debug, thiserror::Error)]
 pub enum PathError {
@@ -52,21 +83,122 @@ pub enum PathError {
     NotAbsolute(PathBuf),
     #[error("Path is not relative: {0}")]
     NotRelative(String),
-    #[error("Path {0} is not parent of {1}")]
-    NotParent(String, String),
+    #[error(
+        "Cannot {operation} {}: {} is not a parent of it",
+        .path.display(),
+        .parent.display()
+    )]
+    NotParent {
+        operation: PathOperation,
+        parent: PathBuf,
+        path: PathBuf,
+    },
     #[error("Path {0} is not a unix path")]
     NotUnix(String),
     #[error("Path {0} is not a system path")]
     NotSystem(String),
-    #[error("IO Error {0}")]
-    IO(#[from] io::Error),
-    #[error("{0} is not a prefix for {1}")]
-    PrefixError(String, String),
+    #[error("Failed to {operation} {}", describe_paths(.path, .other))]
+    IO {
+        operation: PathOperation,
+        path: PathBuf,
+        // The other path involved, e.g. the target of a symlink
+        other: Option<PathBuf>,
+        #[source]
+        source: io::Error,
+    },
+    #[error(
+        "No relative path leads from {} to {}",
+        .base.display(),
+        .path.display()
+    )]
+    NoRelativePath { base: PathBuf, path: PathBuf },
+    #[error("Invalid glob {0}: {1}")]
+    InvalidGlob(String, String),
+    #[error("Path {} escapes {}", .path.display(), .base.display())]
+    Escapes { base: PathBuf, path: PathBuf },
+    #[error(
+        "Symlink {} leads back to its ancestor {}",
+        .path.display(),
+        .target.display()
+    )]
+    SymlinkLoop { path: PathBuf, target: PathBuf },
+    #[error("Cannot expand {0}: {1}")]
+    InvalidExpansion(String, String),
+    #[error("Cannot expand ~{0}: no such user")]
+    UnknownUser(String),
+    #[error("Cannot expand ${{{0}}}: environment variable is not set")]
+    UnsetVariable(String),
+}
+
+fn describe_paths(path: &Path, other: &Option<PathBuf>) -> String {
+    match other {
+        Some(other) => format!("{} ({})", path.display(), other.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// The operation that failed, as reported by a `PathError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathOperation {
+    Anchor,
+    Strip,
+    Relativize,
+    Join,
+    Resolve,
+    Read,
+    Write,
+    Stat,
+    SetPermissions,
+    Symlink,
+}
+
+impl fmt::Display for PathOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PathOperation::Anchor => "anchor",
+            PathOperation::Strip => "strip prefix from",
+            PathOperation::Relativize => "find relative path to",
+            PathOperation::Join => "join",
+            PathOperation::Resolve => "resolve",
+            PathOperation::Read => "read",
+            PathOperation::Write => "write",
+            PathOperation::Stat => "stat",
+            PathOperation::SetPermissions => "set permissions of",
+            PathOperation::Symlink => "create symlink",
+        })
+    }
 }
 
 impl PathError {
     pub fn is_io_error(&self, kind: io::ErrorKind) -> bool {
-        matches!(self, PathError::IO(err) if err.kind() == kind)
+        matches!(self, PathError::IO { source, .. } if source.kind() == kind)
+    }
+
+    /// The operation that failed, if the error came from an operation rather
+    /// than from validating a single path
+    pub fn operation(&self) -> Option<PathOperation> {
+        match self {
+            PathError::NotParent { operation, .. } | PathError::IO { operation, .. } => {
+                Some(*operation)
+            }
+            PathError::NoRelativePath { .. } => Some(PathOperation::Relativize),
+            PathError::Escapes { .. } => Some(PathOperation::Join),
+            _ => None,
+        }
+    }
+
+    // For use with `map_err` on the result of an IO operation on `path`
+    pub(crate) fn io(
+        operation: PathOperation,
+        path: impl Into<PathBuf>,
+    ) -> impl FnOnce(io::Error) -> PathError {
+        let path = path.into();
+        move |source| PathError::IO {
+            operation,
+            path,
+            other: None,
+            source,
+        }
     }
 
     pub(crate) fn not_relative_error(bytes: &[u8]) -> PathError {
@@ -0,0 +1,134 @@
+/// Creates an `AnchoredSystemPathBuf` from a string literal, checking at
+/// compile time that the literal is a relative path. Components are separated
+/// with `/`, which is converted to the system separator.
+///
+/// # Examples
+///
+/// ```
+/// use turbopath::anchored_path;
+/// let path = anchored_path!("packages/ui");
+/// assert_eq!(path.file_name().unwrap(), "ui");
+/// ```
+///
+/// ```compile_fail
+/// let path = turbopath::anchored_path!("/packages/ui");
+/// ```
+#[macro_export]
+macro_rules! anchored_path {
+    ($path:literal) => {{
+        const _: () = assert!(
+            $crate::__private::is_anchored_literal($path),
+            concat!("not an anchored path: ", $path)
+        );
+        $crate::__private::anchored_path_unchecked($path)
+    }};
+}
+
+/// Creates an `AbsoluteSystemPathBuf` from a string literal, checking at
+/// compile time that the literal is an absolute path on the target platform.
+/// Components can be separated with `/`, which is converted to the system
+/// separator.
+///
+/// # Examples
+///
+/// ```
+/// use turbopath::abs_path;
+/// #[cfg(unix)]
+/// let path = abs_path!("/opt/turbo");
+/// #[cfg(windows)]
+/// let path = abs_path!("C:/opt/turbo");
+/// assert_eq!(path.file_name().unwrap(), "turbo");
+/// ```
+///
+/// ```compile_fail
+/// let path = turbopath::abs_path!("opt/turbo");
+/// ```
+#[macro_export]
+macro_rules! abs_path {
+    ($path:literal) => {{
+        const _: () = assert!(
+            $crate::__private::is_absolute_literal($path),
+            concat!("not an absolute path: ", $path)
+        );
+        $crate::__private::absolute_path_unchecked($path)
+    }};
+}
+
+// Used by the macros above. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use std::path::PathBuf;
+
+    use path_slash::PathBufExt;
+
+    use crate::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+
+    const fn is_separator(byte: u8) -> bool {
+        byte == b'/' || (cfg!(windows) && byte == b'\\')
+    }
+
+    const fn has_drive_prefix(path: &[u8]) -> bool {
+        path.len() >= 2 && path[0].is_ascii_alphabetic() && path[1] == b':'
+    }
+
+    pub const fn is_anchored_literal(path: &str) -> bool {
+        let path = path.as_bytes();
+        let rooted = !path.is_empty() && is_separator(path[0]);
+        !(rooted || cfg!(windows) && has_drive_prefix(path))
+    }
+
+    pub const fn is_absolute_literal(path: &str) -> bool {
+        let path = path.as_bytes();
+        if cfg!(windows) {
+            has_drive_prefix(path) && path.len() >= 3 && is_separator(path[2])
+        } else {
+            !path.is_empty() && path[0] == b'/'
+        }
+    }
+
+    pub fn anchored_path_unchecked(path: &str) -> AnchoredSystemPathBuf {
+        AnchoredSystemPathBuf(PathBuf::from_slash(path))
+    }
+
+    pub fn absolute_path_unchecked(path: &str) -> AbsoluteSystemPathBuf {
+        AbsoluteSystemPathBuf(PathBuf::from_slash(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::__private::*;
+
+    #[test]
+    fn test_path_literals() {
+        assert!(is_anchored_literal("packages/ui"));
+        assert!(is_anchored_literal(""));
+        assert!(!is_anchored_literal("/packages/ui"));
+        assert_eq!(
+            anchored_path!("packages/ui").as_path(),
+            Path::new("packages").join("ui")
+        );
+
+        #[cfg(unix)]
+        {
+            assert!(is_absolute_literal("/opt/turbo"));
+            assert!(!is_absolute_literal("opt/turbo"));
+            assert!(!is_absolute_literal("C:/opt/turbo"));
+            assert_eq!(abs_path!("/opt/turbo").as_path(), Path::new("/opt/turbo"));
+        }
+
+        #[cfg(windows)]
+        {
+            assert!(is_absolute_literal("C:/opt/turbo"));
+            assert!(is_absolute_literal("C:\\opt\\turbo"));
+            assert!(!is_absolute_literal("/opt/turbo"));
+            assert!(!is_anchored_literal("C:opt"));
+            assert_eq!(
+                abs_path!("C:/opt/turbo").as_path(),
+                Path::new("C:\\opt\\turbo")
+            );
+        }
+    }
+}
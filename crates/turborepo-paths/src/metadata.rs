@@ -0,0 +1,260 @@
+use std::{
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+use crate::{AbsoluteSystemPath, AbsoluteSystemPathBuf, PathError, PathOperation};
+
+/// What a path points at, without following symlinks, along with the parts
+/// of its metadata that callers need for that kind of file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileMetadata {
+    File {
+        size: u64,
+        /// The permission bits on unix. On Windows, `0o444` for read-only
+        /// files and `0o666` otherwise.
+        mode: u32,
+    },
+    Dir,
+    /// A symlink, or on Windows a junction or other reparse point that links
+    /// somewhere else
+    Symlink {
+        target: PathBuf,
+    },
+    /// Anything else, e.g. a socket or a FIFO
+    Other,
+}
+
+impl FileMetadata {
+    pub fn kind(&self) -> FileKind {
+        match self {
+            FileMetadata::File { .. } => FileKind::File,
+            FileMetadata::Dir => FileKind::Dir,
+            FileMetadata::Symlink { .. } => FileKind::Symlink,
+            FileMetadata::Other => FileKind::Other,
+        }
+    }
+}
+
+/// The kind of file a path points at, see `FileMetadata`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+impl FileKind {
+    // Classifies metadata that was read without following symlinks. Unlike
+    // `symlink_metadata_typed` this can't read the link, so every reparse
+    // point on Windows counts as a symlink.
+    pub(crate) fn from_metadata(metadata: &Metadata) -> Self {
+        let file_type = metadata.file_type();
+        if is_link(metadata) {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+}
+
+impl AbsoluteSystemPath {
+    /// Like `symlink_metadata`, but interprets the metadata according to the
+    /// kind of file, including reading the target of symlinks
+    pub fn symlink_metadata_typed(&self) -> Result<FileMetadata, PathError> {
+        let metadata = self.symlink_metadata()?;
+        if is_link(&metadata) {
+            match self.read_link() {
+                Ok(target) => return Ok(FileMetadata::Symlink { target }),
+                // Other reparse points, e.g. cloud storage placeholders,
+                // aren't links, so are treated like the file they stand in for
+                Err(_) if !metadata.is_symlink() => {}
+                Err(err) => return Err(PathError::io(PathOperation::Read, self.as_path())(err)),
+            }
+        }
+        let file_type = metadata.file_type();
+        Ok(if file_type.is_dir() {
+            FileMetadata::Dir
+        } else if file_type.is_file() {
+            FileMetadata::File {
+                size: metadata.len(),
+                mode: mode(&metadata),
+            }
+        } else {
+            FileMetadata::Other
+        })
+    }
+}
+
+impl AbsoluteSystemPath {
+    /// Whether this path and `other` lead to the same file once symlinks are
+    /// followed, e.g. `/var/folders/.../repo` and
+    /// `/private/var/folders/.../repo` on macOS. Files are compared by device
+    /// and inode on unix and by volume and file ID on Windows, so it also
+    /// sees through hard links, junctions, and differences in spelling.
+    ///
+    /// Both paths must exist.
+    pub fn points_to_same_file(
+        &self,
+        other: impl AsRef<AbsoluteSystemPath>,
+    ) -> Result<bool, PathError> {
+        let other = other.as_ref();
+        Ok(file_id(self.as_path())? == file_id(other.as_path())?)
+    }
+}
+
+impl AbsoluteSystemPathBuf {
+    pub fn symlink_metadata_typed(&self) -> Result<FileMetadata, PathError> {
+        self.as_absolute_path().symlink_metadata_typed()
+    }
+
+    pub fn points_to_same_file(
+        &self,
+        other: impl AsRef<AbsoluteSystemPath>,
+    ) -> Result<bool, PathError> {
+        self.as_absolute_path().points_to_same_file(other)
+    }
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Result<(u64, u64), PathError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).map_err(PathError::io(PathOperation::Stat, path))?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(windows)]
+fn file_id(path: &Path) -> Result<(u32, u64), PathError> {
+    use std::{
+        io,
+        mem::MaybeUninit,
+        os::windows::{fs::OpenOptionsExt, io::AsRawHandle},
+    };
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, FILE_FLAG_BACKUP_SEMANTICS,
+    };
+
+    // Directories can only be opened with backup semantics. No access rights
+    // are needed to read the file's information.
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+        .map_err(PathError::io(PathOperation::Stat, path))?;
+    let mut info = MaybeUninit::uninit();
+    // SAFETY: the handle is open for the duration of the call, and `info` is
+    // only read if the call succeeds
+    let info = unsafe {
+        if GetFileInformationByHandle(file.as_raw_handle() as _, info.as_mut_ptr()) == 0 {
+            let err = io::Error::last_os_error();
+            return Err(PathError::io(PathOperation::Stat, path)(err));
+        }
+        info.assume_init()
+    };
+    let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+    Ok((info.dwVolumeSerialNumber, index))
+}
+
+#[cfg(windows)]
+fn is_link(metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+    // `is_symlink` only covers symlinks, not junctions
+    metadata.is_symlink() || metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+}
+
+#[cfg(not(windows))]
+fn is_link(metadata: &Metadata) -> bool {
+    metadata.is_symlink()
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o666
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_symlink_metadata_typed() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?;
+        let file = dir.join_component("package.json");
+        file.create_with_contents("{}")?;
+        let link = dir.join_component("link");
+        link.symlink_to_file("package.json")?;
+
+        assert_eq!(dir.symlink_metadata_typed()?, FileMetadata::Dir);
+        assert_eq!(
+            link.symlink_metadata_typed()?,
+            FileMetadata::Symlink {
+                target: PathBuf::from("package.json")
+            }
+        );
+        let FileMetadata::File { size, mode } = file.symlink_metadata_typed()? else {
+            panic!("expected a file");
+        };
+        assert_eq!(size, 2);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = fs::metadata(file.as_path())?.permissions();
+            assert_eq!(mode, permissions.mode() & 0o7777);
+        }
+        #[cfg(not(unix))]
+        assert_eq!(mode, 0o666);
+
+        assert!(dir
+            .join_component("missing")
+            .symlink_metadata_typed()
+            .unwrap_err()
+            .is_io_error(std::io::ErrorKind::NotFound));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_points_to_same_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?;
+        let repo = dir.join_component("repo");
+        repo.create_dir_all()?;
+        let linked_repo = dir.join_component("linked-repo");
+        linked_repo.symlink_to_dir("repo")?;
+        let other = dir.join_component("other");
+        other.create_dir_all()?;
+
+        assert!(repo.points_to_same_file(&linked_repo)?);
+        assert!(repo.points_to_same_file(repo.join_components(&["..", "repo"]))?);
+        assert!(repo.points_to_same_file(repo.to_realpath()?)?);
+        assert!(!repo.points_to_same_file(&other)?);
+        assert!(repo
+            .points_to_same_file(dir.join_component("missing"))
+            .unwrap_err()
+            .is_io_error(std::io::ErrorKind::NotFound));
+
+        Ok(())
+    }
+}
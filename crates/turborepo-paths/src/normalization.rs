@@ -0,0 +1,128 @@
+use std::{
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{AnchoredSystemPath, AnchoredSystemPathBuf};
+
+// The same file name can be spelled with different sequences of code points:
+// macOS stores names decomposed (NFD), while most other systems, and archives
+// created on them, keep them composed (NFC). Comparing paths in NFC makes
+// both spellings equal.
+
+impl AnchoredSystemPath {
+    /// Returns this path with every component in Unicode Normalization Form
+    /// C. Paths that aren't valid UTF-8 are returned unchanged.
+    pub fn to_nfc(&self) -> AnchoredSystemPathBuf {
+        match self.as_path().to_str() {
+            Some(path) if !is_nfc(path) => {
+                AnchoredSystemPathBuf(PathBuf::from(path.nfc().collect::<String>()))
+            }
+            _ => self.to_owned(),
+        }
+    }
+
+    /// Whether this path and `other` name the same file once differences in
+    /// Unicode normalization are ignored
+    pub fn eq_normalized(&self, other: impl AsRef<AnchoredSystemPath>) -> bool {
+        let other = other.as_ref();
+        self.as_path() == other.as_path() || self.to_nfc() == other.to_nfc()
+    }
+}
+
+impl AnchoredSystemPathBuf {
+    pub fn to_nfc(&self) -> AnchoredSystemPathBuf {
+        self.as_anchored_path().to_nfc()
+    }
+
+    pub fn eq_normalized(&self, other: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.as_anchored_path().eq_normalized(other)
+    }
+}
+
+/// An anchored path that compares and hashes by its NFC form, for use as a
+/// map key when paths may come from systems that normalize file names
+/// differently, e.g. restoring a cache artifact created on Linux on macOS.
+/// The path keeps its original spelling.
+#[derive(Debug, Clone)]
+pub struct NormalizedPath {
+    path: AnchoredSystemPathBuf,
+    normalized: AnchoredSystemPathBuf,
+}
+
+impl NormalizedPath {
+    pub fn new(path: AnchoredSystemPathBuf) -> Self {
+        let normalized = path.to_nfc();
+        NormalizedPath { path, normalized }
+    }
+
+    /// The path as it was originally spelled
+    pub fn as_path(&self) -> &AnchoredSystemPath {
+        self.path.as_anchored_path()
+    }
+
+    pub fn normalized(&self) -> &AnchoredSystemPath {
+        self.normalized.as_anchored_path()
+    }
+
+    pub fn into_inner(self) -> AnchoredSystemPathBuf {
+        self.path
+    }
+}
+
+impl From<AnchoredSystemPathBuf> for NormalizedPath {
+    fn from(path: AnchoredSystemPathBuf) -> Self {
+        NormalizedPath::new(path)
+    }
+}
+
+impl PartialEq for NormalizedPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized == other.normalized
+    }
+}
+
+impl Eq for NormalizedPath {}
+
+impl Hash for NormalizedPath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    // "café" with a precomposed é, and with an e followed by a combining acute
+    // accent
+    const COMPOSED: &str = "caf\u{e9}/menu.txt";
+    const DECOMPOSED: &str = "cafe\u{301}/menu.txt";
+
+    #[test]
+    fn test_normalized_comparison() -> Result<()> {
+        let composed = AnchoredSystemPathBuf::from_raw(COMPOSED)?;
+        let decomposed = AnchoredSystemPathBuf::from_raw(DECOMPOSED)?;
+        assert_ne!(composed, decomposed);
+        assert!(composed.eq_normalized(&decomposed));
+        assert_eq!(decomposed.to_nfc(), composed);
+        assert!(!composed.eq_normalized(AnchoredSystemPathBuf::from_raw("cafe/menu.txt")?));
+
+        let keys: HashSet<_> = [composed, decomposed.clone()]
+            .into_iter()
+            .map(NormalizedPath::new)
+            .collect();
+        assert_eq!(keys.len(), 1);
+        let key = NormalizedPath::new(decomposed.clone());
+        assert!(keys.contains(&key));
+        assert_eq!(key.as_path().to_str()?, decomposed.to_str()?);
+
+        Ok(())
+    }
+}
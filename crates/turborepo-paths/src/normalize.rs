@@ -0,0 +1,185 @@
+use crate::AbsoluteSystemPathBuf;
+
+impl AbsoluteSystemPathBuf {
+    /// Returns a single spelling for this path, so that different ways of
+    /// writing the same path compare equal:
+    ///
+    /// - `.` and `..` are resolved and repeated separators removed, as with
+    ///   `clean`
+    /// - on Windows, the drive letter is uppercased and 8.3 short names (e.g.
+    ///   `C:\PROGRA~1`) are expanded to their long form
+    ///
+    /// Unlike `to_realpath` this doesn't resolve symlinks or junctions, since
+    /// that would move packages reached through a link out of the workspace
+    /// they were found in. It works for paths longer than `MAX_PATH`, and for
+    /// paths that don't exist, in which case only the part that exists has
+    /// its short names expanded.
+    pub fn normalize(&self) -> Self {
+        #[cfg(windows)]
+        return Self(windows::normalize(self.clean().0));
+        #[cfg(not(windows))]
+        self.clean()
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{
+        ffi::OsString,
+        os::windows::ffi::{OsStrExt, OsStringExt},
+        path::{Component, Path, PathBuf},
+    };
+
+    use windows_sys::Win32::Storage::FileSystem::GetLongPathNameW;
+
+    pub(super) fn normalize(path: PathBuf) -> PathBuf {
+        let path = uppercase_drive_letter(path);
+        let tilde = u16::from(b'~');
+        let has_short_name = path.components().any(|component| {
+            matches!(component, Component::Normal(name) if to_wide(name).contains(&tilde))
+        });
+        if !has_short_name {
+            return path;
+        }
+
+        // GetLongPathNameW fails for paths that don't exist, so expand the
+        // longest prefix that does and keep the rest as is
+        for ancestor in path.ancestors() {
+            if let Some(long_path) = long_path_name(ancestor) {
+                let rest = path
+                    .strip_prefix(ancestor)
+                    .expect("ancestor is a prefix of the path");
+                return if rest.as_os_str().is_empty() {
+                    long_path
+                } else {
+                    long_path.join(rest)
+                };
+            }
+        }
+        path
+    }
+
+    fn uppercase_drive_letter(path: PathBuf) -> PathBuf {
+        let mut wide = to_wide(path.as_os_str());
+        match wide.as_mut_slice() {
+            [letter, colon, ..] if *colon == u16::from(b':') && *letter < 0x80 => {
+                *letter = u16::from((*letter as u8).to_ascii_uppercase());
+                PathBuf::from(OsString::from_wide(&wide))
+            }
+            _ => path,
+        }
+    }
+
+    fn long_path_name(path: &Path) -> Option<PathBuf> {
+        // The extended-length form lifts the MAX_PATH limit
+        let path = to_wide(path.as_os_str());
+        let backslash = u16::from(b'\\');
+        let mut input: Vec<u16> = match path.as_slice() {
+            [first, second, unc @ ..] if *first == backslash && *second == backslash => r"\\?\UNC\"
+                .encode_utf16()
+                .chain(unc.iter().copied())
+                .collect(),
+            _ => r"\\?\".encode_utf16().chain(path.iter().copied()).collect(),
+        };
+        input.push(0);
+
+        let mut buffer = vec![0u16; 260];
+        loop {
+            // SAFETY: `input` is nul terminated, and `buffer`'s length is
+            // passed along with it
+            let len = unsafe {
+                GetLongPathNameW(input.as_ptr(), buffer.as_mut_ptr(), buffer.len() as u32)
+            } as usize;
+            if len == 0 {
+                return None;
+            }
+            // When the buffer is too small, the returned length includes the
+            // terminating nul
+            if len < buffer.len() {
+                buffer.truncate(len);
+                break;
+            }
+            buffer.resize(len, 0);
+        }
+        let long_path = PathBuf::from(OsString::from_wide(&buffer));
+        Some(dunce::simplified(&long_path).to_path_buf())
+    }
+
+    fn to_wide(s: &std::ffi::OsStr) -> Vec<u16> {
+        s.encode_wide().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize_keeps_symlinks() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?.to_realpath()?;
+        dir.join_components(&["packages", "ui"]).create_dir_all()?;
+        let link = dir.join_component("linked");
+        link.symlink_to_dir("packages")?;
+
+        let unnormalized =
+            AbsoluteSystemPathBuf::new(link.as_path().join("ui").join("..").join(".").join("ui"))?;
+        let normalized = unnormalized.normalize();
+        assert_eq!(normalized, link.join_component("ui"));
+        assert_ne!(normalized, normalized.to_realpath()?);
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_normalize_windows() -> Result<()> {
+        let path = AbsoluteSystemPathBuf::new(r"c:\Users\..\Windows")?;
+        assert_eq!(
+            path.normalize().as_path(),
+            std::path::Path::new(r"C:\Windows")
+        );
+
+        let dir = tempfile::tempdir()?;
+        let dir = AbsoluteSystemPathBuf::new(dir.path())?.normalize();
+        let long_name = dir.join_component("long directory name");
+        long_name.create_dir_all()?;
+        // Short names can be disabled per volume, so only check them if one
+        // was generated
+        if let Some(short) = short_path_name(&long_name) {
+            assert_eq!(short.normalize(), long_name);
+            let missing = short.join_component("missing");
+            assert_eq!(missing.normalize(), long_name.join_component("missing"));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn short_path_name(path: &AbsoluteSystemPathBuf) -> Option<AbsoluteSystemPathBuf> {
+        use std::{
+            ffi::OsString,
+            os::windows::ffi::{OsStrExt, OsStringExt},
+        };
+
+        use windows_sys::Win32::Storage::FileSystem::GetShortPathNameW;
+
+        let input: Vec<u16> = path
+            .as_path()
+            .as_os_str()
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        let mut buffer = vec![0u16; 1024];
+        // SAFETY: `input` is nul terminated, and `buffer`'s length is passed
+        // along with it
+        let len =
+            unsafe { GetShortPathNameW(input.as_ptr(), buffer.as_mut_ptr(), buffer.len() as u32) }
+                as usize;
+        buffer.truncate(len);
+        let short = AbsoluteSystemPathBuf::new(OsString::from_wide(&buffer)).ok()?;
+        (short != *path).then_some(short)
+    }
+}
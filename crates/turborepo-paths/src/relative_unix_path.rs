@@ -1,8 +1,11 @@
-use std::path::PathBuf;
+use std::{
+    ops::Range,
+    path::{Component, PathBuf},
+};
 
-use bstr::BStr;
+use bstr::{BStr, ByteSlice};
 
-use crate::PathError;
+use crate::{AnchoredSystemPathBuf, PathError, RelativeUnixPathBuf};
 
 #[repr(transparent)]
 pub struct RelativeUnixPath {
@@ -21,6 +24,67 @@ impl RelativeUnixPath {
         Ok(unsafe { &*(path as *const BStr as *const Self) })
     }
 
+    pub(crate) fn new_unchecked(bytes: &[u8]) -> &Self {
+        let path = bytes.as_bstr();
+        unsafe { &*(path as *const BStr as *const Self) }
+    }
+
+    pub fn as_str(&self) -> Result<&str, PathError> {
+        let s = self
+            .inner
+            .to_str()
+            .map_err(|_| PathError::InvalidUnicode(self.inner.to_str_lossy().to_string()))?;
+        Ok(s)
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+
+    /// Iterates over the components of this path. Empty and `.` components
+    /// are skipped, `..` components are kept.
+    pub fn components(&self) -> impl Iterator<Item = &BStr> {
+        self.inner
+            .split_str("/")
+            .filter(|component| !component.is_empty() && *component != b".")
+            .map(|component| component.as_bstr())
+    }
+
+    /// Returns the last component of this path, or `None` if it's empty or
+    /// ends in `..`
+    pub fn file_name(&self) -> Option<&BStr> {
+        file_name_range(&self.inner).map(|range| self.inner[range].as_bstr())
+    }
+
+    /// Returns the part of the file name after its last `.`. Names that start
+    /// with their only `.`, like `.gitignore`, have no extension.
+    pub fn extension(&self) -> Option<&BStr> {
+        let name = self.file_name()?;
+        match name.rfind_byte(b'.') {
+            Some(0) | None => None,
+            Some(index) => Some(name[index + 1..].as_bstr()),
+        }
+    }
+
+    /// Converts this path into an anchored path for the current platform.
+    /// Errors if the path would mean something else on this platform, e.g. a
+    /// path containing `\`, which Windows treats as a separator, or one
+    /// starting with a drive letter.
+    pub fn to_anchored_system_path_buf(&self) -> Result<AnchoredSystemPathBuf, PathError> {
+        #[cfg(windows)]
+        if self.inner.contains(&b'\\') {
+            return Err(PathError::NotUnix(self.inner.to_str_lossy().to_string()));
+        }
+        let path = self.to_system_path_buf()?;
+        if path
+            .components()
+            .any(|component| matches!(component, Component::Prefix(_) | Component::RootDir))
+        {
+            return Err(PathError::not_relative_error(&self.inner));
+        }
+        Ok(AnchoredSystemPathBuf(path))
+    }
+
     pub(crate) fn to_system_path_buf(&self) -> Result<PathBuf, PathError> {
         #[cfg(unix)]
         {
@@ -45,6 +109,37 @@ impl RelativeUnixPath {
     }
 }
 
+// The byte range of the last component of `path`, see `file_name`
+pub(crate) fn file_name_range(path: &[u8]) -> Option<Range<usize>> {
+    let mut start = 0;
+    let mut file_name = None;
+    for component in path.split_str("/") {
+        let end = start + component.len();
+        if !component.is_empty() && component != b"." {
+            file_name = Some(start..end);
+        }
+        start = end + 1;
+    }
+    file_name.filter(|range| &path[range.clone()] != b"..")
+}
+
+impl std::fmt::Debug for RelativeUnixPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.as_str() {
+            Ok(s) => write!(f, "{}", s),
+            Err(_) => write!(f, "Non-utf8 {:?}", &self.inner),
+        }
+    }
+}
+
+impl ToOwned for RelativeUnixPath {
+    type Owned = RelativeUnixPathBuf;
+
+    fn to_owned(&self) -> Self::Owned {
+        RelativeUnixPathBuf(self.inner.to_owned())
+    }
+}
+
 impl AsRef<RelativeUnixPath> for RelativeUnixPath {
     fn as_ref(&self) -> &RelativeUnixPath {
         self
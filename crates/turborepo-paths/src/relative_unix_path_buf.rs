@@ -1,11 +1,14 @@
-use std::{borrow::Borrow, fmt::Debug, io::Write};
+use std::{borrow::Borrow, fmt::Debug, io::Write, ops::Deref, path::PathBuf};
 
 use bstr::{BStr, BString, ByteSlice};
 
-use crate::{PathError, RelativeUnixPath};
+use crate::{
+    relative_unix_path::file_name_range, AnchoredSystemPathBuf, PathError, PathOperation,
+    RelativeUnixPath,
+};
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct RelativeUnixPathBuf(BString);
+pub struct RelativeUnixPathBuf(pub(crate) BString);
 
 impl RelativeUnixPathBuf {
     pub fn new(path: impl Into<Vec<u8>>) -> Result<Self, PathError> {
@@ -25,10 +28,79 @@ impl RelativeUnixPathBuf {
         Ok(s)
     }
 
+    pub fn as_relative_path(&self) -> &RelativeUnixPath {
+        self.borrow()
+    }
+
+    /// Appends `tail` to this path, adding a `/` between them if needed
+    pub fn push(&mut self, tail: impl AsRef<RelativeUnixPath>) {
+        let tail = tail.as_ref().as_bytes();
+        if tail.is_empty() {
+            return;
+        }
+        if !self.0.is_empty() && !self.0.ends_with(b"/") {
+            self.0.push(b'/');
+        }
+        self.0.extend_from_slice(tail);
+    }
+
+    /// Removes the last component of this path, returning whether there was
+    /// one to remove
+    pub fn pop(&mut self) -> bool {
+        let Some(range) = file_name_range(&self.0) else {
+            return false;
+        };
+        let parent_len = self.0[..range.start].trim_end_with(|c| c == '/').len();
+        self.0.truncate(parent_len);
+        true
+    }
+
+    pub fn components(&self) -> impl Iterator<Item = &BStr> {
+        self.as_relative_path().components()
+    }
+
+    pub fn file_name(&self) -> Option<&BStr> {
+        self.as_relative_path().file_name()
+    }
+
+    pub fn extension(&self) -> Option<&BStr> {
+        self.as_relative_path().extension()
+    }
+
+    /// Replaces the extension of the file name with `extension`, or removes
+    /// it if `extension` is empty. Returns false if there's no file name.
+    pub fn set_extension(&mut self, extension: &str) -> bool {
+        let Some(range) = file_name_range(&self.0) else {
+            return false;
+        };
+        let stem_end = match self.0[range.clone()].rfind_byte(b'.') {
+            Some(0) | None => range.end,
+            Some(index) => range.start + index,
+        };
+        self.0.truncate(stem_end);
+        if !extension.is_empty() {
+            self.0.push(b'.');
+            self.0.extend_from_slice(extension.as_bytes());
+        }
+        true
+    }
+
+    pub fn to_anchored_system_path_buf(&self) -> Result<AnchoredSystemPathBuf, PathError> {
+        self.as_relative_path().to_anchored_system_path_buf()
+    }
+
     // write_escaped_bytes writes this path to the given writer in the form
     // "<escaped path>", where escaped_path is the path with '"' and '\n'
     // characters escaped with '\'.
     pub fn write_escaped_bytes<W: Write>(&self, writer: &mut W) -> Result<(), PathError> {
+        self.write_escaped_bytes_inner(writer)
+            .map_err(PathError::io(
+                PathOperation::Write,
+                self.0.to_str_lossy().into_owned(),
+            ))
+    }
+
+    fn write_escaped_bytes_inner<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
         writer.write_all(&[b'\"'])?;
         // i is our pointer into self.0, and to_escape_index is a pointer to the next
         // byte to be escaped. Each time we find a byte to be escaped, we write
@@ -53,16 +125,35 @@ impl RelativeUnixPathBuf {
         Ok(())
     }
 
+    /// Resolves `.` and `..` components and removes repeated separators,
+    /// purely lexically. `..` components that would climb above the start of
+    /// the path are kept.
+    pub fn clean(&self) -> Self {
+        let mut components: Vec<&[u8]> = Vec::new();
+        for component in self.0.split_str("/") {
+            match component {
+                b"" | b"." => {}
+                b".." if matches!(components.last(), Some(last) if *last != b"..") => {
+                    components.pop();
+                }
+                component => components.push(component),
+            }
+        }
+        Self(BString::from(components.join(&b'/')))
+    }
+
     pub fn strip_prefix(&self, prefix: &RelativeUnixPathBuf) -> Result<Self, PathError> {
         let prefix_len = prefix.0.len();
         if prefix_len == 0 {
             return Ok(self.clone());
         }
+        let not_parent = || PathError::NotParent {
+            operation: PathOperation::Strip,
+            parent: PathBuf::from(prefix.0.to_str_lossy().into_owned()),
+            path: PathBuf::from(self.0.to_str_lossy().into_owned()),
+        };
         if !self.0.starts_with(&prefix.0) {
-            return Err(PathError::NotParent(
-                prefix.0.to_string(),
-                self.0.to_string(),
-            ));
+            return Err(not_parent());
         }
 
         // Handle the case where we are stripping the entire contents of this path
@@ -73,9 +164,7 @@ impl RelativeUnixPathBuf {
         // We now know that this path starts with the prefix, and that this path's
         // length is greater than the prefix's length
         if self.0[prefix_len] != b'/' {
-            let prefix_str = prefix.0.to_str_lossy().into_owned();
-            let this = self.0.to_str_lossy().into_owned();
-            return Err(PathError::PrefixError(prefix_str, this));
+            return Err(not_parent());
         }
 
         let tail_slice = &self.0[(prefix_len + 1)..];
@@ -110,6 +199,14 @@ impl Borrow<RelativeUnixPath> for RelativeUnixPathBuf {
     }
 }
 
+impl Deref for RelativeUnixPathBuf {
+    type Target = RelativeUnixPath;
+
+    fn deref(&self) -> &Self::Target {
+        self.borrow()
+    }
+}
+
 impl AsRef<RelativeUnixPath> for RelativeUnixPathBuf {
     fn as_ref(&self) -> &RelativeUnixPath {
         self.borrow()
@@ -180,6 +277,109 @@ mod tests {
         assert_eq!(tail, combined);
     }
 
+    #[test]
+    fn test_clean() {
+        for (path, cleaned) in [
+            ("some/./path//child/../leaf/", "some/path/leaf"),
+            ("../a/../../b", "../../b"),
+            ("a/..", ""),
+            ("", ""),
+        ] {
+            assert_eq!(
+                RelativeUnixPathBuf::new(path)
+                    .unwrap()
+                    .clean()
+                    .as_str()
+                    .unwrap(),
+                cleaned
+            );
+        }
+    }
+
+    #[test]
+    fn test_push_and_pop() {
+        let mut path = RelativeUnixPathBuf::new("").unwrap();
+        path.push(RelativeUnixPathBuf::new("packages").unwrap());
+        path.push(RelativeUnixPathBuf::new("ui/").unwrap());
+        path.push(RelativeUnixPathBuf::new("dist").unwrap());
+        assert_eq!(path.as_str().unwrap(), "packages/ui/dist");
+        assert_eq!(
+            path.components().collect::<Vec<_>>(),
+            ["packages", "ui", "dist"]
+        );
+
+        assert!(path.pop());
+        assert_eq!(path.as_str().unwrap(), "packages/ui");
+        let mut trailing = RelativeUnixPathBuf::new("packages/ui//./").unwrap();
+        assert!(trailing.pop());
+        assert_eq!(trailing.as_str().unwrap(), "packages");
+        assert!(trailing.pop());
+        assert!(!trailing.pop());
+        assert!(!RelativeUnixPathBuf::new("a/..").unwrap().pop());
+    }
+
+    #[test]
+    fn test_extensions() {
+        let mut path = RelativeUnixPathBuf::new("dist/index.d.ts").unwrap();
+        assert_eq!(path.file_name().unwrap(), "index.d.ts");
+        assert_eq!(path.extension().unwrap(), "ts");
+        assert!(path.set_extension("js"));
+        assert_eq!(path.as_str().unwrap(), "dist/index.d.js");
+        assert!(path.set_extension(""));
+        assert_eq!(path.as_str().unwrap(), "dist/index.d");
+
+        let mut dotfile = RelativeUnixPathBuf::new("pkg/.gitignore").unwrap();
+        assert_eq!(dotfile.extension(), None);
+        assert!(dotfile.set_extension("bak"));
+        assert_eq!(dotfile.as_str().unwrap(), "pkg/.gitignore.bak");
+        assert!(!RelativeUnixPathBuf::new("").unwrap().set_extension("js"));
+    }
+
+    #[test]
+    fn test_anchored_round_trip() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // Characters that mean different things on different platforms
+        const ALPHABET: &[char] = &['a', 'C', '.', '/', '\\', ':', ' ', '\u{e9}'];
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..10_000 {
+            let len = rng.gen_range(0..12);
+            let raw: String = (0..len)
+                .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())])
+                .collect();
+            let Ok(unix) = RelativeUnixPathBuf::new(raw.as_str()) else {
+                assert!(raw.starts_with('/'));
+                continue;
+            };
+            let Ok(anchored) = unix.to_anchored_system_path_buf() else {
+                #[cfg(unix)]
+                panic!("{} should convert to an anchored path", raw);
+                #[cfg(not(unix))]
+                continue;
+            };
+            #[cfg(windows)]
+            assert!(!raw.contains('\\'), "{} should be rejected", raw);
+
+            let round_tripped = anchored.to_unix().unwrap();
+            assert_eq!(
+                round_tripped.components().collect::<Vec<_>>(),
+                unix.components().collect::<Vec<_>>(),
+                "{}",
+                raw
+            );
+            assert_eq!(
+                round_tripped
+                    .to_anchored_system_path_buf()
+                    .unwrap()
+                    .components()
+                    .collect::<Vec<_>>(),
+                anchored.components().collect::<Vec<_>>(),
+                "{}",
+                raw
+            );
+        }
+    }
+
     #[test]
     fn test_write_escaped() {
         let input = "\"quote\"\nnewline\n".as_bytes();
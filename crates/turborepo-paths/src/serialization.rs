@@ -0,0 +1,141 @@
+//! Serde implementations for the path types.
+//!
+//! Every path type serializes as a string. Relative paths always use `/` as
+//! the separator on the wire, and anchored paths are converted to and from
+//! the system separator, so that e.g. cache metadata written on Windows can be
+//! read on Linux. Absolute paths only make sense on the machine they came
+//! from, so they are serialized as-is. Deserializing validates the path like
+//! the type's constructor does.
+
+use std::path::Path;
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPath, AnchoredSystemPathBuf,
+    AnchoredUnixPath, AnchoredUnixPathBuf, RelativeUnixPath, RelativeUnixPathBuf,
+};
+
+impl Serialize for AbsoluteSystemPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let path = self
+            .as_path()
+            .to_str()
+            .ok_or_else(|| ser::Error::custom(format!("path is non-UTF-8: {}", self)))?;
+        serializer.serialize_str(path)
+    }
+}
+
+impl Serialize for AbsoluteSystemPathBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_absolute_path().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AbsoluteSystemPathBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        AbsoluteSystemPathBuf::new(path).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for AnchoredSystemPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let path = self.to_unix().map_err(ser::Error::custom)?;
+        path.serialize(serializer)
+    }
+}
+
+impl Serialize for AnchoredSystemPathBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_anchored_path().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnchoredSystemPathBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = RelativeUnixPathBuf::deserialize(deserializer)?;
+        let path = path.as_str().map_err(de::Error::custom)?;
+        AnchoredSystemPathBuf::try_from(Path::new(path)).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for AnchoredUnixPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_relative_path().serialize(serializer)
+    }
+}
+
+impl Serialize for AnchoredUnixPathBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_anchored_path().serialize(serializer)
+    }
+}
+
+impl Serialize for RelativeUnixPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str().map_err(ser::Error::custom)?)
+    }
+}
+
+impl Serialize for RelativeUnixPathBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let path: &RelativeUnixPath = self.as_ref();
+        path.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RelativeUnixPathBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        // Windows separators would be read back as part of a file name on
+        // other platforms
+        if path.contains('\\') {
+            return Err(de::Error::custom(format!(
+                "path is not a unix path: {}",
+                path
+            )));
+        }
+        RelativeUnixPathBuf::new(path).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_anchored_paths_use_unix_separators() -> Result<()> {
+        let path = AnchoredSystemPathBuf::from_raw("packages/ui/package.json")?;
+        let serialized = serde_json::to_string(&path)?;
+        assert_eq!(serialized, r#""packages/ui/package.json""#);
+        assert_eq!(
+            serde_json::from_str::<AnchoredSystemPathBuf>(&serialized)?,
+            path
+        );
+
+        assert!(serde_json::from_str::<AnchoredSystemPathBuf>(r#""/etc/passwd""#).is_err());
+        assert!(serde_json::from_str::<RelativeUnixPathBuf>(r#""packages\\ui""#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_absolute_paths() -> Result<()> {
+        #[cfg(unix)]
+        let path = AbsoluteSystemPathBuf::new("/repo/packages")?;
+        #[cfg(windows)]
+        let path = AbsoluteSystemPathBuf::new(r"C:\repo\packages")?;
+
+        let serialized = serde_json::to_string(&path)?;
+        assert_eq!(
+            serde_json::from_str::<AbsoluteSystemPathBuf>(&serialized)?,
+            path
+        );
+        assert!(serde_json::from_str::<AbsoluteSystemPathBuf>(r#""repo""#).is_err());
+
+        Ok(())
+    }
+}
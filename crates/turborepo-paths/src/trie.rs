@@ -0,0 +1,269 @@
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    path::{Component, PathBuf},
+};
+
+use crate::{AnchoredSystemPath, AnchoredSystemPathBuf};
+
+/// A map keyed by anchored paths that stores paths by component, so that
+/// finding the closest ancestor of a path with a value, or every entry under a
+/// directory, only walks the components of the path rather than every key.
+///
+/// Useful for e.g. finding which workspace owns a file, or which ignore rules
+/// apply to it.
+#[derive(Debug, Clone)]
+pub struct PathTrie<V> {
+    root: Node<V>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<V> {
+    value: Option<V>,
+    children: BTreeMap<OsString, Node<V>>,
+}
+
+impl<V> Default for Node<V> {
+    fn default() -> Self {
+        Node {
+            value: None,
+            children: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V> Default for PathTrie<V> {
+    fn default() -> Self {
+        PathTrie {
+            root: Node::default(),
+            len: 0,
+        }
+    }
+}
+
+fn keys(path: &AnchoredSystemPath) -> impl Iterator<Item = &OsStr> {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .map(|component| component.as_os_str())
+}
+
+impl<V> PathTrie<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts `value` for `path`, returning the value previously stored for
+    /// it. The empty path is the root of the trie and can hold a value too.
+    pub fn insert(&mut self, path: impl AsRef<AnchoredSystemPath>, value: V) -> Option<V> {
+        let mut node = &mut self.root;
+        for key in keys(path.as_ref()) {
+            node = node.children.entry(key.to_owned()).or_default();
+        }
+        let previous = node.value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    fn node(&self, path: &AnchoredSystemPath) -> Option<&Node<V>> {
+        keys(path).try_fold(&self.root, |node, key| node.children.get(key))
+    }
+
+    pub fn get(&self, path: impl AsRef<AnchoredSystemPath>) -> Option<&V> {
+        self.node(path.as_ref())?.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, path: impl AsRef<AnchoredSystemPath>) -> Option<&mut V> {
+        keys(path.as_ref())
+            .try_fold(&mut self.root, |node, key| node.children.get_mut(key))?
+            .value
+            .as_mut()
+    }
+
+    pub fn contains_key(&self, path: impl AsRef<AnchoredSystemPath>) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// Removes the value for `path`. Entries below `path` are kept.
+    pub fn remove(&mut self, path: impl AsRef<AnchoredSystemPath>) -> Option<V> {
+        let keys: Vec<_> = keys(path.as_ref()).collect();
+        let removed = Self::remove_from(&mut self.root, &keys);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    // Removes the value at `keys` below `node`, pruning nodes that are left
+    // without a value or children
+    fn remove_from(node: &mut Node<V>, keys: &[&OsStr]) -> Option<V> {
+        let Some((key, rest)) = keys.split_first() else {
+            return node.value.take();
+        };
+        let child = node.children.get_mut(*key)?;
+        let removed = Self::remove_from(child, rest);
+        if child.value.is_none() && child.children.is_empty() {
+            node.children.remove(*key);
+        }
+        removed
+    }
+
+    /// Returns the entry for the longest path that is `path` or one of its
+    /// ancestors, e.g. the workspace directory that contains a file
+    pub fn longest_prefix(
+        &self,
+        path: impl AsRef<AnchoredSystemPath>,
+    ) -> Option<(AnchoredSystemPathBuf, &V)> {
+        let mut node = &self.root;
+        let mut prefix = PathBuf::new();
+        let mut longest = node.value.as_ref().map(|value| (prefix.clone(), value));
+        for key in keys(path.as_ref()) {
+            let Some(child) = node.children.get(key) else {
+                break;
+            };
+            node = child;
+            prefix.push(key);
+            if let Some(value) = &node.value {
+                longest = Some((prefix.clone(), value));
+            }
+        }
+        longest.map(|(prefix, value)| (AnchoredSystemPathBuf(prefix), value))
+    }
+
+    /// Iterates over the entries for `path` and every path below it, in
+    /// lexicographic order by component
+    pub fn subtree(&self, path: impl AsRef<AnchoredSystemPath>) -> PathTrieIter<'_, V> {
+        let path = path.as_ref();
+        let stack = match self.node(path) {
+            Some(node) => vec![(keys(path).collect(), node)],
+            None => Vec::new(),
+        };
+        PathTrieIter { stack }
+    }
+
+    pub fn iter(&self) -> PathTrieIter<'_, V> {
+        PathTrieIter {
+            stack: vec![(PathBuf::new(), &self.root)],
+        }
+    }
+}
+
+impl<V> FromIterator<(AnchoredSystemPathBuf, V)> for PathTrie<V> {
+    fn from_iter<I: IntoIterator<Item = (AnchoredSystemPathBuf, V)>>(iter: I) -> Self {
+        let mut trie = Self::new();
+        for (path, value) in iter {
+            trie.insert(path, value);
+        }
+        trie
+    }
+}
+
+/// Iterator over the entries of a `PathTrie`, created by `PathTrie::iter` and
+/// `PathTrie::subtree`
+pub struct PathTrieIter<'a, V> {
+    stack: Vec<(PathBuf, &'a Node<V>)>,
+}
+
+impl<'a, V> Iterator for PathTrieIter<'a, V> {
+    type Item = (AnchoredSystemPathBuf, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, node)) = self.stack.pop() {
+            // Pushed in reverse so that children are visited in order
+            for (key, child) in node.children.iter().rev() {
+                self.stack.push((path.join(key), child));
+            }
+            if let Some(value) = &node.value {
+                return Some((AnchoredSystemPathBuf(path), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    fn path(path: &str) -> AnchoredSystemPathBuf {
+        AnchoredSystemPathBuf::from_raw(path).unwrap()
+    }
+
+    #[test]
+    fn test_longest_prefix() {
+        let mut workspaces = PathTrie::new();
+        workspaces.insert(path(""), "root");
+        workspaces.insert(path("packages/ui"), "ui");
+        workspaces.insert(path("packages/ui-utils"), "ui-utils");
+        assert_eq!(workspaces.len(), 3);
+
+        assert_eq!(
+            workspaces.longest_prefix(path("packages/ui/src/index.ts")),
+            Some((path("packages/ui"), &"ui"))
+        );
+        assert_eq!(
+            workspaces.longest_prefix(path("packages/ui-utils")),
+            Some((path("packages/ui-utils"), &"ui-utils"))
+        );
+        assert_eq!(
+            workspaces.longest_prefix(path("packages/docs/index.md")),
+            Some((path(""), &"root"))
+        );
+
+        workspaces.remove(path(""));
+        assert_eq!(workspaces.longest_prefix(path("turbo.json")), None);
+    }
+
+    #[test]
+    fn test_subtree() -> Result<()> {
+        let trie: PathTrie<_> = ["dist/main.js", "dist", "dist/chunks/a.js", "src/index.ts"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, raw)| Ok((AnchoredSystemPathBuf::from_raw(raw)?, index)))
+            .collect::<Result<_>>()?;
+
+        let entries: Vec<_> = trie
+            .subtree(path("dist"))
+            .map(|(path, index)| (path, *index))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (path("dist"), 1),
+                (path("dist/chunks/a.js"), 2),
+                (path("dist/main.js"), 0),
+            ]
+        );
+        assert_eq!(trie.iter().count(), 4);
+        assert_eq!(trie.subtree(path("build")).count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_prunes_empty_nodes() {
+        let mut trie = PathTrie::new();
+        trie.insert(path("a/b/c"), 1);
+        trie.insert(path("a"), 2);
+        assert_eq!(trie.remove(path("a/b")), None);
+        assert_eq!(trie.remove(path("a/b/c")), Some(1));
+        assert!(trie.root.children[OsStr::new("a")].children.is_empty());
+        assert_eq!(trie.get(path("a")), Some(&2));
+        *trie.get_mut(path("a")).unwrap() += 1;
+        assert_eq!(trie.remove(path("a")), Some(3));
+        assert!(trie.is_empty());
+        assert!(trie.root.children.is_empty());
+    }
+}
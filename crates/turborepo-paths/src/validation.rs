@@ -0,0 +1,178 @@
+use std::{fmt, ops::Range};
+
+use crate::{Glob, PathError};
+
+/// A problem with an output pattern, see `validate_output_spec`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OutputSpecError {
+    #[error("output pattern is empty")]
+    Empty,
+    #[error("output pattern is absolute, outputs must be relative to the package")]
+    Absolute,
+    #[error("output pattern uses `..`, outputs must be inside the package")]
+    Traversal,
+    #[error("{0:?} is not allowed in file names on Windows")]
+    UnsafeChar(char),
+    #[error("{0} is a reserved file name on Windows")]
+    ReservedName(String),
+    #[error("file names ending in {0:?} can't be created on Windows")]
+    TrailingChar(char),
+    #[error("invalid glob: {0}")]
+    InvalidGlob(String),
+}
+
+/// An `OutputSpecError` along with the byte range of the pattern it applies
+/// to, for pointing at the offending part of the pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSpecDiagnostic {
+    pub error: OutputSpecError,
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for OutputSpecDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.error, self.span.start, self.span.end
+        )
+    }
+}
+
+const WINDOWS_RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '|'];
+
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Checks a user-provided output pattern, e.g. an entry of a task's `outputs`
+/// in `turbo.json`, for anything that could write outside the package or
+/// can't be restored on every platform: absolute paths, `..` components,
+/// characters and names that Windows doesn't allow in file names, and
+/// invalid glob syntax. A leading `!` for exclusions is allowed.
+///
+/// Returns every problem found, rather than just the first.
+pub fn validate_output_spec(spec: &str) -> Result<(), Vec<OutputSpecDiagnostic>> {
+    let mut diagnostics = Vec::new();
+    let mut report = |error, span| diagnostics.push(OutputSpecDiagnostic { error, span });
+
+    let offset = usize::from(spec.starts_with('!'));
+    let pattern = &spec[offset..];
+    if pattern.is_empty() {
+        report(OutputSpecError::Empty, 0..spec.len());
+        return Err(diagnostics);
+    }
+
+    let bytes = pattern.as_bytes();
+    if bytes[0] == b'/' || bytes[0] == b'\\' {
+        report(OutputSpecError::Absolute, offset..offset + 1);
+    } else if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        report(OutputSpecError::Absolute, offset..offset + 2);
+    }
+
+    // Backslashes are separators on Windows, so `dist\..\..` escapes there
+    let mut start = offset;
+    for component in pattern.split(['/', '\\']) {
+        let span = start..start + component.len();
+        start = span.end + 1;
+        if component == ".." {
+            report(OutputSpecError::Traversal, span);
+            continue;
+        }
+        for (index, c) in component.char_indices() {
+            // Drive letters are already reported as absolute
+            let is_drive = c == ':' && span.start + index == offset + 1;
+            if (WINDOWS_RESERVED_CHARS.contains(&c) && !is_drive) || c.is_control() {
+                report(
+                    OutputSpecError::UnsafeChar(c),
+                    span.start + index..span.start + index + c.len_utf8(),
+                );
+            }
+        }
+        // Names are reserved regardless of extension, e.g. `nul.txt`
+        let stem = component.split('.').next().unwrap_or_default();
+        if WINDOWS_RESERVED_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(stem))
+        {
+            report(OutputSpecError::ReservedName(component.to_string()), span);
+        } else if component != "." {
+            if let Some(c) = component.chars().last().filter(|c| matches!(c, '.' | ' ')) {
+                report(OutputSpecError::TrailingChar(c), span.end - 1..span.end);
+            }
+        }
+    }
+
+    if let Err(PathError::InvalidGlob(_, reason)) = Glob::new(pattern) {
+        report(OutputSpecError::InvalidGlob(reason), offset..spec.len());
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(spec: &str) -> Vec<OutputSpecError> {
+        validate_output_spec(spec)
+            .unwrap_err()
+            .into_iter()
+            .map(|diagnostic| diagnostic.error)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_output_specs() {
+        for spec in [
+            "dist/**",
+            ".next/**",
+            "!.next/cache/**",
+            "./build/*.{js,map}",
+            "coverage/lcov.info",
+        ] {
+            assert_eq!(validate_output_spec(spec), Ok(()), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_invalid_output_specs() {
+        assert_eq!(errors(""), [OutputSpecError::Empty]);
+        assert_eq!(errors("!"), [OutputSpecError::Empty]);
+        assert_eq!(errors("/etc/passwd"), [OutputSpecError::Absolute]);
+        assert_eq!(errors("C:/dist/**"), [OutputSpecError::Absolute]);
+        assert_eq!(errors("!../shared/**"), [OutputSpecError::Traversal]);
+        assert_eq!(
+            errors("dist\\..\\..\\secret"),
+            [OutputSpecError::Traversal, OutputSpecError::Traversal]
+        );
+        assert_eq!(
+            errors("dist/a|b/con.txt/x."),
+            [
+                OutputSpecError::UnsafeChar('|'),
+                OutputSpecError::ReservedName("con.txt".to_string()),
+                OutputSpecError::TrailingChar('.'),
+            ]
+        );
+        assert_eq!(
+            errors("dist/{a,b"),
+            [OutputSpecError::InvalidGlob("unclosed '{'".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_spans() {
+        let spec = "!dist/../out<1>";
+        let diagnostics = validate_output_spec(spec).unwrap_err();
+        let spans: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| &spec[diagnostic.span.clone()])
+            .collect();
+        assert_eq!(spans, ["..", "<", ">"]);
+    }
+}
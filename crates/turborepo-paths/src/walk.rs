@@ -0,0 +1,245 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    metadata::FileKind, AbsoluteSystemPath, AnchoredSystemPathBuf, PathError, PathOperation,
+};
+
+/// Walks the directory tree under `root`, see `Walk`
+pub fn walk(root: impl AsRef<AbsoluteSystemPath>) -> Walk {
+    Walk::new(root)
+}
+
+/// A recursive directory walk that yields every entry under a root directory
+/// as a path anchored at that root, along with what kind of file it is.
+///
+/// The root itself comes first, as the empty path, and directories come
+/// before their contents. By default symlinks aren't followed, entries are
+/// yielded in the order the file system returns them, and there is no depth
+/// limit.
+///
+/// An error reading one directory or entry is yielded in its place and the
+/// walk carries on with the rest of the tree.
+pub struct Walk {
+    root: PathBuf,
+    follow_symlinks: bool,
+    sort: bool,
+    max_depth: Option<usize>,
+    // Entries that have been found but not yielded yet, in reverse order
+    pending: Vec<Pending>,
+    started: bool,
+}
+
+struct Pending {
+    path: AnchoredSystemPathBuf,
+    depth: usize,
+    // The resolved directories this entry is inside of, for detecting
+    // symlink loops. Only tracked when following symlinks.
+    ancestors: Option<Rc<Vec<PathBuf>>>,
+}
+
+impl Walk {
+    pub fn new(root: impl AsRef<AbsoluteSystemPath>) -> Self {
+        Walk {
+            root: root.as_ref().as_path().to_path_buf(),
+            follow_symlinks: false,
+            sort: false,
+            max_depth: None,
+            pending: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Follows symlinks, reporting the kind of file they point at and
+    /// walking into linked directories. Links that would walk into one of
+    /// their own ancestors produce a `PathError::SymlinkLoop`, and broken
+    /// links are reported as `FileKind::Symlink`.
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Yields the entries of each directory sorted by file name
+    pub fn with_sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Doesn't walk further than `max_depth` levels below the root. The
+    /// root is at depth 0, so a depth of 1 only yields the root and its
+    /// immediate entries.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    fn kind(&self, path: &Path) -> Result<FileKind, PathError> {
+        let metadata =
+            fs::symlink_metadata(path).map_err(PathError::io(PathOperation::Stat, path))?;
+        let kind = FileKind::from_metadata(&metadata);
+        if kind != FileKind::Symlink || !self.follow_symlinks {
+            return Ok(kind);
+        }
+        match fs::metadata(path) {
+            Ok(metadata) => Ok(FileKind::from_metadata(&metadata)),
+            Err(_) => Ok(FileKind::Symlink),
+        }
+    }
+
+    // Queues the entries of the directory at `entry`
+    fn push_children(&mut self, entry: &Pending, dir: &Path) -> Result<(), PathError> {
+        let ancestors = match &entry.ancestors {
+            Some(ancestors) => {
+                let resolved =
+                    dunce::canonicalize(dir).map_err(PathError::io(PathOperation::Resolve, dir))?;
+                if ancestors.contains(&resolved) {
+                    return Err(PathError::SymlinkLoop {
+                        path: dir.to_path_buf(),
+                        target: resolved,
+                    });
+                }
+                let mut ancestors = ancestors.as_ref().clone();
+                ancestors.push(resolved);
+                Some(Rc::new(ancestors))
+            }
+            None => None,
+        };
+
+        let mut names = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.file_name()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(PathError::io(PathOperation::Read, dir))?;
+        if self.sort {
+            names.sort();
+        }
+        self.pending
+            .extend(names.into_iter().rev().map(|name| Pending {
+                path: AnchoredSystemPathBuf(entry.path.as_path().join(name)),
+                depth: entry.depth + 1,
+                ancestors: ancestors.clone(),
+            }));
+        Ok(())
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<(AnchoredSystemPathBuf, FileKind), PathError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            self.pending.push(Pending {
+                path: AnchoredSystemPathBuf::default(),
+                depth: 0,
+                ancestors: self.follow_symlinks.then(|| Rc::new(Vec::new())),
+            });
+        }
+
+        let entry = self.pending.pop()?;
+        let path = self.root.join(entry.path.as_path());
+        let kind = match self.kind(&path) {
+            Ok(kind) => kind,
+            Err(err) => return Some(Err(err)),
+        };
+        let at_max_depth = matches!(self.max_depth, Some(max) if entry.depth >= max);
+        if kind == FileKind::Dir && !at_max_depth {
+            if let Err(err) = self.push_children(&entry, &path) {
+                return Some(Err(err));
+            }
+        }
+        Some(Ok((entry.path, kind)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::AbsoluteSystemPathBuf;
+
+    fn setup() -> Result<(tempfile::TempDir, AbsoluteSystemPathBuf)> {
+        let dir = tempfile::tempdir()?;
+        let root = AbsoluteSystemPathBuf::new(dir.path())?;
+        root.join_components(&["dist", "chunks"]).create_dir_all()?;
+        root.join_components(&["dist", "index.js"])
+            .create_with_contents("")?;
+        root.join_components(&["dist", "chunks", "a.js"])
+            .create_with_contents("")?;
+        root.join_component("package.json")
+            .create_with_contents("{}")?;
+        Ok((dir, root))
+    }
+
+    fn collect(walk: Walk) -> Result<Vec<(String, FileKind)>> {
+        walk.map(|entry| {
+            let (path, kind) = entry?;
+            Ok((path.to_unix()?.as_str()?.to_string(), kind))
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_walk_sorted() -> Result<()> {
+        let (_dir, root) = setup()?;
+        let entries = collect(walk(&root).with_sort(true))?;
+        assert_eq!(
+            entries,
+            [
+                ("".to_string(), FileKind::Dir),
+                ("dist".to_string(), FileKind::Dir),
+                ("dist/chunks".to_string(), FileKind::Dir),
+                ("dist/chunks/a.js".to_string(), FileKind::File),
+                ("dist/index.js".to_string(), FileKind::File),
+                ("package.json".to_string(), FileKind::File),
+            ]
+        );
+
+        let shallow = collect(walk(&root).with_sort(true).with_max_depth(Some(1)))?;
+        assert_eq!(
+            shallow
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            ["", "dist", "package.json"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_symlinks() -> Result<()> {
+        let (_dir, root) = setup()?;
+        root.join_component("out").symlink_to_dir("dist")?;
+        root.join_components(&["dist", "chunks", "loop"])
+            .symlink_to_dir(["..", ".."].join(std::path::MAIN_SEPARATOR_STR))?;
+
+        let entries = collect(walk(&root).with_sort(true))?;
+        assert!(entries.contains(&("out".to_string(), FileKind::Symlink)));
+        assert!(!entries.iter().any(|(path, _)| path.starts_with("out/")));
+
+        let followed: Vec<_> = walk(&root)
+            .with_sort(true)
+            .with_follow_symlinks(true)
+            .collect();
+        let linked_file = Path::new("out").join("index.js");
+        assert!(followed.iter().any(|entry| matches!(
+            entry,
+            Ok((path, FileKind::File)) if path.as_path() == linked_file
+        )));
+        assert_matches!(
+            followed.iter().find(|entry| entry.is_err()),
+            Some(Err(PathError::SymlinkLoop { .. }))
+        );
+
+        Ok(())
+    }
+}
@@ -625,7 +625,7 @@ mod tests {
 
         assert_matches!(
             turbo_root_is_not_subdir_of_git_root,
-            Err(Error::Path(PathError::NotParent(_, _), _))
+            Err(Error::Path(PathError::NotParent { .. }, _))
         );
 
         Ok(())
@@ -159,7 +159,7 @@ mod tests {
         let root_file_path = repo_root.join_component("new-root-file");
         root_file_path.create_with_contents("new-root bytes")?;
 
-        let package_path = AnchoredSystemPathBuf::from_raw("my-pkg")?;
+        let package_path = turbopath::anchored_path!("my-pkg");
 
         let expected = to_hash_map(&[
             ("committed-file", "3a29e62ea9ba15c4a4009d1f605d391cdd262033"),
@@ -1,9 +1,21 @@
-use std::net::SocketAddr;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
 
 use anyhow::Result;
-use axum::{routing::get, Json, Router};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
 use turborepo_api_client::{
-    CachingStatus, CachingStatusResponse, Membership, Role, Space, SpacesResponse, Team,
+    CachingStatus, CachingStatusResponse, DeleteArtifactsResponse, Membership, Role,
+    SignedArtifactUrl, SignedUrlMethod, SignedUrlRequest, Space, SpacesResponse, Team,
     TeamsResponse, User, UserResponse, VerificationResponse,
 };
 
@@ -24,7 +36,194 @@ pub const EXPECTED_SPACE_NAME: &str = "expected_space_name";
 pub const EXPECTED_SSO_TEAM_ID: &str = "expected_sso_team_id";
 pub const EXPECTED_SSO_TEAM_SLUG: &str = "expected_sso_team_slug";
 
+#[derive(Default)]
+struct StoredArtifact {
+    body: Bytes,
+    duration: Option<HeaderValue>,
+    tag: Option<HeaderValue>,
+    digest: Option<HeaderValue>,
+}
+
+#[derive(Default)]
+struct Artifacts {
+    stored: HashMap<String, StoredArtifact>,
+    // Metadata sent along with a request for a signed upload URL, waiting for
+    // the upload itself
+    signed_uploads: HashMap<String, StoredArtifact>,
+}
+
+type ArtifactStore = Arc<Mutex<Artifacts>>;
+
+async fn put_artifact(
+    State(artifacts): State<ArtifactStore>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    artifacts.lock().unwrap().stored.insert(
+        hash,
+        StoredArtifact {
+            body,
+            duration: headers.get("x-artifact-duration").cloned(),
+            tag: headers.get("x-artifact-tag").cloned(),
+            digest: headers.get("x-artifact-digest").cloned(),
+        },
+    );
+    StatusCode::ACCEPTED
+}
+
+async fn delete_artifact(
+    State(artifacts): State<ArtifactStore>,
+    Path(hash): Path<String>,
+) -> StatusCode {
+    match artifacts.lock().unwrap().stored.remove(&hash) {
+        Some(_) => StatusCode::OK,
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+async fn delete_artifacts(
+    State(artifacts): State<ArtifactStore>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<DeleteArtifactsResponse>, StatusCode> {
+    let prefix = query
+        .get("prefix")
+        .filter(|prefix| !prefix.is_empty())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let mut artifacts = artifacts.lock().unwrap();
+    let before = artifacts.stored.len();
+    artifacts
+        .stored
+        .retain(|hash, _| !hash.starts_with(prefix.as_str()));
+    Ok(Json(DeleteArtifactsResponse {
+        deleted: (before - artifacts.stored.len()) as u64,
+    }))
+}
+
+async fn get_artifact(
+    State(artifacts): State<ArtifactStore>,
+    Path(hash): Path<String>,
+    request_headers: HeaderMap,
+) -> Response {
+    let artifacts = artifacts.lock().unwrap();
+    let Some(artifact) = artifacts.stored.get(&hash) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let mut headers = HeaderMap::new();
+    if let Some(duration) = &artifact.duration {
+        headers.insert("x-artifact-duration", duration.clone());
+    }
+    if let Some(tag) = &artifact.tag {
+        headers.insert("x-artifact-tag", tag.clone());
+    }
+    if let Some(digest) = &artifact.digest {
+        headers.insert("x-artifact-digest", digest.clone());
+    }
+
+    // Only `bytes=<start>-` ranges are supported, which is all the client
+    // sends when resuming a download
+    let range_start = request_headers
+        .get("range")
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.parse::<usize>().ok());
+    match range_start {
+        Some(start) if start >= artifact.body.len() => {
+            StatusCode::RANGE_NOT_SATISFIABLE.into_response()
+        }
+        Some(start) => {
+            let content_range = format!(
+                "bytes {}-{}/{}",
+                start,
+                artifact.body.len() - 1,
+                artifact.body.len()
+            );
+            headers.insert(
+                "content-range",
+                HeaderValue::from_str(&content_range).unwrap(),
+            );
+            (
+                StatusCode::PARTIAL_CONTENT,
+                headers,
+                artifact.body.slice(start..),
+            )
+                .into_response()
+        }
+        None => (headers, artifact.body.clone()).into_response(),
+    }
+}
+
+// Hands out URLs to the `/storage` routes below, which stand in for object
+// storage. The mock doesn't actually check signatures.
+async fn sign_artifact_url(
+    State(artifacts): State<ArtifactStore>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<SignedUrlRequest>,
+) -> Json<SignedArtifactUrl> {
+    let host = headers
+        .get("host")
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let url = format!("http://{}/storage/{}?signature=mock", host, hash);
+
+    let mut artifacts = artifacts.lock().unwrap();
+    match request.method {
+        SignedUrlMethod::Put => {
+            let header = |value: String| HeaderValue::from_str(&value).ok();
+            artifacts.signed_uploads.insert(
+                hash,
+                StoredArtifact {
+                    body: Bytes::new(),
+                    duration: request
+                        .duration
+                        .and_then(|duration| header(duration.to_string())),
+                    tag: request.tag.and_then(header),
+                    digest: request.digest.and_then(header),
+                },
+            );
+            Json(SignedArtifactUrl {
+                url,
+                duration: None,
+                tag: None,
+                digest: None,
+            })
+        }
+        SignedUrlMethod::Get => {
+            let artifact = artifacts.stored.get(&hash);
+            let header = |value: Option<&HeaderValue>| {
+                value.and_then(|value| value.to_str().ok().map(|value| value.to_string()))
+            };
+            Json(SignedArtifactUrl {
+                url,
+                duration: header(artifact.and_then(|artifact| artifact.duration.as_ref()))
+                    .and_then(|duration| duration.parse().ok()),
+                tag: header(artifact.and_then(|artifact| artifact.tag.as_ref())),
+                digest: header(artifact.and_then(|artifact| artifact.digest.as_ref())),
+            })
+        }
+    }
+}
+
+async fn put_signed_artifact(
+    State(artifacts): State<ArtifactStore>,
+    Path(hash): Path<String>,
+    body: Bytes,
+) -> StatusCode {
+    let mut artifacts = artifacts.lock().unwrap();
+    let Some(artifact) = artifacts.signed_uploads.remove(&hash) else {
+        return StatusCode::FORBIDDEN;
+    };
+    artifacts
+        .stored
+        .insert(hash, StoredArtifact { body, ..artifact });
+    StatusCode::OK
+}
+
 pub async fn start_test_server(port: u16) -> Result<()> {
+    let artifacts = ArtifactStore::default();
     let app = Router::new()
         .route(
             "/v2/user",
@@ -82,7 +281,15 @@ pub async fn start_test_server(port: u16) -> Result<()> {
                     team_id: Some(EXPECTED_SSO_TEAM_ID.to_string()),
                 })
             }),
-        );
+        )
+        .route("/v8/artifacts", delete(delete_artifacts))
+        .route(
+            "/v8/artifacts/:hash",
+            get(get_artifact).put(put_artifact).delete(delete_artifact),
+        )
+        .route("/v8/artifacts/:hash/signed-url", post(sign_artifact_url))
+        .route("/storage/:hash", get(get_artifact).put(put_signed_artifact))
+        .with_state(artifacts);
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     // We print the port so integration tests can use it
     println!("{}", port);
@@ -39,6 +39,7 @@ Make sure exit code is 2 when no args are passed
     -h, --help                            Print help
   
   Run Arguments:
+        --affected                       Only run the tasks of workspaces affected by changes since the merge base with TURBO_SCM_BASE (default main), and of their dependents
         --cache-dir <CACHE_DIR>          Override the filesystem cache directory
         --cache-workers <CACHE_WORKERS>  Set the number of concurrent cache operations (default 10) [default: 10]
         --concurrency <CONCURRENCY>      Limit the concurrency of task execution. Use 1 for serial (i.e. one-at-a-time) execution
@@ -63,6 +64,8 @@ Make sure exit code is 2 when no args are passed
         --since <SINCE>                  Limit/Set scope to changed packages since a mergebase. This uses the git diff ${target_branch}... mechanism to identify which packages have changed
         --summarize [<SUMMARIZE>]        Generate a summary of the turbo run [env: TURBO_RUN_SUMMARY=] [possible values: true, false]
         --log-prefix <LOG_PREFIX>        Use "none" to remove prefixes from task logs. Note that tasks running in parallel interleave their logs and prefix is the only way to identify which task produced a log [possible values: none]
+        --watch                          Keep running and re-run the tasks of workspaces whose inputs changed, and of their dependents. Requires the daemon
+        --watch-debounce <MS>            Milliseconds to wait for further changes before re-running tasks in watch mode [default: 100]
   [1]
   $ ${TURBO} run
   ERROR at least one task must be specified